@@ -0,0 +1,141 @@
+//! qr-cli - пакетное сканирование изображений
+//!
+//! Usage: qr-cli [--payment-only] [--config config.json] <DIR | GLOB | FILE>...
+//!
+//! Для каждого файла печатается одна JSON-строка:
+//! `{"file": "...", "qr_codes": [{"content", "content_type", "payment"}]}`
+//! или `{"file": "...", "error": "..."}`.
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use qr_core::{ContentType, PaymentInfo, QRScanner, ScannerConfig};
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Расширения, которые берутся из каталога
+const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "bmp"];
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Batch QR code scanner: prints one JSON line per image")]
+struct Args {
+    /// Каталоги, glob-шаблоны (`dataset/*_clean.png`) или файлы
+    #[arg(required = true)]
+    inputs: Vec<String>,
+
+    /// Печатать только платёжные QR (файлы без них пропускаются)
+    #[arg(long)]
+    payment_only: bool,
+
+    /// JSON в формате `qr_core::ScannerConfig` (как `withConfigObject` в WASM):
+    /// поля ProcessingConfig на верхнем уровне, DetectorConfig — в `detection`
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+}
+
+/// Один QR в выводе
+#[derive(Serialize)]
+struct CodeLine<'a> {
+    content: &'a str,
+    content_type: &'a ContentType,
+    payment: Option<&'a PaymentInfo>,
+}
+
+/// Строка вывода для файла
+#[derive(Serialize)]
+struct FileLine<'a> {
+    file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    qr_codes: Option<Vec<CodeLine<'a>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let scanner = match &args.config {
+        Some(path) => {
+            let text = std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+            let value: Value = serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+            let config = ScannerConfig::from_json(value).with_context(|| format!("invalid config {}", path.display()))?;
+            QRScanner::with_config(config.processing, config.detection)
+        }
+        None => QRScanner::new(),
+    };
+
+    let files = collect_files(&args.inputs)?;
+    if files.is_empty() {
+        bail!("no images matched {:?}", args.inputs);
+    }
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for file in files {
+        let result = scanner.scan_file(&file);
+        let line = match &result {
+            Ok(result) => {
+                let codes: Vec<CodeLine> = result
+                    .qr_codes
+                    .iter()
+                    .filter(|qr| !args.payment_only || qr.payment.is_some())
+                    .map(|qr| CodeLine {
+                        content: &qr.content,
+                        content_type: &qr.content_type,
+                        payment: qr.payment.as_ref(),
+                    })
+                    .collect();
+                if args.payment_only && codes.is_empty() {
+                    continue;
+                }
+                FileLine {
+                    file: file.display().to_string(),
+                    qr_codes: Some(codes),
+                    error: None,
+                }
+            }
+            Err(e) => FileLine {
+                file: file.display().to_string(),
+                qr_codes: None,
+                error: Some(e.to_string()),
+            },
+        };
+        writeln!(out, "{}", serde_json::to_string(&line)?)?;
+    }
+
+    Ok(())
+}
+
+/// Входные аргументы → отсортированный список файлов: каталог даёт изображения
+/// из него (без рекурсии), шаблон раскрывается через glob, остальное — путь к файлу
+fn collect_files(inputs: &[String]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let path = Path::new(input);
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = std::fs::read_dir(path)
+                .with_context(|| format!("reading {}", input))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file() && is_image(path))
+                .collect();
+            entries.sort();
+            files.extend(entries);
+        } else if input.contains(['*', '?', '[']) {
+            let mut matched = glob::glob(input)
+                .with_context(|| format!("invalid glob {}", input))?
+                .collect::<Result<Vec<_>, _>>()?;
+            matched.sort();
+            files.extend(matched);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+}
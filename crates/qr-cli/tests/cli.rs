@@ -0,0 +1,60 @@
+//! Запуск qr-cli над generated_dataset
+
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn dataset(pattern: &str) -> String {
+    let root_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).parent().unwrap().parent().unwrap().to_path_buf();
+    root_dir.join("generated_dataset").join(pattern).display().to_string()
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_qr-cli")).args(args).output().expect("qr-cli runs")
+}
+
+fn json_lines(output: &Output) -> Vec<Value> {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("JSON line"))
+        .collect()
+}
+
+#[test]
+fn test_scan_glob() {
+    let lines = json_lines(&run(&[&dataset("*_clean.png"), &dataset("missing.png")]));
+    assert_eq!(lines.len(), 5);
+
+    for line in &lines[..4] {
+        let codes = line["qr_codes"].as_array().expect("qr_codes");
+        assert_eq!(codes.len(), 1, "{}", line);
+        assert!(!codes[0]["content"].as_str().unwrap().is_empty());
+    }
+    let url = lines.iter().find(|l| l["file"].as_str().unwrap().ends_with("url_clean.png")).unwrap();
+    assert_eq!(url["qr_codes"][0]["content_type"], "url");
+
+    assert!(lines[4]["file"].as_str().unwrap().ends_with("missing.png"));
+    assert!(lines[4]["error"].as_str().unwrap().contains("missing.png"));
+}
+
+#[test]
+fn test_payment_only_with_config() {
+    let config = std::env::temp_dir().join(format!("qr-cli-config-{}.json", std::process::id()));
+    std::fs::write(&config, r#"{"maxDimension": 800, "detection": {"min_size": 20}}"#).unwrap();
+
+    let output = run(&["--payment-only", "--config", config.to_str().unwrap(), &dataset("*_clean.png")]);
+    let lines = json_lines(&output);
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0]["file"].as_str().unwrap().ends_with("payment_clean.png"));
+    assert_eq!(lines[0]["qr_codes"][0]["payment"]["format"], "sbp_russia");
+
+    // Неизвестное поле конфигурации — ошибка до сканирования
+    std::fs::write(&config, r#"{"block_sise": 31}"#).unwrap();
+    let output = run(&["--config", config.to_str().unwrap(), &dataset("url_clean.png")]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("block_sise"));
+
+    std::fs::remove_file(&config).ok();
+}
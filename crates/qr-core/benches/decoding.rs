@@ -0,0 +1,177 @@
+//! Benchmarks for QR decoding performance
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use image::{GrayImage, Luma};
+use qr_core::testing::{draw_modules, render_code};
+use qr_core::{BinarizerPreference, DecodeStrategy, DecoderConfig, QRDecoder};
+use rxing::Reader;
+
+/// Кадр 1920x1080 с QR-кодом в центре
+fn create_frame() -> GrayImage {
+    let qr = qrcode::QrCode::new("https://example.com/benchmark/frame").unwrap();
+    let module = 10u32;
+    let width = qr.width() as u32;
+    let mut img = GrayImage::from_pixel(1920, 1080, Luma([230]));
+    let origin = ((1920 - width * module) / 2, (1080 - width * module) / 2);
+    draw_modules(&mut img, &qr.to_colors(), width, origin, module, Luma([20]));
+    img
+}
+
+/// Прежний путь rxing: упаковка яркости в ARGB u32 и `RGBLuminanceSource`
+fn decode_via_argb(img: &GrayImage) -> bool {
+    let pixels: Vec<u32> = img
+        .as_raw()
+        .iter()
+        .map(|&g| {
+            let g = g as u32;
+            0xFF000000 | (g << 16) | (g << 8) | g
+        })
+        .collect();
+    let source = rxing::RGBLuminanceSource::new_with_width_height_pixels(
+        img.width() as usize,
+        img.height() as usize,
+        &pixels,
+    );
+    let mut bitmap = rxing::BinaryBitmap::new(rxing::common::HybridBinarizer::new(source));
+    rxing::qrcode::QRCodeReader::new().decode(&mut bitmap).is_ok()
+}
+
+/// Новый путь rxing: буфер яркости напрямую через `Luma8LuminanceSource`
+fn decode_via_luma(img: &GrayImage) -> bool {
+    let source = rxing::Luma8LuminanceSource::new(img.as_raw().clone(), img.width(), img.height());
+    let mut bitmap = rxing::BinaryBitmap::new(rxing::common::HybridBinarizer::new(source));
+    rxing::qrcode::QRCodeReader::new().decode(&mut bitmap).is_ok()
+}
+
+fn benchmark_luminance_source(c: &mut Criterion) {
+    let frame = create_frame();
+    assert!(decode_via_argb(&frame) && decode_via_luma(&frame));
+
+    c.bench_function("rxing_argb_source_1920x1080", |b| {
+        b.iter(|| decode_via_argb(black_box(&frame)))
+    });
+
+    c.bench_function("rxing_luma_source_1920x1080", |b| {
+        b.iter(|| decode_via_luma(black_box(&frame)))
+    });
+}
+
+/// 100 небольших кодов с разным содержимым (модуль 4px, quiet zone 4 модуля)
+fn create_batch() -> Vec<GrayImage> {
+    (0..100)
+        .map(|i| {
+            let qr = qrcode::QrCode::new(format!("https://example.com/receipt/{:04}", i)).unwrap();
+            render_code(&qr, 4, 4)
+        })
+        .collect()
+}
+
+/// `decode_batch` против последовательного цикла.
+/// Без `--features rayon` оба варианта последовательны.
+fn benchmark_decode_batch(c: &mut Criterion) {
+    let decoder = QRDecoder::new();
+    let images = create_batch();
+    assert!(decoder.decode_batch(&images).iter().all(|r| r.is_ok()));
+
+    c.bench_function("decode_batch_serial_100", |b| {
+        b.iter(|| black_box(&images).iter().map(|img| decoder.decode(img)).collect::<Vec<_>>())
+    });
+
+    c.bench_function("decode_batch_100", |b| {
+        b.iter(|| decoder.decode_batch(black_box(&images)))
+    });
+}
+
+fn benchmark_decode_luma(c: &mut Criterion) {
+    let decoder = QRDecoder::new();
+    let frame = create_frame();
+    let (width, height) = frame.dimensions();
+
+    c.bench_function("decode_luma_1920x1080", |b| {
+        b.iter(|| decoder.decode_luma(width, height, black_box(frame.as_raw())))
+    });
+}
+
+/// Кадр 1280x960 без QR-кода: текстура из псевдослучайных блоков
+fn create_noise_frame() -> GrayImage {
+    let mut state = 0x2545F491u32;
+    let blocks: Vec<u8> = (0..160 * 120)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state >> 24) as u8
+        })
+        .collect();
+    GrayImage::from_fn(1280, 960, |x, y| Luma([blocks[(y / 8 * 160 + x / 8) as usize]]))
+}
+
+/// Полный каскад на кадре без кода: все fallback-стадии выполняются до конца
+fn benchmark_decode_not_found(c: &mut Criterion) {
+    let decoder = QRDecoder::new();
+    let frame = create_noise_frame();
+    assert!(decoder.decode(&frame).is_err());
+
+    let mut group = c.benchmark_group("decode_not_found");
+    group.sample_size(10);
+    group.bench_function("1280x960", |b| b.iter(|| decoder.decode(black_box(&frame))));
+    group.finish();
+}
+
+/// Скриншот 1280x720: шапка, боковая панель, строки текста и код из фикстуры
+/// (модуль ~5px без сглаживания) справа; `with_code = false` — тот же экран без кода
+fn create_screenshot(with_code: bool) -> GrayImage {
+    let mut frame = GrayImage::from_fn(1280, 720, |x, y| {
+        let text_line = (y / 18) % 3 == 0 && (300..900).contains(&x) && (x / 7) % 5 != 0;
+        match (y < 48, x < 240, text_line) {
+            (true, _, _) => Luma([40]),
+            (_, true, _) => Luma([230]),
+            (_, _, true) => Luma([70]),
+            _ => Luma([250]),
+        }
+    });
+    if with_code {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../generated_dataset/url_clean.png");
+        let code = image::open(path).unwrap().to_luma8();
+        let code = image::imageops::resize(&code, code.width() / 2, code.height() / 2, image::imageops::FilterType::Nearest);
+        image::imageops::overlay(&mut frame, &code, 960, 300);
+    }
+    frame
+}
+
+/// Режимы бинаризации rxing на стадии Standard. Код на скриншоте обычно читает rqrr,
+/// поэтому разница заметна прежде всего на экранах без кода.
+fn benchmark_binarizer_preference(c: &mut Criterion) {
+    let mut group = c.benchmark_group("binarizer_screenshot");
+    group.sample_size(20);
+    for (name, with_code) in [("with_code", true), ("no_code", false)] {
+        let frame = create_screenshot(with_code);
+        for binarizer in [
+            BinarizerPreference::HybridThenGlobal,
+            BinarizerPreference::HybridOnly,
+            BinarizerPreference::GlobalOnly,
+        ] {
+            let decoder = QRDecoder::with_config(DecoderConfig {
+                strategy_order: vec![DecodeStrategy::Standard],
+                binarizer,
+                ..Default::default()
+            })
+            .unwrap();
+            assert_eq!(decoder.decode(&frame).is_ok(), with_code);
+            group.bench_function(format!("{}_{:?}", name, binarizer), |b| {
+                b.iter(|| decoder.decode(black_box(&frame)))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    benchmark_binarizer_preference,
+    benchmark_luminance_source,
+    benchmark_decode_luma,
+    benchmark_decode_batch,
+    benchmark_decode_not_found
+);
+criterion_main!(benches);
@@ -51,5 +51,51 @@ fn benchmark_detection(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, benchmark_preprocessing, benchmark_detection);
+/// Кадр 1600x1600 с 200 отдельными finder-паттернами (модуль 3-5 px) без QR-кодов
+fn create_false_finders_image() -> GrayImage {
+    let size = 1600u32;
+    let mut img = GrayImage::from_pixel(size, size, Luma([255]));
+    let mut seed = 7u32;
+    let mut next = |n: u32| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        (seed >> 8) % n
+    };
+    for _ in 0..200 {
+        let module = 3 + next(3);
+        let (x0, y0) = (next(size - 7 * module), next(size - 7 * module));
+        for my in 0..7 {
+            for mx in 0..7 {
+                // Внешнее кольцо и центр 3x3 тёмные, между ними светлое кольцо
+                let ring = mx.min(my).min(6 - mx).min(6 - my);
+                if ring != 1 {
+                    for dy in 0..module {
+                        for dx in 0..module {
+                            img.put_pixel(x0 + mx * module + dx, y0 + my * module + dy, Luma([0]));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    img
+}
+
+fn benchmark_false_finders(c: &mut Criterion) {
+    let img = create_false_finders_image();
+    let capped = QRDetector::new(DetectorConfig::default());
+    let uncapped = QRDetector::new(DetectorConfig {
+        max_finder_candidates: usize::MAX,
+        ..Default::default()
+    });
+
+    c.bench_function("detect_200_false_finders_capped", |b| {
+        b.iter(|| capped.detect(black_box(&img)))
+    });
+
+    c.bench_function("detect_200_false_finders_uncapped", |b| {
+        b.iter(|| uncapped.detect(black_box(&img)))
+    });
+}
+
+criterion_group!(benches, benchmark_preprocessing, benchmark_detection, benchmark_false_finders);
 criterion_main!(benches);
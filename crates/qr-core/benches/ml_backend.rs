@@ -0,0 +1,62 @@
+//! Benchmarks for ML detector backends: tract vs ONNX Runtime on a fixed model
+//!
+//! ONNX Runtime сравнивается только при сборке с `--features onnxruntime`
+//! и доступной libonnxruntime (ORT_DYLIB_PATH); иначе замеряется только tract.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use image::{GrayImage, Luma};
+use qr_core::{ExecutionProvider, InferenceBackend, OnnxDetector};
+
+const MODEL_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../public/model.onnx");
+
+fn create_test_image(width: u32, height: u32) -> GrayImage {
+    let mut img = GrayImage::new(width, height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let value = if ((x / 12) + (y / 12)) % 2 == 0 { 30 } else { 220 };
+            img.put_pixel(x, y, Luma([value]));
+        }
+    }
+
+    img
+}
+
+fn benchmark_backends(c: &mut Criterion) {
+    let model = std::fs::read(MODEL_PATH).expect("public/model.onnx");
+    let img = create_test_image(1280, 720);
+
+    let backends = [
+        ("tract", InferenceBackend::Tract),
+        ("ort_cpu", InferenceBackend::OnnxRuntime(ExecutionProvider::Cpu)),
+        ("ort_cuda", InferenceBackend::OnnxRuntime(ExecutionProvider::Cuda)),
+        ("ort_coreml", InferenceBackend::OnnxRuntime(ExecutionProvider::CoreMl)),
+    ];
+
+    let mut group = c.benchmark_group("ml_detect_1280x720");
+    group.sample_size(10);
+
+    for (name, backend) in backends {
+        let detector = match OnnxDetector::load_with_backend(&model, backend) {
+            Ok(detector) => detector,
+            Err(e) => {
+                eprintln!("skip {}: {}", name, e);
+                continue;
+            }
+        };
+        // Бэкенд недоступен в этой сборке: загрузка перешла на другой
+        if detector.backend() != backend {
+            eprintln!("skip {}: active backend is {:?}", name, detector.backend());
+            continue;
+        }
+
+        group.bench_function(name, |b| {
+            b.iter(|| detector.detect(black_box(&img)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_backends);
+criterion_main!(benches);
@@ -0,0 +1,217 @@
+//! Модуль парсинга событий календаря (iCalendar `VEVENT`, RFC 5545)
+//!
+//! Формат:
+//! ```text
+//! BEGIN:VEVENT
+//! SUMMARY:Планёрка
+//! DTSTART:20240515T090000Z
+//! DTEND:20240515T093000Z
+//! LOCATION:Переговорная 3
+//! END:VEVENT
+//! ```
+//! Событие может быть обёрнуто в `BEGIN:VCALENDAR`; берётся первый `VEVENT`.
+
+use serde::{Deserialize, Serialize};
+
+/// Время начала или окончания события
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum EventTime {
+    /// Событие на весь день (`DTSTART;VALUE=DATE:20240515`)
+    Date { year: u16, month: u8, day: u8 },
+    /// Момент времени (`20240515T090000`); `utc` — суффикс `Z`,
+    /// иначе локальное время (часовой пояс `TZID` не применяется)
+    DateTime {
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+        second: u8,
+        utc: bool,
+    },
+}
+
+impl EventTime {
+    /// Разбор `YYYYMMDD` или `YYYYMMDDTHHMMSS[Z]`
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if !value.is_ascii() {
+            return None;
+        }
+        let number = |range: std::ops::Range<usize>| -> Option<u16> {
+            let digits = value.get(range)?;
+            digits.bytes().all(|b| b.is_ascii_digit()).then(|| digits.parse().ok())?
+        };
+
+        let (year, month, day) = (number(0..4)?, number(4..6)? as u8, number(6..8)? as u8);
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        if value.len() == 8 {
+            return Some(EventTime::Date { year, month, day });
+        }
+
+        let (time, utc) = match value[8..].strip_suffix(['Z', 'z']) {
+            Some(time) => (time, true),
+            None => (&value[8..], false),
+        };
+        if time.len() != 7 || !time.starts_with(['T', 't']) {
+            return None;
+        }
+        let (hour, minute, second) = (number(9..11)? as u8, number(11..13)? as u8, number(13..15)? as u8);
+        if hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+        Some(EventTime::DateTime { year, month, day, hour, minute, second, utc })
+    }
+
+    /// Событие на весь день
+    pub fn is_all_day(&self) -> bool {
+        matches!(self, EventTime::Date { .. })
+    }
+}
+
+/// Событие календаря из QR-кода
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CalendarEvent {
+    /// Название (`SUMMARY`)
+    pub summary: Option<String>,
+    /// Начало (`DTSTART`)
+    pub start: EventTime,
+    /// Окончание (`DTEND`), если задано
+    pub end: Option<EventTime>,
+    /// Место (`LOCATION`)
+    pub location: Option<String>,
+    /// Описание (`DESCRIPTION`)
+    pub description: Option<String>,
+}
+
+impl CalendarEvent {
+    /// Парсинг первого `VEVENT`. Без корректного `DTSTART` — `None`.
+    pub fn parse(content: &str) -> Option<Self> {
+        let mut in_event = false;
+        let mut summary = None;
+        let mut start = None;
+        let mut end = None;
+        let mut location = None;
+        let mut description = None;
+
+        for line in unfold(content) {
+            let Some((name_params, value)) = line.split_once(':') else {
+                continue;
+            };
+            let mut params = name_params.split(';');
+            let name = params.next().unwrap_or_default().trim().to_ascii_uppercase();
+
+            match name.as_str() {
+                "BEGIN" if value.trim().eq_ignore_ascii_case("VEVENT") => in_event = true,
+                "END" if value.trim().eq_ignore_ascii_case("VEVENT") => break,
+                _ if !in_event => {}
+                "SUMMARY" => summary = Some(unescape(value)),
+                "LOCATION" => location = Some(unescape(value)),
+                "DESCRIPTION" => description = Some(unescape(value)),
+                "DTSTART" | "DTEND" => {
+                    let time = EventTime::parse(value)?;
+                    // VALUE=DATE требует дату без времени
+                    let date_only = params.any(|p| p.trim().eq_ignore_ascii_case("VALUE=DATE"));
+                    if date_only && !time.is_all_day() {
+                        return None;
+                    }
+                    if name == "DTSTART" {
+                        start = Some(time);
+                    } else {
+                        end = Some(time);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            summary,
+            start: start?,
+            end,
+            location,
+            description,
+        })
+    }
+}
+
+/// Строки с восстановленными переносами: строка, начинающаяся с пробела
+/// или табуляции, продолжает предыдущую
+fn unfold(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Снятие экранирования TEXT: `\n`, `\,`, `\;`, `\\`
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.trim().chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timed_event_with_end() {
+        let content = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nSUMMARY:Планёрка\\, отдел QA\r\n\
+                       DTSTART:20240515T090000Z\r\nDTEND:20240515T093000Z\r\nLOCATION:Переговорная 3\r\n\
+                       DESCRIPTION:Повестка:\\nрелиз\r\n  и ретро\r\nEND:VEVENT\r\nEND:VCALENDAR";
+        let event = CalendarEvent::parse(content).unwrap();
+        assert_eq!(event.summary.as_deref(), Some("Планёрка, отдел QA"));
+        assert_eq!(
+            event.start,
+            EventTime::DateTime { year: 2024, month: 5, day: 15, hour: 9, minute: 0, second: 0, utc: true }
+        );
+        assert_eq!(
+            event.end,
+            Some(EventTime::DateTime { year: 2024, month: 5, day: 15, hour: 9, minute: 30, second: 0, utc: true })
+        );
+        assert_eq!(event.location.as_deref(), Some("Переговорная 3"));
+        assert_eq!(event.description.as_deref(), Some("Повестка:\nрелиз и ретро"));
+    }
+
+    #[test]
+    fn test_all_day_event_without_end() {
+        let content = "BEGIN:VEVENT\nSUMMARY:День рождения\nDTSTART;VALUE=DATE:20240601\nEND:VEVENT";
+        let event = CalendarEvent::parse(content).unwrap();
+        assert_eq!(event.start, EventTime::Date { year: 2024, month: 6, day: 1 });
+        assert!(event.start.is_all_day());
+        assert_eq!(event.end, None);
+        assert_eq!(event.location, None);
+
+        // Локальное время с TZID
+        let local = CalendarEvent::parse("BEGIN:VEVENT\nDTSTART;TZID=Europe/Moscow:20240601T183000\nEND:VEVENT").unwrap();
+        assert!(matches!(local.start, EventTime::DateTime { hour: 18, minute: 30, utc: false, .. }));
+    }
+
+    #[test]
+    fn test_invalid_events() {
+        assert!(CalendarEvent::parse("BEGIN:VEVENT\nSUMMARY:Без даты\nEND:VEVENT").is_none());
+        assert!(CalendarEvent::parse("BEGIN:VEVENT\nDTSTART:20241332\nEND:VEVENT").is_none());
+        assert!(CalendarEvent::parse("BEGIN:VEVENT\nDTSTART;VALUE=DATE:20240601T100000\nEND:VEVENT").is_none());
+        assert!(CalendarEvent::parse("BEGIN:VEVENT\nDTSTART:20240601T250000\nEND:VEVENT").is_none());
+        // DTSTART вне VEVENT не учитывается
+        assert!(CalendarEvent::parse("DTSTART:20240601\nBEGIN:VEVENT\nEND:VEVENT").is_none());
+    }
+}
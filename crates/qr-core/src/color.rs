@@ -0,0 +1,113 @@
+//! Преобразование кадров камеры в план яркости
+//!
+//! Камеры мобильных устройств отдают YUV420 (NV21, I420, `YUV_420_888`),
+//! где Y-плоскость — уже готовое grayscale-изображение, но строки могут быть
+//! выровнены (`y_stride > width`). Функции возвращают плотный буфер
+//! `width * height` байт для `QRScanner::scan_luma`.
+
+use crate::QRError;
+
+/// Y-плоскость кадра NV21 (Android `ImageFormat.NV21`): `data` — весь кадр,
+/// Y-строки по `y_stride` байт, за ними чередующиеся VU (не используются)
+pub fn nv21_to_luma(data: &[u8], width: u32, height: u32, y_stride: u32) -> Result<Vec<u8>, QRError> {
+    // За Y-плоскостью идёт VU, поэтому последняя строка Y тоже дополнена до y_stride
+    let plane = plane_len(width, height, y_stride, y_stride)?;
+    pack_rows(data, width, height, y_stride, plane)
+}
+
+/// Y-плоскость планарного YUV420 (I420, YV12, плоскость 0 `YUV_420_888`):
+/// `y_plane` — только плоскость яркости; последняя строка может быть без отступа
+pub fn yuv420_to_luma(y_plane: &[u8], width: u32, height: u32, y_stride: u32) -> Result<Vec<u8>, QRError> {
+    let plane = plane_len(width, height, y_stride, width)?;
+    pack_rows(y_plane, width, height, y_stride, plane)
+}
+
+/// Минимальная длина плоскости: `height - 1` строк по `y_stride` и последняя длиной `last_row`
+fn plane_len(width: u32, height: u32, y_stride: u32, last_row: u32) -> Result<usize, QRError> {
+    if width == 0 || height == 0 {
+        return Err(QRError::InvalidFormat(format!("empty frame ({}x{})", width, height)));
+    }
+    if y_stride < width {
+        return Err(QRError::InvalidFormat(format!(
+            "y_stride ({}) is smaller than width ({})",
+            y_stride, width
+        )));
+    }
+    (height as usize - 1)
+        .checked_mul(y_stride as usize)
+        .and_then(|rows| rows.checked_add(last_row as usize))
+        .ok_or_else(|| QRError::InvalidFormat(format!("frame {}x{} is too large", width, height)))
+}
+
+/// Копирование `height` строк по `width` байт из плоскости с шагом `y_stride`
+fn pack_rows(data: &[u8], width: u32, height: u32, y_stride: u32, required: usize) -> Result<Vec<u8>, QRError> {
+    if data.len() < required {
+        return Err(QRError::InvalidFormat(format!(
+            "Y plane for {}x{} frame with stride {} needs {} bytes, got {}",
+            width,
+            height,
+            y_stride,
+            required,
+            data.len()
+        )));
+    }
+
+    let (width, stride) = (width as usize, y_stride as usize);
+    let mut luma = Vec::with_capacity(width * height as usize);
+    for row in data.chunks(stride).take(height as usize) {
+        luma.extend_from_slice(&row[..width]);
+    }
+    Ok(luma)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Y-плоскость с отступом строк: значение пикселя `x + 10 * y`, отступ заполнен 0xEE
+    fn padded_plane(width: u32, height: u32, stride: u32) -> (Vec<u8>, Vec<u8>) {
+        let mut plane = Vec::new();
+        let mut expected = Vec::new();
+        for y in 0..height {
+            for x in 0..stride {
+                let value = if x < width { (x + 10 * y) as u8 } else { 0xEE };
+                plane.push(value);
+                if x < width {
+                    expected.push(value);
+                }
+            }
+        }
+        (plane, expected)
+    }
+
+    #[test]
+    fn test_padded_y_plane_is_unpadded() {
+        // Нечётные размеры и отступ строк
+        let (width, height, stride) = (7, 5, 12);
+        let (plane, expected) = padded_plane(width, height, stride);
+
+        // NV21: за Y-плоскостью — VU на ceil(h/2) строк
+        let mut nv21 = plane.clone();
+        nv21.extend(std::iter::repeat_n(0x80, (stride * height.div_ceil(2)) as usize));
+        assert_eq!(nv21_to_luma(&nv21, width, height, stride).unwrap(), expected);
+        assert_eq!(yuv420_to_luma(&plane, width, height, stride).unwrap(), expected);
+
+        // Последняя строка планарной Y-плоскости без отступа
+        let trimmed = &plane[..plane.len() - (stride - width) as usize];
+        assert_eq!(yuv420_to_luma(trimmed, width, height, stride).unwrap(), expected);
+        assert!(nv21_to_luma(trimmed, width, height, stride).is_err());
+
+        // Без отступа
+        let (plane, expected) = padded_plane(width, height, width);
+        assert_eq!(yuv420_to_luma(&plane, width, height, width).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_invalid_planes_are_rejected() {
+        let (plane, _) = padded_plane(8, 4, 8);
+        assert!(yuv420_to_luma(&plane, 8, 4, 6).is_err());
+        assert!(yuv420_to_luma(&plane[..20], 8, 4, 8).is_err());
+        assert!(yuv420_to_luma(&plane, 0, 4, 8).is_err());
+        assert!(nv21_to_luma(&plane, u32::MAX, u32::MAX, u32::MAX).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Конфигурация сканера из JSON: общий формат для CLI (`--config`) и
+//! `withConfigObject` в WASM
+//!
+//! ```text
+//! { "maxDimension": null, "blockSize": 31, "detection": { "minSize": 40 } }
+//! ```
+//! Поля `ProcessingConfig` — на верхнем уровне, `DetectorConfig` — в объекте
+//! `detection`. Ключи в snake_case или camelCase; незаданные поля берутся из
+//! `Default`, неизвестные — ошибка. Значения перечислений — как в serde
+//! (`{ "binarization": { "Sauvola": { "k": 0.2, "window": 31 } } }`).
+
+use crate::detection::DetectorConfig;
+use crate::preprocessing::ProcessingConfig;
+use serde::de::Error as _;
+use serde_json::Value;
+
+/// Ключ объекта с полями `DetectorConfig`
+const DETECTION_KEY: &str = "detection";
+
+/// Конфигурации предобработки и детектора
+#[derive(Debug, Clone, Default)]
+pub struct ScannerConfig {
+    pub processing: ProcessingConfig,
+    pub detection: DetectorConfig,
+}
+
+impl ScannerConfig {
+    /// Разбор JSON-объекта
+    pub fn from_json(value: Value) -> Result<Self, serde_json::Error> {
+        let Value::Object(mut options) = snake_case_keys(value) else {
+            return Err(serde_json::Error::custom("config must be an object"));
+        };
+        let detection = match options.remove(DETECTION_KEY) {
+            Some(Value::Null) | None => DetectorConfig::default(),
+            Some(detection @ Value::Object(_)) => serde_json::from_value(detection)?,
+            Some(_) => return Err(serde_json::Error::custom("config.detection must be an object")),
+        };
+        Ok(Self {
+            processing: serde_json::from_value(Value::Object(options))?,
+            detection,
+        })
+    }
+
+    /// JSON-объект со всеми полями (ключи в snake_case), который принимает `from_json`
+    pub fn to_json(&self) -> Value {
+        let mut options = serde_json::to_value(&self.processing).unwrap_or_default();
+        if let Value::Object(map) = &mut options {
+            map.insert(DETECTION_KEY.to_string(), serde_json::to_value(&self.detection).unwrap_or_default());
+        }
+        options
+    }
+}
+
+/// `blockSize` → `block_size` во всех ключах объектов. Ключи с заглавной буквы
+/// (варианты перечислений `Sauvola`, `Clahe`) не меняются.
+fn snake_case_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (snake_case(&key), snake_case_keys(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(snake_case_keys).collect()),
+        other => other,
+    }
+}
+
+fn snake_case(key: &str) -> String {
+    if !key.starts_with(|c: char| c.is_ascii_lowercase()) {
+        return key.to_string();
+    }
+    let mut snake = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocessing::BinarizationMethod;
+    use serde_json::json;
+
+    #[test]
+    fn test_partial_config_in_both_key_styles() {
+        let config = ScannerConfig::from_json(json!({
+            "blockSize": 31,
+            "max_dimension": null,
+            "binarization": { "Sauvola": { "k": 0.3, "window": 25 } },
+            "detection": { "minSize": 40, "perspective_correct": false }
+        }))
+        .unwrap();
+
+        assert_eq!(config.processing.block_size, 31);
+        assert_eq!(config.processing.max_dimension, None);
+        assert_eq!(config.processing.binarization, Some(BinarizationMethod::Sauvola { k: 0.3, window: 25 }));
+        assert_eq!(config.processing.denoise_strength, ProcessingConfig::default().denoise_strength);
+        assert_eq!(config.detection.min_size, 40);
+        assert!(!config.detection.perspective_correct);
+        assert_eq!(config.detection.max_size, DetectorConfig::default().max_size);
+
+        let again = ScannerConfig::from_json(config.to_json()).unwrap();
+        assert_eq!(again.to_json(), config.to_json());
+        assert_eq!(ScannerConfig::from_json(json!({})).unwrap().to_json(), ScannerConfig::default().to_json());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let error = |value: Value| ScannerConfig::from_json(value).unwrap_err().to_string();
+        assert!(error(json!({ "blokSize": 31 })).contains("blok_size"));
+        assert!(error(json!({ "detection": { "minSise": 1 } })).contains("min_sise"));
+        assert!(error(json!({ "processing": {} })).contains("processing"));
+        assert!(ScannerConfig::from_json(json!({ "blockSize": "big" })).is_err());
+        assert!(ScannerConfig::from_json(json!({ "detection": 5 })).is_err());
+        assert!(ScannerConfig::from_json(json!([1])).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+//! Коды валют ISO 4217
+//!
+//! EMV (тег 53) и ряд других платёжных форматов передают валюту трёхзначным
+//! числовым кодом; для отображения нужен буквенный (`704` → `VND`).
+
+/// Числовой код → буквенный, отсортировано по коду (действующие валюты, фонды и
+/// коды драгметаллов из таблицы ISO 4217, а также HRK для старых кодов)
+static ISO_4217: &[(u16, &str)] = &[
+    (8, "ALL"), (12, "DZD"), (32, "ARS"), (36, "AUD"), (44, "BSD"), (48, "BHD"), (50, "BDT"), (51, "AMD"),
+    (52, "BBD"), (60, "BMD"), (64, "BTN"), (68, "BOB"), (72, "BWP"), (84, "BZD"), (90, "SBD"), (96, "BND"),
+    (104, "MMK"), (108, "BIF"), (116, "KHR"), (124, "CAD"), (132, "CVE"), (136, "KYD"), (144, "LKR"), (152, "CLP"),
+    (156, "CNY"), (170, "COP"), (174, "KMF"), (188, "CRC"), (191, "HRK"), (192, "CUP"), (203, "CZK"), (208, "DKK"),
+    (214, "DOP"), (222, "SVC"), (230, "ETB"), (232, "ERN"), (238, "FKP"), (242, "FJD"), (262, "DJF"), (270, "GMD"),
+    (292, "GIP"), (320, "GTQ"), (324, "GNF"), (328, "GYD"), (332, "HTG"), (340, "HNL"), (344, "HKD"), (348, "HUF"),
+    (352, "ISK"), (356, "INR"), (360, "IDR"), (364, "IRR"), (368, "IQD"), (376, "ILS"), (388, "JMD"), (392, "JPY"),
+    (398, "KZT"), (400, "JOD"), (404, "KES"), (408, "KPW"), (410, "KRW"), (414, "KWD"), (417, "KGS"), (418, "LAK"),
+    (422, "LBP"), (426, "LSL"), (430, "LRD"), (434, "LYD"), (446, "MOP"), (454, "MWK"), (458, "MYR"), (462, "MVR"),
+    (480, "MUR"), (484, "MXN"), (496, "MNT"), (498, "MDL"), (504, "MAD"), (512, "OMR"), (516, "NAD"), (524, "NPR"),
+    (532, "ANG"), (533, "AWG"), (548, "VUV"), (554, "NZD"), (558, "NIO"), (566, "NGN"), (578, "NOK"), (586, "PKR"),
+    (590, "PAB"), (598, "PGK"), (600, "PYG"), (604, "PEN"), (608, "PHP"), (634, "QAR"), (643, "RUB"), (646, "RWF"),
+    (654, "SHP"), (682, "SAR"), (690, "SCR"), (694, "SLL"), (702, "SGD"), (704, "VND"), (706, "SOS"), (710, "ZAR"),
+    (728, "SSP"), (748, "SZL"), (752, "SEK"), (756, "CHF"), (760, "SYP"), (764, "THB"), (776, "TOP"), (780, "TTD"),
+    (784, "AED"), (788, "TND"), (800, "UGX"), (807, "MKD"), (818, "EGP"), (826, "GBP"), (834, "TZS"), (840, "USD"),
+    (858, "UYU"), (860, "UZS"), (882, "WST"), (886, "YER"), (901, "TWD"), (924, "ZWG"), (925, "SLE"), (926, "VED"),
+    (927, "UYW"), (928, "VES"), (929, "MRU"), (930, "STN"), (932, "ZWL"), (933, "BYN"), (934, "TMT"), (936, "GHS"),
+    (938, "SDG"), (940, "UYI"), (941, "RSD"), (943, "MZN"), (944, "AZN"), (946, "RON"), (947, "CHE"), (948, "CHW"),
+    (949, "TRY"), (950, "XAF"), (951, "XCD"), (952, "XOF"), (953, "XPF"), (955, "XBA"), (956, "XBB"), (957, "XBC"),
+    (958, "XBD"), (959, "XAU"), (960, "XDR"), (961, "XAG"), (962, "XPT"), (963, "XTS"), (964, "XPD"), (965, "XUA"),
+    (967, "ZMW"), (968, "SRD"), (969, "MGA"), (970, "COU"), (971, "AFN"), (972, "TJS"), (973, "AOA"), (975, "BGN"),
+    (976, "CDF"), (977, "BAM"), (978, "EUR"), (979, "MXV"), (980, "UAH"), (981, "GEL"), (984, "BOV"), (985, "PLN"),
+    (986, "BRL"), (990, "CLF"), (994, "XSU"), (997, "USN"), (999, "XXX"),
+];
+
+/// Буквенный код валюты по числовому ISO 4217 (`"643"` → `RUB`, `"008"` → `ALL`).
+/// Ведущие нули необязательны; неизвестный или нечисловой код даёт `None`.
+pub fn currency_alpha(code: &str) -> Option<&'static str> {
+    let code = code.trim();
+    if code.is_empty() || code.len() > 3 || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let numeric: u16 = code.parse().ok()?;
+    ISO_4217
+        .binary_search_by_key(&numeric, |&(n, _)| n)
+        .ok()
+        .map(|idx| ISO_4217[idx].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_is_sorted() {
+        assert!(ISO_4217.len() >= 180);
+        assert!(ISO_4217.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(ISO_4217.iter().all(|(_, alpha)| alpha.len() == 3));
+    }
+
+    #[test]
+    fn test_currency_alpha() {
+        assert_eq!(currency_alpha("643"), Some("RUB"));
+        assert_eq!(currency_alpha("704"), Some("VND"));
+        assert_eq!(currency_alpha("764"), Some("THB"));
+        assert_eq!(currency_alpha("986"), Some("BRL"));
+        assert_eq!(currency_alpha("356"), Some("INR"));
+        // Ведущие нули
+        assert_eq!(currency_alpha("008"), Some("ALL"));
+        assert_eq!(currency_alpha("036"), Some("AUD"));
+        assert_eq!(currency_alpha("096"), Some("BND"));
+        assert_eq!(currency_alpha("36"), Some("AUD"));
+    }
+
+    #[test]
+    fn test_currency_alpha_unknown() {
+        assert_eq!(currency_alpha("000"), None);
+        assert_eq!(currency_alpha("123"), None);
+        assert_eq!(currency_alpha("RUB"), None);
+        assert_eq!(currency_alpha("+43"), None);
+        assert_eq!(currency_alpha("0643"), None);
+        assert_eq!(currency_alpha(""), None);
+    }
+}
@@ -2,10 +2,19 @@
 //!
 //! Использует rxing как основной декодер с fallback на rqrr
 
-use image::GrayImage;
-use rxing::{BarcodeFormat, DecodingHintDictionary, Exceptions, Reader};
+use image::{GrayImage, ImageBuffer, Luma};
+use rxing::{DecodingHintDictionary, MultiFormatReader, Reader};
+use rxing::qrcode::cpp_port::QrReader;
 use rxing::qrcode::QRCodeReader;
 use serde::{Deserialize, Serialize};
+use crate::geometry;
+use crate::preprocessing::{histogram, otsu_from_histogram};
+use crate::timing::Instant;
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::ops::Deref;
+use std::time::Duration;
 use thiserror::Error;
 
 
@@ -23,6 +32,48 @@ pub enum DecodeError {
     
     #[error("Checksum error")]
     ChecksumError,
+
+    #[error("Invalid decoder config: {0}")]
+    InvalidConfig(String),
+}
+
+impl DecodeError {
+    /// Насколько далеко продвинулось декодирование: код не найден < найден, но не
+    /// прочитан < прочитан, но не исправлен Reed–Solomon
+    fn progress(&self) -> u8 {
+        match self {
+            DecodeError::NotFound => 0,
+            DecodeError::DecodeFailed(_) => 1,
+            DecodeError::ChecksumError => 2,
+            DecodeError::InvalidImage(_) | DecodeError::InvalidConfig(_) => 3,
+        }
+    }
+
+    /// Более информативная из двух ошибок (при равенстве — первая)
+    fn most_informative(self, other: DecodeError) -> DecodeError {
+        if other.progress() > self.progress() {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn from_rxing(e: rxing::Exceptions) -> Self {
+        match e {
+            rxing::Exceptions::NotFoundException(_) => DecodeError::NotFound,
+            rxing::Exceptions::ChecksumException(_) | rxing::Exceptions::ReedSolomonException(_) => {
+                DecodeError::ChecksumError
+            }
+            other => DecodeError::DecodeFailed(other.to_string()),
+        }
+    }
+
+    fn from_rqrr(e: rqrr::DeQRError) -> Self {
+        match e {
+            rqrr::DeQRError::DataEcc | rqrr::DeQRError::FormatEcc => DecodeError::ChecksumError,
+            other => DecodeError::DecodeFailed(format!("{:?}", other)),
+        }
+    }
 }
 
 /// Уровень коррекции ошибок
@@ -35,6 +86,134 @@ pub enum ErrorCorrectionLevel {
     Unknown,
 }
 
+impl ErrorCorrectionLevel {
+    fn from_rxing(level: rxing::qrcode::decoder::ErrorCorrectionLevel) -> Self {
+        match level {
+            rxing::qrcode::decoder::ErrorCorrectionLevel::L => Self::L,
+            rxing::qrcode::decoder::ErrorCorrectionLevel::M => Self::M,
+            rxing::qrcode::decoder::ErrorCorrectionLevel::Q => Self::Q,
+            rxing::qrcode::decoder::ErrorCorrectionLevel::H => Self::H,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Формат штрихкода
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum BarcodeFormat {
+    QrCode,
+    /// Micro QR (M1-M4): один finder-паттерн, до 17x17 модулей
+    MicroQr,
+    /// Прямоугольный Micro QR (rMQR, ISO/IEC 23941)
+    RectangularMicroQr,
+    DataMatrix,
+    Aztec,
+    Pdf417,
+    Ean8,
+    Ean13,
+    UpcA,
+    UpcE,
+    Code39,
+    Code93,
+    Code128,
+    Codabar,
+    Itf,
+}
+
+impl BarcodeFormat {
+    fn to_rxing(self) -> rxing::BarcodeFormat {
+        match self {
+            BarcodeFormat::QrCode => rxing::BarcodeFormat::QR_CODE,
+            BarcodeFormat::MicroQr => rxing::BarcodeFormat::MICRO_QR_CODE,
+            BarcodeFormat::RectangularMicroQr => rxing::BarcodeFormat::RECTANGULAR_MICRO_QR_CODE,
+            BarcodeFormat::DataMatrix => rxing::BarcodeFormat::DATA_MATRIX,
+            BarcodeFormat::Aztec => rxing::BarcodeFormat::AZTEC,
+            BarcodeFormat::Pdf417 => rxing::BarcodeFormat::PDF_417,
+            BarcodeFormat::Ean8 => rxing::BarcodeFormat::EAN_8,
+            BarcodeFormat::Ean13 => rxing::BarcodeFormat::EAN_13,
+            BarcodeFormat::UpcA => rxing::BarcodeFormat::UPC_A,
+            BarcodeFormat::UpcE => rxing::BarcodeFormat::UPC_E,
+            BarcodeFormat::Code39 => rxing::BarcodeFormat::CODE_39,
+            BarcodeFormat::Code93 => rxing::BarcodeFormat::CODE_93,
+            BarcodeFormat::Code128 => rxing::BarcodeFormat::CODE_128,
+            BarcodeFormat::Codabar => rxing::BarcodeFormat::CODABAR,
+            BarcodeFormat::Itf => rxing::BarcodeFormat::ITF,
+        }
+    }
+
+    fn from_rxing(format: &rxing::BarcodeFormat) -> Option<Self> {
+        Some(match format {
+            rxing::BarcodeFormat::QR_CODE => BarcodeFormat::QrCode,
+            rxing::BarcodeFormat::MICRO_QR_CODE => BarcodeFormat::MicroQr,
+            rxing::BarcodeFormat::RECTANGULAR_MICRO_QR_CODE => BarcodeFormat::RectangularMicroQr,
+            rxing::BarcodeFormat::DATA_MATRIX => BarcodeFormat::DataMatrix,
+            rxing::BarcodeFormat::AZTEC => BarcodeFormat::Aztec,
+            rxing::BarcodeFormat::PDF_417 => BarcodeFormat::Pdf417,
+            rxing::BarcodeFormat::EAN_8 => BarcodeFormat::Ean8,
+            rxing::BarcodeFormat::EAN_13 => BarcodeFormat::Ean13,
+            rxing::BarcodeFormat::UPC_A => BarcodeFormat::UpcA,
+            rxing::BarcodeFormat::UPC_E => BarcodeFormat::UpcE,
+            rxing::BarcodeFormat::CODE_39 => BarcodeFormat::Code39,
+            rxing::BarcodeFormat::CODE_93 => BarcodeFormat::Code93,
+            rxing::BarcodeFormat::CODE_128 => BarcodeFormat::Code128,
+            rxing::BarcodeFormat::CODABAR => BarcodeFormat::Codabar,
+            rxing::BarcodeFormat::ITF => BarcodeFormat::Itf,
+            _ => return None,
+        })
+    }
+
+    /// Линейный (1D) штрихкод: читается по строкам, минимальная высота не нужна
+    fn is_linear(self) -> bool {
+        matches!(
+            self,
+            BarcodeFormat::Ean8
+                | BarcodeFormat::Ean13
+                | BarcodeFormat::UpcA
+                | BarcodeFormat::UpcE
+                | BarcodeFormat::Code39
+                | BarcodeFormat::Code93
+                | BarcodeFormat::Code128
+                | BarcodeFormat::Codabar
+                | BarcodeFormat::Itf
+        )
+    }
+
+    /// Семейство QR: читается ридерами QR без `MultiFormatReader`
+    fn is_qr_family(self) -> bool {
+        matches!(
+            self,
+            BarcodeFormat::QrCode | BarcodeFormat::MicroQr | BarcodeFormat::RectangularMicroQr
+        )
+    }
+
+    /// Формат по имени класса ML-модели (`qr`, `QR_CODE`, `data-matrix`, `pdf417`, ...)
+    pub fn from_label(label: &str) -> Option<Self> {
+        let normalized: String = label
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        Some(match normalized.as_str() {
+            "qr" | "qrcode" => BarcodeFormat::QrCode,
+            "microqr" | "mqr" => BarcodeFormat::MicroQr,
+            "rmqr" | "rectangularmicroqr" => BarcodeFormat::RectangularMicroQr,
+            "datamatrix" | "dm" => BarcodeFormat::DataMatrix,
+            "aztec" => BarcodeFormat::Aztec,
+            "pdf417" => BarcodeFormat::Pdf417,
+            "ean8" => BarcodeFormat::Ean8,
+            "ean13" => BarcodeFormat::Ean13,
+            "upca" => BarcodeFormat::UpcA,
+            "upce" => BarcodeFormat::UpcE,
+            "code39" => BarcodeFormat::Code39,
+            "code93" => BarcodeFormat::Code93,
+            "code128" => BarcodeFormat::Code128,
+            "codabar" => BarcodeFormat::Codabar,
+            "itf" => BarcodeFormat::Itf,
+            _ => return None,
+        })
+    }
+}
+
 /// Декодированный QR-код
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DecodedQR {
@@ -44,14 +223,449 @@ pub struct DecodedQR {
     pub error_correction: ErrorCorrectionLevel,
     /// Версия QR-кода (1-40)
     pub version: Option<u8>,
+    /// Формат штрихкода (QR, если не включены другие форматы)
+    pub format: BarcodeFormat,
     /// Формат данных (Numeric, Alphanumeric, Byte, Kanji)
     pub encoding: String,
+    /// Количество кодовых слов, исправленных Reed–Solomon
+    /// (известно только для пути с собственной выборкой сетки через rqrr)
+    pub errors_corrected: Option<u16>,
+    /// Углы кода (TL, TR, BR, BL) в координатах изображения, переданного в `decode`.
+    /// Для rqrr — границы сетки, для rxing — углы, достроенные от центров finder-паттернов
+    /// (`None` для не-QR форматов и QR без определённой версии).
+    pub corners: Option<[(f32, f32); 4]>,
+    /// Заголовок Structured Append, если код — часть последовательности
+    /// (читается только через rxing: rqrr не поддерживает этот режим)
+    pub structured_append: Option<StructuredAppend>,
+    /// Байты полезной нагрузки до перекодирования в текст.
+    /// Для rqrr — весь поток данных, для rxing — байтовые сегменты
+    /// (или UTF-8 текста, если байтовых сегментов нет).
+    pub raw_bytes: Vec<u8>,
+    /// ECI-назначение кодировки из потока данных (только путь rqrr)
+    pub eci: Option<u32>,
+}
+
+/// Заголовок Structured Append (ISO 18004, 8.3.2)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StructuredAppend {
+    /// Позиция символа в последовательности (с нуля)
+    pub index: u8,
+    /// Общее количество символов (1-16)
+    pub total: u8,
+    /// Чётность: XOR всех байтов исходного сообщения
+    pub parity: u8,
+}
+
+impl StructuredAppend {
+    /// Заголовок из метаданных результата rxing
+    fn from_rxing(result: &rxing::RXingResult) -> Option<Self> {
+        use rxing::{RXingResultMetadataType as Type, RXingResultMetadataValue as Value};
+        let metadata = result.getRXingResultMetadata();
+        let sequence = match metadata.get(&Type::STRUCTURED_APPEND_SEQUENCE)? {
+            Value::StructuredAppendSequence(sequence) => *sequence,
+            _ => return None,
+        };
+        let parity = match metadata.get(&Type::STRUCTURED_APPEND_PARITY)? {
+            Value::StructuredAppendParity(parity) => *parity,
+            _ => return None,
+        };
+        Some(Self {
+            index: (sequence >> 4 & 0x0F) as u8,
+            total: (sequence & 0x0F) as u8 + 1,
+            parity: parity as u8,
+        })
+    }
+}
+
+/// Сборка сообщения из символов Structured Append
+///
+/// Части сортируются по индексу и склеиваются; проверяются полнота
+/// последовательности и чётность. Порядок `parts` не важен.
+pub fn reassemble(parts: &[DecodedQR]) -> Result<String, DecodeError> {
+    let mut headers = Vec::with_capacity(parts.len());
+    for part in parts {
+        let header = part.structured_append.ok_or_else(|| {
+            DecodeError::DecodeFailed("part has no structured append header".to_string())
+        })?;
+        headers.push((header, part.content.as_str()));
+    }
+    let Some(&(first, _)) = headers.first() else {
+        return Err(DecodeError::DecodeFailed("no parts to reassemble".to_string()));
+    };
+    if headers.iter().any(|(h, _)| h.total != first.total || h.parity != first.parity) {
+        return Err(DecodeError::DecodeFailed("parts belong to different sequences".to_string()));
+    }
+
+    headers.sort_by_key(|(h, _)| h.index);
+    let complete = headers.len() == first.total as usize
+        && headers.iter().enumerate().all(|(i, (h, _))| h.index as usize == i);
+    if !complete {
+        return Err(DecodeError::DecodeFailed(format!(
+            "expected {} distinct parts, got {:?}",
+            first.total,
+            headers.iter().map(|(h, _)| h.index).collect::<Vec<_>>()
+        )));
+    }
+
+    let message: String = headers.iter().map(|(_, content)| *content).collect();
+    let parity = message.bytes().fold(0u8, |acc, b| acc ^ b);
+    if parity != first.parity {
+        return Err(DecodeError::ChecksumError);
+    }
+    Ok(message)
+}
+
+impl DecodedQR {
+    /// Перекодирование `raw_bytes` в текст
+    ///
+    /// `charset` — метка кодировки WHATWG (`"windows-1251"`, `"shift_jis"`, ...).
+    /// Без неё используется ECI-назначение, а при его отсутствии — UTF-8,
+    /// если байты корректны, иначе Windows-1251 (русскоязычные платёжные коды).
+    pub fn decode_text(&self, charset: Option<&str>) -> Result<String, DecodeError> {
+        let encoding = match charset {
+            Some(label) => encoding_rs::Encoding::for_label(label.as_bytes())
+                .ok_or_else(|| DecodeError::DecodeFailed(format!("unknown charset: {}", label)))?,
+            None => match self.eci.and_then(eci_encoding) {
+                Some(encoding) => encoding,
+                None if std::str::from_utf8(&self.raw_bytes).is_ok() => encoding_rs::UTF_8,
+                None => encoding_rs::WINDOWS_1251,
+            },
+        };
+        let (text, _, _) = encoding.decode(&self.raw_bytes);
+        Ok(text.into_owned())
+    }
+
+    /// Перенос углов в другую систему координат
+    pub(crate) fn map_corners(mut self, f: impl Fn((f32, f32)) -> (f32, f32)) -> Self {
+        self.corners = self.corners.map(|corners| corners.map(&f));
+        self
+    }
+}
+
+/// Метаданные QR-кода без декодирования содержимого
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QrMetadata {
+    /// Версия QR-кода (1-40)
+    pub version: u8,
+    /// Уровень коррекции ошибок
+    pub ecc: ErrorCorrectionLevel,
+    /// Номер маски данных (0-7)
+    pub mask: u8,
+    /// Количество модулей по стороне
+    pub module_count: u32,
+}
+
+/// Стратегия (стадия) каскада декодирования
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum DecodeStrategy {
+    /// Исходное изображение (rqrr, затем rxing)
+    Standard,
+    /// Инвертированное изображение
+    Inverted,
+    /// Растяжение контраста + повышение резкости
+    Preprocessed,
+    /// Предобработка + инверсия
+    PreprocessedInverted,
+    /// Белая рамка (восстановление quiet zone)
+    Padded,
+    /// Инверсия + белая рамка
+    PaddedInverted,
+    /// Повороты на фиксированные углы
+    Rotation,
+    /// Несколько порогов бинаризации (включая Otsu)
+    MultiThreshold,
+    /// Уменьшение изображения в 2 раза
+    Downscale,
+}
+
+impl DecodeStrategy {
+    /// Порядок каскада по умолчанию
+    pub const DEFAULT_ORDER: [DecodeStrategy; 9] = [
+        DecodeStrategy::Standard,
+        DecodeStrategy::Inverted,
+        DecodeStrategy::Preprocessed,
+        DecodeStrategy::PreprocessedInverted,
+        DecodeStrategy::Padded,
+        DecodeStrategy::PaddedInverted,
+        DecodeStrategy::Rotation,
+        DecodeStrategy::MultiThreshold,
+        DecodeStrategy::Downscale,
+    ];
+
+    /// Стратегия работает с инвертированной полярностью
+    fn needs_inversion(self) -> bool {
+        matches!(
+            self,
+            DecodeStrategy::Inverted | DecodeStrategy::PreprocessedInverted | DecodeStrategy::PaddedInverted
+        )
+    }
+
+    /// Имя стадии (совпадает с именем варианта в конфигурации)
+    pub fn name(self) -> &'static str {
+        match self {
+            DecodeStrategy::Standard => "Standard",
+            DecodeStrategy::Inverted => "Inverted",
+            DecodeStrategy::Preprocessed => "Preprocessed",
+            DecodeStrategy::PreprocessedInverted => "PreprocessedInverted",
+            DecodeStrategy::Padded => "Padded",
+            DecodeStrategy::PaddedInverted => "PaddedInverted",
+            DecodeStrategy::Rotation => "Rotation",
+            DecodeStrategy::MultiThreshold => "MultiThreshold",
+            DecodeStrategy::Downscale => "Downscale",
+        }
+    }
+
+    /// Та же стадия с противоположной полярностью (для стадий без пары — `None`)
+    fn opposite_polarity(self) -> Option<DecodeStrategy> {
+        match self {
+            DecodeStrategy::Standard => Some(DecodeStrategy::Inverted),
+            DecodeStrategy::Inverted => Some(DecodeStrategy::Standard),
+            DecodeStrategy::Preprocessed => Some(DecodeStrategy::PreprocessedInverted),
+            DecodeStrategy::PreprocessedInverted => Some(DecodeStrategy::Preprocessed),
+            DecodeStrategy::Padded => Some(DecodeStrategy::PaddedInverted),
+            DecodeStrategy::PaddedInverted => Some(DecodeStrategy::Padded),
+            DecodeStrategy::Rotation | DecodeStrategy::MultiThreshold | DecodeStrategy::Downscale => None,
+        }
+    }
+}
+
+/// Оценка полярности кода по яркости рамки изображения
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Polarity {
+    /// Светлая рамка (quiet zone) — тёмный код на светлом фоне
+    Normal,
+    /// Тёмная рамка — светлый код на тёмном фоне
+    Inverted,
+    /// Рамка неоднородна или близка по яркости к центру
+    Ambiguous,
+}
+
+/// Минимальная разница яркости рамки и центра для уверенной оценки полярности
+const POLARITY_MARGIN: f32 = 32.0;
+
+/// Оценка полярности: средняя яркость центрального окна против пикселей рамки
+/// шириной 1/16 стороны. Уверенный ответ — только если не меньше 90% рамки
+/// светлее (или темнее) центра на `POLARITY_MARGIN`.
+fn estimate_polarity<C: LumaBuffer>(img: &LumaImage<C>) -> Polarity {
+    let (width, height) = img.dimensions();
+    if width < 8 || height < 8 {
+        return Polarity::Ambiguous;
+    }
+
+    let (mut sum, mut count) = (0u64, 0u64);
+    for y in height / 4..height * 3 / 4 {
+        for x in width / 4..width * 3 / 4 {
+            sum += img.get_pixel(x, y).0[0] as u64;
+            count += 1;
+        }
+    }
+    let center = sum as f32 / count as f32;
+
+    let ring = (width.min(height) / 16).max(1);
+    let (mut lighter, mut darker, mut total) = (0u64, 0u64, 0u64);
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if x >= ring && x < width - ring && y >= ring && y < height - ring {
+            continue;
+        }
+        let value = pixel.0[0] as f32;
+        if value >= center + POLARITY_MARGIN {
+            lighter += 1;
+        } else if value <= center - POLARITY_MARGIN {
+            darker += 1;
+        }
+        total += 1;
+    }
+
+    if lighter * 10 >= total * 9 {
+        Polarity::Normal
+    } else if darker * 10 >= total * 9 {
+        Polarity::Inverted
+    } else {
+        Polarity::Ambiguous
+    }
+}
+
+/// Светлый код на тёмном фоне (тёмная однородная рамка вокруг более светлого центра).
+///
+/// Дешёвая оценка для выбора порядка стадий: `false` и для обычных, и для неоднозначных изображений.
+pub fn is_likely_inverted(img: &GrayImage) -> bool {
+    estimate_polarity(img) == Polarity::Inverted
+}
+
+/// Бинаризаторы rxing и порядок их применения
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum BinarizerPreference {
+    /// HybridBinarizer, при неудаче — GlobalHistogramBinarizer
+    #[default]
+    HybridThenGlobal,
+    /// Только HybridBinarizer (локальный порог: тени, неравномерный свет)
+    HybridOnly,
+    /// Только GlobalHistogramBinarizer: быстрее для чистых контрастных скриншотов
+    GlobalOnly,
+}
+
+/// Конфигурация декодера
+///
+/// Значения по умолчанию повторяют полный каскад. Для покадрового видео имеет смысл
+/// отключить тяжёлые стадии и задать `max_total_duration`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecoderConfig {
+    /// Порядок стратегий каскада. Стратегии, не перечисленные здесь, не выполняются.
+    /// Неизвестные имена отклоняются при десериализации, дубликаты — в `validate`.
+    pub strategy_order: Vec<DecodeStrategy>,
+    /// Стадии с инверсией (Inverted, PreprocessedInverted, PaddedInverted)
+    pub try_inverted: bool,
+    /// Стадии с контрастом и резкостью (Preprocessed, PreprocessedInverted)
+    pub try_preprocess: bool,
+    /// Стадии с белой рамкой (Padded, PaddedInverted)
+    pub try_padding: bool,
+    /// Углы стадии Rotation в градусах в порядке перебора (пустой список отключает стадию).
+    /// Перебор прерывается по `max_total_duration`.
+    pub rotation_angles: Vec<f32>,
+    /// Пороги стадии MultiThreshold; перед ними всегда пробуется порог Otsu
+    /// (пустой список отключает стадию)
+    pub thresholds: Vec<u8>,
+    /// Стадия Downscale
+    pub try_downscale: bool,
+    /// Бинаризаторы rxing на каждой попытке декодирования
+    pub binarizer: BinarizerPreference,
+    /// Оценивать полярность по рамке изображения: при уверенной оценке стадии
+    /// с нужной полярностью идут первыми, а противоположные пропускаются для
+    /// обычного кода. `false` — всегда пробовать обе полярности в порядке `strategy_order`.
+    pub detect_polarity: bool,
+    /// Распознаваемые форматы. Только `QrCode` — специализированный QR-ридер rxing
+    /// и rqrr; иначе `MultiFormatReader` (rqrr используется, если в наборе есть QR).
+    pub formats: HashSet<BarcodeFormat>,
+    /// Бюджет времени на один вызов `decode`: после его исчерпания
+    /// следующие стадии не запускаются (первая стадия выполняется всегда)
+    pub max_total_duration: Option<Duration>,
+}
+
+impl Default for DecoderConfig {
+    fn default() -> Self {
+        Self {
+            strategy_order: DecodeStrategy::DEFAULT_ORDER.to_vec(),
+            try_inverted: true,
+            try_preprocess: true,
+            try_padding: true,
+            rotation_angles: vec![15.0, -15.0, 30.0, -30.0, 45.0, -45.0, 60.0, -60.0, 75.0, -75.0],
+            thresholds: vec![64, 96, 128, 160, 192],
+            try_downscale: true,
+            binarizer: BinarizerPreference::default(),
+            detect_polarity: true,
+            formats: HashSet::from([BarcodeFormat::QrCode, BarcodeFormat::MicroQr]),
+            max_total_duration: None,
+        }
+    }
+}
+
+impl DecoderConfig {
+    /// Проверка конфигурации (пустой порядок или повторяющиеся стратегии)
+    pub fn validate(&self) -> Result<(), DecodeError> {
+        if self.strategy_order.is_empty() {
+            return Err(DecodeError::InvalidConfig("strategy_order is empty".to_string()));
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        for strategy in &self.strategy_order {
+            if !seen.insert(strategy) {
+                return Err(DecodeError::InvalidConfig(format!(
+                    "duplicate strategy in strategy_order: {:?}",
+                    strategy
+                )));
+            }
+        }
+
+        if self.formats.is_empty() {
+            return Err(DecodeError::InvalidConfig("formats is empty".to_string()));
+        }
+
+        if let Some(angle) = self.rotation_angles.iter().find(|a| !a.is_finite()) {
+            return Err(DecodeError::InvalidConfig(format!("invalid rotation angle: {}", angle)));
+        }
+
+        Ok(())
+    }
+
+    /// Стратегия разрешена флагами конфигурации
+    fn allows(&self, strategy: DecodeStrategy) -> bool {
+        if strategy.needs_inversion() && !self.try_inverted {
+            return false;
+        }
+        match strategy {
+            DecodeStrategy::Preprocessed | DecodeStrategy::PreprocessedInverted => self.try_preprocess,
+            DecodeStrategy::Padded | DecodeStrategy::PaddedInverted => self.try_padding,
+            DecodeStrategy::Rotation => !self.rotation_angles.is_empty(),
+            DecodeStrategy::MultiThreshold => !self.thresholds.is_empty(),
+            DecodeStrategy::Downscale => self.try_downscale,
+            DecodeStrategy::Standard | DecodeStrategy::Inverted => true,
+        }
+    }
+}
+
+/// Отчёт о прохождении каскада (`QRDecoder::decode_with_report`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecodeReport {
+    /// Запущенные стадии в порядке выполнения (`DecodeStrategy::name`)
+    pub stages_tried: Vec<&'static str>,
+    /// Стадия, давшая результат (`None` при ошибке)
+    pub winning_stage: Option<&'static str>,
+    /// Общее время каскада
+    pub elapsed: Duration,
+}
+
+/// Ограничение времени одного вызова `decode`
+struct Deadline {
+    start: Instant,
+    budget: Option<Duration>,
+}
+
+impl Deadline {
+    fn new(budget: Option<Duration>) -> Self {
+        Self {
+            start: Instant::now(),
+            budget,
+        }
+    }
+
+    fn expired(&self) -> bool {
+        self.budget.is_some_and(|budget| self.start.elapsed() >= budget)
+    }
+}
+
+/// Промежуточные изображения, общие для нескольких стадий одного вызова `decode`
+#[derive(Default)]
+struct StageCache {
+    inverted: OnceCell<GrayImage>,
+    preprocessed: OnceCell<GrayImage>,
+    downscaled: OnceCell<GrayImage>,
+    /// Гистограмма исходного изображения (Otsu и растяжение контраста)
+    histogram: OnceCell<[u32; 256]>,
+}
+
+impl StageCache {
+    fn histogram<C: LumaBuffer>(&self, img: &LumaImage<C>) -> &[u32; 256] {
+        self.histogram.get_or_init(|| histogram(img))
+    }
+
+    fn downscaled(&self, f: impl FnOnce() -> GrayImage) -> &GrayImage {
+        self.downscaled.get_or_init(f)
+    }
+
+    fn inverted(&self, f: impl FnOnce() -> GrayImage) -> &GrayImage {
+        self.inverted.get_or_init(f)
+    }
+
+    fn preprocessed(&self, f: impl FnOnce() -> GrayImage) -> &GrayImage {
+        self.preprocessed.get_or_init(f)
+    }
 }
 
 /// Декодер QR-кодов с fallback
 pub struct QRDecoder {
-    /// Попробовать инвертированное изображение
-    try_inverted: bool,
+    /// Конфигурация каскада
+    config: DecoderConfig,
 }
 
 impl Default for QRDecoder {
@@ -64,183 +678,307 @@ impl QRDecoder {
     /// Создание декодера
     pub fn new() -> Self {
         Self {
-            try_inverted: true,
+            config: DecoderConfig::default(),
         }
     }
+
+    /// Создание декодера с пользовательской конфигурацией
+    pub fn with_config(config: DecoderConfig) -> Result<Self, DecodeError> {
+        config.validate()?;
+        Ok(Self { config })
+    }
+
+    /// Текущая конфигурация
+    pub fn config(&self) -> &DecoderConfig {
+        &self.config
+    }
     
+    /// Декодирование с ридером одного формата (например, по классу ML-детектора)
+    pub fn decode_format(&self, img: &GrayImage, format: BarcodeFormat) -> Result<DecodedQR, DecodeError> {
+        if self.config.formats.len() == 1 && self.config.formats.contains(&format) {
+            return self.decode(img);
+        }
+        let decoder = Self {
+            config: DecoderConfig {
+                formats: HashSet::from([format]),
+                ..self.config.clone()
+            },
+        };
+        decoder.decode(img)
+    }
+
     /// Декодирование QR-кода
+    ///
+    /// Проходит стратегии в порядке `DecoderConfig::strategy_order`
+    /// и возвращает первый успешный результат.
     pub fn decode(&self, img: &GrayImage) -> Result<DecodedQR, DecodeError> {
-        // 1. Стандартное декодирование
-        // Пробуем rqrr сначала (более стабилен для WASM)
-        if let Ok(result) = self.decode_with_rqrr(img) {
-            return Ok(result);
-        }
+        self.decode_with_report(img).0
+    }
 
-        // Пробуем rxing. В V14 мы убираем ограничение strong_hint для GlobalHistogram,
-        // чтобы вернуть максимальную надежность. Всегда пробуем все методы.
-        if let Ok(result) = self.decode_with_rxing(img, true) {
-            return Ok(result);
-        }
-        
-        // 2. Инвертированное изображение
-        if self.try_inverted {
-            log::info!("FALLBACK: Trying inverted image...");
-            let inverted = self.invert_image(img);
-            
-            if let Ok(result) = self.decode_with_rqrr(&inverted) {
-                return Ok(result);
+    /// Декодирование с отчётом: какие стадии запускались, какая сработала и сколько времени заняло
+    pub fn decode_with_report(&self, img: &GrayImage) -> (Result<DecodedQR, DecodeError>, DecodeReport) {
+        self.run_cascade(img, None)
+    }
+
+    /// Декодирование кода с известным наклоном `tilt` (градусы по часовой стрелке,
+    /// например `DetectedQR::tilt`): стадия Rotation сначала пробует точный обратный поворот
+    pub fn decode_tilted(&self, img: &GrayImage, tilt: f32) -> Result<DecodedQR, DecodeError> {
+        self.run_cascade(img, Some(tilt)).0
+    }
+
+    fn run_cascade<C: LumaBuffer>(&self, img: &LumaImage<C>, tilt: Option<f32>) -> (Result<DecodedQR, DecodeError>, DecodeReport) {
+        let cache = StageCache::default();
+        let deadline = Deadline::new(self.config.max_total_duration);
+        let mut report = DecodeReport::default();
+        let result = self.run_stages(img, tilt, &cache, &deadline, &mut report);
+        report.elapsed = deadline.start.elapsed();
+        (result, report)
+    }
+
+    /// Проход каскада с записью стадий в `report`
+    fn run_stages<C: LumaBuffer>(
+        &self,
+        img: &LumaImage<C>,
+        tilt: Option<f32>,
+        cache: &StageCache,
+        deadline: &Deadline,
+        report: &mut DecodeReport,
+    ) -> Result<DecodedQR, DecodeError> {
+        // `NotFound` — только если ни одна стадия не увидела структуру кода
+        let mut error = DecodeError::NotFound;
+
+        for strategy in self.stage_order(img) {
+            report.stages_tried.push(strategy.name());
+            match self.try_strategy(strategy, img, tilt, cache, deadline) {
+                Ok(result) => {
+                    log::info!(
+                        "SUCCESS: stage {} after {} stage(s), {:?}",
+                        strategy.name(),
+                        report.stages_tried.len(),
+                        deadline.start.elapsed()
+                    );
+                    report.winning_stage = Some(strategy.name());
+                    return Ok(result);
+                }
+                Err(e) => error = error.most_informative(e),
             }
-            if let Ok(result) = self.decode_with_rxing(&inverted, true) {
-                return Ok(result);
+            // Первая разрешённая стадия выполняется всегда, бюджет ограничивает fallback'и
+            if deadline.expired() {
+                log::info!("Decode time budget exhausted after {:?}", strategy);
+                break;
             }
         }
 
-        // 3. Улучшенное изображение (Контраст + Резкость)
-        log::info!("FALLBACK: Standard/Inverted failed. Trying Advanced Preprocessing (Contrast + Sharpen)...");
-        let preprocessed = self.preprocess_image(img);
+        Err(error)
+    }
 
-        // a) Standard Preprocessed
-        if let Ok(result) = self.decode_with_rqrr(&preprocessed) {
-            log::info!("SUCCESS: Advanced Preprocessing + RQRR worked!");
-            return Ok(result);
-        }
-        if let Ok(result) = self.decode_with_rxing(&preprocessed, true) {
-            log::info!("SUCCESS: Advanced Preprocessing + RXING worked!");
-            return Ok(result);
-        }
+    /// Разрешённые стадии каскада с учётом оценки полярности `img`.
+    ///
+    /// Для светлого кода на тёмном фоне инвертированная пара каждой стадии
+    /// (Standard/Inverted, Preprocessed/…, Padded/…) выполняется первой, для
+    /// обычного кода инвертированные стадии пропускаются; при неоднозначной
+    /// оценке порядок `strategy_order` не меняется.
+    fn stage_order<C: LumaBuffer>(&self, img: &LumaImage<C>) -> Vec<DecodeStrategy> {
+        let allowed = self.config.strategy_order.iter().copied().filter(|&s| self.config.allows(s));
+        let polarity = if self.config.detect_polarity && self.config.try_inverted {
+            estimate_polarity(img)
+        } else {
+            Polarity::Ambiguous
+        };
 
-        // b) Inverted Preprocessed
-        if self.try_inverted {
-            log::info!("FALLBACK: Trying Preprocessed + Inverted...");
-            let prep_inverted = self.invert_image(&preprocessed);
-            
-            if let Ok(result) = self.decode_with_rqrr(&prep_inverted) {
-                log::info!("SUCCESS: Preprocessed+Inverted + RQRR worked!");
-                return Ok(result);
-            }
-            if let Ok(result) = self.decode_with_rxing(&prep_inverted, true) {
-                log::info!("SUCCESS: Preprocessed+Inverted + RXING worked!");
-                return Ok(result);
+        match polarity {
+            Polarity::Ambiguous => allowed.collect(),
+            Polarity::Normal => allowed.filter(|s| !s.needs_inversion()).collect(),
+            Polarity::Inverted => {
+                let allowed: Vec<_> = allowed.collect();
+                let mut order = Vec::with_capacity(allowed.len());
+                for &strategy in &allowed {
+                    if order.contains(&strategy) {
+                        continue;
+                    }
+                    // Пара с инверсией ставится перед своей обычной стадией
+                    match strategy.opposite_polarity() {
+                        Some(inverted)
+                            if !strategy.needs_inversion() && allowed.contains(&inverted) && !order.contains(&inverted) =>
+                        {
+                            order.push(inverted);
+                        }
+                        _ => {}
+                    }
+                    order.push(strategy);
+                }
+                order
             }
         }
+    }
 
-        // 4. Add Padding Fallback (V17 - Quiet Zone Restoration)
-        // Если изображение обрезано слишком близко к QR-коду (особенно при повороте),
-        // добавляем белую рамку (Quiet Zone).
-        log::info!("FALLBACK: Trying Padding (Quiet Zone Restoration)...");
-        let padded = self.add_white_padding(img, 20); // 20px padding
-        if let Ok(result) = self.decode_with_rqrr(&padded) {
-            log::info!("SUCCESS: Padding + RQRR worked!");
-            return Ok(result);
-        }
-        if let Ok(result) = self.decode_with_rxing(&padded, true) {
-            log::info!("SUCCESS: Padding + RXING worked!");
-            return Ok(result);
-        }
-
-        // Также пробуем инвертированный вариант с padding (на случай черного фона)
-        if self.try_inverted {
-            log::info!("FALLBACK: Trying Padding + Inverted...");
-            // Инвертируем СНАЧАЛА, потом добавляем паддинг (чтобы был белый фон вокруг инвертированного QR)
-            // Но если QR был "белый на черном", то после инверсии он стал "черный на белом".
-            // Значит паддинг должен быть БЕЛЫМ.
-            let inverted = self.invert_image(img);
-            let padded_inverted = self.add_white_padding(&inverted, 20);
-            
-            if let Ok(result) = self.decode_with_rqrr(&padded_inverted) {
-                log::info!("SUCCESS: Padding + Inverted + RQRR worked!");
-                return Ok(result);
-            }
-            if let Ok(result) = self.decode_with_rxing(&padded_inverted, true) {
-                log::info!("SUCCESS: Padding + Inverted + RXING worked!");
-                return Ok(result);
+    /// Попытка декодирования одной стратегией из каскада
+    fn try_strategy<C: LumaBuffer>(
+        &self,
+        strategy: DecodeStrategy,
+        img: &LumaImage<C>,
+        tilt: Option<f32>,
+        cache: &StageCache,
+        deadline: &Deadline,
+    ) -> Result<DecodedQR, DecodeError> {
+        match strategy {
+            // 1. Стандартное декодирование
+            DecodeStrategy::Standard => {
+                // Пробуем rqrr сначала (более стабилен для WASM), затем rxing.
+                // В V14 мы убираем ограничение strong_hint для GlobalHistogram,
+                // чтобы вернуть максимальную надежность. Всегда пробуем все методы.
+                self.decode_with_both(img, "Standard")
             }
-        }
 
+            // 2. Инвертированное изображение
+            DecodeStrategy::Inverted => {
+                log::info!("FALLBACK: Trying inverted image...");
+                let inverted = cache.inverted(|| self.invert_image(img));
+                self.decode_with_both(inverted, "Inverted")
+            }
 
+            // 3. Улучшенное изображение (Контраст + Резкость)
+            DecodeStrategy::Preprocessed => {
+                log::info!("FALLBACK: Trying Advanced Preprocessing (Contrast + Sharpen)...");
+                let preprocessed = cache.preprocessed(|| self.preprocess_image(img, cache.histogram(img)));
+                self.decode_with_both(preprocessed, "Advanced Preprocessing")
+            }
 
-        // 5. Rotation Fallback (V18)
-        // Если изображение повернуто под экзотическим углом (например 45 градусов),
-        // стандартные сканеры могут не справиться. Мы поворачиваем изображение, чтобы выровнять QR.
-        // Пробуем 45, 30, 60 градусов.
-        // 5. Rotation Fallback (V18)
-        // Если изображение повернуто под экзотическим углом (например 45 градусов),
-        // стандартные сканеры могут не справиться. Мы поворачиваем изображение, чтобы выровнять QR.
-        // Пробуем 45, 30, 60 градусов (и отрицательные).
-        // 5. Rotation Fallback (V18)
-        // Если изображение повернуто под экзотическим углом (например 45 градусов),
-        // стандартные сканеры могут не справиться. Мы поворачиваем изображение, чтобы выровнять QR.
-        // Пробуем 30, 45, 60 градусов (и отрицательные).
-        log::info!("FALLBACK: Trying Rotation (±30, ±45, ±60)...");
-        let angles = [30.0, -30.0, 45.0, -45.0, 60.0, -60.0];
-        
-        for angle in angles {
-            // Rotate
-            let rotated = self.rotate_image(img, angle);
-            
-            // Sharpen the rotated image (restore edges after interpolation blur)
-            let sharpened_rotated = self.apply_sharpen(&rotated);
-            
-            // 1. Try Standard on rotated & sharpened
-            if let Ok(result) = self.decode_with_rqrr(&sharpened_rotated) {
-                log::info!("SUCCESS: Rotation ({} deg) + Sharpen + RQRR worked!", angle);
-                return Ok(result);
+            DecodeStrategy::PreprocessedInverted => {
+                log::info!("FALLBACK: Trying Preprocessed + Inverted...");
+                let preprocessed = cache.preprocessed(|| self.preprocess_image(img, cache.histogram(img)));
+                let prep_inverted = self.invert_image(preprocessed);
+                self.decode_with_both(&prep_inverted, "Preprocessed+Inverted")
             }
-            if let Ok(result) = self.decode_with_rxing(&sharpened_rotated, true) {
-                log::info!("SUCCESS: Rotation ({} deg) + Sharpen + RXING worked!", angle);
-                return Ok(result);
+
+            // 4. Add Padding Fallback (V17 - Quiet Zone Restoration)
+            // Если изображение обрезано слишком близко к QR-коду (особенно при повороте),
+            // добавляем белую рамку (Quiet Zone).
+            DecodeStrategy::Padded => {
+                log::info!("FALLBACK: Trying Padding (Quiet Zone Restoration)...");
+                let padded = self.add_white_padding(img, 20); // 20px padding
+                self.decode_with_both(&padded, "Padding").map(|r| unpad(r, 20))
             }
 
-            // 2. Try Hard Threshold (128) on rotated (Fix aliasing/gray pixels)
-            let thresholded = self.apply_threshold(&sharpened_rotated, 128);
-            if let Ok(result) = self.decode_with_rqrr(&thresholded) {
-                log::info!("SUCCESS: Rotation ({} deg) + Threshold(128) + RQRR worked!", angle);
-                return Ok(result);
+            // Также пробуем инвертированный вариант с padding (на случай черного фона)
+            DecodeStrategy::PaddedInverted => {
+                log::info!("FALLBACK: Trying Padding + Inverted...");
+                // Инвертируем СНАЧАЛА, потом добавляем паддинг: после инверсии QR
+                // стал "черный на белом", значит паддинг должен быть БЕЛЫМ.
+                let inverted = cache.inverted(|| self.invert_image(img));
+                let padded_inverted = self.add_white_padding(inverted, 20);
+                self.decode_with_both(&padded_inverted, "Padding + Inverted").map(|r| unpad(r, 20))
             }
-            if let Ok(result) = self.decode_with_rxing(&thresholded, true) {
-                log::info!("SUCCESS: Rotation ({} deg) + Threshold(128) + RXING worked!", angle);
-                return Ok(result);
+
+            // 5. Rotation Fallback (V18)
+            // Если изображение повернуто под экзотическим углом (например 45 градусов),
+            // стандартные сканеры могут не справиться. Мы поворачиваем изображение, чтобы выровнять QR.
+            // Известный наклон выпрямляется точно, затем перебираются углы из конфигурации.
+            DecodeStrategy::Rotation => {
+                let angles: Vec<f32> = tilt
+                    .and_then(tilt_correction)
+                    .into_iter()
+                    .chain(self.config.rotation_angles.iter().copied())
+                    .collect();
+                log::info!("FALLBACK: Trying Rotation {:?}...", angles);
+
+                first_success(&angles, deadline, |&angle| self.decode_rotated(img, angle))
+                    .ok_or(DecodeError::NotFound)
             }
-        }
 
-        // 6. Multi-Threshold Fallback (V16)
-        // Пробуем несколько порогов бинаризации, включая автоматический (Otsu).
-        let otsu_threshold = self.calculate_otsu_threshold(img);
-        log::info!("FALLBACK: Trying Multi-Threshold (Otsu={}, 64, 96, 128, 160, 192)...", otsu_threshold);
-        
-        let thresholds: [u8; 6] = [otsu_threshold, 64, 96, 128, 160, 192];
-        
-        for threshold in thresholds {
-            let thresholded = self.apply_threshold(img, threshold);
-            if let Ok(result) = self.decode_with_rqrr(&thresholded) {
-                log::info!("SUCCESS: Multi-Threshold ({}) + RQRR worked!", threshold);
-                return Ok(result);
+            // 6. Multi-Threshold Fallback (V16)
+            // Пробуем несколько порогов бинаризации, включая автоматический (Otsu).
+            DecodeStrategy::MultiThreshold => {
+                let otsu_threshold = otsu_from_histogram(cache.histogram(img));
+                log::info!("FALLBACK: Trying Multi-Threshold (Otsu={}, {:?})...", otsu_threshold, self.config.thresholds);
+
+                let thresholds: Vec<u8> = std::iter::once(otsu_threshold).chain(self.config.thresholds.iter().copied()).collect();
+
+                first_success(&thresholds, deadline, |&threshold| self.decode_thresholded(img, threshold))
+                    .ok_or(DecodeError::NotFound)
             }
-            if let Ok(result) = self.decode_with_rxing(&thresholded, true) {
-                log::info!("SUCCESS: Multi-Threshold ({}) + RXING worked!", threshold);
-                return Ok(result);
+
+            // 7. Downscale Fallback (V16)
+            DecodeStrategy::Downscale => {
+                if img.width() <= 400 && img.height() <= 400 {
+                    return Err(DecodeError::NotFound);
+                }
+                log::info!("FALLBACK: Trying Downscale (50%)...");
+                let downscaled = cache.downscaled(|| self.downscale_image(img, 2));
+                let upscale = |result: DecodedQR| result.map_corners(|(x, y)| (x * 2.0, y * 2.0));
+                self.decode_with_both(downscaled, "Downscale").map(upscale)
             }
         }
+    }
 
-        // 7. Downscale Fallback (V16)
-        if img.width() > 400 || img.height() > 400 {
-            log::info!("FALLBACK: Trying Downscale (50%)...");
-            let downscaled = self.downscale_image(img, 2);
-            if let Ok(result) = self.decode_with_rqrr(&downscaled) {
-                log::info!("SUCCESS: Downscale + RQRR worked!");
+    /// rqrr, затем rxing; при неудаче обоих — более информативная ошибка
+    fn decode_with_both<C: LumaBuffer>(&self, img: &LumaImage<C>, stage: &str) -> Result<DecodedQR, DecodeError> {
+        let rqrr_error = match self.decode_with_rqrr(img) {
+            Ok(result) => {
+                log::info!("SUCCESS: {} + RQRR worked!", stage);
                 return Ok(result);
             }
-            if let Ok(result) = self.decode_with_rxing(&downscaled, true) {
-                log::info!("SUCCESS: Downscale + RXING worked!");
-                return Ok(result);
+            Err(e) => e,
+        };
+        match self.decode_with_rxing(img, true) {
+            Ok(result) => {
+                log::info!("SUCCESS: {} + RXING worked!", stage);
+                Ok(result)
             }
+            Err(e) => Err(rqrr_error.most_informative(e)),
         }
-        
-        Err(DecodeError::NotFound)
+    }
+
+    /// Одна попытка стадии Rotation: поворот, резкость, затем жёсткий порог
+    fn decode_rotated<C: LumaBuffer>(&self, img: &LumaImage<C>, angle: f32) -> Option<DecodedQR> {
+        // Rotate
+        let rotated = geometry::rotate_image(img, angle);
+        let unrotate = |result: DecodedQR| {
+            result.map_corners(|p| unrotate_point(p, img.dimensions(), rotated.dimensions(), angle))
+        };
+
+        // Sharpen the rotated image (restore edges after interpolation blur)
+        let sharpened_rotated = self.apply_sharpen(&rotated);
+
+        // 1. Try Standard on rotated & sharpened
+        if let Ok(result) = self.decode_with_rqrr(&sharpened_rotated) {
+            log::info!("SUCCESS: Rotation ({} deg) + Sharpen + RQRR worked!", angle);
+            return Some(unrotate(result));
+        }
+        if let Ok(result) = self.decode_with_rxing(&sharpened_rotated, true) {
+            log::info!("SUCCESS: Rotation ({} deg) + Sharpen + RXING worked!", angle);
+            return Some(unrotate(result));
+        }
+
+        // 2. Try Hard Threshold (128) on rotated (Fix aliasing/gray pixels)
+        let thresholded = self.apply_threshold(&sharpened_rotated, 128);
+        if let Ok(result) = self.decode_with_rqrr(&thresholded) {
+            log::info!("SUCCESS: Rotation ({} deg) + Threshold(128) + RQRR worked!", angle);
+            return Some(unrotate(result));
+        }
+        if let Ok(result) = self.decode_with_rxing(&thresholded, true) {
+            log::info!("SUCCESS: Rotation ({} deg) + Threshold(128) + RXING worked!", angle);
+            return Some(unrotate(result));
+        }
+        None
+    }
+
+    /// Одна попытка стадии MultiThreshold
+    fn decode_thresholded<C: LumaBuffer>(&self, img: &LumaImage<C>, threshold: u8) -> Option<DecodedQR> {
+        let thresholded = self.apply_threshold(img, threshold);
+        if let Ok(result) = self.decode_with_rqrr(&thresholded) {
+            log::info!("SUCCESS: Multi-Threshold ({}) + RQRR worked!", threshold);
+            return Some(result);
+        }
+        if let Ok(result) = self.decode_with_rxing(&thresholded, true) {
+            log::info!("SUCCESS: Multi-Threshold ({}) + RXING worked!", threshold);
+            return Some(result);
+        }
+        None
     }
 
     /// Добавляет белую рамку вокруг изображения
-    fn add_white_padding(&self, img: &GrayImage, padding: u32) -> GrayImage {
+    fn add_white_padding<C: LumaBuffer>(&self, img: &LumaImage<C>, padding: u32) -> GrayImage {
         let (width, height) = img.dimensions();
         let new_width = width + padding * 2;
         let new_height = height + padding * 2;
@@ -254,111 +992,31 @@ impl QRDecoder {
         padded
     }
 
-
-    // ... (skipping to function definition)
-
-    /// Поворачивает изображение на заданный угол (в градусах) с изменением размера холста.
-    /// Использует билинейную интерполяцию (Bilinear Interpolation) для улучшения качества.
-    fn rotate_image(&self, img: &GrayImage, angle_degrees: f32) -> GrayImage {
-        let (w, h) = img.dimensions();
-        let rad = angle_degrees.to_radians();
-        let cos_a = rad.cos();
-        let sin_a = rad.sin();
-        
-        // Calculate new dimensions (Bounding Box)
-        let new_w = (w as f32 * cos_a.abs() + h as f32 * sin_a.abs()).ceil() as u32;
-        let new_h = (w as f32 * sin_a.abs() + h as f32 * cos_a.abs()).ceil() as u32;
-        
-        // Centers
-        let cx = w as f32 / 2.0;
-        let cy = h as f32 / 2.0;
-        let new_cx = new_w as f32 / 2.0;
-        let new_cy = new_h as f32 / 2.0;
-        
-        // Create new white image (Quiet Zone)
-        let mut new_img = GrayImage::from_pixel(new_w, new_h, image::Luma([255]));
-
-        // Fast access to raw buffer (unsafe for speed, but staying safe for now)
-        // Using get_pixel is slow but safe.
-        
-        for y in 0..new_h {
-            for x in 0..new_w {
-                // Shift to center
-                let dx = x as f32 - new_cx;
-                let dy = y as f32 - new_cy;
-                
-                // Inverse transform (Destination -> Source)
-                let src_x = dx * cos_a + dy * sin_a + cx;
-                let src_y = -dx * sin_a + dy * cos_a + cy;
-                
-                // Bilinear Interpolation
-                if src_x >= 0.0 && src_x < (w as f32 - 1.0) && src_y >= 0.0 && src_y < (h as f32 - 1.0) {
-                    let x0 = src_x.floor() as u32;
-                    let y0 = src_y.floor() as u32;
-                    let x1 = x0 + 1;
-                    let y1 = y0 + 1;
-                    
-                    let wx = src_x - x0 as f32; // weight x
-                    let wy = src_y - y0 as f32; // weight y
-                    
-                    // Safety check for x1, y1 (though bounds check above should handle it)
-                    if x1 < w && y1 < h {
-                         let p00 = img.get_pixel(x0, y0).0[0] as f32;
-                         let p10 = img.get_pixel(x1, y0).0[0] as f32;
-                         let p01 = img.get_pixel(x0, y1).0[0] as f32;
-                         let p11 = img.get_pixel(x1, y1).0[0] as f32;
-                         
-                         // Interpolate X
-                         let top = p00 * (1.0 - wx) + p10 * wx;
-                         let bottom = p01 * (1.0 - wx) + p11 * wx;
-                         
-                         // Interpolate Y
-                         let final_val = top * (1.0 - wy) + bottom * wy;
-                         
-                         new_img.put_pixel(x, y, image::Luma([final_val as u8]));
-                    }
-                }
-            }
-        }
-        new_img
-    }
-
     /// Предобработка: Растяжение контраста + Повышение резкости
-    fn preprocess_image(&self, img: &GrayImage) -> GrayImage {
+    fn preprocess_image<C: LumaBuffer>(&self, img: &LumaImage<C>, histogram: &[u32; 256]) -> GrayImage {
         // 1. Растяжение контраста (нормализация гистограммы)
-        let contrast_img = self.apply_contrast_stretch(img);
+        let contrast_img = self.apply_contrast_stretch(img, histogram);
 
         // 2. Повышение резкости (Sharpening)
         // Используем стандартный 3x3 фильтр для выделения краев модулей QR кода
         self.apply_sharpen(&contrast_img)
     }
 
-    fn apply_contrast_stretch(&self, img: &GrayImage) -> GrayImage {
-        let mut min_val = 255u8;
-        let mut max_val = 0u8;
-
-        for p in img.pixels() {
-            let val = p.0[0];
-            if val < min_val { min_val = val; }
-            if val > max_val { max_val = val; }
-        }
-
+    /// Растяжение `[min, max]` гистограммы на весь диапазон через таблицу значений
+    fn apply_contrast_stretch<C: LumaBuffer>(&self, img: &LumaImage<C>, histogram: &[u32; 256]) -> GrayImage {
+        let Some(min_val) = histogram.iter().position(|&count| count > 0) else {
+            return to_gray(img);
+        };
+        let max_val = histogram.iter().rposition(|&count| count > 0).unwrap_or(min_val);
         if min_val >= max_val {
-            return img.clone();
+            return to_gray(img);
         }
 
-        let (width, height) = img.dimensions();
-        let mut result = GrayImage::new(width, height);
-        
         let range = (max_val - min_val) as f32;
-
-        for (x, y, p) in img.enumerate_pixels() {
-            let val = p.0[0];
-            // (val - min) / (max - min) * 255
-            let new_val = ((val as f32 - min_val as f32) / range * 255.0) as u8;
-            result.put_pixel(x, y, image::Luma([new_val]));
-        }
-        result
+        // (val - min) / (max - min) * 255; значения вне [min, max] в изображении не встречаются
+        let lut: [u8; 256] =
+            std::array::from_fn(|val| ((val as f32 - min_val as f32) / range * 255.0) as u8);
+        map_pixels(img, |val| lut[val as usize])
     }
 
     fn apply_sharpen(&self, img: &GrayImage) -> GrayImage {
@@ -380,7 +1038,7 @@ impl QRDecoder {
                         - (img.get_pixel(x-1, y).0[0] as i32)
                         - (img.get_pixel(x+1, y).0[0] as i32);
                 
-                let clamped = val.max(0).min(255) as u8;
+                let clamped = val.clamp(0, 255) as u8;
                 result.put_pixel(x, y, image::Luma([clamped]));
             }
         }
@@ -388,62 +1046,12 @@ impl QRDecoder {
     }
 
     /// Жесткая бинаризация по порогу
-    fn apply_threshold(&self, img: &GrayImage, threshold: u8) -> GrayImage {
-        let (width, height) = img.dimensions();
-        let mut result = GrayImage::new(width, height);
-        
-        for (x, y, p) in img.enumerate_pixels() {
-            let val = if p.0[0] < threshold { 0 } else { 255 };
-            result.put_pixel(x, y, image::Luma([val]));
-        }
-        result
-    }
-
-    /// Вычисление порога по методу Otsu (минимизация внутриклассовой дисперсии)
-    fn calculate_otsu_threshold(&self, img: &GrayImage) -> u8 {
-        // Строим гистограмму
-        let mut histogram = [0u32; 256];
-        let total_pixels = (img.width() * img.height()) as f64;
-        
-        for p in img.pixels() {
-            histogram[p.0[0] as usize] += 1;
-        }
-
-        let mut sum: f64 = 0.0;
-        for (i, &count) in histogram.iter().enumerate() {
-            sum += i as f64 * count as f64;
-        }
-
-        let mut sum_b: f64 = 0.0;
-        let mut w_b: f64 = 0.0;
-        let mut max_variance: f64 = 0.0;
-        let mut threshold: u8 = 128; // Default fallback
-
-        for (t, &count) in histogram.iter().enumerate() {
-            w_b += count as f64;
-            if w_b == 0.0 { continue; }
-            
-            let w_f = total_pixels - w_b;
-            if w_f == 0.0 { break; }
-
-            sum_b += t as f64 * count as f64;
-            
-            let m_b = sum_b / w_b;
-            let m_f = (sum - sum_b) / w_f;
-            
-            let variance = w_b * w_f * (m_b - m_f) * (m_b - m_f);
-            
-            if variance > max_variance {
-                max_variance = variance;
-                threshold = t as u8;
-            }
-        }
-        
-        threshold
+    fn apply_threshold<C: LumaBuffer>(&self, img: &LumaImage<C>, threshold: u8) -> GrayImage {
+        map_pixels(img, |val| if val < threshold { 0 } else { 255 })
     }
 
     /// Уменьшение изображения в заданное число раз (простое усреднение)
-    fn downscale_image(&self, img: &GrayImage, factor: u32) -> GrayImage {
+    fn downscale_image<C: LumaBuffer>(&self, img: &LumaImage<C>, factor: u32) -> GrayImage {
         let new_width = img.width() / factor;
         let new_height = img.height() / factor;
         let mut result = GrayImage::new(new_width, new_height);
@@ -464,97 +1072,111 @@ impl QRDecoder {
     }
     
     /// Декодирование через rxing
-    fn decode_with_rxing(&self, img: &GrayImage, try_fallback: bool) -> Result<DecodedQR, DecodeError> {
+    fn decode_with_rxing<C: LumaBuffer>(&self, img: &LumaImage<C>, try_fallback: bool) -> Result<DecodedQR, DecodeError> {
+        let formats = &self.config.formats;
+        if !formats.iter().all(|f| f.is_qr_family()) {
+            let formats: Vec<BarcodeFormat> = formats.iter().copied().collect();
+            return self.decode_with_reader(MultiFormatReader::default(), img, try_fallback, &formats);
+        }
+
+        // Обычный QR — специализированным ридером, Micro QR и rMQR — ридером
+        // с поиском одиночного finder-паттерна
+        let mut error = DecodeError::NotFound;
+        if formats.contains(&BarcodeFormat::QrCode) {
+            match self.decode_with_reader(QRCodeReader::new(), img, try_fallback, &[BarcodeFormat::QrCode]) {
+                Ok(result) => return Ok(result),
+                Err(e) => error = e,
+            }
+        }
+        let micro: Vec<BarcodeFormat> = formats.iter().copied().filter(|&f| f != BarcodeFormat::QrCode).collect();
+        if micro.is_empty() {
+            return Err(error);
+        }
+        self.decode_with_reader(QrReader, img, try_fallback, &micro)
+            .map_err(|e| error.most_informative(e))
+    }
+
+    /// Декодирование конкретным ридером rxing с бинаризаторами из `DecoderConfig::binarizer`
+    fn decode_with_reader<R: Reader, C: LumaBuffer>(
+        &self,
+        mut reader: R,
+        img: &LumaImage<C>,
+        try_fallback: bool,
+        formats: &[BarcodeFormat],
+    ) -> Result<DecodedQR, DecodeError> {
         log::info!("RXING: Starting decode on {}x{} image", img.width(), img.height());
+        // Минимум MIN_QR_SIDE — только для 2D-символов; полоске с 1D-штрихкодом он не нужен
+        let linear: Vec<BarcodeFormat>;
+        let formats = if fits_qr(img) {
+            formats
+        } else {
+            linear = formats.iter().copied().filter(|f| f.is_linear()).collect();
+            &linear[..]
+        };
+        if formats.is_empty() || img.width() == 0 || img.height() == 0 {
+            return Err(DecodeError::NotFound);
+        }
         let (width, height) = img.dimensions();
         
-        // Конвертируем grayscale в packed ARGB u32 формат для rxing
-        // Формат: 0xAARRGGBB
-        let pixels: Vec<u32> = img.as_raw()
-            .iter()
-            .map(|&gray| {
-                let g = gray as u32;
-                0xFF000000 | (g << 16) | (g << 8) | g  // ARGB with gray repeated
-            })
-            .collect();
-        
-        let luminance_source = rxing::RGBLuminanceSource::new_with_width_height_pixels(
-            width as usize,
-            height as usize,
-            &pixels,
-        );
-        
-        let mut bitmap = rxing::BinaryBitmap::new(rxing::common::HybridBinarizer::new(luminance_source));
-        
         let mut hints = DecodingHintDictionary::new();
         hints.insert(
             rxing::DecodeHintType::POSSIBLE_FORMATS,
-            rxing::DecodeHintValue::PossibleFormats(std::collections::HashSet::from([
-                BarcodeFormat::QR_CODE,
-            ])),
+            rxing::DecodeHintValue::PossibleFormats(formats.iter().map(|f| f.to_rxing()).collect()),
         );
         // TryHarder is now safe with chrono + wasmbind
         hints.insert(
             rxing::DecodeHintType::TRY_HARDER,
             rxing::DecodeHintValue::TryHarder(true),
         );
-        
-        let mut reader = QRCodeReader::new();
-        
-        // Попытка 1: HybridBinarizer (стандарт)
-        match reader.decode_with_hints(&mut bitmap, &hints) {
-            Ok(result) => {
-                log::info!("RXING: Decode success (HybridBinarizer)!");
-                return Ok(DecodedQR {
-                    content: result.getText().to_string(),
-                    error_correction: ErrorCorrectionLevel::Unknown,
-                    version: None,
-                    encoding: format!("{:?}", result.getBarcodeFormat()),
-                });
-            }
-            Err(_) => {
-                // Ignore error
-            }
-        }
 
-        // Попытка 2: GlobalHistogramBinarizer (только если есть сильный сигнал)
-        if try_fallback {
-            log::info!("RXING: HybridBinarizer failed, trying GlobalHistogramBinarizer (strong hint)");
-            
-            let luminance_source_global = rxing::RGBLuminanceSource::new_with_width_height_pixels(
-                width as usize,
-                height as usize,
-                &pixels,
-            );
-            let mut bitmap_global = rxing::BinaryBitmap::new(rxing::common::GlobalHistogramBinarizer::new(luminance_source_global));
-    
-            match reader.decode_with_hints(&mut bitmap_global, &hints) {
+        // Яркость передаётся в rxing как есть, без упаковки в ARGB u32
+        let source = || rxing::Luma8LuminanceSource::new(img.as_raw().to_vec(), width, height);
+        let decode_global = |reader: &mut R| {
+            let mut bitmap = rxing::BinaryBitmap::new(rxing::common::GlobalHistogramBinarizer::new(source()));
+            match reader.decode_with_hints(&mut bitmap, &hints) {
                 Ok(result) => {
                     log::info!("RXING: Decode success (GlobalHistogramBinarizer)!");
-                    return Ok(DecodedQR {
-                        content: result.getText().to_string(),
-                        error_correction: ErrorCorrectionLevel::Unknown,
-                        version: None,
-                        encoding: format!("{:?}", result.getBarcodeFormat()),
-                    });
+                    Ok(decoded_from_rxing(&result))
                 }
                 Err(e) => {
                     log::info!("RXING: GlobalHistogram failed: {}", e);
+                    Err(DecodeError::from_rxing(e))
                 }
             }
-        } else {
-             log::info!("RXING: Not found (HybridBinarizer)");
-             return Err(DecodeError::NotFound);
+        };
+
+        // Только GlobalHistogram: быстрый режим для чистых скриншотов
+        if self.config.binarizer == BinarizerPreference::GlobalOnly {
+            return decode_global(&mut reader);
         }
-        
-        log::info!("RXING: Not found");
-        Err(DecodeError::NotFound)
+
+        // Попытка 1: HybridBinarizer (стандарт)
+        let mut bitmap = rxing::BinaryBitmap::new(rxing::common::HybridBinarizer::new(source()));
+        let hybrid_error = match reader.decode_with_hints(&mut bitmap, &hints) {
+            Ok(result) => {
+                log::info!("RXING: Decode success (HybridBinarizer)!");
+                return Ok(decoded_from_rxing(&result));
+            }
+            Err(e) => DecodeError::from_rxing(e),
+        };
+
+        // Попытка 2: GlobalHistogramBinarizer (только если есть сильный сигнал)
+        if try_fallback && self.config.binarizer == BinarizerPreference::HybridThenGlobal {
+            log::info!("RXING: HybridBinarizer failed, trying GlobalHistogramBinarizer (strong hint)");
+            return decode_global(&mut reader).map_err(|e| hybrid_error.most_informative(e));
+        }
+
+        log::info!("RXING: Failed (HybridBinarizer): {}", hybrid_error);
+        Err(hybrid_error)
     }
     
     /// Декодирование через rqrr (fallback)
-    fn decode_with_rqrr(&self, img: &GrayImage) -> Result<DecodedQR, DecodeError> {
+    fn decode_with_rqrr<C: LumaBuffer>(&self, img: &LumaImage<C>) -> Result<DecodedQR, DecodeError> {
         log::info!("RQRR: Starting detection on {}x{} image", img.width(), img.height());
-        let mut prepared = rqrr::PreparedImage::prepare(img.clone());
+        if !fits_qr(img) || !self.config.formats.contains(&BarcodeFormat::QrCode) {
+            return Err(DecodeError::NotFound);
+        }
+        let mut prepared = rqrr::PreparedImage::prepare(to_gray(img));
         let grids = prepared.detect_grids();
         log::info!("RQRR: Detected {} grids", grids.len());
         
@@ -564,10 +1186,15 @@ impl QRDecoder {
         }
         
         // Берём первый найденный QR
-        let grid = &grids[0];
-        
-        match grid.decode() {
-            Ok((meta, content)) => {
+        Self::decode_grid(&grids[0])
+    }
+
+    /// Декодирование одной сетки rqrr
+    fn decode_grid<G: rqrr::BitGrid>(grid: &rqrr::Grid<G>) -> Result<DecodedQR, DecodeError> {
+        // `Grid::decode` требует UTF-8, поэтому берём сырые байты и перекодируем сами
+        let mut raw_bytes = Vec::new();
+        match grid.decode_to(&mut raw_bytes) {
+            Ok(meta) => {
                 log::info!("RQRR: Decode success!");
                 let error_correction = match meta.ecc_level {
                     0 => ErrorCorrectionLevel::L,
@@ -576,48 +1203,1116 @@ impl QRDecoder {
                     3 => ErrorCorrectionLevel::H,
                     _ => ErrorCorrectionLevel::Unknown,
                 };
+                let corrected = corrected_data_codewords(&grid.grid);
                 
-                Ok(DecodedQR {
-                    content,
+                let mut result = DecodedQR {
+                    content: String::new(),
                     error_correction,
                     version: Some(meta.version.0 as u8),
+                    format: BarcodeFormat::QrCode,
                     encoding: "Byte".to_string(),
-                })
+                    errors_corrected: corrected.as_ref().and_then(|(_, count)| u16::try_from(*count).ok()),
+                    corners: Some(rqrr_corners(grid)),
+                    structured_append: None,
+                    raw_bytes,
+                    eci: corrected.as_ref().and_then(|(data, _)| leading_eci(data)),
+                };
+                result.content = result.decode_text(None)?;
+                Ok(result)
             }
             Err(e) => {
                 log::info!("RQRR: Decode failed: {:?}", e);
-                Err(DecodeError::DecodeFailed(format!("{:?}", e)))
+                Err(DecodeError::from_rqrr(e))
             },
         }
     }
+
+    /// Декодирование всех QR-кодов на изображении
+    ///
+    /// В отличие от `decode`, не останавливается на первой сетке rqrr:
+    /// возвращает все успешно декодированные коды (пустой вектор, если ни одного).
+    /// Если на исходном изображении ничего не найдено, пробуется инверсия.
+    pub fn decode_all(&self, img: &GrayImage) -> Vec<DecodedQR> {
+        let found = self.decode_all_grids(img);
+        if !found.is_empty() || !self.config.try_inverted {
+            return found;
+        }
+        self.decode_all_grids(&self.invert_image(img))
+    }
+
+    /// Декодирование каждой сетки, найденной rqrr
+    fn decode_all_grids(&self, img: &GrayImage) -> Vec<DecodedQR> {
+        if !fits_qr(img) || !self.config.formats.contains(&BarcodeFormat::QrCode) {
+            return Vec::new();
+        }
+        let mut prepared = rqrr::PreparedImage::prepare(img.clone());
+        let grids = prepared.detect_grids();
+        log::info!("RQRR: decode_all found {} grids", grids.len());
+
+        grids
+            .iter()
+            .filter_map(|grid| Self::decode_grid(grid).ok())
+            .collect()
+    }
     
-    /// Пакетное декодирование
+    /// Чтение только метаданных (версия, уровень коррекции, маска) без извлечения данных.
+    ///
+    /// Выполняет поиск сетки и чтение format/version information, но не декодирует
+    /// содержимое — работает и для повреждённых/зашифрованных полезных нагрузок.
+    pub fn decode_metadata(&self, img: &GrayImage) -> Result<QrMetadata, DecodeError> {
+        let result = self.read_metadata(img);
+        if result.is_ok() || !self.config.try_inverted {
+            return result;
+        }
+
+        log::info!("METADATA: Trying inverted image...");
+        match self.read_metadata(&self.invert_image(img)) {
+            Ok(meta) => Ok(meta),
+            // Сохраняем более информативную ошибку
+            Err(DecodeError::NotFound) => result,
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Матрица модулей первого найденного кода: `matrix[y][x]`, начало — верхний левый
+    /// угол кода (finder-паттерн без пары снизу справа), `true` — тёмный модуль.
+    ///
+    /// Модули считываются из изображения как есть, до снятия маски данных и без
+    /// исправления ошибок; quiet zone в матрицу не входит. Если на исходном
+    /// изображении сетка не найдена, пробуется инверсия (при `try_inverted`).
+    pub fn extract_matrix(&self, img: &GrayImage) -> Option<Vec<Vec<bool>>> {
+        let matrix = self.read_matrix(img);
+        if matrix.is_some() || !self.config.try_inverted {
+            return matrix;
+        }
+        self.read_matrix(&self.invert_image(img))
+    }
+
+    fn read_matrix(&self, img: &GrayImage) -> Option<Vec<Vec<bool>>> {
+        if !fits_qr(img) {
+            return None;
+        }
+        let mut prepared = rqrr::PreparedImage::prepare(img.clone());
+        let grids = prepared.detect_grids();
+        let grid = &grids.first()?.grid;
+        let size = rqrr::BitGrid::size(grid);
+        Some(
+            (0..size)
+                .map(|y| (0..size).map(|x| rqrr::BitGrid::bit(grid, y, x)).collect())
+                .collect(),
+        )
+    }
+
+    /// Чтение метаданных первой найденной сетки
+    fn read_metadata(&self, img: &GrayImage) -> Result<QrMetadata, DecodeError> {
+        let mut prepared = rqrr::PreparedImage::prepare(img.clone());
+        let grids = prepared.detect_grids();
+        let grid = grids.first().ok_or(DecodeError::NotFound)?;
+
+        let bits = grid_to_bit_matrix(&grid.grid)
+            .ok_or_else(|| DecodeError::DecodeFailed("invalid grid size".to_string()))?;
+        let module_count = bits.getWidth();
+        let mut parser = rxing::qrcode::decoder::BitMatrixParser::new(bits)
+            .map_err(|e| DecodeError::DecodeFailed(e.to_string()))?;
+        let version = parser
+            .readVersion()
+            .map_err(|e| DecodeError::DecodeFailed(format!("version information: {}", e)))?
+            .getVersionNumber();
+        let format = parser
+            .readFormatInformation()
+            .map_err(|e| DecodeError::DecodeFailed(format!("format information: {}", e)))?;
+
+        Ok(QrMetadata {
+            version: version as u8,
+            ecc: ErrorCorrectionLevel::from_rxing(format.getErrorCorrectionLevel()),
+            mask: format.getDataMask(),
+            module_count,
+        })
+    }
+
+    /// Декодирование из буфера яркости (по байту на пиксель, построчно).
+    /// Буфер не копируется: каскад читает срез напрямую.
+    pub fn decode_luma<'a>(&self, width: u32, height: u32, luma: impl Into<Cow<'a, [u8]>>) -> Result<DecodedQR, DecodeError> {
+        check_dimensions(width, height)?;
+        let luma = luma.into();
+        let expected = (width as usize)
+            .checked_mul(height as usize)
+            .ok_or_else(|| DecodeError::InvalidImage(format!("image {}x{} is too large", width, height)))?;
+        if luma.len() != expected {
+            return Err(DecodeError::InvalidImage(format!(
+                "luma buffer has {} bytes, expected {} for {}x{}",
+                luma.len(),
+                expected,
+                width,
+                height
+            )));
+        }
+        let img = LumaImage::from_raw(width, height, luma)
+            .ok_or_else(|| DecodeError::InvalidImage("invalid dimensions".to_string()))?;
+        self.run_cascade(&img, None).0
+    }
+
+    /// Декодирование закодированного изображения (PNG, JPEG, ...) без `QRScanner`
+    pub fn decode_bytes(&self, bytes: &[u8]) -> Result<DecodedQR, DecodeError> {
+        let img = image::load_from_memory(bytes).map_err(|e| DecodeError::InvalidImage(e.to_string()))?;
+        check_dimensions(img.width(), img.height())?;
+        self.decode(&img.to_luma8())
+    }
+
+    /// Пакетное декодирование (параллельно при feature `rayon`)
     pub fn decode_batch(&self, images: &[GrayImage]) -> Vec<Result<DecodedQR, DecodeError>> {
-        images.iter().map(|img| self.decode(img)).collect()
+        #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+        {
+            use rayon::prelude::*;
+            images.par_iter().map(|img| self.decode(img)).collect()
+        }
+        #[cfg(not(all(feature = "rayon", not(target_arch = "wasm32"))))]
+        {
+            images.iter().map(|img| self.decode(img)).collect()
+        }
     }
     
     /// Инвертирование изображения
-    fn invert_image(&self, img: &GrayImage) -> GrayImage {
-        let (width, height) = img.dimensions();
-        let mut result = GrayImage::new(width, height);
-        
-        for y in 0..height {
-            for x in 0..width {
-                let pixel = img.get_pixel(x, y).0[0];
-                result.put_pixel(x, y, image::Luma([255 - pixel]));
+    fn invert_image<C: LumaBuffer>(&self, img: &LumaImage<C>) -> GrayImage {
+        map_pixels(img, |val| 255 - val)
+    }
+}
+
+/// Изображение яркости с любым буфером: `GrayImage` или заимствованный срез
+/// (`decode_luma` передаёт в каскад чужой буфер без копии)
+type LumaImage<C> = ImageBuffer<Luma<u8>, C>;
+
+/// Буфер пикселей `LumaImage`
+trait LumaBuffer: Deref<Target = [u8]> + Sync {}
+
+impl<C: Deref<Target = [u8]> + Sync> LumaBuffer for C {}
+
+/// Копия в `GrayImage` для кода, которому нужен собственный буфер (rqrr)
+fn to_gray<C: LumaBuffer>(img: &LumaImage<C>) -> GrayImage {
+    GrayImage::from_raw(img.width(), img.height(), img.as_raw().to_vec()).expect("buffer size matches dimensions")
+}
+
+/// Ни один бэкенд не работает с изображением без пикселей
+fn check_dimensions(width: u32, height: u32) -> Result<(), DecodeError> {
+    if width == 0 || height == 0 {
+        return Err(DecodeError::InvalidImage(format!("empty image ({}x{})", width, height)));
+    }
+    Ok(())
+}
+
+/// Попиксельное преобразование яркости
+fn map_pixels<C: LumaBuffer>(img: &LumaImage<C>, f: impl Fn(u8) -> u8) -> GrayImage {
+    let (width, height) = img.dimensions();
+    let pixels = img.as_raw().iter().map(|&val| f(val)).collect();
+    GrayImage::from_raw(width, height, pixels).expect("buffer size matches dimensions")
+}
+
+/// Углы сетки rqrr. `bounds` у rqrr захватывают лишний модуль справа и снизу
+/// (отображается `grid_size + 1`), поэтому сжимаем их к левому верхнему углу.
+fn rqrr_corners<G: rqrr::BitGrid>(grid: &rqrr::Grid<G>) -> [(f32, f32); 4] {
+    let size = grid.grid.size() as f32;
+    let k = size / (size + 1.0);
+    let tl = (grid.bounds[0].x as f32, grid.bounds[0].y as f32);
+    grid.bounds.map(|p| {
+        (
+            tl.0 + (p.x as f32 - tl.0) * k,
+            tl.1 + (p.y as f32 - tl.1) * k,
+        )
+    })
+}
+
+/// Внешние углы QR по точкам rxing: [нижний левый, верхний левый, верхний правый, ...]
+/// — это центры finder-паттернов. До углов кода они достраиваются на 3.5 модуля
+/// наружу; размер модуля — расстояние между центрами, делённое на `size - 7`
+/// (`size` — число модулей версии). Без версии углы неизвестны.
+fn rxing_corners(points: &[rxing::Point], version: Option<u8>) -> Option<[(f32, f32); 4]> {
+    let [bl, tl, tr] = [points.first()?, points.get(1)?, points.get(2)?];
+    let span = (17 + 4 * version? as u32 - 7) as f32;
+    // Шаг в один модуль вдоль верхней и левой сторон
+    let u = ((tr.x - tl.x) / span, (tr.y - tl.y) / span);
+    let v = ((bl.x - tl.x) / span, (bl.y - tl.y) / span);
+    let offset = |p: (f32, f32), su: f32, sv: f32| (p.0 + 3.5 * (su * u.0 + sv * v.0), p.1 + 3.5 * (su * u.1 + sv * v.1));
+    let br = (tr.x + bl.x - tl.x, tr.y + bl.y - tl.y);
+    Some([
+        offset((tl.x, tl.y), -1.0, -1.0),
+        offset((tr.x, tr.y), 1.0, -1.0),
+        offset(br, 1.0, 1.0),
+        offset((bl.x, bl.y), -1.0, 1.0),
+    ])
+}
+
+/// Первая успешная попытка из независимых вариантов стадии (углы, пороги)
+///
+/// С feature `rayon` варианты перебираются параллельно; при нескольких успехах
+/// берётся самый ранний по списку, как и в последовательном режиме.
+fn first_success<T: Sync, R: Send>(
+    items: &[T],
+    deadline: &Deadline,
+    attempt: impl Fn(&T) -> Option<R> + Sync,
+) -> Option<R> {
+    #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+    {
+        use rayon::prelude::*;
+        items
+            .par_iter()
+            .find_map_first(|item| if deadline.expired() { None } else { attempt(item) })
+    }
+    #[cfg(not(all(feature = "rayon", not(target_arch = "wasm32"))))]
+    {
+        items.iter().take_while(|_| !deadline.expired()).find_map(attempt)
+    }
+}
+
+/// Угол поворота, выпрямляющий код с наклоном `tilt`. Повороты на 90° ридеры
+/// обрабатывают сами, поэтому компенсируется только остаток в [-45°, 45°];
+/// наклон меньше градуса (или нечисловой) не исправляется.
+fn tilt_correction(tilt: f32) -> Option<f32> {
+    let residual = tilt - 90.0 * (tilt / 90.0).round();
+    (residual.abs() >= 1.0).then_some(-residual)
+}
+
+/// Перенос углов из изображения с белой рамкой обратно в исходное
+fn unpad(result: DecodedQR, padding: u32) -> DecodedQR {
+    let padding = padding as f32;
+    result.map_corners(|(x, y)| (x - padding, y - padding))
+}
+
+/// Обратное преобразование точки повёрнутого изображения (см. `geometry::rotate_image`) в исходное
+fn unrotate_point(
+    (x, y): (f32, f32),
+    (w, h): (u32, u32),
+    (new_w, new_h): (u32, u32),
+    angle_degrees: f32,
+) -> (f32, f32) {
+    let rad = angle_degrees.to_radians();
+    let (sin_a, cos_a) = rad.sin_cos();
+    let dx = x - new_w as f32 / 2.0;
+    let dy = y - new_h as f32 / 2.0;
+    (
+        dx * cos_a + dy * sin_a + w as f32 / 2.0,
+        -dx * sin_a + dy * cos_a + h as f32 / 2.0,
+    )
+}
+
+/// Минимальная сторона изображения, в которую помещается QR версии 1
+const MIN_QR_SIDE: u32 = 21;
+
+/// Оба бэкенда паникуют на вырожденных размерах, поэтому такие кадры отсекаем
+/// заранее для 2D-символов (1D-ридеры rxing работают и на одной строке)
+fn fits_qr<C: LumaBuffer>(img: &LumaImage<C>) -> bool {
+    img.width() >= MIN_QR_SIDE && img.height() >= MIN_QR_SIDE
+}
+
+/// Перенос модулей сетки rqrr в `BitMatrix` rxing (true = тёмный модуль)
+fn grid_to_bit_matrix<G: rqrr::BitGrid>(grid: &G) -> Option<rxing::common::BitMatrix> {
+    let size = grid.size() as u32;
+    let mut bits = rxing::common::BitMatrix::with_single_dimension(size).ok()?;
+    for y in 0..size {
+        for x in 0..size {
+            if grid.bit(y as usize, x as usize) {
+                bits.set(x, y);
             }
         }
-        
-        result
+    }
+    Some(bits)
+}
+
+/// Кодовые слова данных после исправления Reed–Solomon и число исправленных слов.
+///
+/// Повторно читает кодовые слова из сетки и прогоняет каждый блок через декодер RS.
+/// Возвращает `None`, если сетку не удалось разобрать.
+fn corrected_data_codewords<G: rqrr::BitGrid>(grid: &G) -> Option<(Vec<u8>, usize)> {
+    use rxing::common::reedsolomon::{get_predefined_genericgf, PredefinedGenericGF, ReedSolomonDecoder};
+    use rxing::qrcode::decoder::{BitMatrixParser, DataBlock};
+
+    let bits = grid_to_bit_matrix(grid)?;
+    let mut parser = BitMatrixParser::new(bits).ok()?;
+    let version = parser.readVersion().ok()?;
+    let ec_level = parser.readFormatInformation().ok()?.getErrorCorrectionLevel();
+    let codewords = parser.readCodewords().ok()?;
+    let blocks = DataBlock::getDataBlocks(&codewords, version, ec_level).ok()?;
+
+    let rs = ReedSolomonDecoder::new(get_predefined_genericgf(PredefinedGenericGF::QrCodeField256));
+    let mut total = 0usize;
+    let mut data = Vec::new();
+    for block in &blocks {
+        let mut received: Vec<i32> = block.getCodewords().iter().map(|&b| b as i32).collect();
+        let ec_codewords = received.len() as i32 - block.getNumDataCodewords() as i32;
+        total += rs.decode(&mut received, ec_codewords).ok()?;
+        data.extend(received[..block.getNumDataCodewords() as usize].iter().map(|&b| b as u8));
+    }
+
+    Some((data, total))
+}
+
+/// ECI-назначение в начале потока данных (после заголовка Structured Append, если он есть)
+fn leading_eci(data: &[u8]) -> Option<u32> {
+    let mut pos = 0usize;
+    let mut read = |n: usize| -> Option<u32> {
+        let mut value = 0u32;
+        for _ in 0..n {
+            let bit = data.get(pos / 8)? >> (7 - pos % 8) & 1;
+            value = value << 1 | bit as u32;
+            pos += 1;
+        }
+        Some(value)
+    };
+
+    let mut mode = read(4)?;
+    if mode == 0b0011 {
+        read(16)?;
+        mode = read(4)?;
+    }
+    if mode != 0b0111 {
+        return None;
+    }
+
+    match read(8)? {
+        first if first & 0x80 == 0 => Some(first),
+        first if first & 0xC0 == 0x80 => Some((first & 0x3F) << 8 | read(8)?),
+        first if first & 0xE0 == 0xC0 => Some((first & 0x1F) << 16 | read(16)?),
+        _ => None,
+    }
+}
+
+/// Кодировка по номеру ECI (ISO/IEC 15424)
+fn eci_encoding(eci: u32) -> Option<&'static encoding_rs::Encoding> {
+    let label = match eci {
+        1 | 3 => "iso-8859-1",
+        4..=13 | 15..=18 => return encoding_rs::Encoding::for_label(format!("iso-8859-{}", eci - 2).as_bytes()),
+        20 => "shift_jis",
+        21 => "windows-1250",
+        22 => "windows-1251",
+        23 => "windows-1252",
+        24 => "windows-1256",
+        25 => "utf-16be",
+        26 => "utf-8",
+        27 => "us-ascii",
+        28 => "big5",
+        29 => "gb18030",
+        30 => "euc-kr",
+        _ => return None,
+    };
+    encoding_rs::Encoding::for_label(label.as_bytes())
+}
+
+/// `DecodedQR` из результата rxing
+fn decoded_from_rxing(result: &rxing::RXingResult) -> DecodedQR {
+    let format = BarcodeFormat::from_rxing(result.getBarcodeFormat()).unwrap_or(BarcodeFormat::QrCode);
+    let (error_correction, version) = if format == BarcodeFormat::QrCode {
+        rxing_qr_metadata(result)
+    } else {
+        (ErrorCorrectionLevel::Unknown, None)
+    };
+
+    DecodedQR {
+        content: result.getText().to_string(),
+        error_correction,
+        version,
+        format,
+        encoding: format!("{:?}", result.getBarcodeFormat()),
+        errors_corrected: None,
+        corners: rxing_corners(result.getPoints(), version),
+        structured_append: StructuredAppend::from_rxing(result),
+        raw_bytes: rxing_byte_segments(result),
+        eci: None,
+    }
+}
+
+/// Уровень коррекции и версия QR из результата rxing.
+///
+/// Уровень берётся из `ERROR_CORRECTION_LEVEL` (rxing пишет туда биты формата,
+/// а не букву). Версию rxing не сообщает; её однозначно задаёт число кодовых слов
+/// данных (`getRawBytes`) при известном уровне — ёмкость растёт с версией.
+fn rxing_qr_metadata(result: &rxing::RXingResult) -> (ErrorCorrectionLevel, Option<u8>) {
+    use rxing::qrcode::decoder::{ErrorCorrectionLevel as RxingLevel, Version};
+    use rxing::{RXingResultMetadataType as Type, RXingResultMetadataValue as Value};
+
+    let level = match result.getRXingResultMetadata().get(&Type::ERROR_CORRECTION_LEVEL) {
+        Some(Value::ErrorCorrectionLevel(level)) => level.parse::<RxingLevel>().ok(),
+        _ => None,
+    };
+    let Some(level) = level.filter(|l| *l != RxingLevel::Invalid) else {
+        return (ErrorCorrectionLevel::Unknown, None);
+    };
+
+    let data_codewords = result.getRawBytes().len() as u32;
+    let version = (1..=40u32).find(|&number| {
+        Version::getVersionForNumber(number).is_ok_and(|v| {
+            v.getTotalCodewords() - v.getECBlocksForLevel(level).getTotalECCodewords() == data_codewords
+        })
+    });
+
+    (ErrorCorrectionLevel::from_rxing(level), version.map(|v| v as u8))
+}
+
+/// Байтовые сегменты из метаданных результата rxing
+fn rxing_byte_segments(result: &rxing::RXingResult) -> Vec<u8> {
+    use rxing::{RXingResultMetadataType as Type, RXingResultMetadataValue as Value};
+    match result.getRXingResultMetadata().get(&Type::BYTE_SEGMENTS) {
+        Some(Value::ByteSegments(segments)) if !segments.is_empty() => segments.concat(),
+        _ => result.getText().as_bytes().to_vec(),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::{render_code, render_modules, MODULE, QUIET};
+    use image::Luma;
+
+    /// QR с уровнем коррекции H (модуль 8px, quiet zone 4 модуля)
+    fn render_qr(content: &str) -> GrayImage {
+        let qr = qrcode::QrCode::with_error_correction_level(content, qrcode::EcLevel::H).unwrap();
+        render_code(&qr, MODULE, QUIET)
+    }
+
+    /// Инвертирует модуль (x, y) отрендеренного кода
+    fn flip_module(img: &mut GrayImage, x: u32, y: u32) {
+        let module = 8u32;
+        let quiet = 4u32;
+        for dy in 0..module {
+            for dx in 0..module {
+                let px = (quiet + x) * module + dx;
+                let py = (quiet + y) * module + dy;
+                let v = img.get_pixel(px, py).0[0];
+                img.put_pixel(px, py, Luma([255 - v]));
+            }
+        }
+    }
     
     #[test]
     fn test_decoder_creation() {
         let _decoder = QRDecoder::new();
     }
+
+    #[test]
+    fn test_strategy_order_validation() {
+        assert!(DecoderConfig::default().validate().is_ok());
+
+        let duplicate = DecoderConfig {
+            strategy_order: vec![DecodeStrategy::Inverted, DecodeStrategy::Standard, DecodeStrategy::Inverted],
+            ..Default::default()
+        };
+        assert!(matches!(QRDecoder::with_config(duplicate), Err(DecodeError::InvalidConfig(_))));
+
+        let empty = DecoderConfig {
+            strategy_order: vec![],
+            ..Default::default()
+        };
+        assert!(QRDecoder::with_config(empty).is_err());
+
+        let unknown: Result<DecoderConfig, _> =
+            serde_json::from_str(r#"{"strategy_order": ["Standard", "Sideways"]}"#);
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn test_custom_strategy_order() {
+        // Белый QR на чёрном фоне декодируется только инверсией
+        let mut img = render_qr("inverted first");
+        image::imageops::invert(&mut img);
+
+        let only_standard = QRDecoder::with_config(DecoderConfig {
+            strategy_order: vec![DecodeStrategy::Standard],
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(only_standard.decode(&img).is_err());
+
+        let inverted_first = QRDecoder::with_config(DecoderConfig {
+            strategy_order: vec![DecodeStrategy::Inverted, DecodeStrategy::Standard],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(inverted_first.decode(&img).unwrap().content, "inverted first");
+    }
+
+    #[test]
+    fn test_polarity_detection_orders_stages() {
+        let decoder = QRDecoder::new();
+        let normal = render_qr("polarity");
+        let mut inverted = normal.clone();
+        image::imageops::invert(&mut inverted);
+
+        assert!(!is_likely_inverted(&normal));
+        assert!(is_likely_inverted(&inverted));
+        assert!(!is_likely_inverted(&GrayImage::from_pixel(64, 64, Luma([128]))));
+
+        // Белый на чёрном: каждая инвертированная стадия идёт перед своей парой
+        let order = decoder.stage_order(&inverted);
+        assert_eq!(
+            &order[..6],
+            &[
+                DecodeStrategy::Inverted,
+                DecodeStrategy::Standard,
+                DecodeStrategy::PreprocessedInverted,
+                DecodeStrategy::Preprocessed,
+                DecodeStrategy::PaddedInverted,
+                DecodeStrategy::Padded,
+            ]
+        );
+        assert_eq!(decoder.decode(&inverted).unwrap().content, "polarity");
+
+        // Обычный код: инвертированные стадии не запускаются
+        assert!(decoder.stage_order(&normal).iter().all(|s| !s.needs_inversion()));
+
+        // Без оценки полярности — исходный порядок
+        let fixed = QRDecoder::with_config(DecoderConfig {
+            detect_polarity: false,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(fixed.stage_order(&inverted), DecodeStrategy::DEFAULT_ORDER.to_vec());
+    }
+
+    #[test]
+    fn test_report_names_winning_stage() {
+        let decoder = QRDecoder::new();
+
+        // Модуль 2px, поворот на 30°: читается только после обратного поворота
+        let qr = qrcode::QrCode::with_error_correction_level("rotated", qrcode::EcLevel::L).unwrap();
+        let rotated = geometry::rotate_image(&render_code(&qr, 2, QUIET), 30.0);
+
+        let (result, report) = decoder.decode_with_report(&rotated);
+        assert_eq!(result.unwrap().content, "rotated");
+        assert_eq!(report.winning_stage, Some("Rotation"));
+        assert_eq!(report.stages_tried.first(), Some(&"Standard"));
+        assert_eq!(report.stages_tried.last(), Some(&"Rotation"));
+
+        let blank = GrayImage::from_pixel(64, 64, Luma([255]));
+        let (result, report) = decoder.decode_with_report(&blank);
+        assert!(result.is_err());
+        assert_eq!(report.winning_stage, None);
+        assert!(!report.stages_tried.is_empty());
+    }
+
+    #[test]
+    fn test_tilt_hint_rotates_exactly() {
+        // Модуль ~1.8px: после поворота на неточный угол сетка не читается
+        let qr = render_qr("https://example.com/tilt/12");
+        let side = qr.width() / 8 * 18 / 10;
+        let small = image::imageops::resize(&qr, side, side, image::imageops::FilterType::Triangle);
+        let img = geometry::rotate_image(&small, 12.0);
+
+        // Только стадия Rotation с прежним фиксированным набором углов
+        let fixed = QRDecoder::with_config(DecoderConfig {
+            strategy_order: vec![DecodeStrategy::Rotation],
+            rotation_angles: vec![30.0, -30.0, 45.0, -45.0, 60.0, -60.0],
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(fixed.decode(&img).is_err());
+        assert_eq!(fixed.decode_tilted(&img, 12.0).unwrap().content, "https://example.com/tilt/12");
+        // Кратные 90° части наклона не компенсируются
+        assert_eq!(fixed.decode_tilted(&img, 102.0).unwrap().content, "https://example.com/tilt/12");
+
+        assert_eq!(tilt_correction(-80.0), Some(-10.0));
+        assert_eq!(tilt_correction(0.5), None);
+        assert_eq!(tilt_correction(f32::NAN), None);
+    }
+
+    #[test]
+    fn test_binarizer_preference() {
+        let img = render_qr("screenshot");
+        for binarizer in [
+            BinarizerPreference::HybridThenGlobal,
+            BinarizerPreference::HybridOnly,
+            BinarizerPreference::GlobalOnly,
+        ] {
+            let decoder = QRDecoder::with_config(DecoderConfig {
+                binarizer,
+                ..Default::default()
+            })
+            .unwrap();
+            // GlobalOnly не зависит от флага fallback
+            assert_eq!(decoder.decode_with_rxing(&img, false).unwrap().content, "screenshot");
+        }
+
+        let config: DecoderConfig = serde_json::from_str(r#"{"binarizer": "GlobalOnly"}"#).unwrap();
+        assert_eq!(config.binarizer, BinarizerPreference::GlobalOnly);
+        assert_eq!(DecoderConfig::default().binarizer, BinarizerPreference::HybridThenGlobal);
+    }
+
+    #[test]
+    fn test_decode_metadata() {
+        let decoder = QRDecoder::new();
+        let qr = qrcode::QrCode::with_error_correction_level("metadata only", qrcode::EcLevel::H).unwrap();
+        let img = render_qr("metadata only");
+
+        let meta = decoder.decode_metadata(&img).unwrap();
+        assert_eq!(meta.ecc, ErrorCorrectionLevel::H);
+        assert_eq!(meta.module_count, qr.width() as u32);
+        assert_eq!(qrcode::Version::Normal(meta.version as i16), qr.version());
+        assert!(meta.mask < 8);
+    }
+
+    #[test]
+    fn test_rxing_reports_version_and_ecc() {
+        let decoder = QRDecoder::new();
+        for (version, level, expected) in [
+            (5, qrcode::EcLevel::Q, ErrorCorrectionLevel::Q),
+            (2, qrcode::EcLevel::L, ErrorCorrectionLevel::L),
+            (7, qrcode::EcLevel::M, ErrorCorrectionLevel::M),
+            (3, qrcode::EcLevel::H, ErrorCorrectionLevel::H),
+        ] {
+            let qr = qrcode::QrCode::with_version("version from rxing", qrcode::Version::Normal(version), level).unwrap();
+            let img = render_code(&qr, MODULE, QUIET);
+
+            // Напрямую через rxing: основной каскад может отдать результат rqrr
+            let decoded = decoder.decode_with_rxing(&img, false).unwrap();
+            assert_eq!(decoded.content, "version from rxing");
+            assert_eq!(decoded.version, Some(version as u8));
+            assert_eq!(decoded.error_correction, expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_metadata_not_found() {
+        let decoder = QRDecoder::new();
+        let img = GrayImage::from_pixel(64, 64, Luma([255]));
+        assert!(matches!(decoder.decode_metadata(&img), Err(DecodeError::NotFound)));
+    }
+
+    #[test]
+    fn test_not_found_vs_decode_failed() {
+        let decoder = QRDecoder::new();
+
+        let blank = GrayImage::from_pixel(200, 200, Luma([255]));
+        assert!(matches!(decoder.decode(&blank), Err(DecodeError::NotFound)));
+
+        // Finder-паттерны и тайминг целы, вся область данных инвертирована
+        let mut img = render_qr("HI");
+        for y in 9..21 {
+            for x in 9..21 {
+                flip_module(&mut img, x, y);
+            }
+        }
+        let rqrr = decoder.decode_with_rqrr(&img);
+        assert!(matches!(rqrr, Err(DecodeError::ChecksumError | DecodeError::DecodeFailed(_))), "{:?}", rqrr);
+        let full = decoder.decode(&img);
+        assert!(matches!(full, Err(DecodeError::ChecksumError | DecodeError::DecodeFailed(_))), "{:?}", full);
+    }
+
+    #[test]
+    fn test_most_informative_error() {
+        let failed = || DecodeError::DecodeFailed("x".into());
+        assert!(matches!(DecodeError::NotFound.most_informative(failed()), DecodeError::DecodeFailed(_)));
+        assert!(matches!(failed().most_informative(DecodeError::NotFound), DecodeError::DecodeFailed(_)));
+        assert!(matches!(failed().most_informative(DecodeError::ChecksumError), DecodeError::ChecksumError));
+    }
+
+    #[test]
+    fn test_errors_corrected_clean_code() {
+        let decoder = QRDecoder::new();
+        let img = render_qr("errors corrected");
+        let result = decoder.decode(&img).unwrap();
+        assert_eq!(result.content, "errors corrected");
+        assert_eq!(result.errors_corrected, Some(0));
+    }
+
+    #[test]
+    fn test_errors_corrected_damaged_code() {
+        let decoder = QRDecoder::new();
+        let mut img = render_qr("errors corrected");
+        let width = qrcode::QrCode::with_error_correction_level("errors corrected", qrcode::EcLevel::H)
+            .unwrap()
+            .width() as u32;
+        // Нижний правый угол — первые биты первого кодового слова
+        for (x, y) in [(width - 1, width - 1), (width - 2, width - 1), (width - 1, width - 2)] {
+            flip_module(&mut img, x, y);
+        }
+        let result = decoder.decode(&img).unwrap();
+        assert_eq!(result.content, "errors corrected");
+        assert!(result.errors_corrected.unwrap() >= 1);
+    }
+
+    fn assert_corners_close(a: [(f32, f32); 4], b: [(f32, f32); 4], tolerance: f32) {
+        for (p, q) in a.iter().zip(&b) {
+            assert!(
+                (p.0 - q.0).abs() <= tolerance && (p.1 - q.1).abs() <= tolerance,
+                "{:?} vs {:?}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn test_corners_clean_code() {
+        let img = render_qr("corners");
+        let decoded = QRDecoder::new().decode(&img).unwrap();
+
+        // Код начинается после quiet zone (4 модуля по 8px)
+        let start = 32.0;
+        let end = img.width() as f32 - 32.0;
+        let expected = [(start, start), (end, start), (end, end), (start, end)];
+        assert_corners_close(decoded.corners.unwrap(), expected, 4.0);
+    }
+
+    #[test]
+    fn test_rxing_corners_are_outer_corners() {
+        let img = render_qr("rxing corners");
+        let decoded = QRDecoder::new().decode_with_rxing(&img, false).unwrap();
+
+        let start = (QUIET * MODULE) as f32;
+        let end = img.width() as f32 - start;
+        let expected = [(start, start), (end, start), (end, end), (start, end)];
+        assert_corners_close(decoded.corners.unwrap(), expected, 2.0);
+    }
+
+    #[test]
+    fn test_corners_mapped_back_from_transforms() {
+        let img = render_qr("mapped corners");
+        let reference = QRDecoder::new().decode(&img).unwrap().corners.unwrap();
+
+        for strategy in [DecodeStrategy::Padded, DecodeStrategy::Rotation] {
+            let decoder = QRDecoder::with_config(DecoderConfig {
+                strategy_order: vec![strategy],
+                ..Default::default()
+            })
+            .unwrap();
+            let corners = decoder.decode(&img).unwrap().corners.unwrap();
+            assert_corners_close(corners, reference, 6.0);
+        }
+    }
+
+    #[test]
+    fn test_config_flags_and_time_budget() {
+        let mut img = render_qr("budget");
+        image::imageops::invert(&mut img);
+
+        let no_inversion = QRDecoder::with_config(DecoderConfig {
+            try_inverted: false,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(no_inversion.config().allows(DecodeStrategy::Standard));
+        assert!(!no_inversion.config().allows(DecodeStrategy::PaddedInverted));
+        // Белый на чёрном без инверсии не читается (и оценка полярности отключается)
+        assert!(no_inversion.decode(&img).is_err());
+        assert!(no_inversion.decode(&render_qr("budget")).is_ok());
+        assert!(QRDecoder::new().decode(&img).is_ok());
+
+        // Нулевой бюджет: только первая стадия, инверсия до неё не доходит
+        let zero_budget = QRDecoder::with_config(DecoderConfig {
+            max_total_duration: Some(Duration::ZERO),
+            detect_polarity: false,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(zero_budget.decode(&img).is_err());
+        assert!(zero_budget.decode(&render_qr("budget")).is_ok());
+
+        // С оценкой полярности первой стадией для белого на чёрном становится Inverted
+        let zero_budget = QRDecoder::with_config(DecoderConfig {
+            max_total_duration: Some(Duration::ZERO),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(zero_budget.decode(&img).is_ok());
+
+        let bad_angle = DecoderConfig {
+            rotation_angles: vec![f32::NAN],
+            ..Default::default()
+        };
+        assert!(bad_angle.validate().is_err());
+
+        let partial: DecoderConfig = serde_json::from_str(r#"{"try_downscale": false}"#).unwrap();
+        assert!(!partial.try_downscale);
+        assert_eq!(partial.thresholds, DecoderConfig::default().thresholds);
+    }
+
+    /// Символ Structured Append (версия 3, уровень M, байтовый режим).
+    /// Поток битов собирается вручную: `qrcode` не умеет писать заголовок SA.
+    fn render_structured_append(chunk: &str, index: u8, total: u8, parity: u8) -> GrayImage {
+        use qrcode::{bits::Bits, canvas::Canvas, EcLevel, Version};
+
+        let version = Version::Normal(3);
+        let capacity = Bits::new(version).max_len(EcLevel::M).unwrap() / 8;
+        let mut bits: Vec<bool> = Vec::new();
+        let mut push = |value: u32, len: u32| {
+            bits.extend((0..len).rev().map(|i| value >> i & 1 == 1));
+        };
+        push(0b0011, 4);
+        push(index as u32, 4);
+        push(total as u32 - 1, 4);
+        push(parity as u32, 8);
+        push(0b0100, 4);
+        push(chunk.len() as u32, 8);
+        for b in chunk.bytes() {
+            push(b as u32, 8);
+        }
+        push(0, 4);
+
+        let mut data: Vec<u8> = bits.chunks(8)
+            .map(|byte| byte.iter().enumerate().fold(0u8, |acc, (i, &bit)| acc | (bit as u8) << (7 - i)))
+            .collect();
+        assert!(data.len() <= capacity, "chunk too long for version 3-M");
+        let mut pad = [0xEC, 0x11].into_iter().cycle();
+        data.resize_with(capacity, || pad.next().unwrap());
+
+        let (data, ec) = qrcode::ec::construct_codewords(&data, version, EcLevel::M).unwrap();
+        let mut canvas = Canvas::new(version, EcLevel::M);
+        canvas.draw_all_functional_patterns();
+        canvas.draw_data(&data, &ec);
+        let colors = canvas.apply_best_mask().into_colors();
+        render_modules(&colors, version.width() as u32, MODULE, QUIET)
+    }
+
+    #[test]
+    fn test_structured_append_reassembly() {
+        let message = "https://example.com/invoice/2024/000123?sum=150000&purpose=structured-append";
+        let parity = message.bytes().fold(0u8, |acc, b| acc ^ b);
+        let chunks = [&message[..26], &message[26..52], &message[52..]];
+
+        let decoder = QRDecoder::new();
+        let mut parts: Vec<DecodedQR> = chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| decoder.decode(&render_structured_append(chunk, i as u8, 3, parity)).unwrap())
+            .collect();
+
+        assert_eq!(parts[1].content, chunks[1]);
+        assert_eq!(parts[1].structured_append, Some(StructuredAppend { index: 1, total: 3, parity }));
+
+        // Порядок сканирования не важен
+        parts.swap(0, 2);
+        assert_eq!(reassemble(&parts).unwrap(), message);
+
+        assert!(reassemble(&parts[..2]).is_err());
+        let mut duplicated = parts.clone();
+        duplicated[0] = parts[1].clone();
+        assert!(reassemble(&duplicated).is_err());
+
+        let mut corrupted = parts.clone();
+        corrupted[1].content = corrupted[1].content.replace('e', "E");
+        assert!(matches!(reassemble(&corrupted), Err(DecodeError::ChecksumError)));
+
+        // Обычный код без заголовка
+        let plain = decoder.decode(&render_qr("plain")).unwrap();
+        assert_eq!(plain.structured_append, None);
+        assert!(reassemble(&[plain]).is_err());
+    }
+
+    /// Рендер кода с байтовым сегментом (и ECI-назначением, если задано)
+    fn render_bytes(data: &[u8], eci: Option<u32>) -> GrayImage {
+        use qrcode::{bits::Bits, EcLevel, QrCode, Version};
+        let mut bits = Bits::new(Version::Normal(5));
+        if let Some(eci) = eci {
+            bits.push_eci_designator(eci).unwrap();
+        }
+        bits.push_byte_data(data).unwrap();
+        bits.push_terminator(EcLevel::M).unwrap();
+        let qr = QrCode::with_bits(bits, EcLevel::M).unwrap();
+        render_code(&qr, MODULE, QUIET)
+    }
+
+    #[test]
+    fn test_windows_1251_payload() {
+        let text = "ST00012|Name=ООО «Ромашка»|PersonalAcc=40702810938000000001";
+        let (cp1251, _, _) = encoding_rs::WINDOWS_1251.encode(text);
+        let decoder = QRDecoder::new();
+
+        // Без ECI: UTF-8 не подходит, срабатывает эвристика Windows-1251
+        let plain = decoder.decode(&render_bytes(&cp1251, None)).unwrap();
+        assert_eq!(plain.raw_bytes, cp1251.as_ref());
+        assert_eq!(plain.eci, None);
+        assert_eq!(plain.content, text);
+
+        // С ECI 22 кодировка берётся из назначения
+        let with_eci = decoder.decode(&render_bytes(&cp1251, Some(22))).unwrap();
+        assert_eq!(with_eci.eci, Some(22));
+        assert_eq!(with_eci.content, text);
+
+        // Явная кодировка важнее эвристики
+        let koi8 = plain.decode_text(Some("koi8-r")).unwrap();
+        assert_ne!(koi8, text);
+        assert!(plain.decode_text(Some("no-such-charset")).is_err());
+
+        let utf8 = decoder.decode(&render_bytes(text.as_bytes(), None)).unwrap();
+        assert_eq!(utf8.content, text);
+    }
+
+    #[test]
+    fn test_first_success_keeps_list_order() {
+        let deadline = Deadline::new(None);
+        let items: Vec<u32> = (0..64).collect();
+        let found = first_success(&items, &deadline, |&i| (i % 10 == 7).then_some(i));
+        assert_eq!(found, Some(7));
+        assert_eq!(first_success(&items, &deadline, |_| None::<u32>), None);
+
+        let expired = Deadline::new(Some(Duration::ZERO));
+        assert_eq!(first_success(&items, &expired, |&i| Some(i)), None);
+    }
+
+    /// Рендер штрихкода через writer rxing (белое поле 16px вокруг)
+    fn render_barcode(content: &str, format: rxing::BarcodeFormat, width: i32, height: i32) -> GrayImage {
+        use rxing::Writer;
+        let matrix = rxing::MultiFormatWriter.encode(content, &format, width, height).unwrap();
+        let margin = 16;
+        let mut img = GrayImage::from_pixel(matrix.getWidth() + margin * 2, matrix.getHeight() + margin * 2, Luma([255]));
+        for y in 0..matrix.getHeight() {
+            for x in 0..matrix.getWidth() {
+                if matrix.get(x, y) {
+                    img.put_pixel(x + margin, y + margin, Luma([0]));
+                }
+            }
+        }
+        img
+    }
+
+    #[test]
+    fn test_multi_format_decoding() {
+        let decoder = QRDecoder::with_config(DecoderConfig {
+            formats: HashSet::from([BarcodeFormat::QrCode, BarcodeFormat::DataMatrix, BarcodeFormat::Ean13]),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let data_matrix = render_barcode("DM-12345", rxing::BarcodeFormat::DATA_MATRIX, 160, 160);
+        let decoded = decoder.decode(&data_matrix).unwrap();
+        assert_eq!(decoded.content, "DM-12345");
+        assert_eq!(decoded.format, BarcodeFormat::DataMatrix);
+
+        let ean = render_barcode("4006381333931", rxing::BarcodeFormat::EAN_13, 300, 120);
+        let decoded = decoder.decode(&ean).unwrap();
+        assert_eq!(decoded.content, "4006381333931");
+        assert_eq!(decoded.format, BarcodeFormat::Ean13);
+
+        // QR по-прежнему декодируется и помечается как QR
+        let decoded = decoder.decode(&render_qr("still qr")).unwrap();
+        assert_eq!(decoded.format, BarcodeFormat::QrCode);
+
+        // По умолчанию распознаётся только QR
+        assert!(QRDecoder::new().decode(&ean).is_err());
+
+        // Узкая полоска EAN ниже 21px по высоте: минимум стороны QR к 1D не относится
+        let strip = image::imageops::crop_imm(&ean, 0, 50, ean.width(), 12).to_image();
+        assert_eq!(decoder.decode(&strip).unwrap().content, "4006381333931");
+        assert!(QRDecoder::new().decode(&strip).is_err());
+        // Одна строка и обрезок 2x2 не роняют 1D-ридеры rxing
+        for (x, w, h) in [(0, ean.width(), 1), (40, 2, 2)] {
+            let _ = decoder.decode(&image::imageops::crop_imm(&ean, x, 50, w, h).to_image());
+        }
+
+        // Формат, выбранный классом ML-детектора, подменяет набор из конфигурации
+        let decoded = QRDecoder::new().decode_format(&data_matrix, BarcodeFormat::DataMatrix).unwrap();
+        assert_eq!(decoded.format, BarcodeFormat::DataMatrix);
+        assert!(QRDecoder::new().decode_format(&render_qr("qr only"), BarcodeFormat::Aztec).is_err());
+    }
+
+    #[test]
+    fn test_micro_qr_decoding() {
+        assert_eq!(BarcodeFormat::from_label("mqr"), Some(BarcodeFormat::MicroQr));
+        assert_eq!(BarcodeFormat::from_label("rMQR"), Some(BarcodeFormat::RectangularMicroQr));
+
+        let decoder = QRDecoder::new();
+        for version in 1..=4 {
+            // M1 поддерживает только обнаружение ошибок (уровень L)
+            let level = if version == 1 { qrcode::EcLevel::L } else { qrcode::EcLevel::M };
+            let qr = qrcode::QrCode::with_version(b"12345", qrcode::Version::Micro(version), level).unwrap();
+            let img = render_code(&qr, MODULE, QUIET);
+
+            let decoded = decoder.decode(&img).unwrap_or_else(|e| panic!("M{}: {}", version, e));
+            assert_eq!(decoded.content, "12345", "M{}", version);
+            assert_eq!(decoded.format, BarcodeFormat::MicroQr);
+            assert_eq!(decoder.decode_format(&img, BarcodeFormat::MicroQr).unwrap().content, "12345");
+        }
+
+        // Без MicroQr в списке форматов ридер его не ищет
+        let qr = qrcode::QrCode::with_version(b"12345", qrcode::Version::Micro(2), qrcode::EcLevel::L).unwrap();
+        let img = render_code(&qr, MODULE, QUIET);
+        let qr_only = QRDecoder::with_config(DecoderConfig {
+            formats: HashSet::from([BarcodeFormat::QrCode]),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(qr_only.decode(&img).is_err());
+    }
+
+    #[test]
+    fn test_decode_bytes() {
+        let mut png = Vec::new();
+        image::DynamicImage::ImageLuma8(render_qr("png bytes"))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let decoder = QRDecoder::new();
+        assert_eq!(decoder.decode_bytes(&png).unwrap().content, "png bytes");
+        assert!(matches!(decoder.decode_bytes(b"not an image"), Err(DecodeError::InvalidImage(_))));
+
+        // Файл без пикселей (GIF 0xN) — InvalidImage, а не паника в каскаде
+        for (width, height) in [(0, 5), (5, 0)] {
+            let mut empty = Vec::new();
+            image::DynamicImage::ImageRgba8(image::RgbaImage::new(width, height))
+                .write_to(&mut std::io::Cursor::new(&mut empty), image::ImageFormat::Gif)
+                .unwrap();
+            assert!(matches!(decoder.decode_bytes(&empty), Err(DecodeError::InvalidImage(_))), "{}x{}", width, height);
+        }
+    }
+
+    #[test]
+    fn test_extract_matrix() {
+        let qr = qrcode::QrCode::with_error_correction_level("module grid", qrcode::EcLevel::M).unwrap();
+        let width = qr.width();
+        let img = render_code(&qr, MODULE, QUIET);
+
+        let matrix = QRDecoder::new().extract_matrix(&img).unwrap();
+        assert_eq!(matrix.len(), width);
+        assert!(matrix.iter().all(|row| row.len() == width));
+
+        // Finder-паттерны в трёх углах: тёмная рамка 7x7, светлое кольцо, тёмный центр 3x3
+        let finder_module = |x: usize, y: usize| {
+            let ring = x.abs_diff(3).max(y.abs_diff(3));
+            ring != 2
+        };
+        for (ox, oy) in [(0, 0), (width - 7, 0), (0, width - 7)] {
+            for y in 0..7 {
+                for x in 0..7 {
+                    assert_eq!(matrix[oy + y][ox + x], finder_module(x, y), "finder at ({}, {})", ox, oy);
+                }
+            }
+        }
+
+        // Матрица совпадает с исходной, включая маскированные модули данных
+        let expected: Vec<Vec<bool>> = qr
+            .to_colors()
+            .chunks(width)
+            .map(|row| row.iter().map(|&c| c == qrcode::Color::Dark).collect())
+            .collect();
+        assert_eq!(matrix, expected);
+
+        // Белый на чёрном — через инверсию; пустое изображение — None
+        let mut inverted = img.clone();
+        image::imageops::invert(&mut inverted);
+        assert_eq!(QRDecoder::new().extract_matrix(&inverted), Some(expected));
+        assert_eq!(QRDecoder::new().extract_matrix(&GrayImage::from_pixel(64, 64, Luma([255]))), None);
+    }
+
+    #[test]
+    fn test_decode_luma() {
+        let img = render_qr("luma buffer");
+        let decoder = QRDecoder::new();
+
+        let decoded = decoder.decode_luma(img.width(), img.height(), img.as_raw()).unwrap();
+        assert_eq!(decoded.content, "luma buffer");
+        // Владеющий буфер передаётся без копирования
+        let owned = decoder.decode_luma(img.width(), img.height(), img.clone().into_raw()).unwrap();
+        assert_eq!(owned.content, "luma buffer");
+
+        let short = &img.as_raw()[1..];
+        assert!(matches!(
+            decoder.decode_luma(img.width(), img.height(), short),
+            Err(DecodeError::InvalidImage(_))
+        ));
+
+        // Пустые размеры — ошибка, а не паника в стадиях каскада
+        let invalid = |width, height, luma: &[u8]| matches!(decoder.decode_luma(width, height, luma), Err(DecodeError::InvalidImage(_)));
+        assert!(invalid(0, 0, &[]));
+        assert!(invalid(0, 10, &[]));
+        assert!(invalid(10, 0, &[]));
+        assert!(invalid(10, 10, &[255; 99]));
+        assert!(invalid(u32::MAX, u32::MAX, &[]));
+    }
 }
@@ -2,22 +2,40 @@
 //!
 //! Реализация алгоритмического обнаружения QR-кодов через finder patterns
 
-use image::{GrayImage, Luma};
+use image::GrayImage;
+use nalgebra::{Matrix3, Point2, Vector3};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::geometry;
+use crate::preprocessing::{local_mean_binarize, BinarizationMethod, ImageProcessor, ProcessingConfig};
 #[cfg(feature = "ml")]
 use crate::ml_detection::OnnxDetector;
 
-/// Конфигурация детектора
+/// Конфигурация детектора. При десериализации незаданные поля берутся
+/// из `Default`, неизвестные — ошибка.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct DetectorConfig {
     /// Минимальный размер QR-кода в пикселях
     pub min_size: u32,
     /// Максимальный размер QR-кода в пикселях  
     pub max_size: u32,
-    /// Порог бинаризации (0-255)
-    pub threshold: u8,
+    /// Бинаризация кадра перед поиском finder-паттернов
+    pub threshold: ThresholdMode,
     /// Допуск отклонения соотношения 1:1:3:1:1
     pub ratio_tolerance: f32,
+    /// Сколько кандидатов в finder-паттерны (с наибольшим числом подтверждений)
+    /// участвует в группировке; остальные отбрасываются
+    pub max_finder_candidates: usize,
+    /// Выпрямлять код по finder-паттернам (гомография в квадрат) вместо
+    /// прямоугольного кропа
+    pub perspective_correct: bool,
+    /// Если finder-паттерны не найдены, возвращать весь кадр с уверенностью 0.5.
+    /// Отключается, когда вызывающий передаёт уже вырезанные области
+    pub whole_image_fallback: bool,
+    /// Сколько finder-паттернов, не вошедших ни в одну тройку, вырезать отдельно
+    /// как кандидаты в Micro QR (один finder-паттерн); 0 отключает
+    pub max_single_finder_crops: usize,
 }
 
 impl Default for DetectorConfig {
@@ -25,12 +43,60 @@ impl Default for DetectorConfig {
         Self {
             min_size: 20,
             max_size: 2000,
-            threshold: 128,
+            threshold: ThresholdMode::Otsu,
             ratio_tolerance: 0.5,
+            max_finder_candidates: 60,
+            perspective_correct: true,
+            whole_image_fallback: true,
+            max_single_finder_crops: 3,
         }
     }
 }
 
+impl DetectorConfig {
+    /// Проверка согласованности параметров
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_size > self.max_size {
+            return Err(format!(
+                "min_size ({}) is greater than max_size ({})",
+                self.min_size, self.max_size
+            ));
+        }
+        if self.ratio_tolerance <= 0.0 {
+            return Err(format!("ratio_tolerance must be positive, got {}", self.ratio_tolerance));
+        }
+        if self.max_finder_candidates < 3 {
+            return Err(format!(
+                "max_finder_candidates must be at least 3, got {}",
+                self.max_finder_candidates
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Порог бинаризации детектора
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ThresholdMode {
+    /// Фиксированный глобальный порог (0-255)
+    Fixed(u8),
+    /// Глобальный порог Otsu по гистограмме кадра
+    Otsu,
+    /// Локальный порог — средняя яркость в окне `LOCAL_MEAN_WINDOW` пикселей.
+    /// Без относительного смещения Bradley, поэтому работает и при низком контрасте
+    LocalMean,
+}
+
+/// Наименьшее расстояние между центрами finder-паттернов одного кода в модулях
+/// (сторона версии 1 — 14 модулей — с запасом на перспективу)
+const MIN_FINDER_SPAN: f32 = 10.0;
+
+/// Наибольшее расстояние в модулях: диагональ версии 40 (170 * √2) с запасом
+const MAX_FINDER_SPAN: f32 = 260.0;
+
+/// Сторона окна `ThresholdMode::LocalMean`
+const LOCAL_MEAN_WINDOW: u32 = 51;
+
 /// Обнаруженный QR-код
 #[derive(Debug, Clone)]
 pub struct DetectedQR {
@@ -42,6 +108,29 @@ pub struct DetectedQR {
     pub image: GrayImage,
     /// Уверенность обнаружения (0.0 - 1.0)
     pub confidence: f32,
+    /// Преобразование из координат `image` в координаты кадра,
+    /// если кроп выпрямлялся гомографией (иначе достаточно сдвига на `bbox`)
+    pub homography: Option<Matrix3<f32>>,
+    /// Класс ML-детектора (для алгоритмической детекции `None`)
+    pub class: Option<u32>,
+    /// Имя класса из метаданных модели или `OnnxDetector::set_labels`
+    pub label: Option<String>,
+    /// Наклон кода внутри `image` в градусах (по часовой стрелке, ось y вниз),
+    /// оценённый по finder-паттернам; `None` для выпрямленных кропов
+    pub tilt: Option<f32>,
+}
+
+impl DetectedQR {
+    /// Перевод точки из координат `image` в координаты кадра
+    pub fn to_frame(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        match &self.homography {
+            Some(h) => {
+                let p = h * Vector3::new(x, y, 1.0);
+                (p.x / p.z, p.y / p.z)
+            }
+            None => (x + self.bbox[0] as f32, y + self.bbox[1] as f32),
+        }
+    }
 }
 
 /// Finder pattern QR-кода
@@ -50,6 +139,204 @@ struct FinderPattern {
     center_x: u32,
     center_y: u32,
     module_size: f32,
+    /// Число объединённых срабатываний сканирования (оценка качества)
+    count: u32,
+    /// Отклонение отрезков от 1:1:3:1:1 в модулях (см. `ratio_error`), среднее по срабатываниям
+    ratio_error: f32,
+}
+
+/// Сумма срабатываний, объединённых в один паттерн (`merge_patterns`)
+#[derive(Debug, Clone, Copy)]
+struct PatternCluster {
+    sum_x: f32,
+    sum_y: f32,
+    sum_size: f32,
+    members: f32,
+    hits: u32,
+    sum_error: f32,
+}
+
+impl PatternCluster {
+    fn new(pattern: &FinderPattern) -> Self {
+        Self {
+            sum_x: pattern.center_x as f32,
+            sum_y: pattern.center_y as f32,
+            sum_size: pattern.module_size,
+            members: 1.0,
+            hits: pattern.count,
+            sum_error: pattern.ratio_error * pattern.count as f32,
+        }
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        Self {
+            sum_x: self.sum_x + other.sum_x,
+            sum_y: self.sum_y + other.sum_y,
+            sum_size: self.sum_size + other.sum_size,
+            members: self.members + other.members,
+            hits: self.hits + other.hits,
+            sum_error: self.sum_error + other.sum_error,
+        }
+    }
+
+    fn center(&self) -> (f32, f32) {
+        (self.sum_x / self.members, self.sum_y / self.members)
+    }
+
+    fn module_size(&self) -> f32 {
+        self.sum_size / self.members
+    }
+
+    fn distance(&self, other: &Self) -> f32 {
+        let ((x1, y1), (x2, y2)) = (self.center(), other.center());
+        (x1 - x2).hypot(y1 - y2)
+    }
+
+    fn pattern(&self) -> FinderPattern {
+        let (x, y) = self.center();
+        FinderPattern {
+            center_x: x.round() as u32,
+            center_y: y.round() as u32,
+            module_size: self.module_size(),
+            count: self.hits,
+            ratio_error: self.sum_error / self.hits.max(1) as f32,
+        }
+    }
+}
+
+/// Alignment pattern QR-кода (версии 2+)
+#[derive(Debug, Clone)]
+struct AlignmentPattern {
+    /// Центр в координатах кадра
+    center: Point2<f32>,
+    /// Центр в координатах сетки модулей (столбец, строка от левого верхнего угла кода)
+    module: Point2<f32>,
+}
+
+/// Оценка сетки кода по трём finder-паттернам
+struct GridEstimate {
+    tl: Point2<f32>,
+    tr: Point2<f32>,
+    bl: Point2<f32>,
+    /// Средний размер модуля в пикселях
+    module: f32,
+    /// Версия QR (1-40)
+    version: u32,
+}
+
+impl GridEstimate {
+    /// Размер кода в модулях
+    fn dimension(&self) -> f32 {
+        17.0 + 4.0 * self.version as f32
+    }
+
+    /// Положение точки сетки модулей в кадре по параллелограмму из finder-паттернов
+    fn project(&self, module: Point2<f32>) -> Point2<f32> {
+        let span = self.dimension() - 7.0;
+        self.tl + (self.tr - self.tl) * ((module.x - 3.5) / span) + (self.bl - self.tl) * ((module.y - 3.5) / span)
+    }
+}
+
+/// Номера строк/столбцов центров alignment-паттернов для версии (ISO/IEC 18004, Annex E)
+fn alignment_positions(version: u32) -> Vec<u32> {
+    if version < 2 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let last = 4 * version + 10;
+    let step = if version == 32 {
+        26
+    } else {
+        (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2
+    };
+    let mut positions: Vec<u32> = (0..count - 1).map(|i| last - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// Направление линий сканирования
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Axis {
+    /// Строки изображения
+    Horizontal,
+    /// Столбцы изображения
+    Vertical,
+}
+
+impl Axis {
+    fn perpendicular(self) -> Self {
+        match self {
+            Axis::Horizontal => Axis::Vertical,
+            Axis::Vertical => Axis::Horizontal,
+        }
+    }
+
+    /// Количество линий и длина линии
+    fn extent(self, img: &GrayImage) -> (u32, u32) {
+        match self {
+            Axis::Horizontal => (img.height(), img.width()),
+            Axis::Vertical => (img.width(), img.height()),
+        }
+    }
+}
+
+/// Итератор `QRDetector::detect_iter`
+struct Detections<'a> {
+    detector: &'a QRDetector,
+    img: &'a GrayImage,
+    /// Бинаризованный кадр для поиска alignment-паттернов
+    binary: GrayImage,
+    /// Готовые результаты (ML-детектор)
+    ready: std::vec::IntoIter<DetectedQR>,
+    /// Группы, кропы которых ещё не построены
+    groups: std::vec::IntoIter<[FinderPattern; 3]>,
+    /// Одиночные finder-паттерны (кандидаты в Micro QR)
+    singles: std::vec::IntoIter<FinderPattern>,
+    /// Выдан ли хотя бы один результат (иначе в конце — весь кадр)
+    found: bool,
+}
+
+impl Iterator for Detections<'_> {
+    type Item = DetectedQR;
+
+    fn next(&mut self) -> Option<DetectedQR> {
+        if let Some(detected) = self.ready.next() {
+            return Some(detected);
+        }
+
+        // 3. Для каждой группы создаём DetectedQR
+        for group in self.groups.by_ref() {
+            if let Some(detected) = self.detector.crop_group(self.img, &self.binary, &group) {
+                self.found = true;
+                return Some(detected);
+            }
+        }
+
+        // Одиночные паттерны не отменяют проверку всего кадра: это лишь кандидаты
+        for pattern in self.singles.by_ref() {
+            if let Some(detected) = self.detector.crop_single(self.img, &pattern) {
+                return Some(detected);
+            }
+        }
+
+        // Если поиск по паттернам не дал результатов, возвращаем всё изображение
+        if self.found || !self.detector.config.whole_image_fallback {
+            return None;
+        }
+        self.found = true;
+        let (width, height) = self.img.dimensions();
+        Some(DetectedQR {
+            bbox: [0, 0, width, height],
+            corners: [(0, 0), (width, 0), (width, height), (0, height)],
+            image: self.img.clone(),
+            confidence: 0.5,
+            homography: None,
+            class: None,
+            label: None,
+            tilt: None,
+        })
+    }
 }
 
 /// Детектор QR-кодов
@@ -69,255 +356,481 @@ impl QRDetector {
         }
     }
 
+    /// Текущая конфигурация
+    pub fn config(&self) -> &DetectorConfig {
+        &self.config
+    }
+
     /// Установка ML детектора
     #[cfg(feature = "ml")]
     pub fn set_ml_detector(&mut self, detector: OnnxDetector) {
         self.ml_detector = Some(detector);
     }
+
+    /// Установленный ML детектор
+    #[cfg(feature = "ml")]
+    pub fn ml_detector(&self) -> Option<&OnnxDetector> {
+        self.ml_detector.as_ref()
+    }
     
     /// Обнаружение всех QR-кодов на изображении
     pub fn detect(&self, img: &GrayImage) -> Vec<DetectedQR> {
+        self.detect_iter(img).collect()
+    }
+
+    /// Ленивое обнаружение: finder-паттерны ищутся сразу, а кроп каждого кода
+    /// строится только при запросе следующего элемента. Вызывающий может
+    /// декодировать и отбросить кроп до построения следующего.
+    ///
+    /// Порядок и содержимое элементов совпадают с `detect`.
+    pub fn detect_iter<'a>(&'a self, img: &'a GrayImage) -> impl Iterator<Item = DetectedQR> + 'a {
+        let mut detections = Detections {
+            detector: self,
+            img,
+            binary: GrayImage::new(0, 0),
+            ready: Vec::new().into_iter(),
+            groups: Vec::new().into_iter(),
+            singles: Vec::new().into_iter(),
+            found: true,
+        };
+        if img.width() == 0 || img.height() == 0 {
+            return detections;
+        }
+
         // 0. Если есть ML-детектор, пробуем его сначала (или комбинируем)
         #[cfg(feature = "ml")]
         if let Some(ml) = &self.ml_detector {
            if let Ok(ml_results) = ml.detect(img) {
                if !ml_results.is_empty() {
                    // Можно вернуть ML результаты, или объединить с алгоритмическими
-                   return ml_results;
+                   detections.ready = ml_results.into_iter();
+                   return detections;
                }
            }
         }
 
-        let mut results = Vec::new();
+        // Порог вычисляется один раз на кадр; поиск паттернов идёт по бинарному изображению
+        detections.binary = self.binarize(img);
         
         // 1. Поиск finder patterns
-        let patterns = self.find_finder_patterns(img);
+        let patterns = self.find_finder_patterns(&detections.binary);
         
-        // 2. Группировка паттернов в тройки (3 finder pattern = 1 QR)
+        // 2. Группировка паттернов в тройки (3 finder pattern = 1 QR);
+        // кропы (шаг 3) строятся в `Detections::next`
         let groups = self.group_patterns(&patterns);
-        
-        // 3. Для каждой группы создаём DetectedQR
-        for group in groups {
-            if let Some(detected) = self.extract_qr(img, &group) {
-                results.push(detected);
-            }
-        }
-        
-        // Если поиск по паттернам не дал результатов, возвращаем всё изображение
-        if results.is_empty() {
-            let (width, height) = img.dimensions();
-            results.push(DetectedQR {
-                bbox: [0, 0, width, height],
-                corners: [(0, 0), (width, 0), (width, height), (0, height)],
-                image: img.clone(),
-                confidence: 0.5,
-            });
+        detections.singles = self.single_patterns(&patterns, &groups).into_iter();
+        detections.groups = groups.into_iter();
+        detections.found = false;
+        detections
+    }
+
+    /// Кроп одной группы finder-паттернов
+    fn crop_group(&self, img: &GrayImage, binary: &GrayImage, group: &[FinderPattern; 3]) -> Option<DetectedQR> {
+        if self.config.perspective_correct {
+            self.rectify_qr(img, binary, group).or_else(|| self.extract_qr(img, group))
+        } else {
+            self.extract_qr(img, group)
         }
-        
-        results
     }
     
-    /// Поиск finder patterns (паттерны 1:1:3:1:1)
+    /// Паттерны вне троек, подтверждённые хотя бы дважды, по убыванию числа подтверждений
+    fn single_patterns(&self, patterns: &[FinderPattern], groups: &[[FinderPattern; 3]]) -> Vec<FinderPattern> {
+        let grouped = |p: &FinderPattern| {
+            groups
+                .iter()
+                .flatten()
+                .any(|g| g.center_x == p.center_x && g.center_y == p.center_y)
+        };
+        let mut singles: Vec<FinderPattern> =
+            patterns.iter().filter(|p| p.count >= 2 && !grouped(p)).cloned().collect();
+        singles.sort_by_key(|p| std::cmp::Reverse(p.count));
+        singles.truncate(self.config.max_single_finder_crops);
+        singles
+    }
+
+    /// Квадратный кроп вокруг одиночного finder-паттерна, вмещающий Micro QR M4
+    /// (17 модулей) с quiet zone при любом положении паттерна в углу кода
+    fn crop_single(&self, img: &GrayImage, pattern: &FinderPattern) -> Option<DetectedQR> {
+        // От центра паттерна до дальнего края M4: 17 - 3.5 модуля, плюс quiet zone 2 модуля
+        const REACH_MODULES: f32 = 15.5;
+        let reach = (REACH_MODULES * pattern.module_size).ceil() as i64;
+        let (width, height) = img.dimensions();
+        let x0 = (pattern.center_x as i64 - reach).max(0) as u32;
+        let y0 = (pattern.center_y as i64 - reach).max(0) as u32;
+        let x1 = ((pattern.center_x as i64 + reach) as u32).min(width);
+        let y1 = ((pattern.center_y as i64 + reach) as u32).min(height);
+        let (w, h) = (x1.checked_sub(x0)?, y1.checked_sub(y0)?);
+        if w.min(h) < self.config.min_size || w.max(h) > self.config.max_size {
+            return None;
+        }
+
+        Some(DetectedQR {
+            bbox: [x0, y0, w, h],
+            corners: [(x0, y0), (x1, y0), (x1, y1), (x0, y1)],
+            image: image::imageops::crop_imm(img, x0, y0, w, h).to_image(),
+            // Формы тройки нет, поэтому учитывается только половина `group_confidence`
+            confidence: 0.5 * (1.0 - (pattern.ratio_error / self.config.ratio_tolerance).min(1.0)),
+            homography: None,
+            class: None,
+            label: None,
+            tilt: None,
+        })
+    }
+
+    /// Бинаризация кадра по `config.threshold` (0 — тёмный, 255 — светлый)
+    fn binarize(&self, img: &GrayImage) -> GrayImage {
+        let method = match self.config.threshold {
+            ThresholdMode::Fixed(threshold) => BinarizationMethod::Fixed(threshold),
+            ThresholdMode::Otsu => BinarizationMethod::Otsu,
+            ThresholdMode::LocalMean => return local_mean_binarize(img, LOCAL_MEAN_WINDOW),
+        };
+        ImageProcessor::new(ProcessingConfig::default()).binarize(img, method)
+    }
+
+    /// Поиск finder patterns (паттерны 1:1:3:1:1) на бинаризованном изображении
+    ///
+    /// Строки сканируются с проверкой по вертикали, столбцы — с проверкой
+    /// по горизонтали; кандидаты обоих проходов объединяются в `merge_patterns`.
     fn find_finder_patterns(&self, img: &GrayImage) -> Vec<FinderPattern> {
+        let mut patterns = self.scan_lines(img, Axis::Horizontal);
+        patterns.extend(self.scan_lines(img, Axis::Vertical));
+        
+        // Удаление дубликатов
+        self.merge_patterns(patterns)
+    }
+
+    /// Проход по всем линиям одного направления
+    fn scan_lines(&self, img: &GrayImage, axis: Axis) -> Vec<FinderPattern> {
         let mut patterns = Vec::new();
-        let (width, height) = img.dimensions();
-        let threshold = self.config.threshold;
+        let (lines, length) = axis.extent(img);
         
-        // Сканируем горизонтальные линии
-        for y in 0..height {
+        for line in 0..lines {
             let mut state_count = [0u32; 5];
             let mut current_state = 0usize;
             
-            for x in 0..width {
-                let pixel = img.get_pixel(x, y).0[0];
-                let is_black = pixel < threshold;
+            for pos in 0..length {
+                let is_black = self.is_black(img, axis, line, pos);
                 
-                // Переключение состояния
                 if is_black {
-                    // Чёрный пиксель
                     if current_state % 2 == 1 {
                         // Переход white -> black
                         current_state += 1;
-                        if current_state >= 5 {
-                            // Проверяем паттерн
-                            if self.check_ratio(&state_count) {
-                                let total_width: u32 = state_count.iter().sum();
-                                let center_x = x - total_width / 2;
-                                
-                                // Верификация по вертикали
-                                if self.verify_vertical(img, center_x, y, &state_count) {
-                                    let module_size = total_width as f32 / 7.0;
-                                    patterns.push(FinderPattern {
-                                        center_x,
-                                        center_y: y,
-                                        module_size,
-                                    });
-                                }
-                            }
-                            
-                            // Сдвиг состояний
-                            state_count[0] = state_count[2];
-                            state_count[1] = state_count[3];
-                            state_count[2] = state_count[4];
-                            state_count[3] = 1;
-                            state_count[4] = 0;
-                            current_state = 3;
-                        }
                     }
                     state_count[current_state] += 1;
-                } else {
-                    // Белый пиксель
-                    if current_state % 2 == 0 {
-                        // Переход black -> white
-                        current_state += 1;
-                        if current_state >= 5 {
-                            current_state = 4;
-                        }
+                } else if current_state == 0 {
+                    // Белый фон до первого чёрного отрезка
+                    if state_count[0] > 0 {
+                        current_state = 1;
+                        state_count[1] = 1;
                     }
-                    if current_state < 5 {
-                        state_count[current_state] += 1;
+                } else if current_state % 2 == 1 {
+                    state_count[current_state] += 1;
+                } else if current_state == 4 {
+                    // Переход black -> white после пятого отрезка: проверяем паттерн
+                    if let Some(pattern) = self.check_candidate(img, axis, line, pos, &state_count) {
+                        patterns.push(pattern);
                     }
+                    
+                    // Сдвиг окна на два отрезка и при успехе, и при неудаче: каждый чёрный
+                    // отрезок становится началом окна, поэтому паттерн сразу за частичным
+                    // или за соседним паттерном (через светлый отрезок) не теряется
+                    state_count = [state_count[2], state_count[3], state_count[4], 1, 0];
+                    current_state = 3;
+                } else {
+                    // Переход black -> white
+                    current_state += 1;
+                    state_count[current_state] += 1;
+                }
+            }
+            
+            // Паттерн, упирающийся в край изображения
+            if current_state == 4 {
+                if let Some(pattern) = self.check_candidate(img, axis, line, length, &state_count) {
+                    patterns.push(pattern);
                 }
             }
         }
         
-        // Удаление дубликатов
-        self.merge_patterns(patterns)
+        patterns
     }
-    
-    /// Проверка соотношения 1:1:3:1:1
-    fn check_ratio(&self, counts: &[u32; 5]) -> bool {
-        let total: u32 = counts.iter().sum();
-        if total < 7 {
-            return false;
+
+    /// Проверка кандидата, закончившегося перед позицией `end` линии `line`,
+    /// с подтверждением в перпендикулярном направлении
+    fn check_candidate(
+        &self,
+        img: &GrayImage,
+        axis: Axis,
+        line: u32,
+        end: u32,
+        counts: &[u32; 5],
+    ) -> Option<FinderPattern> {
+        let error = self.ratio_error(counts);
+        if error > self.config.ratio_tolerance {
+            return None;
         }
         
-        let module_size = total as f32 / 7.0;
-        let tolerance = module_size * self.config.ratio_tolerance;
+        let total: u32 = counts.iter().sum();
+        let center = end - counts[4] - counts[3] - counts[2] / 2;
+        let (cross_center, cross_total, cross_error) = self.cross_check(img, axis.perpendicular(), center, line, total)?;
         
-        // Проверяем каждый сегмент
-        let expected = [1.0, 1.0, 3.0, 1.0, 1.0];
+        let (center_x, center_y) = match axis {
+            Axis::Horizontal => (center, cross_center),
+            Axis::Vertical => (cross_center, center),
+        };
+        Some(FinderPattern {
+            center_x,
+            center_y,
+            module_size: (total + cross_total) as f32 / 14.0,
+            count: 1,
+            ratio_error: error.max(cross_error),
+        })
+    }
+
+    /// Подтверждение паттерна вдоль линии `line` направления `axis`, начиная с `start`
+    ///
+    /// Отрезки считаются от центра в обе стороны. Возвращает уточнённый центр,
+    /// суммарную длину паттерна и `ratio_error`, если соотношение и размер совпадают с исходным.
+    fn cross_check(&self, img: &GrayImage, axis: Axis, line: u32, start: u32, expected_total: u32) -> Option<(u32, u32, f32)> {
+        let (_, length) = axis.extent(img);
+        let mut counts = [0u32; 5];
         
-        for (i, &count) in counts.iter().enumerate() {
-            let expected_size = expected[i] * module_size;
-            if (count as f32 - expected_size).abs() > tolerance {
-                return false;
+        // Назад от центра: центральный чёрный, белый, внешний чёрный
+        let mut pos = start as i64;
+        for (state, black) in [(2, true), (1, false), (0, true)] {
+            while pos >= 0 && self.is_black(img, axis, line, pos as u32) == black {
+                counts[state] += 1;
+                pos -= 1;
             }
         }
         
-        true
-    }
-    
-    /// Верификация паттерна по вертикали
-    fn verify_vertical(&self, img: &GrayImage, center_x: u32, center_y: u32, h_counts: &[u32; 5]) -> bool {
-        let (_, height) = img.dimensions();
-        let threshold = self.config.threshold;
-        
-        let mut v_counts = [0u32; 5];
-        let total_h: u32 = h_counts.iter().sum();
-        let check_range = total_h / 2;
-        
-        // Сканируем вверх и вниз от центра
-        let start_y = center_y.saturating_sub(check_range);
-        let end_y = (center_y + check_range).min(height - 1);
-        
-        let mut state = 0usize;
-        for y in start_y..=end_y {
-            let pixel = img.get_pixel(center_x, y).0[0];
-            let is_black = pixel < threshold;
-            
-            let expected_black = state % 2 == 0;
-            
-            if is_black == expected_black {
-                v_counts[state] += 1;
-            } else {
-                state += 1;
-                if state >= 5 {
-                    break;
-                }
-                v_counts[state] = 1;
+        // Вперёд от центра
+        let mut pos = start + 1;
+        for (state, black) in [(2, true), (3, false), (4, true)] {
+            while pos < length && self.is_black(img, axis, line, pos) == black {
+                counts[state] += 1;
+                pos += 1;
             }
         }
         
-        self.check_ratio(&v_counts)
+        let total: u32 = counts.iter().sum();
+        let error = self.ratio_error(&counts);
+        // Размер в перпендикулярном направлении не должен сильно отличаться
+        if 5 * total.abs_diff(expected_total) >= 2 * expected_total || error > self.config.ratio_tolerance {
+            return None;
+        }
+        
+        Some((pos - counts[4] - counts[3] - counts[2] / 2, total, error))
+    }
+
+    /// Тёмный ли пиксель в позиции `pos` линии `line` бинаризованного изображения
+    fn is_black(&self, img: &GrayImage, axis: Axis, line: u32, pos: u32) -> bool {
+        let (x, y) = match axis {
+            Axis::Horizontal => (pos, line),
+            Axis::Vertical => (line, pos),
+        };
+        img.get_pixel(x, y).0[0] < 128
     }
     
-    /// Объединение близких паттернов
-    fn merge_patterns(&self, patterns: Vec<FinderPattern>) -> Vec<FinderPattern> {
-        if patterns.is_empty() {
-            return patterns;
+    /// Наибольшее отклонение отрезка от 1:1:3:1:1 в модулях
+    /// (бесконечность, если паттерн короче 7 пикселей); паттерн принимается
+    /// при отклонении не больше `ratio_tolerance`
+    fn ratio_error(&self, counts: &[u32; 5]) -> f32 {
+        let total: u32 = counts.iter().sum();
+        if total < 7 {
+            return f32::INFINITY;
         }
         
-        let mut merged = Vec::new();
-        let mut used = vec![false; patterns.len()];
+        let module_size = total as f32 / 7.0;
+        let expected = [1.0, 1.0, 3.0, 1.0, 1.0];
         
-        for (i, p1) in patterns.iter().enumerate() {
-            if used[i] {
-                continue;
-            }
-            
-            let mut sum_x = p1.center_x as f32;
-            let mut sum_y = p1.center_y as f32;
-            let mut sum_size = p1.module_size;
-            let mut count = 1.0f32;
-            
-            for (j, p2) in patterns.iter().enumerate().skip(i + 1) {
-                if used[j] {
+        counts
+            .iter()
+            .zip(expected)
+            .map(|(&count, expected)| (count as f32 - expected * module_size).abs() / module_size)
+            .fold(0.0, f32::max)
+    }
+    
+    /// Объединение близких паттернов
+    ///
+    /// Кластеры сливаются (union-find), пока расстояние между их центрами меньше
+    /// двух модулей — среднего размера модуля объединённого кластера; проходы
+    /// повторяются до неподвижной точки, поэтому результат не зависит от порядка.
+    fn merge_patterns(&self, patterns: Vec<FinderPattern>) -> Vec<FinderPattern> {
+        let mut clusters: Vec<PatternCluster> = patterns.iter().map(PatternCluster::new).collect();
+        let mut parent: Vec<usize> = (0..patterns.len()).collect();
+
+        loop {
+            let mut changed = false;
+            for i in 0..parent.len() {
+                if parent[i] != i {
                     continue;
                 }
-                
-                let dist = ((p1.center_x as f32 - p2.center_x as f32).powi(2) +
-                           (p1.center_y as f32 - p2.center_y as f32).powi(2))
-                    .sqrt();
-                
-                // Объединяем если расстояние меньше 2 размеров модуля
-                if dist < p1.module_size * 2.0 {
-                    sum_x += p2.center_x as f32;
-                    sum_y += p2.center_y as f32;
-                    sum_size += p2.module_size;
-                    count += 1.0;
-                    used[j] = true;
+                for j in i + 1..parent.len() {
+                    // Слияние подвешивает j прямо к корню i, поэтому find не нужен
+                    if parent[j] != j {
+                        continue;
+                    }
+                    let joined = clusters[i].join(&clusters[j]);
+                    if clusters[i].distance(&clusters[j]) < joined.module_size() * 2.0 {
+                        clusters[i] = joined;
+                        parent[j] = i;
+                        changed = true;
+                    }
                 }
             }
-            
-            merged.push(FinderPattern {
-                center_x: (sum_x / count) as u32,
-                center_y: (sum_y / count) as u32,
-                module_size: sum_size / count,
-            });
-            used[i] = true;
+            if !changed {
+                break;
+            }
         }
-        
-        merged
+
+        (0..parent.len())
+            .filter(|&i| parent[i] == i)
+            .map(|i| clusters[i].pattern())
+            .collect()
     }
     
     /// Группировка паттернов в тройки
+    ///
+    /// Каждый паттерн входит не более чем в одну тройку: среди пересекающихся
+    /// троек остаются наиболее близкие к квадрату. Перебираются только тройки,
+    /// все пары которых правдоподобны по расстоянию и размеру модуля
+    /// (`plausible_pair`); соседи ищутся по сетке, а кандидатов не больше
+    /// `max_finder_candidates`.
     fn group_patterns(&self, patterns: &[FinderPattern]) -> Vec<[FinderPattern; 3]> {
         let mut groups = Vec::new();
         
         if patterns.len() < 3 {
             return groups;
         }
+
+        // Лучшие кандидаты по числу подтверждений (при равенстве — в порядке обнаружения)
+        let mut ranked: Vec<&FinderPattern> = patterns.iter().collect();
+        ranked.sort_by_key(|p| std::cmp::Reverse(p.count));
+        ranked.truncate(self.config.max_finder_candidates);
+        let patterns = ranked;
+
+        let neighbors = self.plausible_neighbors(&patterns);
+        // Тройки с наименьшим индексом i независимы. С feature `rayon` они
+        // перебираются параллельно; collect сохраняет порядок по i, поэтому
+        // результат совпадает с последовательным.
+        #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+        let mut candidates: Vec<(f32, [usize; 3])> = {
+            use rayon::prelude::*;
+            (0..patterns.len())
+                .into_par_iter()
+                .flat_map_iter(|i| self.groups_from(i, &patterns, &neighbors))
+                .collect()
+        };
+        #[cfg(not(all(feature = "rayon", not(target_arch = "wasm32"))))]
+        let mut candidates: Vec<(f32, [usize; 3])> = (0..patterns.len())
+            .flat_map(|i| self.groups_from(i, &patterns, &neighbors))
+            .collect();
         
-        // Простая эвристика: берём все комбинации из 3 паттернов
-        // и проверяем, образуют ли они правильный угол
-        for i in 0..patterns.len() {
-            for j in (i + 1)..patterns.len() {
-                for k in (j + 1)..patterns.len() {
-                    let p1 = &patterns[i];
-                    let p2 = &patterns[j];
-                    let p3 = &patterns[k];
-                    
-                    if self.is_valid_qr_group(p1, p2, p3) {
-                        groups.push([p1.clone(), p2.clone(), p3.clone()]);
-                    }
-                }
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut used = vec![false; patterns.len()];
+        for (_, indices) in candidates {
+            if indices.iter().any(|&i| used[i]) {
+                continue;
             }
+            for &i in &indices {
+                used[i] = true;
+            }
+            groups.push(indices.map(|i| patterns[i].clone()));
         }
         
         groups
     }
+
+    /// Допустимые тройки `[i, j, k]` с `i < j < k` и их ошибка геометрии
+    fn groups_from(&self, i: usize, patterns: &[&FinderPattern], neighbors: &[Vec<usize>]) -> Vec<(f32, [usize; 3])> {
+        let near = &neighbors[i];
+        let mut groups = Vec::new();
+        // near отсортирован, поэтому j < k
+        for (a, &j) in near.iter().enumerate().filter(|(_, &j)| j > i) {
+            for &k in &near[a + 1..] {
+                if neighbors[j].binary_search(&k).is_err() {
+                    continue;
+                }
+                let (p1, p2, p3) = (patterns[i], patterns[j], patterns[k]);
+                if self.is_valid_qr_group(p1, p2, p3) {
+                    groups.push((self.group_error(p1, p2, p3), [i, j, k]));
+                }
+            }
+        }
+        groups
+    }
+
+    /// Для каждого паттерна — отсортированные индексы правдоподобных соседей.
+    ///
+    /// Паттерны раскладываются по сетке с ячейкой не меньше максимального
+    /// допустимого расстояния, поэтому соседи ищутся только в 3x3 ячейках.
+    fn plausible_neighbors(&self, patterns: &[&FinderPattern]) -> Vec<Vec<usize>> {
+        let max_module = patterns.iter().map(|p| p.module_size).fold(0.0f32, f32::max);
+        let cell = (MAX_FINDER_SPAN * max_module).max(1.0);
+        let cell_of = |p: &FinderPattern| ((p.center_x as f32 / cell) as i64, (p.center_y as f32 / cell) as i64);
+
+        let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, p) in patterns.iter().enumerate() {
+            grid.entry(cell_of(p)).or_default().push(i);
+        }
+
+        patterns
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let (cx, cy) = cell_of(p);
+                let mut near: Vec<usize> = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (cx + dx, cy + dy)))
+                    .filter_map(|key| grid.get(&key))
+                    .flatten()
+                    .copied()
+                    .filter(|&j| j != i && self.plausible_pair(p, patterns[j]))
+                    .collect();
+                near.sort_unstable();
+                near
+            })
+            .collect()
+    }
+
+    /// Могут ли два паттерна принадлежать одному коду: модули отличаются
+    /// не более чем втрое, расстояние — от `MIN_FINDER_SPAN` до `MAX_FINDER_SPAN` модулей
+    fn plausible_pair(&self, p1: &FinderPattern, p2: &FinderPattern) -> bool {
+        let (small, large) = if p1.module_size <= p2.module_size {
+            (p1.module_size, p2.module_size)
+        } else {
+            (p2.module_size, p1.module_size)
+        };
+        if large > small * 3.0 {
+            return false;
+        }
+        let distance = self.distance(p1, p2);
+        distance >= MIN_FINDER_SPAN * small && distance <= MAX_FINDER_SPAN * large
+    }
+
+    /// Отклонение тройки от равнобедренного прямоугольного треугольника
+    /// (относительные разница катетов и ошибка диагонали)
+    fn group_error(&self, p1: &FinderPattern, p2: &FinderPattern, p3: &FinderPattern) -> f32 {
+        let mut distances = [self.distance(p1, p2), self.distance(p2, p3), self.distance(p1, p3)];
+        distances.sort_by(|a, b| a.total_cmp(b));
+        let [side1, side2, diagonal] = distances;
+        let expected_diagonal = (side1 * side1 + side2 * side2).sqrt();
+        (side1 - side2).abs() / side2.max(1.0) + (diagonal - expected_diagonal).abs() / expected_diagonal.max(1.0)
+    }
     
+    /// Уверенность детекции по группе (0-1): насколько точно finder-паттерны
+    /// совпали с 1:1:3:1:1 относительно `ratio_tolerance` и насколько тройка
+    /// близка к равнобедренному прямоугольному треугольнику
+    fn group_confidence(&self, group: &[FinderPattern; 3]) -> f32 {
+        // Наибольшая `group_error`, которую ещё пропускает `is_valid_qr_group`
+        const MAX_GROUP_ERROR: f32 = 0.6;
+
+        let ratio_error = group.iter().map(|p| p.ratio_error).sum::<f32>() / 3.0;
+        let ratio_quality = 1.0 - (ratio_error / self.config.ratio_tolerance).min(1.0);
+        let shape_error = self.group_error(&group[0], &group[1], &group[2]);
+        let shape_quality = 1.0 - (shape_error / MAX_GROUP_ERROR).min(1.0);
+        (0.5 * ratio_quality + 0.5 * shape_quality).clamp(0.0, 1.0)
+    }
+
     /// Проверка, образуют ли 3 паттерна валидный QR
     fn is_valid_qr_group(&self, p1: &FinderPattern, p2: &FinderPattern, p3: &FinderPattern) -> bool {
         // Размеры модулей должны быть примерно одинаковыми
@@ -391,9 +904,273 @@ impl QRDetector {
                 (x, y + h),
             ],
             image: cropped,
-            confidence: 0.8,
+            confidence: self.group_confidence(group),
+            homography: None,
+            class: None,
+            label: None,
+            tilt: Some(Self::group_tilt(group)),
         })
     }
+
+    /// Выпрямление QR по центрам трёх finder-паттернов
+    ///
+    /// Четвёртый угол достраивается до параллелограмма, поэтому сдвиг и поворот
+    /// компенсируются полностью, а сильная перспектива — приближённо.
+    /// Результат — квадрат с целым числом пикселей на модуль и quiet zone 4 модуля.
+    /// Alignment-паттерны ищутся на `binary` — бинаризованной копии `img`.
+    fn rectify_qr(&self, img: &GrayImage, binary: &GrayImage, group: &[FinderPattern; 3]) -> Option<DetectedQR> {
+        const QUIET: f32 = 4.0;
+
+        let grid = Self::estimate_grid(group)?;
+        let dimension = grid.dimension();
+        let scale = grid.module.ceil().max(3.0);
+        let side = ((dimension + QUIET * 2.0) * scale) as u32;
+        if side > self.config.max_size.saturating_mul(2) {
+            return None;
+        }
+
+        let to_dst = |module: Point2<f32>| Point2::new((QUIET + module.x) * scale, (QUIET + module.y) * scale);
+        let (near, far) = (3.5, dimension - 3.5);
+        let finders = [
+            (grid.tl, Point2::new(near, near)),
+            (grid.tr, Point2::new(far, near)),
+            (grid.bl, Point2::new(near, far)),
+        ];
+        let alignments = self.find_alignment_patterns(binary, group);
+        let h = if alignments.is_empty() {
+            // Четвёртая точка — достроенный параллелограмм
+            let br = grid.tr + (grid.bl - grid.tl);
+            let dst = [(near, near), (far, near), (far, far), (near, far)].map(|(x, y)| to_dst(Point2::new(x, y)));
+            geometry::find_homography([grid.tl, grid.tr, br, grid.bl], dst)?
+        } else {
+            // Alignment-паттерны компенсируют перспективу, которую параллелограмм не учитывает
+            let (src, dst): (Vec<_>, Vec<_>) = finders
+                .iter()
+                .map(|&(frame, module)| (frame, to_dst(module)))
+                .chain(alignments.iter().map(|a| (a.center, to_dst(a.module))))
+                .unzip();
+            geometry::find_homography_ransac(&src, &dst, 50, scale * 1.5)?
+        };
+        let inverse = h.try_inverse()?;
+
+        // Углы кода (без quiet zone) в координатах кадра
+        let code_min = QUIET * scale;
+        let code_max = (QUIET + dimension) * scale;
+        let corners = [
+            (code_min, code_min),
+            (code_max, code_min),
+            (code_max, code_max),
+            (code_min, code_max),
+        ]
+        .map(|(x, y)| {
+            let p = inverse * Vector3::new(x, y, 1.0);
+            (p.x / p.z, p.y / p.z)
+        });
+        if corners.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+            return None;
+        }
+
+        let (width, height) = img.dimensions();
+        let clamp_x = |v: f32| v.clamp(0.0, width as f32) as u32;
+        let clamp_y = |v: f32| v.clamp(0.0, height as f32) as u32;
+        let x0 = clamp_x(corners.iter().map(|c| c.0).fold(f32::MAX, f32::min));
+        let x1 = clamp_x(corners.iter().map(|c| c.0).fold(f32::MIN, f32::max));
+        let y0 = clamp_y(corners.iter().map(|c| c.1).fold(f32::MAX, f32::min));
+        let y1 = clamp_y(corners.iter().map(|c| c.1).fold(f32::MIN, f32::max));
+        let (w, h_box) = (x1 - x0, y1 - y0);
+        if w.max(h_box) < self.config.min_size || w.min(h_box) > self.config.max_size {
+            return None;
+        }
+
+        let image = geometry::warp_perspective(
+            img,
+            &h,
+            side,
+            side,
+            geometry::Interpolation::Bilinear,
+            geometry::DEFAULT_FILL,
+        )?;
+
+        Some(DetectedQR {
+            bbox: [x0, y0, w, h_box],
+            corners: corners.map(|(x, y)| (clamp_x(x), clamp_y(y))),
+            image,
+            confidence: self.group_confidence(group),
+            homography: Some(inverse),
+            class: None,
+            label: None,
+            tilt: None,
+        })
+    }
+
+    /// Оценка сетки по тройке finder-паттернов: размер в модулях — расстояние
+    /// между центрами + 7, округлённое до 17 + 4 * version
+    fn estimate_grid(group: &[FinderPattern; 3]) -> Option<GridEstimate> {
+        let [tl, tr, bl] = Self::order_group(group);
+        let point = |p: &FinderPattern| Point2::new(p.center_x as f32, p.center_y as f32);
+        let (tl, tr, bl) = (point(tl), point(tr), point(bl));
+
+        let module = group.iter().map(|p| p.module_size).sum::<f32>() / 3.0;
+        if module <= 0.0 {
+            return None;
+        }
+        let span = ((tr - tl).norm() + (bl - tl).norm()) / 2.0 / module;
+        let version = ((span + 7.0 - 17.0) / 4.0).round().clamp(1.0, 40.0) as u32;
+        Some(GridEstimate { tl, tr, bl, module, version })
+    }
+
+    /// Поиск alignment-паттернов в ожидаемых по версии местах
+    ///
+    /// Каждое место (кроме занятых finder-паттернами) проецируется в кадр
+    /// по параллелограмму из `finders`; в окне вокруг проекции ищется центр
+    /// с отрезками светлый-тёмный-светлый 1:1:1 по горизонтали и вертикали
+    /// и тёмным внешним кольцом. Из кандидатов берётся ближайший к проекции.
+    fn find_alignment_patterns(&self, img: &GrayImage, finders: &[FinderPattern; 3]) -> Vec<AlignmentPattern> {
+        let Some(grid) = Self::estimate_grid(finders) else {
+            return Vec::new();
+        };
+        let positions = alignment_positions(grid.version);
+        let last = grid.dimension() as u32 - 7;
+        let radius = grid.module * 4.0;
+
+        let mut patterns = Vec::new();
+        for &row in &positions {
+            for &col in &positions {
+                if (row == 6 && (col == 6 || col == last)) || (row == last && col == 6) {
+                    continue;
+                }
+                let module = Point2::new(col as f32 + 0.5, row as f32 + 0.5);
+                let expected = grid.project(module);
+                if let Some(center) = self.search_alignment(img, expected, radius, grid.module) {
+                    patterns.push(AlignmentPattern { center, module });
+                }
+            }
+        }
+        patterns
+    }
+
+    /// Центр alignment-паттерна в окне `radius` вокруг `expected`, ближайший к нему
+    fn search_alignment(&self, img: &GrayImage, expected: Point2<f32>, radius: f32, module: f32) -> Option<Point2<f32>> {
+        let (width, height) = img.dimensions();
+        let x0 = (expected.x - radius).max(0.0) as u32;
+        let x1 = ((expected.x + radius).max(0.0) as u32).min(width);
+        let y0 = (expected.y - radius).max(0.0) as u32;
+        let y1 = ((expected.y + radius).max(0.0) as u32).min(height);
+        let fits = |run: u32| (run as f32 - module).abs() <= module * self.config.ratio_tolerance.max(0.5);
+
+        let mut best: Option<(f32, Point2<f32>)> = None;
+        for y in y0..y1 {
+            // Отрезки строки в окне: (начало, длина, тёмный)
+            let mut runs: Vec<(u32, u32, bool)> = Vec::new();
+            for x in x0..x1 {
+                let black = self.is_black(img, Axis::Horizontal, y, x);
+                match runs.last_mut() {
+                    Some(run) if run.2 == black => run.1 += 1,
+                    _ => runs.push((x, 1, black)),
+                }
+            }
+            for window in runs.windows(3) {
+                let [(_, before, false), (start, len, true), (_, after, false)] = *window else {
+                    continue;
+                };
+                if !fits(before) || !fits(len) || !fits(after) {
+                    continue;
+                }
+                let cx = start + len / 2;
+                let Some(cy) = self.cross_check_alignment(img, cx, y, module, &fits) else {
+                    continue;
+                };
+                // Внешнее тёмное кольцо на расстоянии двух модулей
+                let ring = (2.0 * module).round() as u32;
+                let dark = |x: Option<u32>, y: Option<u32>| match (x, y) {
+                    (Some(x), Some(y)) if x < width && y < height => self.is_black(img, Axis::Horizontal, y, x),
+                    _ => false,
+                };
+                let outer = [
+                    dark(cx.checked_sub(ring), Some(cy)),
+                    dark(Some(cx + ring), Some(cy)),
+                    dark(Some(cx), cy.checked_sub(ring)),
+                    dark(Some(cx), Some(cy + ring)),
+                ];
+                if !outer.iter().all(|&d| d) {
+                    continue;
+                }
+                let center = Point2::new(cx as f32 + 0.5, cy as f32 + 0.5);
+                let distance = (center - expected).norm();
+                if best.as_ref().is_none_or(|(d, _)| distance < *d) {
+                    best = Some((distance, center));
+                }
+            }
+        }
+        best.map(|(_, center)| center)
+    }
+
+    /// Подтверждение центра alignment-паттерна по вертикали; возвращает уточнённую строку
+    fn cross_check_alignment(&self, img: &GrayImage, x: u32, y: u32, module: f32, fits: &dyn Fn(u32) -> bool) -> Option<u32> {
+        let height = img.height();
+        let limit = (module * 3.0).ceil() as u32;
+        let black = |y: u32| self.is_black(img, Axis::Vertical, x, y);
+        let run = |mut pos: i64, step: i64, dark: bool| {
+            let mut count = 0u32;
+            while pos >= 0 && pos < height as i64 && black(pos as u32) == dark && count <= limit {
+                count += 1;
+                pos += step;
+            }
+            (count, pos)
+        };
+
+        let (up, top) = run(y as i64, -1, true);
+        let (down, bottom) = run(y as i64 + 1, 1, true);
+        let (light_up, _) = run(top, -1, false);
+        let (light_down, _) = run(bottom, 1, false);
+        let center = up + down;
+        if !fits(center) || !fits(light_up) || !fits(light_down) {
+            return None;
+        }
+        Some(((top + bottom) / 2) as u32)
+    }
+
+    /// Угол верхней стороны кода (от верхнего левого к верхнему правому паттерну) в градусах
+    fn group_tilt(group: &[FinderPattern; 3]) -> f32 {
+        let [tl, tr, _] = Self::order_group(group);
+        let dx = tr.center_x as f32 - tl.center_x as f32;
+        let dy = tr.center_y as f32 - tl.center_y as f32;
+        dy.atan2(dx).to_degrees()
+    }
+
+    /// Упорядочивание паттернов группы как [TL, TR, BL]
+    ///
+    /// TL лежит напротив самой длинной стороны (диагонали); TR и BL
+    /// различаются знаком векторного произведения (ось Y направлена вниз).
+    fn order_group(group: &[FinderPattern; 3]) -> [&FinderPattern; 3] {
+        let [a, b, c] = group;
+        let (tl, p, q) = {
+            let ab = Self::distance_sq(a, b);
+            let bc = Self::distance_sq(b, c);
+            let ac = Self::distance_sq(a, c);
+            if bc >= ab && bc >= ac {
+                (a, b, c)
+            } else if ac >= ab {
+                (b, a, c)
+            } else {
+                (c, a, b)
+            }
+        };
+
+        let cross = (p.center_x as f32 - tl.center_x as f32) * (q.center_y as f32 - tl.center_y as f32)
+            - (p.center_y as f32 - tl.center_y as f32) * (q.center_x as f32 - tl.center_x as f32);
+        if cross >= 0.0 {
+            [tl, p, q]
+        } else {
+            [tl, q, p]
+        }
+    }
+
+    fn distance_sq(p1: &FinderPattern, p2: &FinderPattern) -> f32 {
+        let dx = p1.center_x as f32 - p2.center_x as f32;
+        let dy = p1.center_y as f32 - p2.center_y as f32;
+        dx * dx + dy * dy
+    }
 }
 
 #[cfg(test)]
@@ -409,14 +1186,353 @@ mod tests {
     #[test]
     fn test_ratio_check() {
         let detector = QRDetector::new(DetectorConfig::default());
+        let check_ratio = |counts: &[u32; 5]| detector.ratio_error(counts) <= detector.config.ratio_tolerance;
         
         // Идеальное соотношение 1:1:3:1:1
-        assert!(detector.check_ratio(&[10, 10, 30, 10, 10]));
+        assert!(check_ratio(&[10, 10, 30, 10, 10]));
         
         // С небольшим отклонением
-        assert!(detector.check_ratio(&[9, 11, 29, 10, 11]));
+        assert!(check_ratio(&[9, 11, 29, 10, 11]));
         
         // Неправильное соотношение
-        assert!(!detector.check_ratio(&[10, 10, 10, 10, 10]));
+        assert!(!check_ratio(&[10, 10, 10, 10, 10]));
+    }
+
+    type Point = (f32, f32);
+
+    /// QR (модуль 6px, quiet zone 4 модуля), повёрнутый и скошенный аффинным преобразованием.
+    /// Возвращает изображение, углы кода и центры finder-паттернов (TL, TR, BL) после преобразования.
+    fn render_sheared_qr(content: &str) -> (GrayImage, [Point; 4], [Point; 3]) {
+        use imageproc::geometric_transformations::{warp, Interpolation, Projection};
+
+        let qr = qrcode::QrCode::new(content).unwrap();
+        let (module, quiet, margin) = (6u32, 4u32, 100u32);
+        let width = qr.width() as u32;
+        let side = (width + quiet * 2) * module + margin * 2;
+        let mut img = GrayImage::from_pixel(side, side, image::Luma([255]));
+        let origin = margin + quiet * module;
+        crate::testing::draw_code(&mut img, &qr, (origin, origin), module);
+
+        let center = side as f32 / 2.0;
+        let shear = Projection::from_matrix([1.0, 0.25, 0.0, 0.1, 1.0, 0.0, 0.0, 0.0, 1.0]).unwrap();
+        let projection = Projection::translate(center, center)
+            * Projection::rotate(20f32.to_radians())
+            * shear
+            * Projection::translate(-center, -center);
+        let warped = warp(&img, &projection, Interpolation::Bilinear, image::Luma([255]));
+
+        let to_px = |modules: f32| (margin + quiet * module) as f32 + modules * module as f32;
+        let (lo, hi) = (to_px(0.0), to_px(width as f32));
+        let corners = [(lo, lo), (hi, lo), (hi, hi), (lo, hi)].map(|p| projection * p);
+        let (near, far) = (to_px(3.5), to_px(width as f32 - 3.5));
+        let finders = [(near, near), (far, near), (near, far)].map(|p| projection * p);
+        (warped, corners, finders)
+    }
+
+    #[test]
+    fn test_perspective_correction() {
+        let (img, expected, [tl, tr, bl]) = render_sheared_qr("https://example.com/angled");
+        let pattern = |(x, y): Point| FinderPattern {
+            center_x: x.round() as u32,
+            center_y: y.round() as u32,
+            module_size: 6.0,
+            count: 1,
+            ratio_error: 0.0,
+        };
+        // Порядок паттернов в группе произвольный
+        let group = [pattern(bl), pattern(tl), pattern(tr)];
+
+        let detector = QRDetector::new(DetectorConfig::default());
+        let rectified = detector.rectify_qr(&img, &img, &group).expect("code rectified");
+        assert_eq!(rectified.image.width(), rectified.image.height());
+
+        let strict = crate::QRDecoder::with_config(crate::DecoderConfig {
+            strategy_order: vec![crate::DecodeStrategy::Standard],
+            ..Default::default()
+        })
+        .unwrap();
+        let decoded = strict.decode(&rectified.image).unwrap();
+        assert_eq!(decoded.content, "https://example.com/angled");
+
+        for (&(x, y), &(ex, ey)) in rectified.corners.iter().zip(&expected) {
+            assert!((x as f32 - ex).abs() < 3.0 && (y as f32 - ey).abs() < 3.0, "{:?} vs {:?}", (x, y), (ex, ey));
+        }
+        // Углы из декодера переводятся обратно в координаты кадра
+        let decoded_tl = rectified.to_frame(decoded.corners.unwrap()[0]);
+        assert!((decoded_tl.0 - expected[0].0).abs() < 6.0 && (decoded_tl.1 - expected[0].1).abs() < 6.0);
+
+        // Без коррекции — прежний прямоугольный кроп
+        let crop = detector.extract_qr(&img, &group).unwrap();
+        assert!(crop.homography.is_none());
+        assert_eq!(crop.to_frame((0.0, 0.0)), (crop.bbox[0] as f32, crop.bbox[1] as f32));
+
+        // Наклон верхней стороны передаётся декодеру только для невыпрямленного кропа
+        let expected_tilt = (tr.1 - tl.1).atan2(tr.0 - tl.0).to_degrees();
+        assert!((crop.tilt.unwrap() - expected_tilt).abs() < 1.0, "{:?} vs {}", crop.tilt, expected_tilt);
+        assert_eq!(rectified.tilt, None);
+    }
+
+    #[test]
+    fn test_grouping_with_many_false_candidates() {
+        let pattern = |x: u32, y: u32, module_size: f32, count: u32| FinderPattern {
+            center_x: x,
+            center_y: y,
+            module_size,
+            count,
+            ratio_error: 0.0,
+        };
+        // Код: центры через 100 px при модуле 5 px (версия 3), подтверждены трижды
+        let real = [(300, 300), (400, 300), (300, 400)];
+        let mut patterns: Vec<FinderPattern> = real.iter().map(|&(x, y)| pattern(x, y, 5.0, 3)).collect();
+        // 200 одиночных ложных срабатываний с разным размером модуля
+        let mut seed = 12345u32;
+        let mut next = |n: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 8) % n
+        };
+        for _ in 0..200 {
+            let (x, y, module) = (next(2000), next(2000), 1.0 + next(40) as f32 / 4.0);
+            patterns.push(pattern(x, y, module, 1));
+        }
+
+        let contains_real = |groups: &[[FinderPattern; 3]]| {
+            groups.iter().any(|g| {
+                let mut centers: Vec<_> = g.iter().map(|p| (p.center_x, p.center_y)).collect();
+                centers.sort();
+                centers == [(300, 300), (300, 400), (400, 300)]
+            })
+        };
+        let detector = QRDetector::new(DetectorConfig::default());
+        assert!(contains_real(&detector.group_patterns(&patterns)));
+
+        // Слабо подтверждённые кандидаты отбрасываются первыми
+        let capped = QRDetector::new(DetectorConfig { max_finder_candidates: 3, ..Default::default() });
+        let groups = capped.group_patterns(&patterns);
+        assert_eq!(groups.len(), 1);
+        assert!(contains_real(&groups));
+
+        // Неправдоподобные пары: слишком близко, слишком далеко, разный модуль
+        let p = pattern(0, 0, 5.0, 1);
+        assert!(!detector.plausible_pair(&p, &pattern(30, 0, 5.0, 1)));
+        assert!(!detector.plausible_pair(&p, &pattern(2000, 0, 5.0, 1)));
+        assert!(!detector.plausible_pair(&p, &pattern(100, 0, 20.0, 1)));
+        assert!(detector.plausible_pair(&p, &pattern(100, 0, 6.0, 1)));
+
+        assert!(DetectorConfig { max_finder_candidates: 2, ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_alignment_positions() {
+        assert!(alignment_positions(1).is_empty());
+        assert_eq!(alignment_positions(2), [6, 18]);
+        assert_eq!(alignment_positions(7), [6, 22, 38]);
+        assert_eq!(alignment_positions(32), [6, 34, 60, 86, 112, 138]);
+        assert_eq!(alignment_positions(40), [6, 30, 58, 86, 114, 142, 170]);
+    }
+
+    #[test]
+    fn test_alignment_patterns_version_7_perspective() {
+        use imageproc::geometric_transformations::{warp, Interpolation, Projection};
+
+        let content = "https://example.com/".to_string() + &"dense-".repeat(20);
+        let qr = qrcode::QrCode::with_version(&content, qrcode::Version::Normal(7), qrcode::EcLevel::L).unwrap();
+        let (module, quiet, margin) = (6u32, 4u32, 120u32);
+        let width = qr.width() as u32;
+        let side = (width + quiet * 2) * module + margin * 2;
+        let mut img = GrayImage::from_pixel(side, side, image::Luma([255]));
+        let origin = margin + quiet * module;
+        crate::testing::draw_code(&mut img, &qr, (origin, origin), module);
+
+        // Съёмка под углом: правый нижний угол дальше от камеры
+        let center = side as f32 / 2.0;
+        let tilt = Projection::from_matrix([1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0005, 0.0004, 1.0]).unwrap();
+        let projection = Projection::translate(center, center)
+            * Projection::rotate(10f32.to_radians())
+            * tilt
+            * Projection::translate(-center, -center);
+        let warped = warp(&img, &projection, Interpolation::Bilinear, image::Luma([255]));
+        let to_frame = |(mx, my): (f32, f32)| {
+            let to_px = |m: f32| (margin + quiet * module) as f32 + m * module as f32;
+            projection * (to_px(mx), to_px(my))
+        };
+
+        let detector = QRDetector::new(DetectorConfig::default());
+        let patterns = detector.find_finder_patterns(&warped);
+        let groups = detector.group_patterns(&patterns);
+        assert_eq!(groups.len(), 1, "{:?}", patterns);
+
+        // Версия 7: шесть alignment-паттернов вне finder-паттернов
+        let alignments = detector.find_alignment_patterns(&warped, &groups[0]);
+        assert_eq!(alignments.len(), 6, "{:?}", alignments);
+        for a in &alignments {
+            let (ex, ey) = to_frame((a.module.x, a.module.y));
+            assert!((a.center.x - ex).abs() < 3.0 && (a.center.y - ey).abs() < 3.0, "{:?} vs {:?}", a, (ex, ey));
+        }
+
+        // Перспектива учтена: правый нижний угол не достраивается параллелограммом
+        let rectified = detector.rectify_qr(&warped, &warped, &groups[0]).expect("code rectified");
+        let (ex, ey) = to_frame((width as f32, width as f32));
+        let (bx, by) = rectified.corners[2];
+        assert!((bx as f32 - ex).abs() < 4.0 && (by as f32 - ey).abs() < 4.0, "{:?} vs {:?}", (bx, by), (ex, ey));
+
+        let strict = crate::QRDecoder::with_config(crate::DecoderConfig {
+            strategy_order: vec![crate::DecodeStrategy::Standard],
+            ..Default::default()
+        })
+        .unwrap();
+        let decoded = strict.decode(&rectified.image).unwrap();
+        assert_eq!(decoded.content, content);
+        assert_eq!(decoded.version, Some(7));
+    }
+
+    #[test]
+    fn test_detect_iter_matches_detect() {
+        // Два кода рядом и пустой кадр (результат — весь кадр)
+        let mut img = GrayImage::from_pixel(700, 360, image::Luma([255]));
+        for (content, ox) in [("first", 20u32), ("second", 380)] {
+            crate::testing::draw_code(&mut img, &qrcode::QrCode::new(content).unwrap(), (ox + 32, 32), 8);
+        }
+        let blank = GrayImage::from_pixel(50, 40, image::Luma([255]));
+
+        let detector = QRDetector::new(DetectorConfig::default());
+        for (frame, expected) in [(&img, 2), (&blank, 1)] {
+            let collected = detector.detect(frame);
+            assert_eq!(collected.len(), expected);
+            let mut lazy = detector.detect_iter(frame);
+            for d in &collected {
+                let item = lazy.next().expect("same number of crops");
+                assert_eq!((item.bbox, item.corners), (d.bbox, d.corners));
+                assert_eq!(item.image, d.image);
+                assert_eq!(item.homography, d.homography);
+            }
+            assert!(lazy.next().is_none());
+        }
+        assert_eq!(detector.detect_iter(&GrayImage::new(0, 0)).count(), 0);
+    }
+
+    #[test]
+    fn test_confidence_reflects_pattern_quality() {
+        let clean = crate::testing::render_qr("confidence");
+
+        // Скошенный код с шумом: отрезки паттернов и форма тройки искажены
+        let (mut noisy, _, _) = render_sheared_qr("confidence");
+        let mut seed = 7u32;
+        for pixel in noisy.pixels_mut() {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            if (seed >> 16).is_multiple_of(40) {
+                pixel.0[0] = 255 - pixel.0[0];
+            }
+        }
+
+        let detector = QRDetector::new(DetectorConfig::default());
+        let score = |img: &GrayImage| {
+            let detected = detector.detect(img);
+            assert_eq!(detected.len(), 1);
+            // Код найден по finder-паттернам, а не возвращён весь кадр
+            assert!(detected[0].homography.is_some());
+            detected[0].confidence
+        };
+        let (clean, noisy) = (score(&clean), score(&noisy));
+        assert!(clean > 0.9, "{}", clean);
+        assert!(noisy > 0.0 && noisy < clean, "clean {} vs noisy {}", clean, noisy);
+        assert!(detector.ratio_error(&[10, 10, 30, 10, 10]) < 1e-6);
+    }
+
+    #[test]
+    fn test_whole_image_fallback_can_be_disabled() {
+        let roi = GrayImage::from_pixel(120, 80, image::Luma([255]));
+
+        let detector = QRDetector::new(DetectorConfig::default());
+        let fallback = detector.detect(&roi);
+        assert_eq!(fallback.len(), 1);
+        assert_eq!(fallback[0].bbox, [0, 0, 120, 80]);
+
+        let strict = QRDetector::new(DetectorConfig { whole_image_fallback: false, ..Default::default() });
+        assert!(strict.detect(&roi).is_empty());
+    }
+
+    #[test]
+    fn test_vertical_scan_on_rotated_code() {
+        let (img, _, _) = render_sheared_qr("rotated");
+        let img = image::imageops::rotate90(&img);
+        let detector = QRDetector::new(DetectorConfig::default());
+
+        let vertical = detector.merge_patterns(detector.scan_lines(&img, Axis::Vertical));
+        assert_eq!(vertical.len(), 3, "{:?}", vertical);
+
+        let patterns = detector.find_finder_patterns(&img);
+        assert_eq!(patterns.len(), 3, "{:?}", patterns);
+        for p in &patterns {
+            assert!(vertical.iter().any(|v| v.center_x.abs_diff(p.center_x) <= 2 && v.center_y.abs_diff(p.center_y) <= 2));
+        }
+
+        let detected = detector.detect(&img);
+        assert_eq!(detected.len(), 1);
+        let decoded = crate::QRDecoder::new().decode(&detected[0].image).unwrap();
+        assert_eq!(decoded.content, "rotated");
+    }
+
+    #[test]
+    fn test_merge_patterns_collapses_chain() {
+        let detector = QRDetector::new(DetectorConfig::default());
+        let pattern = |center_x, module_size| FinderPattern { center_x, center_y: 100, module_size, count: 1, ratio_error: 0.1 };
+
+        // Соседние пары ближе двух средних модулей, крайние — дальше порога по первому паттерну
+        let merged = detector.merge_patterns(vec![pattern(100, 2.0), pattern(104, 4.0), pattern(108, 4.0)]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!((merged[0].center_x, merged[0].center_y), (104, 100));
+        assert_eq!(merged[0].count, 3);
+        assert!((merged[0].module_size - 10.0 / 3.0).abs() < 1e-4);
+
+        // Порядок входа не влияет на результат
+        let reversed = detector.merge_patterns(vec![pattern(108, 4.0), pattern(104, 4.0), pattern(100, 2.0)]);
+        assert_eq!(reversed.len(), 1);
+        assert_eq!(reversed[0].center_x, 104);
+
+        // Далёкие паттерны не сливаются
+        let separate = detector.merge_patterns(vec![pattern(100, 2.0), pattern(160, 2.0)]);
+        assert_eq!(separate.len(), 2);
+    }
+
+    #[test]
+    fn test_adjacent_patterns_on_one_scanline() {
+        let detector = QRDetector::new(DetectorConfig::default());
+        let module = 3u32;
+        // Строка в модулях: частичный отрезок Ч-Б-Ч, светлый модуль, два паттерна
+        // через один светлый модуль; второй упирается в правый край
+        let (first, second) = (4u32, 12u32);
+        let mut img = GrayImage::from_pixel(19 * module, 11 * module, image::Luma([255]));
+        let mut fill = |mx: u32, my: u32| {
+            for dy in 0..module {
+                for dx in 0..module {
+                    img.put_pixel(mx * module + dx, my * module + dy, image::Luma([0]));
+                }
+            }
+        };
+        for y in 0..7u32 {
+            for x in 0..7u32 {
+                if x.abs_diff(3).max(y.abs_diff(3)) != 2 {
+                    fill(first + x, 2 + y);
+                    fill(second + x, 2 + y);
+                }
+            }
+            fill(0, 2 + y);
+            fill(2, 2 + y);
+        }
+
+        let horizontal = detector.scan_lines(&img, Axis::Horizontal);
+        let center_y = (2 * 2 + 7) * module / 2;
+        for origin in [first, second] {
+            let center_x = (2 * origin + 7) * module / 2;
+            let hits = horizontal
+                .iter()
+                .filter(|p| p.center_x.abs_diff(center_x) <= 1 && p.center_y.abs_diff(center_y) <= 1)
+                .count();
+            // Каждая строка центрального квадрата 3x3 модуля
+            assert_eq!(hits, 3 * module as usize, "pattern at module {}: {:?}", origin, horizontal);
+        }
+
+        let merged = detector.find_finder_patterns(&img);
+        assert_eq!(merged.len(), 2, "{:?}", merged);
     }
 }
@@ -1,5 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use serde::{Serialize, Deserialize};
+use crate::payment::TipSpec;
+use rust_decimal::Decimal;
 use thiserror::Error;
 
 /// EMV Parsing Errors
@@ -25,6 +27,9 @@ pub struct EmvData {
     pub merchant_category_code: Option<String>, // (52)
     pub transaction_currency: Option<String>, // (53)
     pub transaction_amount: Option<String>, // (54)
+    pub tip_indicator: Option<String>, // (55)
+    pub convenience_fee_fixed: Option<String>, // (56)
+    pub convenience_fee_percentage: Option<String>, // (57)
     pub country_code: Option<String>, // (58)
     pub merchant_name: Option<String>, // (59)
     pub merchant_city: Option<String>, // (60)
@@ -38,36 +43,37 @@ impl EmvData {
     pub fn parse(raw: &str) -> Result<Self, EmvError> {
         // 1. Validate CRC first
         Self::validate_crc(raw)?;
+        Self::parse_tlv(raw, true)
+    }
+
+    /// Разбор TLV без проверки CRC (тег 63 может отсутствовать, тогда `crc` пуст)
+    pub fn parse_unchecked(raw: &str) -> Result<Self, EmvError> {
+        Self::parse_tlv(raw, false)
+    }
 
-        let mut tags = HashMap::new();
-        let mut idx = 0;
-        let query_chars: Vec<char> = raw.chars().collect();
-        let len = query_chars.len();
+    /// Мягкая проверка для определения типа содержимого: PFI `000201` в начале,
+    /// корректный TLV и последний тег 63. Значение CRC не сверяется и может
+    /// быть обрезано (`...6304` в конце строки).
+    pub(crate) fn is_emv(raw: &str) -> bool {
+        let crc_start = raw
+            .len()
+            .checked_sub(8)
+            .filter(|&start| raw.get(start..start + 4) == Some("6304"))
+            .or_else(|| raw.strip_suffix("6304").map(str::len));
+        raw.starts_with("000201") && crc_start.is_some_and(|start| Self::parse_unchecked(&raw[..start]).is_ok())
+    }
 
+    fn parse_tlv(raw: &str, require_crc: bool) -> Result<Self, EmvError> {
         // 2. Parse TLV
-        while idx < len {
-            if idx + 4 > len {
-                break; // Should ideally be error if trailing garbage, but robust to ignore
-            }
-            
-            let tag: String = query_chars[idx..idx+2].iter().collect();
-            let len_str: String = query_chars[idx+2..idx+4].iter().collect();
-            
-            let value_len = len_str.parse::<usize>().map_err(|_| EmvError::MalformedData)?;
-            
-            if idx + 4 + value_len > len {
-                return Err(EmvError::MalformedData);
-            }
-            
-            let value: String = query_chars[idx+4..idx+4+value_len].iter().collect();
-            
-            tags.insert(tag, value);
-            idx = idx + 4 + value_len;
-        }
+        let mut tags = split_tlv(raw)?;
 
         // 3. Map to Struct
         let pfi = tags.remove("00").ok_or(EmvError::MalformedData)?;
-        let crc = tags.remove("63").ok_or(EmvError::MissingChecksum)?; // Should be present due to step 1
+        let crc = match tags.remove("63") {
+            Some(crc) => crc,
+            None if require_crc => return Err(EmvError::MissingChecksum),
+            None => String::new(),
+        };
         
         let mut merchant_account_information = HashMap::new();
         let mut additional_data = HashMap::new();
@@ -76,7 +82,7 @@ impl EmvData {
         let keys: Vec<String> = tags.keys().cloned().collect();
         for k in keys {
             if let Ok(id) = k.parse::<u32>() {
-                if id >= 2 && id <= 51 {
+                if (2..=51).contains(&id) {
                     if let Some(v) = tags.remove(&k) {
                         merchant_account_information.insert(k, v);
                     }
@@ -97,6 +103,9 @@ impl EmvData {
             merchant_category_code: tags.remove("52"),
             transaction_currency: tags.remove("53"),
             transaction_amount: tags.remove("54"),
+            tip_indicator: tags.remove("55"),
+            convenience_fee_fixed: tags.remove("56"),
+            convenience_fee_percentage: tags.remove("57"),
             country_code: tags.remove("58"),
             merchant_name: tags.remove("59"),
             merchant_city: tags.remove("60"),
@@ -107,7 +116,36 @@ impl EmvData {
         })
     }
 
-    fn validate_crc(raw: &str) -> Result<(), EmvError> {
+    /// Буквенный код валюты транзакции (тег 53, ISO 4217)
+    pub fn currency_alpha(&self) -> Option<&'static str> {
+        self.transaction_currency.as_deref().and_then(crate::currency::currency_alpha)
+    }
+
+    /// Вложенные поля тега 62 (Additional Data Field Template):
+    /// 01 — номер счёта, 02 — телефон, 03 — магазин, 04 — программа лояльности,
+    /// 05 — референс, 06 — покупатель, 07 — терминал, 08 — назначение платежа.
+    /// Ключи — двузначные подтеги; некорректный TLV даёт пустую карту.
+    pub fn parse_additional_data(&self) -> HashMap<String, String> {
+        self.additional_data
+            .get("62")
+            .and_then(|value| split_tlv(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// Шаблоны Merchant Account Information (теги 26–51): подтег 00 — GUI
+    /// платёжной схемы, остальные — идентификаторы мерчанта в этой схеме.
+    /// Теги 02–25 (примитивные идентификаторы карточных сетей) и шаблоны
+    /// с некорректным TLV пропускаются.
+    pub fn parse_merchant_accounts(&self) -> HashMap<String, HashMap<String, String>> {
+        self.merchant_account_information
+            .iter()
+            .filter(|(tag, _)| tag.parse::<u32>().is_ok_and(|id| (26..=51).contains(&id)))
+            .filter_map(|(tag, value)| Some((tag.clone(), split_tlv(value).ok()?)))
+            .collect()
+    }
+
+    /// Проверка CRC (тег 63 должен быть последним)
+    pub(crate) fn validate_crc(raw: &str) -> Result<(), EmvError> {
         let len = raw.len();
         if len < 4 {
              return Err(EmvError::MalformedData);
@@ -121,7 +159,8 @@ impl EmvData {
              return Err(EmvError::MalformedData);
         }
         
-        let checksum_tag = &raw[len-8..len-4]; // Should be '6304'
+        // get() вместо индексации: UTF-8 в имени мерчанта не должен вызывать панику
+        let checksum_tag = raw.get(len-8..len-4).ok_or(EmvError::MalformedData)?; // Should be '6304'
         if checksum_tag != "6304" {
             // It's possible custom extensions follow, but standard says CRC is last.
             // For robustness, we search for '6304' from the end? 
@@ -129,7 +168,7 @@ impl EmvData {
             return Err(EmvError::MissingChecksum);
         }
         
-        let provided_crc = &raw[len-4..];
+        let provided_crc = raw.get(len-4..).ok_or(EmvError::MalformedData)?;
         let data_to_check = &raw[..len-4];
         
         let calculated_crc = crc16_ccitt_kermit(data_to_check.as_bytes());
@@ -146,15 +185,156 @@ impl EmvData {
     }
 }
 
+/// Сборка EMV QR-полезной нагрузки
+///
+/// Поля выводятся по возрастанию тега, в конце добавляется тег 63 с CRC.
+/// Длина TLV — две цифры, поэтому сеттеры паникуют на значениях длиннее 99 символов.
+#[derive(Debug, Clone)]
+pub struct EmvBuilder {
+    fields: BTreeMap<String, String>,
+}
+
+impl Default for EmvBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmvBuilder {
+    pub fn new() -> Self {
+        let mut fields = BTreeMap::new();
+        fields.insert("00".to_string(), "01".to_string());
+        Self { fields }
+    }
+
+    fn field(mut self, tag: &str, value: impl Into<String>) -> Self {
+        let value = value.into();
+        check_length(tag, &value);
+        self.fields.insert(tag.to_string(), value);
+        self
+    }
+
+    /// 01: "11" — статический QR, "12" — динамический
+    pub fn point_of_initiation(self, value: impl Into<String>) -> Self {
+        self.field("01", value)
+    }
+
+    /// 26–51: шаблон Merchant Account Information (GUI схемы + идентификатор мерчанта).
+    /// Паникует на теге вне 26..=51.
+    pub fn add_merchant_account(self, tag: u8, gui: &str, id: &str) -> Self {
+        assert!(
+            (26..=51).contains(&tag),
+            "EMV merchant account tag must be in 26..=51, got {}",
+            tag
+        );
+        let value = format!("{}{}", tlv("00", gui), tlv("01", id));
+        self.field(&format!("{:02}", tag), value)
+    }
+
+    /// 52
+    pub fn merchant_category_code(self, mcc: impl Into<String>) -> Self {
+        self.field("52", mcc)
+    }
+
+    /// 53: числовой код ISO 4217 ("643", "840", ...)
+    pub fn currency(self, code: impl Into<String>) -> Self {
+        self.field("53", code)
+    }
+
+    /// 54 (округляется до двух знаков)
+    pub fn amount(self, amount: Decimal) -> Self {
+        self.field("54", format!("{:.2}", amount))
+    }
+
+    /// 55–57: чаевые / сервисный сбор
+    pub fn tip(self, tip: TipSpec) -> Self {
+        match tip {
+            TipSpec::Prompt => self.field("55", "01"),
+            TipSpec::Fixed(fee) => self.field("55", "02").field("56", format!("{:.2}", fee)),
+            TipSpec::Percentage(percent) => self.field("55", "03").field("57", format!("{}", percent)),
+        }
+    }
+
+    /// 58: ISO 3166-1 alpha-2
+    pub fn country_code(self, code: impl Into<String>) -> Self {
+        self.field("58", code)
+    }
+
+    /// 59
+    pub fn merchant_name(self, name: impl Into<String>) -> Self {
+        self.field("59", name)
+    }
+
+    /// 60
+    pub fn merchant_city(self, city: impl Into<String>) -> Self {
+        self.field("60", city)
+    }
+
+    /// Полезная нагрузка с CRC
+    pub fn build(&self) -> String {
+        let mut payload: String = self
+            .fields
+            .iter()
+            .map(|(tag, value)| tlv(tag, value))
+            .collect();
+        payload.push_str("6304");
+        let crc = crc16_ccitt_kermit(payload.as_bytes());
+        payload.push_str(&format!("{:04X}", crc));
+        payload
+    }
+}
+
+/// Одно TLV-поле (длина в символах, не более 99)
+fn tlv(tag: &str, value: &str) -> String {
+    check_length(tag, value);
+    format!("{}{:02}{}", tag, value.chars().count(), value)
+}
+
+/// Паника, если значение не помещается в двузначную длину TLV
+fn check_length(tag: &str, value: &str) {
+    let len = value.chars().count();
+    assert!(len <= 99, "EMV field {} is {} characters long, the maximum is 99", tag, len);
+}
+
+/// Разбор последовательности TLV-полей (тег и длина — по 2 цифры, длина в символах)
+fn split_tlv(raw: &str) -> Result<HashMap<String, String>, EmvError> {
+    let mut tags = HashMap::new();
+    let mut idx = 0;
+    let query_chars: Vec<char> = raw.chars().collect();
+    let len = query_chars.len();
+
+    while idx < len {
+        if idx + 4 > len {
+            break; // Should ideally be error if trailing garbage, but robust to ignore
+        }
+        
+        let tag: String = query_chars[idx..idx+2].iter().collect();
+        let len_str: String = query_chars[idx+2..idx+4].iter().collect();
+        
+        let value_len = len_str.parse::<usize>().map_err(|_| EmvError::MalformedData)?;
+        
+        if idx + 4 + value_len > len {
+            return Err(EmvError::MalformedData);
+        }
+        
+        let value: String = query_chars[idx+4..idx+4+value_len].iter().collect();
+        
+        tags.insert(tag, value);
+        idx = idx + 4 + value_len;
+    }
+
+    Ok(tags)
+}
+
 // CRC-16/CCITT-FALSE (Kermit)
 // Poly: 0x1021
 // Init: 0xFFFF
-fn crc16_ccitt_kermit(data: &[u8]) -> u16 {
+pub(crate) fn crc16_ccitt_kermit(data: &[u8]) -> u16 {
     let mut crc: u16 = 0xFFFF;
     for &byte in data {
         // crc = (crc >> 8) | (crc << 8); // No, standard CCITT implementation
         let x = ((crc >> 8) ^ (byte as u16)) & 0xFF;
-        let mut x = x ^ (x >> 4);
+        let x = x ^ (x >> 4);
         crc = (crc << 8) ^ (x << 12) ^ (x << 5) ^ x;
     }
     crc
@@ -179,4 +359,84 @@ mod tests {
         assert_eq!(parsed.pfi, "01");
         assert_eq!(parsed.merchant_name, Some("SomeMerch".to_string()));
     }
+
+    #[test]
+    fn test_is_emv_lenient() {
+        let payload = EmvBuilder::new().point_of_initiation("11").build();
+        assert!(EmvData::is_emv(&payload));
+
+        // CRC не сверяется и может быть обрезан
+        let (body, _) = payload.split_at(payload.len() - 4);
+        assert!(EmvData::is_emv(&format!("{}0000", body)));
+        assert!(EmvData::is_emv(body));
+
+        assert!(!EmvData::is_emv("000201010211"));
+        assert!(!EmvData::is_emv("00020101996304ABCD"));
+        assert!(!EmvData::is_emv("0102110002016304ABCD"));
+    }
+
+    #[test]
+    fn test_builder_round_trip() {
+        let payload = EmvBuilder::new()
+            .merchant_city("Bangkok")
+            .merchant_name("Som Tam Shop")
+            .add_merchant_account(29, "A000000677010111", "0066812345678")
+            .currency("764")
+            .amount(Decimal::new(1205, 1))
+            .country_code("TH")
+            .tip(TipSpec::Fixed(Decimal::new(10, 0)))
+            .build();
+
+        assert!(payload.starts_with("000201"));
+        let parsed = EmvData::parse(&payload).expect("CRC must be valid");
+        assert_eq!(parsed.merchant_name.as_deref(), Some("Som Tam Shop"));
+        assert_eq!(parsed.merchant_city.as_deref(), Some("Bangkok"));
+        assert_eq!(parsed.transaction_amount.as_deref(), Some("120.50"));
+        assert_eq!(parsed.currency_alpha(), Some("THB"));
+        assert_eq!(parsed.tip_indicator.as_deref(), Some("02"));
+        assert_eq!(parsed.convenience_fee_fixed.as_deref(), Some("10.00"));
+        assert_eq!(parsed.parse_merchant_accounts()["29"]["01"], "0066812345678");
+    }
+
+    #[test]
+    #[should_panic(expected = "EMV field 59 is 100 characters long")]
+    fn test_builder_rejects_long_value() {
+        EmvBuilder::new().merchant_name("x".repeat(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "EMV field 01 is 120 characters long")]
+    fn test_builder_rejects_long_merchant_account_id() {
+        EmvBuilder::new().add_merchant_account(29, "A000000677010111", &"1".repeat(120));
+    }
+
+    #[test]
+    #[should_panic(expected = "EMV merchant account tag must be in 26..=51, got 59")]
+    fn test_builder_rejects_merchant_account_tag() {
+        EmvBuilder::new().add_merchant_account(59, "A000000677010111", "0066812345678");
+    }
+
+    #[test]
+    fn test_merchant_accounts() {
+        // PromptPay: GUI A000000677010111 + телефон (01)
+        let parsed = EmvData::parse_unchecked(
+            "0002010102110216411111111111111129370016A0000006770101110113006681234567853037645802TH",
+        )
+        .unwrap();
+        let accounts = parsed.parse_merchant_accounts();
+        assert_eq!(accounts.len(), 1);
+        let promptpay = &accounts["29"];
+        assert_eq!(promptpay.get("00").map(String::as_str), Some("A000000677010111"));
+        assert_eq!(promptpay.get("01").map(String::as_str), Some("0066812345678"));
+    }
+
+    #[test]
+    fn test_additional_data() {
+        // 62: 01 (bill number) + 05 (reference label)
+        let parsed = EmvData::parse_unchecked("0002015909SomeMerch62230108INV-00420507REF-777").unwrap();
+        let additional = parsed.parse_additional_data();
+        assert_eq!(additional.get("01").map(String::as_str), Some("INV-0042"));
+        assert_eq!(additional.get("05").map(String::as_str), Some("REF-777"));
+        assert_eq!(additional.len(), 2);
+    }
 }
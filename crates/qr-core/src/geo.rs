@@ -0,0 +1,145 @@
+//! Модуль парсинга геолокации (`geo:` URI, RFC 5870)
+//!
+//! Поддерживаются формы:
+//! - `geo:55.7558,37.6173`
+//! - `geo:55.7558,37.6173,150` (высота в метрах)
+//! - `geo:55.7558,37.6173;u=35` (погрешность в метрах)
+//! - `geo:0,0?q=55.7558,37.6173(Кремль)` (координаты в запросе, Android)
+
+use serde::{Deserialize, Serialize};
+
+/// Координаты из `geo:` URI
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GeoLocation {
+    /// Широта (-90..90)
+    pub lat: f64,
+    /// Долгота (-180..180)
+    pub lon: f64,
+    /// Высота в метрах
+    pub alt: Option<f64>,
+    /// Погрешность в метрах (параметр `u`)
+    pub uncertainty: Option<f64>,
+}
+
+impl GeoLocation {
+    /// Парсинг `geo:` URI. Некорректные координаты дают `None`.
+    pub fn parse(content: &str) -> Option<Self> {
+        let prefix = content.get(..4)?;
+        if !prefix.eq_ignore_ascii_case("geo:") {
+            return None;
+        }
+
+        let (path, query) = match content[4..].split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (&content[4..], None),
+        };
+
+        let mut params = path.split(';');
+        let mut location = Self::parse_coordinates(params.next()?)?;
+
+        for param in params {
+            if let Some((key, value)) = param.split_once('=') {
+                if key.eq_ignore_ascii_case("u") {
+                    let uncertainty: f64 = value.parse().ok()?;
+                    if !uncertainty.is_finite() || uncertainty < 0.0 {
+                        return None;
+                    }
+                    location.uncertainty = Some(uncertainty);
+                }
+            }
+        }
+
+        // geo:0,0?q=lat,lon(метка) — координаты передаются в запросе
+        if let Some(q) = query.and_then(query_param) {
+            let coords = q.split('(').next().unwrap_or_default();
+            if let Some(from_query) = Self::parse_coordinates(coords) {
+                if location.lat == 0.0 && location.lon == 0.0 {
+                    location.lat = from_query.lat;
+                    location.lon = from_query.lon;
+                }
+            }
+        }
+
+        Some(location)
+    }
+
+    /// Разбор `lat,lon[,alt]` с проверкой диапазонов
+    fn parse_coordinates(s: &str) -> Option<Self> {
+        let mut parts = s.split(',').map(|p| p.trim().parse::<f64>());
+        let lat = parts.next()?.ok()?;
+        let lon = parts.next()?.ok()?;
+        let alt = match parts.next() {
+            Some(alt) => Some(alt.ok().filter(|a| a.is_finite())?),
+            None => None,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return None;
+        }
+
+        Some(Self {
+            lat,
+            lon,
+            alt,
+            uncertainty: None,
+        })
+    }
+}
+
+/// Значение параметра `q` из строки запроса (с URL-декодированием)
+fn query_param(query: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == "q")
+        .and_then(|(_, value)| urlencoding::decode(&value.replace('+', " ")).ok().map(|v| v.into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_basic() {
+        let geo = GeoLocation::parse("geo:55.7558,37.6173").unwrap();
+        assert_eq!(geo.lat, 55.7558);
+        assert_eq!(geo.lon, 37.6173);
+        assert_eq!(geo.alt, None);
+        assert_eq!(geo.uncertainty, None);
+    }
+
+    #[test]
+    fn test_parse_altitude_and_uncertainty() {
+        let geo = GeoLocation::parse("GEO:-33.8688,151.2093,58.5;u=35;crs=wgs84").unwrap();
+        assert_eq!(geo.lat, -33.8688);
+        assert_eq!(geo.lon, 151.2093);
+        assert_eq!(geo.alt, Some(58.5));
+        assert_eq!(geo.uncertainty, Some(35.0));
+    }
+
+    #[test]
+    fn test_parse_query_form() {
+        let geo = GeoLocation::parse("geo:0,0?q=55.7520,37.6175(Red%20Square)").unwrap();
+        assert_eq!(geo.lat, 55.7520);
+        assert_eq!(geo.lon, 37.6175);
+
+        // Явные координаты важнее запроса с адресом
+        let geo = GeoLocation::parse("geo:59.9343,30.3351?q=Nevsky+Prospect").unwrap();
+        assert_eq!(geo.lat, 59.9343);
+        assert_eq!(geo.lon, 30.3351);
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed() {
+        assert!(GeoLocation::parse("geo:abc,37.6").is_none());
+        assert!(GeoLocation::parse("geo:91.0,37.6").is_none());
+        assert!(GeoLocation::parse("geo:55.7,-180.5").is_none());
+        assert!(GeoLocation::parse("geo:55.7").is_none());
+        assert!(GeoLocation::parse("geo:55.7,37.6,1,2").is_none());
+        assert!(GeoLocation::parse("geo:55.7,37.6;u=-1").is_none());
+        assert!(GeoLocation::parse("https://maps.example.com").is_none());
+    }
+}
@@ -1,15 +1,40 @@
 use image::{GrayImage, ImageBuffer, Luma};
+use std::ops::Deref;
 use nalgebra::{Matrix3, Point2, Vector3};
 
+/// Интерполяция при выборке пикселя исходного изображения
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// Ближайший пиксель: сохраняет резкие границы модулей бинаризованного входа
+    NearestNeighbor,
+    /// Билинейная интерполяция по 2x2 соседям
+    #[default]
+    Bilinear,
+    /// Бикубическая интерполяция (Catmull-Rom) по 4x4 соседям
+    Bicubic,
+}
+
+/// Заливка за пределами исходного изображения по умолчанию: белый сохраняет quiet zone
+pub const DEFAULT_FILL: u8 = 255;
+
 /// Apply perspective warp to an image
+///
+/// Пиксели, отображающиеся за границы `img`, заливаются значением `fill`.
+/// `None`, если матрица вырождена или плохо обусловлена.
 pub fn warp_perspective(
     img: &GrayImage,
     matrix: &Matrix3<f32>,
     out_width: u32,
     out_height: u32,
-) -> GrayImage {
-    let mut output = ImageBuffer::new(out_width, out_height);
-    let inv_matrix = matrix.try_inverse().unwrap_or(Matrix3::identity());
+    interpolation: Interpolation,
+    fill: u8,
+) -> Option<GrayImage> {
+    // Плохо обусловленная матрица даёт обратную, не восстанавливающую единичную
+    let inv_matrix = matrix
+        .try_inverse()
+        .filter(|inv| inv.iter().all(|v| v.is_finite()))
+        .filter(|inv| (matrix * inv - Matrix3::identity()).abs().max() < 1e-3)?;
+    let mut output = ImageBuffer::from_pixel(out_width, out_height, Luma([fill]));
 
     for y in 0..out_height {
         for x in 0..out_width {
@@ -26,53 +51,231 @@ pub fn warp_perspective(
             let src_x = src_point_h.x / z;
             let src_y = src_point_h.y / z;
 
-            // Bilinear interpolation
-            let pixel = bilinear_sample(img, src_x, src_y);
+            let pixel = match interpolation {
+                Interpolation::NearestNeighbor => nearest_sample(img, src_x, src_y, fill),
+                Interpolation::Bilinear => bilinear_sample(img, src_x, src_y, fill),
+                Interpolation::Bicubic => bicubic_sample(img, src_x, src_y, fill),
+            };
             output.put_pixel(x, y, Luma([pixel]));
         }
     }
-    output
+    Some(output)
 }
 
 /// Compute Homography Matrix mapping src_points to dst_points
 /// Uses 4 corresponding points.
+///
+/// `None` для вырожденных наборов: три точки на одной прямой или нулевая площадь.
 pub fn find_homography(
     src: [Point2<f32>; 4],
     dst: [Point2<f32>; 4],
 ) -> Option<Matrix3<f32>> {
-    // Basic Direct Linear Transform (DLT) solver
-    // For 4 points, we solve Ah = 0.
-    
-    // Using nalgebra's DMatrix for solving SVD or linear system would be ideal,
-    // but for fixed 4 points we can just construct the 8x9 matrix.
-    // However, opencv "getPerspectiveTransform" is robust.
-    
-    // We can use a simpler approach if we trust the points form a convex quad.
-    // But let's implementing a Gaussian elimination or SVD based solver for Ax=0 is heavy.
-    // Better idea: map unit square to quad, or quad to quad.
-    
-    // Let's implement the standard 8-mult-eqn solver.
-    let mut matrix_a = nalgebra::DMatrix::<f32>::zeros(8, 9);
-    
+    if is_degenerate(&src) || is_degenerate(&dst) {
+        return None;
+    }
+    dlt(&src, &dst)
+}
+
+/// Минимальная площадь треугольника из трёх точек четвёрки относительно
+/// квадрата её наибольшего расстояния, ниже которой точки считаются коллинеарными
+const MIN_RELATIVE_AREA: f32 = 1e-3;
+
+/// Лежат ли какие-либо три из четырёх точек (почти) на одной прямой
+fn is_degenerate(points: &[Point2<f32>; 4]) -> bool {
+    let mut extent = 0.0f32;
     for i in 0..4 {
-        let x = src[i].x;
-        let y = src[i].y;
-        let u = dst[i].x;
-        let v = dst[i].y;
-        
+        for j in i + 1..4 {
+            extent = extent.max((points[i] - points[j]).norm_squared());
+        }
+    }
+    if extent.is_nan() || extent <= 0.0 {
+        return true;
+    }
+    [(0, 1, 2), (0, 1, 3), (0, 2, 3), (1, 2, 3)].iter().any(|&(a, b, c)| {
+        let (ab, ac) = (points[b] - points[a], points[c] - points[a]);
+        let area = (ab.x * ac.y - ab.y * ac.x).abs() / 2.0;
+        area < MIN_RELATIVE_AREA * extent
+    })
+}
+
+/// Устойчивая гомография по N >= 4 соответствиям (например, центры finder-паттернов
+/// и alignment-паттерн). Случайные 4-точечные выборки оцениваются по числу точек
+/// с ошибкой репроекции меньше `threshold` пикселей; итог уточняется методом
+/// наименьших квадратов по лучшему консенсусу. Выборка детерминирована.
+pub fn find_homography_ransac(
+    src: &[Point2<f32>],
+    dst: &[Point2<f32>],
+    iterations: usize,
+    threshold: f32,
+) -> Option<Matrix3<f32>> {
+    if src.len() != dst.len() || src.len() < 4 {
+        return None;
+    }
+
+    let n = src.len();
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+    let mut best: Option<(Vec<usize>, f32)> = None;
+
+    for _ in 0..iterations.max(1) {
+        let mut sample = [0usize; 4];
+        for i in 0..4 {
+            sample[i] = loop {
+                let candidate = rng.next_index(n);
+                if !sample[..i].contains(&candidate) {
+                    break candidate;
+                }
+            };
+        }
+        let Some(h) = find_homography(sample.map(|i| src[i]), sample.map(|i| dst[i])) else {
+            continue;
+        };
+
+        let mut inliers = Vec::new();
+        let mut error = 0.0;
+        for i in 0..n {
+            let e = reprojection_error(&h, src[i], dst[i]);
+            if e < threshold {
+                inliers.push(i);
+                error += e;
+            }
+        }
+        let better = match &best {
+            None => true,
+            Some((best_inliers, best_error)) => {
+                inliers.len() > best_inliers.len() || (inliers.len() == best_inliers.len() && error < *best_error)
+            }
+        };
+        if inliers.len() >= 4 && better {
+            best = Some((inliers, error));
+        }
+    }
+
+    let (inliers, _) = best?;
+    let src: Vec<_> = inliers.iter().map(|&i| src[i]).collect();
+    let dst: Vec<_> = inliers.iter().map(|&i| dst[i]).collect();
+    dlt(&src, &dst)
+}
+
+/// Ошибка репроекции точки `src` гомографией `h` относительно `dst`
+pub fn reprojection_error(h: &Matrix3<f32>, src: Point2<f32>, dst: Point2<f32>) -> f32 {
+    let p = h * Vector3::new(src.x, src.y, 1.0);
+    if p.z.abs() < 1e-9 {
+        return f32::INFINITY;
+    }
+    (p.x / p.z - dst.x).hypot(p.y / p.z - dst.y)
+}
+
+/// Выпуклая оболочка (монотонная цепочка Эндрю). Вершины по часовой стрелке
+/// при оси y вниз, без коллинеарных точек.
+pub fn convex_hull(points: &[Point2<f32>]) -> Vec<Point2<f32>> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    let turns_right = |hull: &[Point2<f32>], p: Point2<f32>| {
+        let (o, a) = (hull[hull.len() - 2], hull[hull.len() - 1]);
+        (a - o).perp(&(p - o)) <= 0.0
+    };
+    let mut hull: Vec<Point2<f32>> = Vec::with_capacity(sorted.len() + 1);
+    // Нижняя цепочка слева направо
+    for &p in &sorted {
+        while hull.len() >= 2 && turns_right(&hull, p) {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+    // Верхняя цепочка справа налево
+    let lower_len = hull.len();
+    for &p in sorted.iter().rev().skip(1) {
+        while hull.len() > lower_len && turns_right(&hull, p) {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+    hull.pop();
+    hull
+}
+
+/// Четырёхугольник наибольшей площади с вершинами в вершинах выпуклого
+/// многоугольника `hull` (порядок обхода сохраняется) и его площадь.
+/// Перебор O(n³): у оболочки пиксельного множества вершин немного.
+pub fn largest_quad(hull: &[Point2<f32>]) -> Option<([Point2<f32>; 4], f32)> {
+    let n = hull.len();
+    if n < 4 {
+        return None;
+    }
+    let triangle = |a: usize, b: usize, c: usize| ((hull[b] - hull[a]).perp(&(hull[c] - hull[a])) / 2.0).abs();
+
+    let mut best: Option<([usize; 4], f32)> = None;
+    for i in 0..n {
+        for k in i + 2..n {
+            // Диагональ (i, k): по одной лучшей вершине с каждой стороны
+            let side = |range: &mut dyn Iterator<Item = usize>| {
+                range.map(|j| (j, triangle(i, j, k))).max_by(|a, b| a.1.total_cmp(&b.1))
+            };
+            let Some((j, left)) = side(&mut (i + 1..k)) else { continue };
+            let Some((l, right)) = side(&mut (k + 1..n).chain(0..i)) else { continue };
+            if best.is_none_or(|(_, area)| left + right > area) {
+                best = Some(([i, j, k, l], left + right));
+            }
+        }
+    }
+    best.map(|(indices, area)| (indices.map(|i| hull[i]), area))
+}
+
+/// Площадь многоугольника (формула шнурования)
+pub fn polygon_area(points: &[Point2<f32>]) -> f32 {
+    let n = points.len();
+    ((0..n).map(|i| points[i].coords.perp(&points[(i + 1) % n].coords)).sum::<f32>() / 2.0).abs()
+}
+
+/// Простой генератор xorshift64 для воспроизводимых выборок RANSAC
+struct XorShift(u64);
+
+impl XorShift {
+    fn next_index(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+/// Нормализация Хартли: центр масс в начало координат, среднее расстояние √2
+fn normalization(points: &[Point2<f32>]) -> Matrix3<f32> {
+    let n = points.len() as f32;
+    let cx = points.iter().map(|p| p.x).sum::<f32>() / n;
+    let cy = points.iter().map(|p| p.y).sum::<f32>() / n;
+    let mean_dist = points.iter().map(|p| (p.x - cx).hypot(p.y - cy)).sum::<f32>() / n;
+    let scale = if mean_dist > 1e-6 { std::f32::consts::SQRT_2 / mean_dist } else { 1.0 };
+    Matrix3::new(scale, 0.0, -scale * cx, 0.0, scale, -scale * cy, 0.0, 0.0, 1.0)
+}
+
+/// DLT по N >= 4 соответствиям (для N > 4 — решение методом наименьших квадратов)
+fn dlt(src: &[Point2<f32>], dst: &[Point2<f32>]) -> Option<Matrix3<f32>> {
+    let t_src = normalization(src);
+    let t_dst = normalization(dst);
+
+    // Для 4 точек недостающие строки остаются нулевыми: квадратная система
+    // гарантирует полную V^T из SVD и доступность вектора ядра.
+    let rows = (src.len() * 2).max(9);
+    let mut matrix_a = nalgebra::DMatrix::<f32>::zeros(rows, 9);
+
+    for (i, (s, d)) in src.iter().zip(dst).enumerate() {
+        let s = t_src.transform_point(s);
+        let d = t_dst.transform_point(d);
+        let (x, y, u, v) = (s.x, s.y, d.x, d.y);
+
         matrix_a[(i * 2, 0)] = -x;
         matrix_a[(i * 2, 1)] = -y;
         matrix_a[(i * 2, 2)] = -1.0;
-        matrix_a[(i * 2, 3)] = 0.0;
-        matrix_a[(i * 2, 4)] = 0.0;
-        matrix_a[(i * 2, 5)] = 0.0;
         matrix_a[(i * 2, 6)] = x * u;
         matrix_a[(i * 2, 7)] = y * u;
         matrix_a[(i * 2, 8)] = u;
 
-        matrix_a[(i * 2 + 1, 0)] = 0.0;
-        matrix_a[(i * 2 + 1, 1)] = 0.0;
-        matrix_a[(i * 2 + 1, 2)] = 0.0;
         matrix_a[(i * 2 + 1, 3)] = -x;
         matrix_a[(i * 2 + 1, 4)] = -y;
         matrix_a[(i * 2 + 1, 5)] = -1.0;
@@ -81,40 +284,70 @@ pub fn find_homography(
         matrix_a[(i * 2 + 1, 8)] = v;
     }
 
-    // Solve using SVD
+    // The solution is the row of V^T corresponding to the smallest singular value.
     let svd = matrix_a.svd(false, true);
-    if let Some(v_t) = svd.v_t {
-         // Safety check: ensure we have enough rows
-         if v_t.nrows() < 9 {
-             return None;
-         }
-         
-         // The solution is the last row of V^T (or last column of V) corresponding to smallest singular value.
-         // svd.v_t is V^T. The last row correspond to smallest sigma.
-         let h_vec = v_t.row(8);
-         
-         let h = Matrix3::new(
-             h_vec[0], h_vec[1], h_vec[2],
-             h_vec[3], h_vec[4], h_vec[5],
-             h_vec[6], h_vec[7], h_vec[8]
-         );
-         
-         // Normalize so h[8] is 1 (if not zero)
-         if h[8].abs() > 1e-6 {
-             return Some(h / h[8]);
-         }
-         return Some(h);
+    let v_t = svd.v_t?;
+    if v_t.nrows() < 9 {
+        return None;
     }
-    
-    None
+    let h_vec = v_t.row(svd.singular_values.imin());
+    let h = Matrix3::new(
+        h_vec[0], h_vec[1], h_vec[2],
+        h_vec[3], h_vec[4], h_vec[5],
+        h_vec[6], h_vec[7], h_vec[8],
+    );
+
+    // Возврат из нормализованных координат
+    let h = t_dst.try_inverse()? * h * t_src;
+
+    // Normalize so h[8] is 1 (if not zero)
+    if h[8].abs() > 1e-6 {
+        return Some(h / h[8]);
+    }
+    Some(h)
+}
+
+/// Поворачивает изображение на заданный угол (в градусах) с изменением размера холста.
+/// Использует билинейную интерполяцию (Bilinear Interpolation) для улучшения качества.
+/// Положительный угол поворачивает содержимое по часовой стрелке (ось y направлена вниз).
+pub fn rotate_image<C: Deref<Target = [u8]>>(img: &ImageBuffer<Luma<u8>, C>, angle_degrees: f32) -> GrayImage {
+    rotate_image_with(img, angle_degrees, Interpolation::Bilinear)
+}
+
+/// Поворот с выбранной интерполяцией; углы холста вне исходного изображения
+/// заливаются белым (`DEFAULT_FILL`), чтобы сохранить quiet zone.
+pub fn rotate_image_with<C: Deref<Target = [u8]>>(img: &ImageBuffer<Luma<u8>, C>, angle_degrees: f32, interpolation: Interpolation) -> GrayImage {
+    let (w, h) = img.dimensions();
+    let (sin_a, cos_a) = angle_degrees.to_radians().sin_cos();
+
+    // Размер описанного прямоугольника
+    let new_w = (w as f32 * cos_a.abs() + h as f32 * sin_a.abs()).ceil() as u32;
+    let new_h = (w as f32 * sin_a.abs() + h as f32 * cos_a.abs()).ceil() as u32;
+
+    let (cx, cy) = (w as f32 / 2.0, h as f32 / 2.0);
+    let (new_cx, new_cy) = (new_w as f32 / 2.0, new_h as f32 / 2.0);
+
+    GrayImage::from_fn(new_w, new_h, |x, y| {
+        // Обратное преобразование: точка результата -> точка исходного изображения
+        let dx = x as f32 - new_cx;
+        let dy = y as f32 - new_cy;
+        let src_x = dx * cos_a + dy * sin_a + cx;
+        let src_y = -dx * sin_a + dy * cos_a + cy;
+
+        Luma([match interpolation {
+            Interpolation::NearestNeighbor => nearest_sample(img, src_x, src_y, DEFAULT_FILL),
+            Interpolation::Bilinear => bilinear_sample(img, src_x, src_y, DEFAULT_FILL),
+            Interpolation::Bicubic => bicubic_sample(img, src_x, src_y, DEFAULT_FILL),
+        }])
+    })
 }
 
-fn bilinear_sample(img: &GrayImage, x: f32, y: f32) -> u8 {
+fn bilinear_sample<C: Deref<Target = [u8]>>(img: &ImageBuffer<Luma<u8>, C>, x: f32, y: f32, fill: u8) -> u8 {
     let width = img.width() as f32;
     let height = img.height() as f32;
     
     if x < 0.0 || x >= width - 1.0 || y < 0.0 || y >= height - 1.0 {
-        return 0; // Black padding
+        return fill;
     }
     
     let x0 = x.floor() as u32;
@@ -136,10 +369,82 @@ fn bilinear_sample(img: &GrayImage, x: f32, y: f32) -> u8 {
     (top * (1.0 - dy) + bottom * dy) as u8
 }
 
+fn nearest_sample<C: Deref<Target = [u8]>>(img: &ImageBuffer<Luma<u8>, C>, x: f32, y: f32, fill: u8) -> u8 {
+    let (x, y) = (x.round(), y.round());
+    if x < 0.0 || x >= img.width() as f32 || y < 0.0 || y >= img.height() as f32 {
+        return fill;
+    }
+    img.get_pixel(x as u32, y as u32).0[0]
+}
+
+fn bicubic_sample<C: Deref<Target = [u8]>>(img: &ImageBuffer<Luma<u8>, C>, x: f32, y: f32, fill: u8) -> u8 {
+    let width = img.width() as f32;
+    let height = img.height() as f32;
+
+    if x < 0.0 || x >= width - 1.0 || y < 0.0 || y >= height - 1.0 {
+        return fill;
+    }
+
+    // Ядро Catmull-Rom (a = -0.5)
+    let weight = |t: f32| {
+        let t = t.abs();
+        if t < 1.0 {
+            1.5 * t * t * t - 2.5 * t * t + 1.0
+        } else if t < 2.0 {
+            -0.5 * t * t * t + 2.5 * t * t - 4.0 * t + 2.0
+        } else {
+            0.0
+        }
+    };
+
+    let x0 = x.floor() as i64;
+    let y0 = y.floor() as i64;
+    let (max_x, max_y) = (img.width() as i64 - 1, img.height() as i64 - 1);
+    let mut value = 0.0;
+    for j in -1..=2 {
+        let sy = (y0 + j).clamp(0, max_y);
+        let wy = weight(y - (y0 + j) as f32);
+        for i in -1..=2 {
+            let sx = (x0 + i).clamp(0, max_x);
+            let wx = weight(x - (x0 + i) as f32);
+            value += img.get_pixel(sx as u32, sy as u32).0[0] as f32 * wx * wy;
+        }
+    }
+    value.round().clamp(0.0, 255.0) as u8
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_convex_hull_and_largest_quad() {
+        // Квадрат с точками внутри и на сторонах плюс срезанный угол
+        let mut points: Vec<Point2<f32>> = (0..=10)
+            .flat_map(|y| (0..=10).map(move |x| Point2::new(x as f32, y as f32)))
+            .filter(|p| p.x + p.y < 19.0)
+            .collect();
+        points.push(Point2::new(5.0, 5.0));
+        let hull = convex_hull(&points);
+        assert_eq!(
+            hull,
+            vec![
+                Point2::new(0.0, 0.0),
+                Point2::new(10.0, 0.0),
+                Point2::new(10.0, 8.0),
+                Point2::new(8.0, 10.0),
+                Point2::new(0.0, 10.0),
+            ]
+        );
+        assert_eq!(polygon_area(&hull), 98.0);
+
+        let (quad, area) = largest_quad(&hull).unwrap();
+        assert_eq!(area, 90.0);
+        assert_eq!(quad[0], Point2::new(0.0, 0.0));
+        assert!(largest_quad(&hull[..3]).is_none());
+        assert_eq!(convex_hull(&[Point2::new(1.0, 1.0); 3]).len(), 1);
+    }
+
     #[test]
     fn test_homography_integrity() {
         let src = [
@@ -148,10 +453,124 @@ mod tests {
             Point2::new(10.0, 10.0),
             Point2::new(0.0, 10.0),
         ];
-        let dst = src.clone();
+        let dst = src;
         
         let h = find_homography(src, dst).unwrap();
         // Should be roughly identity
         assert!((h[(0,0)] - 1.0).abs() < 1e-3);
     }
+
+    #[test]
+    fn test_degenerate_homography_rejected() {
+        let square = [
+            Point2::new(0.0, 0.0),
+            Point2::new(10.0, 0.0),
+            Point2::new(10.0, 10.0),
+            Point2::new(0.0, 10.0),
+        ];
+        // Три угла на одной прямой
+        let collinear = [
+            Point2::new(0.0, 0.0),
+            Point2::new(10.0, 0.0),
+            Point2::new(20.0, 0.0),
+            Point2::new(0.0, 10.0),
+        ];
+        assert!(find_homography(collinear, square).is_none());
+        assert!(find_homography(square, collinear).is_none());
+        // Почти коллинеарные и совпадающие точки
+        let flat = [
+            Point2::new(0.0, 0.0),
+            Point2::new(100.0, 0.01),
+            Point2::new(200.0, 0.0),
+            Point2::new(300.0, 0.02),
+        ];
+        assert!(find_homography(flat, square).is_none());
+        assert!(find_homography([Point2::new(5.0, 5.0); 4], square).is_none());
+
+        let img = GrayImage::from_pixel(10, 10, Luma([100]));
+        let singular = Matrix3::new(1.0, 2.0, 0.0, 2.0, 4.0, 0.0, 0.0, 0.0, 1.0);
+        assert!(warp_perspective(&img, &singular, 10, 10, Interpolation::Bilinear, DEFAULT_FILL).is_none());
+        assert!(warp_perspective(&img, &Matrix3::zeros(), 10, 10, Interpolation::Bilinear, DEFAULT_FILL).is_none());
+        let identity = Matrix3::identity();
+        assert!(warp_perspective(&img, &identity, 10, 10, Interpolation::Bilinear, DEFAULT_FILL).is_some());
+    }
+
+    #[test]
+    fn test_warp_interpolation_sharpness() {
+        // Шахматная доска 8px, увеличение в 3.3 раза с дробным сдвигом
+        let board = GrayImage::from_fn(64, 64, |x, y| Luma([if (x / 8 + y / 8) % 2 == 0 { 0 } else { 255 }]));
+        let scale = Matrix3::new(3.3, 0.0, -1.7, 0.0, 3.3, -1.7, 0.0, 0.0, 1.0);
+        let warp = |interpolation| warp_perspective(&board, &scale, 180, 180, interpolation, DEFAULT_FILL).unwrap();
+
+        // Доля промежуточных серых пикселей и самый резкий перепад вдоль строки
+        let gray = |img: &GrayImage| img.pixels().filter(|p| p.0[0] > 10 && p.0[0] < 245).count();
+        let steepest = |img: &GrayImage| {
+            (1..img.width())
+                .map(|x| (img.get_pixel(x, 90).0[0] as i32 - img.get_pixel(x - 1, 90).0[0] as i32).abs())
+                .max()
+                .unwrap()
+        };
+
+        let nearest = warp(Interpolation::NearestNeighbor);
+        let bilinear = warp(Interpolation::Bilinear);
+        let bicubic = warp(Interpolation::Bicubic);
+
+        assert_eq!(gray(&nearest), 0);
+        assert_eq!(steepest(&nearest), 255);
+        assert!(gray(&bilinear) > 0);
+        assert!(gray(&bicubic) < gray(&bilinear));
+        assert!(steepest(&bicubic) > steepest(&bilinear));
+        assert_eq!(bicubic.get_pixel(90, 90), bilinear.get_pixel(90, 90));
+    }
+
+    #[test]
+    fn test_warp_fill_value() {
+        let img = GrayImage::from_pixel(10, 10, Luma([100]));
+        // Сдвиг на 20px: левая часть выхода берётся за пределами исходника
+        let shift = Matrix3::new(1.0, 0.0, 20.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0);
+        for interpolation in [Interpolation::NearestNeighbor, Interpolation::Bilinear, Interpolation::Bicubic] {
+            let white = warp_perspective(&img, &shift, 30, 10, interpolation, DEFAULT_FILL).unwrap();
+            assert_eq!(white.get_pixel(5, 5).0[0], 255);
+            assert_eq!(white.get_pixel(25, 5).0[0], 100);
+
+            let black = warp_perspective(&img, &shift, 30, 10, interpolation, 0).unwrap();
+            assert_eq!(black.get_pixel(5, 5).0[0], 0);
+        }
+    }
+
+    #[test]
+    fn test_ransac_rejects_outlier() {
+        let truth = Matrix3::new(1.2, 0.1, 30.0, -0.05, 0.9, 12.0, 0.0004, -0.0002, 1.0);
+        let project = |p: &Point2<f32>| {
+            let v = truth * Vector3::new(p.x, p.y, 1.0);
+            Point2::new(v.x / v.z, v.y / v.z)
+        };
+        // Три центра finder-паттернов, alignment-паттерн и точки таймингов
+        let src = vec![
+            Point2::new(3.5, 3.5),
+            Point2::new(121.5, 3.5),
+            Point2::new(3.5, 121.5),
+            Point2::new(115.0, 115.0),
+            Point2::new(62.0, 6.5),
+            Point2::new(6.5, 62.0),
+            Point2::new(80.0, 40.0),
+        ];
+        let mut dst: Vec<_> = src.iter().map(project).collect();
+        dst[3].x += 25.0;
+        dst[3].y -= 10.0;
+
+        let h = find_homography_ransac(&src, &dst, 100, 1.0).unwrap();
+        for (i, (s, d)) in src.iter().zip(&dst).enumerate() {
+            let error = reprojection_error(&h, *s, *d);
+            if i == 3 {
+                assert!(error > 20.0);
+            } else {
+                assert!(error < 0.05, "point {}: {}", i, error);
+            }
+        }
+        assert!(reprojection_error(&h, src[3], project(&src[3])) < 0.05);
+
+        assert!(find_homography_ransac(&src[..3], &dst[..3], 100, 1.0).is_none());
+        assert!(find_homography_ransac(&src, &dst[..5], 100, 1.0).is_none());
+    }
 }
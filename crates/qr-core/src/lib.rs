@@ -13,26 +13,50 @@ pub mod payment;
 #[cfg(feature = "ml")]
 pub mod ml_detection;
 pub mod emv;
+pub mod currency;
 pub mod geometry;
+pub mod timing;
+pub mod wifi;
+pub mod geo;
+pub mod color;
+pub mod structured;
+pub mod calendar;
+pub mod uri;
+pub mod config;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
 
-pub use preprocessing::{ImageProcessor, ProcessingConfig};
-pub use detection::{QRDetector, DetectedQR, DetectorConfig};
-pub use decoding::{QRDecoder, DecodedQR, DecodeError};
-pub use payment::{PaymentParser, PaymentInfo, PaymentFormat};
+pub use preprocessing::{ImageProcessor, ProcessingConfig, GrayscaleMethod, BinarizationMethod, ContrastMethod, PreprocessStep};
+pub use detection::{QRDetector, DetectedQR, DetectorConfig, ThresholdMode};
+pub use decoding::{QRDecoder, DecodedQR, DecodeError, DecoderConfig, DecodeStrategy, DecodeReport, BinarizerPreference, QrMetadata, BarcodeFormat, StructuredAppend, reassemble};
+pub use payment::{PaymentParser, PaymentInfo, PaymentFormat, TipSpec, build_sbp, build_st};
 #[cfg(feature = "ml")]
-pub use ml_detection::OnnxDetector;
-pub use emv::EmvData;
+pub use ml_detection::{OnnxDetector, InferenceBackend, ExecutionProvider};
+pub use emv::{EmvData, EmvBuilder};
+pub use currency::currency_alpha;
+pub use rust_decimal::Decimal;
+pub use wifi::{WiFiInfo, WiFiEncryption};
+pub use geo::GeoLocation;
+pub use structured::parse_key_value;
+pub use calendar::{CalendarEvent, EventTime};
+pub use uri::UriInfo;
+pub use config::ScannerConfig;
 
 use image::GrayImage;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Mutex;
 use thiserror::Error;
 
 /// Основные ошибки модуля
 #[derive(Error, Debug)]
 pub enum QRError {
+    /// Изображение непригодно для обработки (например, нулевой размер)
     #[error("Image processing error: {0}")]
     ImageProcessing(String),
     
+    /// Детектор не может работать (например, некорректный `DetectorConfig`)
     #[error("Detection error: {0}")]
     Detection(String),
     
@@ -48,12 +72,34 @@ pub enum QRError {
 pub struct ScanResult {
     /// Все обнаруженные и декодированные QR-коды
     pub qr_codes: Vec<QRResult>,
-    /// Наиболее релевантный платёжный QR (если есть)
+    /// Наиболее релевантный платёжный QR (если есть), первый элемент `payment_ranking`
     pub best_payment: Option<usize>,
+    /// Все QR с ненулевой платёжной релевантностью: (индекс в `qr_codes`, оценка)
+    /// по убыванию оценки
+    #[serde(default)]
+    pub payment_ranking: Vec<(usize, f32)>,
     /// Время обработки в миллисекундах
     pub processing_time_ms: u64,
 }
 
+impl ScanResult {
+    /// Непустой код с наибольшей уверенностью детекции (при равенстве — первый)
+    pub fn best_qr(&self) -> Option<&QRResult> {
+        self.qr_codes
+            .iter()
+            .filter(|qr| !qr.content.is_empty())
+            .reduce(|best, qr| if qr.confidence > best.confidence { qr } else { best })
+    }
+
+    /// Разобранные платёжные данные в порядке `payment_ranking`: первым идёт
+    /// `best_payment`; коды без `PaymentInfo` пропускаются
+    pub fn payments(&self) -> impl Iterator<Item = &PaymentInfo> {
+        self.payment_ranking
+            .iter()
+            .filter_map(|&(idx, _)| self.qr_codes.get(idx)?.payment.as_ref())
+    }
+}
+
 /// Результат для одного QR-кода
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QRResult {
@@ -65,22 +111,62 @@ pub struct QRResult {
     pub content_type: ContentType,
     /// Платёжная информация (если это платёжный QR)
     pub payment: Option<PaymentInfo>,
+    /// Параметры сети (если это WiFi QR)
+    pub wifi: Option<WiFiInfo>,
+    /// Координаты (если это `geo:` QR)
+    pub geo: Option<GeoLocation>,
+    /// Части ссылки (если это `ContentType::Url`, включая диплинки `myapp://`)
+    #[serde(default)]
+    pub uri: Option<UriInfo>,
+    /// Событие (если это `VEVENT` QR)
+    #[serde(default)]
+    pub calendar: Option<CalendarEvent>,
+    /// Поля ключ-значение (если это `ContentType::Structured`)
+    #[serde(default)]
+    pub fields: Option<HashMap<String, String>>,
     /// Уверенность детекции (0.0 - 1.0)
     pub confidence: f32,
+    /// Количество кодовых слов, исправленных Reed–Solomon (если декодер это сообщает)
+    pub errors_corrected: Option<u16>,
+    /// Углы кода (TL, TR, BR, BL) в той же системе координат, что и `bbox`
+    pub corners: Option<[(f32, f32); 4]>,
+    /// Номер страницы или кадра (с нуля) для многостраничных и анимированных входов
+    #[serde(default)]
+    pub page: Option<u32>,
+    /// Изображение, поданное в декодер (только при `QRScanner::set_debug_crops(true)`)
+    #[serde(skip)]
+    pub debug_crop: Option<GrayImage>,
+}
+
+impl QRResult {
+    /// Разобран ли платёжный QR (`payment` заполнен)
+    pub fn is_payment(&self) -> bool {
+        self.payment.is_some()
+    }
 }
 
 /// Тип контента QR-кода
+///
+/// В JSON — snake_case (`"payment"`, `"vcard"`, `"wifi"`): строки входят в контракт
+/// WASM/CLI и не должны меняться при переименовании вариантов
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
 pub enum ContentType {
     Text,
     Url,
+    #[serde(rename = "vcard")]
     VCard,
+    #[serde(rename = "wifi")]
     WiFi,
     Payment,
     Email,
     Phone,
     Sms,
     Geo,
+    /// Событие календаря (`BEGIN:VEVENT`)
+    Calendar,
+    /// Поля `KEY=VALUE` / `KEY:VALUE` (только при `QRScanner::set_key_value_delimiters`)
+    Structured,
     Unknown,
 }
 
@@ -90,13 +176,17 @@ impl ContentType {
         
         if content_lower.starts_with("http://") || content_lower.starts_with("https://") {
             // Проверка на платёжные URL
-            if content_lower.contains("qr.nspk.ru") || content_lower.contains("pay") {
+            if is_payment_url(&content_lower) {
                 ContentType::Payment
             } else {
                 ContentType::Url
             }
         } else if content_lower.starts_with("begin:vcard") {
             ContentType::VCard
+        } else if content_lower.starts_with("begin:vevent")
+            || (content_lower.starts_with("begin:vcalendar") && content_lower.contains("begin:vevent"))
+        {
+            ContentType::Calendar
         } else if content_lower.starts_with("wifi:") {
             ContentType::WiFi
         } else if content_lower.starts_with("mailto:") {
@@ -107,24 +197,72 @@ impl ContentType {
             ContentType::Sms
         } else if content_lower.starts_with("geo:") {
             ContentType::Geo
-        } else if content.starts_with("00") && content.len() > 50 {
-            // EMV QR обычно начинается с "00" (Payload Format Indicator)
+        } else if emv::EmvData::is_emv(content) {
+            // EMV QR: Payload Format Indicator, TLV и CRC (тег 63); длина не важна
             ContentType::Payment
         } else if content_lower.starts_with("st.") {
             // Российский стандарт ST.00012
             ContentType::Payment
+        } else if payment::is_epc(content) {
+            // SEPA GiroCode (EPC069-12)
+            ContentType::Payment
+        } else if payment::is_upi(content) || payment::is_sberpay(content) {
+            // UPI и диплинки SberPay (PIX и PromptPay — EMV, см. выше)
+            ContentType::Payment
+        } else if uri::has_scheme(content) {
+            // Диплинки приложений: myapp://, intent://, market://
+            ContentType::Url
         } else {
             ContentType::Text
         }
     }
 }
 
+/// Платёжная ли ссылка (`url` в нижнем регистре): хост НСПК или отдельное слово
+/// `pay`/`payment(s)` среди частей хоста и пути. Подстрока не считается:
+/// `https://paypal-lookalike.com/pages` — обычный URL.
+fn is_payment_url(url: &str) -> bool {
+    const KEYWORDS: [&str; 3] = ["pay", "payment", "payments"];
+
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (authority, path) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default();
+    if host == "qr.nspk.ru" || host.ends_with(".qr.nspk.ru") {
+        return true;
+    }
+
+    host.split(['.', '-'])
+        .chain(path.split(|c: char| !c.is_alphanumeric()))
+        .any(|word| KEYWORDS.contains(&word))
+}
+
+/// Ключ кадра в кэше: размер и average hash
+type FrameKey = ((u32, u32), u64);
+
+/// Кэш результата последнего кадра (для камерного цикла)
+struct FrameCache {
+    /// Максимальное расстояние Хэмминга между хэшами, при котором кадр считается повтором
+    threshold: u32,
+    /// Размер и хэш последнего просканированного кадра и его результат
+    last: Option<(FrameKey, ScanResult)>,
+}
+
+/// Предел кадров `scan_animated_bytes` по умолчанию
+pub const DEFAULT_MAX_FRAMES: usize = 100;
+
 /// Главный сканер QR-кодов
 pub struct QRScanner {
     processor: ImageProcessor,
     detector: QRDetector,
     decoder: QRDecoder,
     payment_parser: PaymentParser,
+    frame_cache: Mutex<Option<FrameCache>>,
+    debug_crops: bool,
+    full_image_decode: bool,
+    multi_code: bool,
+    key_value_delimiters: Vec<(char, char)>,
+    max_frames: usize,
 }
 
 impl Default for QRScanner {
@@ -133,6 +271,59 @@ impl Default for QRScanner {
     }
 }
 
+/// Пошаговая сборка `QRScanner`: незаданные подсистемы получают настройки по умолчанию
+#[derive(Default)]
+pub struct QRScannerBuilder {
+    processing: ProcessingConfig,
+    detection: DetectorConfig,
+    decoder: Option<DecoderConfig>,
+    #[cfg(feature = "ml")]
+    ml_detector: Option<OnnxDetector>,
+}
+
+impl QRScannerBuilder {
+    /// Конфигурация предобработки
+    pub fn processing(mut self, config: ProcessingConfig) -> Self {
+        self.processing = config;
+        self
+    }
+
+    /// Конфигурация классического детектора
+    pub fn detection(mut self, config: DetectorConfig) -> Self {
+        self.detection = config;
+        self
+    }
+
+    /// Конфигурация каскада декодера (проверяется в `build`)
+    pub fn decoder(mut self, config: DecoderConfig) -> Self {
+        self.decoder = Some(config);
+        self
+    }
+
+    /// ML-детектор вместо классического поиска finder patterns
+    #[cfg(feature = "ml")]
+    pub fn ml_detector(mut self, detector: OnnxDetector) -> Self {
+        self.ml_detector = Some(detector);
+        self
+    }
+
+    /// Сборка сканера; ошибка — только при некорректном `DecoderConfig`
+    pub fn build(self) -> Result<QRScanner, QRError> {
+        let decoder = match self.decoder {
+            Some(config) => QRDecoder::with_config(config)?,
+            None => QRDecoder::new(),
+        };
+
+        let mut scanner = QRScanner::with_config(self.processing, self.detection);
+        scanner.decoder = decoder;
+        #[cfg(feature = "ml")]
+        if let Some(detector) = self.ml_detector {
+            scanner.set_ml_detector(detector);
+        }
+        Ok(scanner)
+    }
+}
+
 impl QRScanner {
     /// Создание нового сканера с настройками по умолчанию
     pub fn new() -> Self {
@@ -141,6 +332,12 @@ impl QRScanner {
             detector: QRDetector::new(DetectorConfig::default()),
             decoder: QRDecoder::new(),
             payment_parser: PaymentParser::new(),
+            frame_cache: Mutex::new(None),
+            debug_crops: false,
+            full_image_decode: true,
+            multi_code: false,
+            key_value_delimiters: Vec::new(),
+            max_frames: DEFAULT_MAX_FRAMES,
         }
     }
     
@@ -154,73 +351,376 @@ impl QRScanner {
             detector: QRDetector::new(detection),
             decoder: QRDecoder::new(),
             payment_parser: PaymentParser::new(),
+            frame_cache: Mutex::new(None),
+            debug_crops: false,
+            full_image_decode: true,
+            multi_code: false,
+            key_value_delimiters: Vec::new(),
+            max_frames: DEFAULT_MAX_FRAMES,
         }
     }
 
-    /// Установка ML детектора
+    /// Сборщик сканера с выборочной настройкой подсистем
+    pub fn builder() -> QRScannerBuilder {
+        QRScannerBuilder::default()
+    }
+
+    /// Установка ML детектора; уточнение углов в его кропах использует
+    /// конфигурацию предобработки сканера
     #[cfg(feature = "ml")]
-    pub fn set_ml_detector(&mut self, detector: OnnxDetector) {
+    pub fn set_ml_detector(&mut self, mut detector: OnnxDetector) {
+        detector.set_processing_config(self.processor.config().clone());
         self.detector.set_ml_detector(detector);
     }
     
-    /// Сканирование изображения из байтов
+    /// Включение кэша кадров: если average hash нового кадра отличается от предыдущего
+    /// не более чем на `threshold` бит (из 64), возвращается предыдущий `ScanResult`
+    /// без повторного запуска конвейера.
+    pub fn enable_frame_cache(&mut self, threshold: u32) {
+        *self.frame_cache.get_mut().unwrap_or_else(|e| e.into_inner()) = Some(FrameCache {
+            threshold,
+            last: None,
+        });
+    }
+
+    /// Отключение кэша кадров
+    pub fn disable_frame_cache(&mut self) {
+        *self.frame_cache.get_mut().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Текущая конфигурация предобработки
+    pub fn processing_config(&self) -> &ProcessingConfig {
+        self.processor.config()
+    }
+
+    /// Текущая конфигурация детектора
+    pub fn detector_config(&self) -> &DetectorConfig {
+        self.detector.config()
+    }
+
+    /// Замена конфигурации каскада декодера (например, с бюджетом времени для видео)
+    pub fn set_decoder_config(&mut self, config: DecoderConfig) -> Result<(), QRError> {
+        self.decoder = QRDecoder::with_config(config)?;
+        Ok(())
+    }
+
+    /// Прикреплять к каждому `QRResult` изображение, которое видел декодер.
+    /// По умолчанию выключено, чтобы не держать копии кадров в памяти.
+    pub fn set_debug_crops(&mut self, enabled: bool) {
+        self.debug_crops = enabled;
+    }
+
+    /// Декодировать кадр целиком помимо найденных детектором кропов: последняя
+    /// попытка, если детектор ничего не дал, и поиск дополнительных кодов в режиме
+    /// `set_multi_code`.
+    /// По умолчанию включено; вызывающим с заранее вырезанными областями
+    /// это удваивает работу и может давать ложные срабатывания.
+    pub fn set_full_image_decode(&mut self, enabled: bool) {
+        self.full_image_decode = enabled;
+    }
+
+    /// Искать все коды кадра: декодировать кадр целиком (`QRDecoder::decode_all`)
+    /// даже после того, как детектор нашёл и прочитал коды. По умолчанию выключено:
+    /// проход примерно удваивает время скана; без него кадр целиком декодируется,
+    /// только если детектор ничего не дал.
+    pub fn set_multi_code(&mut self, enabled: bool) {
+        self.multi_code = enabled;
+    }
+
+    /// Сколько кадров анимации просматривает `scan_animated_bytes` (не меньше одного);
+    /// остальные кадры не декодируются. По умолчанию `DEFAULT_MAX_FRAMES`.
+    pub fn set_max_frames(&mut self, max_frames: usize) {
+        self.max_frames = max_frames.max(1);
+    }
+
+    /// Разделители `(поле, ключ-значение)`, при которых текстовый QR, разобранный
+    /// `parse_key_value`, получает тип `ContentType::Structured` и поле `fields`.
+    /// Пробуются по порядку; по умолчанию список пуст.
+    pub fn set_key_value_delimiters(&mut self, delimiters: Vec<(char, char)>) {
+        self.key_value_delimiters = delimiters;
+    }
+
+    /// Преобразование цветного изображения в grayscale согласно конфигурации
+    pub fn to_grayscale(&self, img: &image::DynamicImage) -> GrayImage {
+        self.processor.to_grayscale(img)
+    }
+
+    /// Преобразование RGBA-буфера канальным методом из конфигурации
+    /// (`None` для `GrayscaleMethod::Luma` или неверного размера буфера)
+    pub fn rgba_channel_to_gray(&self, rgba: &[u8], width: u32, height: u32) -> Option<GrayImage> {
+        self.processor.rgba_channel_to_gray(rgba, width, height)
+    }
+    
+    /// Сканирование файла изображения (формат по расширению/содержимому).
+    /// Ошибки чтения и декодирования файла — `QRError::InvalidFormat`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn scan_file(&self, path: impl AsRef<std::path::Path>) -> Result<ScanResult, QRError> {
+        let start = timing::Instant::now();
+        let path = path.as_ref();
+        let img = image::open(path)
+            .map_err(|e| QRError::InvalidFormat(format!("{}: {}", path.display(), e)))?;
+        let gray = self.processor.to_grayscale(&img);
+
+        self.scan_image_timed(&gray, start)
+    }
+
     /// Сканирование изображения из байтов
     pub fn scan_bytes(&self, image_bytes: &[u8]) -> Result<ScanResult, QRError> {
+        let start = timing::Instant::now();
         // Загрузка изображения
         let img = image::load_from_memory(image_bytes)
             .map_err(|e| QRError::InvalidFormat(e.to_string()))?;
-        let gray = img.to_luma8();
+        let gray = self.processor.to_grayscale(&img);
         
         // Сканирование
-        self.scan_image(&gray)
+        self.scan_image_timed(&gray, start)
     }
     
+    /// Сканирование сырого плана яркости (Y-плоскость камеры) без декодирования
+    /// изображения: `luma` — ровно `width * height` байт построчно без отступов.
+    /// `Vec<u8>` принимается без копирования, срез копируется один раз.
+    pub fn scan_luma<'a>(&self, width: u32, height: u32, luma: impl Into<Cow<'a, [u8]>>) -> Result<ScanResult, QRError> {
+        let luma = luma.into().into_owned();
+        let expected = (width as usize)
+            .checked_mul(height as usize)
+            .ok_or_else(|| QRError::InvalidFormat(format!("frame {}x{} is too large", width, height)))?;
+        if luma.len() != expected {
+            return Err(QRError::InvalidFormat(format!(
+                "invalid luma buffer for {}x{} frame: expected {} bytes, got {}",
+                width,
+                height,
+                expected,
+                luma.len()
+            )));
+        }
+        let gray = GrayImage::from_raw(width, height, luma)
+            .ok_or_else(|| QRError::InvalidFormat(format!("invalid luma buffer for {}x{} frame", width, height)))?;
+
+        self.scan_image(&gray)
+    }
+
+    /// Декодирование заранее вырезанного QR без детекции и предобработки.
+    ///
+    /// Кроп сразу подаётся в `QRDecoder::decode` (каскад fallback'ов запускается,
+    /// только если стандартная стадия не справилась); `bbox` — весь кроп,
+    /// тип контента и платёжные данные заполняются как в `scan_image`.
+    pub fn decode_cropped(&self, img: &GrayImage) -> Result<QRResult, QRError> {
+        if img.width() == 0 || img.height() == 0 {
+            return Err(QRError::ImageProcessing(format!(
+                "empty image ({}x{})",
+                img.width(),
+                img.height()
+            )));
+        }
+
+        let decoded = self.decoder.decode(img)?;
+        let crop = self.debug_crops.then(|| img.clone());
+        Ok(self.build_result(decoded, [0, 0, img.width(), img.height()], 1.0, crop))
+    }
+
+    /// Только текст кода: предобработка и `QRDecoder::decode` всего кадра
+    /// без детекции, определения типа контента и платёжного разбора
+    pub fn decode_text(&self, gray: &GrayImage) -> Result<String, QRError> {
+        if gray.width() == 0 || gray.height() == 0 {
+            return Err(QRError::ImageProcessing(format!(
+                "empty image ({}x{})",
+                gray.width(),
+                gray.height()
+            )));
+        }
+
+        let processed = self.processor.process(gray);
+        Ok(self.decoder.decode(&processed)?.content)
+    }
+
+    /// Сканирование анимированного изображения (GIF, APNG) по кадрам.
+    ///
+    /// Возвращается результат первого кадра с платёжным QR, иначе первого кадра
+    /// с любым QR, иначе первого кадра. Просматриваются не больше `set_max_frames`
+    /// первых кадров; `processing_time_ms` — время всех просмотренных кадров.
+    /// Статичные изображения сканируются как в `scan_bytes`.
+    pub fn scan_animated_bytes(&self, image_bytes: &[u8]) -> Result<ScanResult, QRError> {
+        use image::AnimationDecoder;
+
+        let start = timing::Instant::now();
+        let invalid = |e: image::ImageError| QRError::InvalidFormat(e.to_string());
+        let cursor = std::io::Cursor::new(image_bytes);
+        let frames = match image::guess_format(image_bytes) {
+            Ok(image::ImageFormat::Gif) => image::codecs::gif::GifDecoder::new(cursor).map_err(invalid)?.into_frames(),
+            Ok(image::ImageFormat::Png) => {
+                let decoder = image::codecs::png::PngDecoder::new(cursor).map_err(invalid)?;
+                if !decoder.is_apng().map_err(invalid)? {
+                    return self.scan_bytes(image_bytes);
+                }
+                decoder.apng().map_err(invalid)?.into_frames()
+            }
+            _ => return self.scan_bytes(image_bytes),
+        };
+
+        let mut first: Option<ScanResult> = None;
+        let mut with_codes: Option<ScanResult> = None;
+        for (index, frame) in frames.take(self.max_frames).enumerate() {
+            let frame = image::DynamicImage::ImageRgba8(frame.map_err(invalid)?.into_buffer());
+            let gray = self.processor.to_grayscale(&frame);
+            // Кадры анимации сканируются независимо, мимо кэша кадров
+            let mut result = self.run_pipeline(&gray, start)?;
+            log::info!("Animation frame #{}: {} codes", index, result.qr_codes.len());
+            for qr in &mut result.qr_codes {
+                qr.page = u32::try_from(index).ok();
+            }
+
+            if result.best_payment.is_some() {
+                return Ok(ScanResult {
+                    processing_time_ms: timing::elapsed_ms(start),
+                    ..result
+                });
+            }
+            if with_codes.is_none() && !result.qr_codes.is_empty() {
+                with_codes = Some(result);
+            } else if first.is_none() {
+                first = Some(result);
+            }
+        }
+
+        let result = with_codes
+            .or(first)
+            .ok_or_else(|| QRError::InvalidFormat("animation has no frames".to_string()))?;
+        Ok(ScanResult {
+            processing_time_ms: timing::elapsed_ms(start),
+            ..result
+        })
+    }
+
+    /// Сканирование всех страниц многостраничного TIFF или PDF.
+    ///
+    /// Коды со всех страниц собираются в один `ScanResult`, у каждого задан `page`;
+    /// ранжирование платёжных QR — общее для документа. Из PDF берутся растровые
+    /// изображения страниц (сканы): векторная графика не рендерится.
+    #[cfg(all(feature = "multipage", not(target_arch = "wasm32")))]
+    pub fn scan_pages(&self, image_bytes: &[u8]) -> Result<ScanResult, QRError> {
+        let start = timing::Instant::now();
+        let mut qr_codes = Vec::new();
+        let mut scan_page = |page: u32, img: &image::DynamicImage| -> Result<(), QRError> {
+            let gray = self.processor.to_grayscale(img);
+            let result = self.run_pipeline(&gray, start)?;
+            log::info!("Page #{}: {} codes", page, result.qr_codes.len());
+            qr_codes.extend(result.qr_codes.into_iter().map(|qr| QRResult { page: Some(page), ..qr }));
+            Ok(())
+        };
+
+        if image_bytes.starts_with(b"%PDF-") {
+            let document = lopdf::Document::load_mem(image_bytes).map_err(|e| QRError::InvalidFormat(e.to_string()))?;
+            // get_pages нумерует страницы с 1
+            for (number, page_id) in document.get_pages() {
+                let page = number - 1;
+                for img in pdf_page_images(&document, page_id) {
+                    scan_page(page, &img)?;
+                }
+            }
+            return Ok(self.rank_payments(qr_codes, start));
+        }
+
+        let invalid = |e: tiff::TiffError| QRError::InvalidFormat(e.to_string());
+        let mut decoder = tiff::decoder::Decoder::new(std::io::Cursor::new(image_bytes)).map_err(invalid)?;
+        for page in 0u32.. {
+            let img = tiff_page(&mut decoder).map_err(|e| match e {
+                QRError::InvalidFormat(e) => QRError::InvalidFormat(format!("page {}: {}", page, e)),
+                e => e,
+            })?;
+            scan_page(page, &img)?;
+
+            if !decoder.more_images() {
+                break;
+            }
+            decoder.next_image().map_err(invalid)?;
+        }
+
+        Ok(self.rank_payments(qr_codes, start))
+    }
+
     /// Сканирование GrayImage
     pub fn scan_image(&self, gray: &GrayImage) -> Result<ScanResult, QRError> {
+        self.scan_image_timed(gray, timing::Instant::now())
+    }
+
+    /// Сканирование GrayImage с отсчётом `processing_time_ms` от `start`
+    /// (например, чтобы учесть декодирование и конвертацию кадра вызывающей стороной)
+    pub fn scan_image_timed(&self, gray: &GrayImage, start: timing::Instant) -> Result<ScanResult, QRError> {
+        // Под блокировкой только поиск в кэше: параллельные сканы не ждут друг друга
+        let lookup = {
+            let cache = self.frame_cache.lock().unwrap_or_else(|e| e.into_inner());
+            cache.as_ref().map(|cache| {
+                let key = (gray.dimensions(), self.processor.average_hash(gray));
+                let hit = cache.last.as_ref().and_then(|((size, hash), result)| {
+                    (*size == key.0 && (hash ^ key.1).count_ones() <= cache.threshold).then(|| result.clone())
+                });
+                (key, hit)
+            })
+        };
+        let Some((key, hit)) = lookup else {
+            return self.run_pipeline(gray, start);
+        };
+        if let Some(mut result) = hit {
+            log::info!("Frame cache hit, reusing previous result");
+            result.processing_time_ms = timing::elapsed_ms(start);
+            return Ok(result);
+        }
+
+        let result = self.run_pipeline(gray, start)?;
+        if let Some(cache) = self.frame_cache.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            cache.last = Some((key, result.clone()));
+        }
+        Ok(result)
+    }
+
+    /// Полный конвейер: предобработка, детекция, декодирование
+    fn run_pipeline(&self, gray: &GrayImage, start: timing::Instant) -> Result<ScanResult, QRError> {
         log::info!("Starting scan_image, size: {:?}", gray.dimensions());
 
+        if gray.width() == 0 || gray.height() == 0 {
+            return Err(QRError::ImageProcessing(format!(
+                "empty image ({}x{})",
+                gray.width(),
+                gray.height()
+            )));
+        }
+        self.detector.config().validate().map_err(QRError::Detection)?;
+
         // Предобработка
         log::info!("Starting preprocessing");
         let processed = self.processor.process(gray);
         log::info!("Preprocessing done, resulting size: {:?}", processed.dimensions());
         
-        // Детекция QR-кодов
+        // Детекция QR-кодов: кропы строятся по одному и освобождаются после декодирования
         log::info!("Starting detection");
-        let detected = self.detector.detect(&processed);
-        log::info!("Detection done, found: {}", detected.len());
-        
-        // Декодирование каждого QR
         let mut qr_codes = Vec::new();
-        let mut best_payment_score = 0.0f32;
-        let mut best_payment_idx = None;
+        let full_frame = [0, 0, processed.width(), processed.height()];
         
-        for (idx, detection) in detected.iter().enumerate() {
+        for (idx, detection) in self.detector.detect_iter(&processed).enumerate() {
             log::info!("Decoding detected QR #{}", idx);
-            // Пробуем декодировать
-            match self.decoder.decode(&detection.image) {
+            // Класс ML-детектора выбирает ридер (QR, DataMatrix, Aztec, ...)
+            let format = detection.label.as_deref().and_then(BarcodeFormat::from_label);
+            let decoded = match (format, detection.tilt) {
+                (Some(format), _) => self.decoder.decode_format(&detection.image, format),
+                (None, Some(tilt)) => self.decoder.decode_tilted(&detection.image, tilt),
+                (None, None) => self.decoder.decode(&detection.image),
+            };
+            match decoded {
                 Ok(decoded) => {
+                    // Весь кадр после кропов одиночных паттернов может повторить уже найденный код
+                    if detection.bbox == full_frame && qr_codes.iter().any(|q: &QRResult| q.content == decoded.content) {
+                        continue;
+                    }
                     log::info!("Decoded successfully: {:?}", decoded.content);
-                    let content_type = ContentType::detect(&decoded.content);
-                    let payment = if content_type == ContentType::Payment {
-                        self.payment_parser.parse(&decoded.content)
+                    let crop = self.debug_crops.then(|| detection.image.clone());
+                    let decoded = decoded.map_corners(|p| detection.to_frame(p));
+                    // Весь кадр от детектора (fallback) сужается до углов кода
+                    let bbox = if detection.bbox == full_frame {
+                        tight_bbox(decoded.corners, full_frame)
                     } else {
-                        None
+                        detection.bbox
                     };
-                    
-                    // Оценка релевантности для оплаты
-                    let payment_score = self.payment_parser.relevance_score(&decoded.content);
-                    if payment_score > best_payment_score {
-                        best_payment_score = payment_score;
-                        best_payment_idx = Some(idx);
-                    }
-                    
-                    qr_codes.push(QRResult {
-                        content: decoded.content,
-                        bbox: detection.bbox,
-                        content_type,
-                        payment,
-                        confidence: detection.confidence,
-                    });
+                    qr_codes.push(self.build_result(decoded, bbox, detection.confidence, crop));
                 }
                 Err(e) => {
                     log::debug!("Failed to decode QR at {:?}: {}", detection.bbox, e);
@@ -228,41 +728,131 @@ impl QRScanner {
             }
         }
         
+        // Детектор находит не все коды на кадре с несколькими QR: добираем остальные
+        // сетки rqrr с полного изображения. Это полный проход rqrr (и инверсия),
+        // поэтому после успешных кропов — только в режиме multi_code.
+        let search_all = self.full_image_decode && (qr_codes.is_empty() || self.multi_code);
+        let extra = if search_all { self.decoder.decode_all(&processed) } else { Vec::new() };
+        for decoded in extra {
+            if qr_codes.iter().any(|q| q.content == decoded.content) {
+                continue;
+            }
+            log::info!("Extra code from full image: {:?}", decoded.content);
+            let crop = self.debug_crops.then(|| processed.clone());
+            let bbox = tight_bbox(decoded.corners, full_frame);
+            qr_codes.push(self.build_result(decoded, bbox, 1.0, crop));
+        }
+
         // Если не нашли QR через детектор, пробуем декодировать всё изображение напрямую
-        if qr_codes.is_empty() {
+        if qr_codes.is_empty() && self.full_image_decode {
             log::info!("No QRs found via detection, trying full image decode");
             if let Ok(decoded) = self.decoder.decode(&processed) {
                 log::info!("Full image decode success: {:?}", decoded.content);
-                let content_type = ContentType::detect(&decoded.content);
-                let payment = if content_type == ContentType::Payment {
-                    self.payment_parser.parse(&decoded.content)
-                } else {
-                    None
-                };
-                
-                qr_codes.push(QRResult {
-                    content: decoded.content,
-                    bbox: [0, 0, processed.width(), processed.height()],
-                    content_type,
-                    payment,
-                    confidence: 1.0,
-                    });
-                
-                if best_payment_idx.is_none() && qr_codes.last().map(|q| q.content_type == ContentType::Payment).unwrap_or(false) {
-                    best_payment_idx = Some(0);
-                }
+                let crop = self.debug_crops.then(|| processed.clone());
+                let bbox = tight_bbox(decoded.corners, full_frame);
+                qr_codes.push(self.build_result(decoded, bbox, 1.0, crop));
             } else {
                 log::info!("Full image decode failed");
             }
         }
-        
+
         log::info!("Scan complete, found {} codes", qr_codes.len());
-        
-        Ok(ScanResult {
+
+        // Предобработка могла уменьшить кадр (max_dimension): bbox и углы — в координаты входа
+        if processed.dimensions() != gray.dimensions() {
+            let scale = (
+                gray.width() as f32 / processed.width() as f32,
+                gray.height() as f32 / processed.height() as f32,
+            );
+            for qr in &mut qr_codes {
+                scale_to_input(qr, scale, gray.dimensions());
+            }
+        }
+
+        Ok(self.rank_payments(qr_codes, start))
+    }
+
+    /// `ScanResult` с платёжными QR по убыванию релевантности;
+    /// при равных оценках — в порядке обнаружения
+    fn rank_payments(&self, qr_codes: Vec<QRResult>, start: timing::Instant) -> ScanResult {
+        let mut payment_ranking: Vec<(usize, f32)> = qr_codes
+            .iter()
+            .enumerate()
+            .map(|(idx, qr)| (idx, self.payment_parser.relevance_score(&qr.content)))
+            .filter(|&(_, score)| score > 0.0)
+            .collect();
+        payment_ranking.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        ScanResult {
             qr_codes,
-            best_payment: best_payment_idx,
-            processing_time_ms: 0,
-        })
+            best_payment: payment_ranking.first().map(|&(idx, _)| idx),
+            payment_ranking,
+            processing_time_ms: timing::elapsed_ms(start),
+        }
+    }
+    
+    /// Сборка `QRResult`: тип контента, платёжные данные.
+    /// Углы `decoded` уже должны быть в координатах кадра.
+    fn build_result(
+        &self,
+        decoded: DecodedQR,
+        bbox: [u32; 4],
+        confidence: f32,
+        debug_crop: Option<GrayImage>,
+    ) -> QRResult {
+        let mut content_type = ContentType::detect(&decoded.content);
+        let fields = if content_type == ContentType::Text {
+            self.key_value_delimiters
+                .iter()
+                .find_map(|&(field_sep, kv_sep)| parse_key_value(&decoded.content, field_sep, kv_sep))
+        } else {
+            None
+        };
+        if fields.is_some() {
+            content_type = ContentType::Structured;
+        }
+        let payment = if content_type == ContentType::Payment {
+            self.payment_parser.parse(&decoded.content)
+        } else {
+            None
+        };
+        let wifi = if content_type == ContentType::WiFi {
+            WiFiInfo::parse(&decoded.content)
+        } else {
+            None
+        };
+        let geo = if content_type == ContentType::Geo {
+            GeoLocation::parse(&decoded.content)
+        } else {
+            None
+        };
+        let uri = if content_type == ContentType::Url {
+            UriInfo::parse(&decoded.content)
+        } else {
+            None
+        };
+        let calendar = if content_type == ContentType::Calendar {
+            CalendarEvent::parse(&decoded.content)
+        } else {
+            None
+        };
+
+        QRResult {
+            content: decoded.content,
+            bbox,
+            content_type,
+            payment,
+            wifi,
+            geo,
+            uri,
+            calendar,
+            fields,
+            confidence,
+            errors_corrected: decoded.errors_corrected,
+            corners: decoded.corners,
+            page: None,
+            debug_crop,
+        }
     }
     
     /// Сканирование с приоритетом платёжных QR
@@ -277,6 +867,136 @@ impl QRScanner {
     }
 }
 
+/// Перевод `bbox` и углов из координат обработанного изображения в координаты
+/// входного (`scale` — отношение размеров вход / обработанное)
+fn scale_to_input(qr: &mut QRResult, (sx, sy): (f32, f32), (width, height): (u32, u32)) {
+    let [x, y, w, h] = qr.bbox;
+    let x0 = ((x as f32 * sx).floor() as u32).min(width.saturating_sub(1));
+    let y0 = ((y as f32 * sy).floor() as u32).min(height.saturating_sub(1));
+    let x1 = (((x + w) as f32 * sx).ceil() as u32).clamp(x0 + 1, width);
+    let y1 = (((y + h) as f32 * sy).ceil() as u32).clamp(y0 + 1, height);
+    qr.bbox = [x0, y0, x1 - x0, y1 - y0];
+    if let Some(corners) = qr.corners.as_mut() {
+        for (cx, cy) in corners.iter_mut() {
+            *cx *= sx;
+            *cy *= sy;
+        }
+    }
+}
+
+/// Описывающий прямоугольник углов кода внутри `frame`;
+/// весь `frame`, если углы неизвестны или вырождены
+fn tight_bbox(corners: Option<[(f32, f32); 4]>, frame: [u32; 4]) -> [u32; 4] {
+    let Some(corners) = corners else {
+        return frame;
+    };
+    let [fx, fy, fw, fh] = frame;
+    let clamp = |v: f32, lo: u32, len: u32| v.clamp(lo as f32, (lo + len) as f32);
+    let (mut x0, mut y0, mut x1, mut y1) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for (x, y) in corners {
+        if !x.is_finite() || !y.is_finite() {
+            return frame;
+        }
+        x0 = x0.min(clamp(x, fx, fw));
+        y0 = y0.min(clamp(y, fy, fh));
+        x1 = x1.max(clamp(x, fx, fw));
+        y1 = y1.max(clamp(y, fy, fh));
+    }
+    let (x, y) = (x0.floor() as u32, y0.floor() as u32);
+    let (w, h) = (x1.ceil() as u32 - x, y1.ceil() as u32 - y);
+    if w == 0 || h == 0 {
+        return frame;
+    }
+    [x, y, w, h]
+}
+
+/// Текущая страница TIFF (8-битные Gray, GrayA, RGB, RGBA)
+#[cfg(all(feature = "multipage", not(target_arch = "wasm32")))]
+fn tiff_page<R: std::io::Read + std::io::Seek>(decoder: &mut tiff::decoder::Decoder<R>) -> Result<image::DynamicImage, QRError> {
+    use tiff::decoder::DecodingResult;
+    use tiff::ColorType;
+
+    let invalid = |e: tiff::TiffError| QRError::InvalidFormat(e.to_string());
+    let (width, height) = decoder.dimensions().map_err(invalid)?;
+    let color = decoder.colortype().map_err(invalid)?;
+    let DecodingResult::U8(data) = decoder.read_image().map_err(invalid)? else {
+        return Err(QRError::InvalidFormat(format!("unsupported TIFF sample format {:?}", color)));
+    };
+
+    let img = match color {
+        ColorType::Gray(8) => image::DynamicImage::ImageLuma8(image::ImageBuffer::from_raw(width, height, data).ok_or_else(short_page)?),
+        ColorType::GrayA(8) => image::DynamicImage::ImageLumaA8(image::ImageBuffer::from_raw(width, height, data).ok_or_else(short_page)?),
+        ColorType::RGB(8) => image::DynamicImage::ImageRgb8(image::ImageBuffer::from_raw(width, height, data).ok_or_else(short_page)?),
+        ColorType::RGBA(8) => image::DynamicImage::ImageRgba8(image::ImageBuffer::from_raw(width, height, data).ok_or_else(short_page)?),
+        other => return Err(QRError::InvalidFormat(format!("unsupported TIFF color type {:?}", other))),
+    };
+    Ok(img)
+}
+
+/// Растровые изображения страницы PDF: JPEG (`DCTDecode`) и несжатые или `FlateDecode`
+/// выборки DeviceGray/DeviceRGB (8 бит, для Gray ещё 1 бит). Остальные форматы
+/// (JBIG2, CCITT, JPEG 2000, палитры) пропускаются с предупреждением.
+#[cfg(all(feature = "multipage", not(target_arch = "wasm32")))]
+fn pdf_page_images(document: &lopdf::Document, page_id: lopdf::ObjectId) -> Vec<image::DynamicImage> {
+    // Страница без ресурсов или без XObject — просто без изображений
+    let Ok(images) = document.get_page_images(page_id) else {
+        return Vec::new();
+    };
+    images
+        .iter()
+        .filter_map(|img| {
+            let decoded = pdf_image(document, img);
+            if decoded.is_none() {
+                log::warn!(
+                    "Skipping PDF image {:?}: filters {:?}, color space {:?}, {:?} bpc",
+                    img.id,
+                    img.filters,
+                    img.color_space,
+                    img.bits_per_component
+                );
+            }
+            decoded
+        })
+        .collect()
+}
+
+#[cfg(all(feature = "multipage", not(target_arch = "wasm32")))]
+fn pdf_image(document: &lopdf::Document, img: &lopdf::xobject::PdfImage) -> Option<image::DynamicImage> {
+    let filters = img.filters.as_deref().unwrap_or_default();
+    if filters.iter().any(|f| f == "DCTDecode") {
+        return image::load_from_memory_with_format(img.content, image::ImageFormat::Jpeg).ok();
+    }
+    let data = match filters {
+        [] => img.content.to_vec(),
+        [flate] if flate == "FlateDecode" => document.get_object(img.id).ok()?.as_stream().ok()?.decompressed_content().ok()?,
+        _ => return None,
+    };
+
+    let (width, height) = (u32::try_from(img.width).ok()?, u32::try_from(img.height).ok()?);
+    match (img.color_space.as_deref()?, img.bits_per_component?) {
+        ("DeviceGray", 8) => image::GrayImage::from_raw(width, height, data).map(image::DynamicImage::ImageLuma8),
+        ("DeviceRGB", 8) => image::RgbImage::from_raw(width, height, data).map(image::DynamicImage::ImageRgb8),
+        ("DeviceGray", 1) => {
+            // Строки выровнены по байту, 1 — белый
+            let stride = (width as usize).div_ceil(8);
+            if data.len() < stride * height as usize {
+                return None;
+            }
+            let gray = image::GrayImage::from_fn(width, height, |x, y| {
+                let byte = data[y as usize * stride + x as usize / 8];
+                image::Luma([if byte & (0x80 >> (x % 8)) != 0 { 255 } else { 0 }])
+            });
+            Some(image::DynamicImage::ImageLuma8(gray))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(all(feature = "multipage", not(target_arch = "wasm32")))]
+fn short_page() -> QRError {
+    QRError::InvalidFormat("truncated TIFF page".to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -288,5 +1008,137 @@ mod tests {
         assert_eq!(ContentType::detect("BEGIN:VCARD\nVERSION:3.0"), ContentType::VCard);
         assert_eq!(ContentType::detect("WIFI:T:WPA;S:MyNetwork;P:pass;;"), ContentType::WiFi);
         assert_eq!(ContentType::detect("Hello World"), ContentType::Text);
+        // EPC — та же проверка, что и у PaymentParser
+        assert_eq!(ContentType::detect("BCD\r\n001\r\n1\r\nSCT\r\n"), ContentType::Payment);
+        assert_eq!(ContentType::detect("BCD\nnot a giro code"), ContentType::Text);
+    }
+
+    #[test]
+    fn test_tight_bbox() {
+        let frame = [0, 0, 100, 80];
+        let corners = [(10.2, 20.0), (50.0, 19.5), (50.5, 60.0), (10.0, 60.0)];
+        assert_eq!(tight_bbox(Some(corners), frame), [10, 19, 41, 41]);
+        // Углы за пределами кадра обрезаются
+        assert_eq!(tight_bbox(Some([(-5.0, -5.0), (50.0, 0.0), (120.0, 90.0), (0.0, 50.0)]), frame), frame);
+        assert_eq!(tight_bbox(None, frame), frame);
+        assert_eq!(tight_bbox(Some([(5.0, 5.0); 4]), frame), frame);
+        assert_eq!(tight_bbox(Some([(f32::NAN, 5.0); 4]), frame), frame);
+    }
+
+    #[test]
+    fn test_builder_with_custom_decoder_only() {
+        let config = DecoderConfig {
+            try_inverted: false,
+            try_padding: false,
+            ..Default::default()
+        };
+        let scanner = QRScanner::builder().decoder(config.clone()).build().unwrap();
+
+        assert!(!scanner.decoder.config().try_inverted);
+        assert!(!scanner.decoder.config().try_padding);
+        assert_eq!(scanner.decoder.config().strategy_order, config.strategy_order);
+        // Остальные подсистемы — как у QRScanner::new()
+        assert_eq!(scanner.processing_config().block_size, ProcessingConfig::default().block_size);
+        assert_eq!(scanner.processing_config().max_dimension, ProcessingConfig::default().max_dimension);
+        assert_eq!(scanner.detector_config().min_size, DetectorConfig::default().min_size);
+
+        let invalid = DecoderConfig {
+            strategy_order: Vec::new(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            QRScanner::builder().decoder(invalid).build(),
+            Err(QRError::Decode(DecodeError::InvalidConfig(_)))
+        ));
+    }
+
+    fn scan_result(codes: &[(&str, f32)]) -> ScanResult {
+        let scanner = QRScanner::new();
+        let qr_codes: Vec<QRResult> = codes
+            .iter()
+            .map(|&(content, confidence)| {
+                let decoded = DecodedQR {
+                    content: content.to_string(),
+                    error_correction: decoding::ErrorCorrectionLevel::M,
+                    version: None,
+                    format: BarcodeFormat::QrCode,
+                    encoding: "Byte".to_string(),
+                    errors_corrected: None,
+                    corners: None,
+                    structured_append: None,
+                    raw_bytes: content.as_bytes().to_vec(),
+                    eci: None,
+                };
+                scanner.build_result(decoded, [0, 0, 10, 10], confidence, None)
+            })
+            .collect();
+        scanner.rank_payments(qr_codes, timing::Instant::now())
+    }
+
+    #[test]
+    fn test_best_qr_and_payments() {
+        let sbp = "https://qr.nspk.ru/AS1000?type=02&bank=100000000111&sum=50000&cur=RUB";
+        let st = "ST.00012|Name=ООО Тест|PersonalAcc=40817810099910004312|BankName=Банк|BIC=044525225|Sum=100000";
+        let result = scan_result(&[
+            ("https://example.com", 0.7),
+            ("", 0.95),
+            (st, 0.4),
+            ("WIFI:T:WPA;S:Office;P:secret;;", 0.9),
+            (sbp, 0.6),
+        ]);
+
+        // Пустой контент не учитывается даже при большей уверенности
+        let best = result.best_qr().unwrap();
+        assert_eq!(best.content_type, ContentType::WiFi);
+        assert!(!best.is_payment());
+
+        // Порядок и первый элемент — как у best_payment
+        let payments: Vec<&PaymentInfo> = result.payments().collect();
+        assert_eq!(payments.len(), 2);
+        let best_payment = result.best_payment.map(|idx| &result.qr_codes[idx]).unwrap();
+        assert!(best_payment.is_payment());
+        assert_eq!(Some(payments[0].format.clone()), best_payment.payment.as_ref().map(|p| p.format.clone()));
+        assert_eq!(result.qr_codes.iter().filter(|qr| qr.is_payment()).count(), 2);
+
+        let empty = scan_result(&[]);
+        assert!(empty.best_qr().is_none());
+        assert_eq!(empty.payments().count(), 0);
+    }
+
+    #[test]
+    fn test_long_zero_prefixed_text_is_not_payment() {
+        // 60 символов с `00` в начале, но не EMV: не платёж ни по одной проверке
+        let text = "00".repeat(30);
+        assert_eq!(ContentType::detect(&text), ContentType::Text);
+        assert!(PaymentParser::new().parse(&text).is_none());
+        assert_eq!(PaymentParser::new().relevance_score(&text), 0.0);
+
+        let result = scan_result(&[(&text, 0.9), ("https://example.com", 0.5)]);
+        assert_eq!(result.best_payment, None);
+        assert!(result.payment_ranking.is_empty());
+    }
+
+    #[test]
+    fn test_content_type_wire_format() {
+        let variants = [
+            (ContentType::Text, "text"),
+            (ContentType::Url, "url"),
+            (ContentType::VCard, "vcard"),
+            (ContentType::WiFi, "wifi"),
+            (ContentType::Payment, "payment"),
+            (ContentType::Email, "email"),
+            (ContentType::Phone, "phone"),
+            (ContentType::Sms, "sms"),
+            (ContentType::Geo, "geo"),
+            (ContentType::Calendar, "calendar"),
+            (ContentType::Structured, "structured"),
+            (ContentType::Unknown, "unknown"),
+        ];
+        for (variant, name) in variants {
+            let json = serde_json::to_string(&variant).unwrap();
+            assert_eq!(json, format!("\"{}\"", name));
+            assert_eq!(serde_json::from_str::<ContentType>(&json).unwrap(), variant);
+        }
+        assert!(serde_json::from_str::<ContentType>("\"Payment\"").is_err());
     }
 }
@@ -5,136 +5,388 @@ use crate::detection::DetectedQR;
 use crate::preprocessing::{ImageProcessor, ProcessingConfig};
 use crate::geometry;
 
+/// Optimized runnable tract plan
+type OnnxPlan = SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>;
+
+/// Бэкенд инференса
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InferenceBackend {
+    /// tract: чистый Rust, работает везде, включая wasm
+    Tract,
+    /// ONNX Runtime (фича `onnxruntime`, только нативные сборки).
+    /// Без фичи или под wasm загрузка молча переходит на [`InferenceBackend::Tract`].
+    OnnxRuntime(ExecutionProvider),
+}
+
+/// Провайдер исполнения ONNX Runtime
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExecutionProvider {
+    #[default]
+    Cpu,
+    /// NVIDIA GPU
+    Cuda,
+    /// Apple Neural Engine / GPU
+    CoreMl,
+}
+
+/// Загруженная модель выбранного бэкенда
+enum Engine {
+    Tract(Box<OnnxPlan>),
+    // `Session::run` требует `&mut self`, а `detect` принимает `&self`
+    #[cfg(all(feature = "onnxruntime", not(target_arch = "wasm32")))]
+    OnnxRuntime(std::sync::Mutex<ort::session::Session>, ExecutionProvider),
+}
+
+/// Размер входа модели, если в графе он не задан явно (экспорт YOLOv8 по умолчанию)
+pub const DEFAULT_INPUT_SIZE: u32 = 640;
+
+/// Порог уверенности по умолчанию
+pub const DEFAULT_CONF_THRESHOLD: f32 = 0.5;
+/// Порог IoU для NMS по умолчанию
+pub const DEFAULT_IOU_THRESHOLD: f32 = 0.45;
+
 /// ML-based QR Detector using YOLOv8 (ONNX)
 pub struct OnnxDetector {
-    model: SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>,
+    engine: Engine,
+    input_size: u32,
+    conf_threshold: f32,
+    iou_threshold: f32,
+    oriented: bool,
+    labels: Vec<String>,
+    /// Уточнение углов в кропах боксов (один на все боксы)
+    processor: ImageProcessor,
 }
 
 impl OnnxDetector {
     /// Load model from bytes (WASM compatible)
+    ///
+    /// Размер входа берётся из графа (`[1, 3, N, N]`); для динамических
+    /// размерностей используется [`DEFAULT_INPUT_SIZE`].
     pub fn load(model_bytes: &[u8]) -> anyhow::Result<Self> {
+        let (model, meta) = Self::read(model_bytes)?;
+        let input_size = Self::declared_input_size(&model).unwrap_or(DEFAULT_INPUT_SIZE);
+        Self::build(model, input_size, meta)
+    }
+
+    /// Load model from bytes with an explicit square input size (например, 320 или 1280)
+    pub fn load_with_size(model_bytes: &[u8], input_size: u32) -> anyhow::Result<Self> {
+        if input_size == 0 {
+            anyhow::bail!("input size must be positive");
+        }
+        let (model, meta) = Self::read(model_bytes)?;
+        Self::build(model, input_size, meta)
+    }
+
+    /// Загрузка с выбором бэкенда: на нативных сборках с фичей `onnxruntime`
+    /// инференс идёт через ONNX Runtime (CUDA/CoreML; недоступный провайдер
+    /// заменяется на CPU), иначе через tract. Активный бэкенд — [`Self::backend`].
+    pub fn load_with_backend(model_bytes: &[u8], backend: InferenceBackend) -> anyhow::Result<Self> {
+        let (model, meta) = Self::read(model_bytes)?;
+        let input_size = Self::declared_input_size(&model).unwrap_or(DEFAULT_INPUT_SIZE);
+        match backend {
+            InferenceBackend::Tract => Self::build(model, input_size, meta),
+            InferenceBackend::OnnxRuntime(provider) => {
+                Self::build_onnxruntime(model_bytes, model, input_size, meta, provider)
+            }
+        }
+    }
+
+    /// Бэкенд, которым фактически выполняется инференс
+    pub fn backend(&self) -> InferenceBackend {
+        match &self.engine {
+            Engine::Tract(_) => InferenceBackend::Tract,
+            #[cfg(all(feature = "onnxruntime", not(target_arch = "wasm32")))]
+            Engine::OnnxRuntime(_, provider) => InferenceBackend::OnnxRuntime(*provider),
+        }
+    }
+
+    /// Сторона квадратного входа модели
+    pub fn input_size(&self) -> u32 {
+        self.input_size
+    }
+
+    /// Выход модели в формате OBB (`cx, cy, w, h, классы..., angle`)
+    pub fn oriented(&self) -> bool {
+        self.oriented
+    }
+
+    /// Явное переключение формата выхода, если в метаданных модели нет `task`
+    pub fn set_oriented(&mut self, oriented: bool) {
+        self.oriented = oriented;
+    }
+
+    /// Имена классов по индексу (`DetectedQR::label`)
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// Явные имена классов, если в метаданных модели нет `names`.
+    /// Имена вроде `qr`, `datamatrix`, `aztec` выбирают ридер для детекции.
+    pub fn set_labels(&mut self, labels: Vec<String>) {
+        self.labels = labels;
+    }
+
+    /// Граф модели и метаданные экспорта Ultralytics (`task`, `names`)
+    fn read(model_bytes: &[u8]) -> anyhow::Result<(InferenceModel, ModelMeta)> {
         let mut cursor = std::io::Cursor::new(model_bytes);
-        let model = tract_onnx::onnx()
-            .model_for_read(&mut cursor)?
-            .with_input_fact(0, f32::fact([1, 3, 640, 640]).into())? // Force input shape
+        let onnx = tract_onnx::onnx();
+        let proto = onnx.proto_model_for_read(&mut cursor)?;
+        let mut meta = ModelMeta::default();
+        for prop in &proto.metadata_props {
+            match prop.key.as_str() {
+                "task" => meta.oriented = prop.value.trim() == "obb",
+                "names" => meta.labels = parse_names(&prop.value),
+                _ => {}
+            }
+        }
+        Ok((onnx.model_for_proto_model(&proto)?, meta))
+    }
+
+    /// Размер входа `[1, 3, N, N]`, объявленный в графе
+    fn declared_input_size(model: &InferenceModel) -> Option<u32> {
+        let shape = model.input_fact(0).ok()?.shape.as_concrete_finite().ok()??;
+        match shape.as_slice() {
+            &[_, 3, h, w] if h == w && h > 0 => u32::try_from(h).ok(),
+            _ => None,
+        }
+    }
+
+    fn build(model: InferenceModel, input_size: u32, meta: ModelMeta) -> anyhow::Result<Self> {
+        let side = input_size as usize;
+        let model = model
+            .with_input_fact(0, f32::fact([1, 3, side, side]).into())? // Force input shape
             .into_optimized()?
             .into_runnable()?;
 
-        Ok(Self { model })
+        Ok(Self::with_engine(Engine::Tract(Box::new(model)), input_size, meta))
+    }
+
+    #[cfg(all(feature = "onnxruntime", not(target_arch = "wasm32")))]
+    fn build_onnxruntime(
+        model_bytes: &[u8],
+        _model: InferenceModel,
+        input_size: u32,
+        meta: ModelMeta,
+        provider: ExecutionProvider,
+    ) -> anyhow::Result<Self> {
+        use ort::execution_providers::{CUDAExecutionProvider, CoreMLExecutionProvider};
+
+        // libonnxruntime грузится лениво, и при её отсутствии ort паникует
+        std::panic::catch_unwind(|| {
+            ort::api();
+        })
+        .map_err(|_| anyhow::anyhow!("ONNX Runtime library could not be loaded (set ORT_DYLIB_PATH)"))?;
+
+        let dispatch = match provider {
+            ExecutionProvider::Cpu => None,
+            ExecutionProvider::Cuda => Some(CUDAExecutionProvider::default().build()),
+            ExecutionProvider::CoreMl => Some(CoreMLExecutionProvider::default().build()),
+        };
+        let (builder, active) = match dispatch {
+            None => (ort::session::Session::builder()?, provider),
+            Some(dispatch) => {
+                match ort::session::Session::builder()?.with_execution_providers([dispatch.error_on_failure()]) {
+                    Ok(builder) => (builder, provider),
+                    Err(e) => {
+                        log::warn!("OnnxDetector: {:?} provider unavailable ({}), using CPU", provider, e);
+                        (ort::session::Session::builder()?, ExecutionProvider::Cpu)
+                    }
+                }
+            }
+        };
+        let session = builder.commit_from_memory(model_bytes)?;
+        log::info!("OnnxDetector: ONNX Runtime session with {:?} provider", active);
+
+        Ok(Self::with_engine(
+            Engine::OnnxRuntime(std::sync::Mutex::new(session), active),
+            input_size,
+            meta,
+        ))
+    }
+
+    #[cfg(not(all(feature = "onnxruntime", not(target_arch = "wasm32"))))]
+    fn build_onnxruntime(
+        _model_bytes: &[u8],
+        model: InferenceModel,
+        input_size: u32,
+        meta: ModelMeta,
+        provider: ExecutionProvider,
+    ) -> anyhow::Result<Self> {
+        log::warn!("OnnxDetector: ONNX Runtime ({:?}) is not available in this build, using tract", provider);
+        Self::build(model, input_size, meta)
+    }
+
+    fn with_engine(engine: Engine, input_size: u32, meta: ModelMeta) -> Self {
+        Self {
+            engine,
+            input_size,
+            conf_threshold: DEFAULT_CONF_THRESHOLD,
+            iou_threshold: DEFAULT_IOU_THRESHOLD,
+            oriented: meta.oriented,
+            labels: meta.labels,
+            processor: ImageProcessor::new(ProcessingConfig::default()),
+        }
+    }
+
+    /// Прогон входа `[1, 3, N, N]`, выход модели целиком
+    fn run(&self, input: Vec<f32>) -> anyhow::Result<tract_ndarray::ArrayD<f32>> {
+        let side = self.input_size as usize;
+        match &self.engine {
+            Engine::Tract(plan) => {
+                let input = tract_ndarray::Array4::from_shape_vec((1, 3, side, side), input)?;
+                let result = plan.run(tvec!(Tensor::from(input).into()))?;
+                Ok(result[0].to_array_view::<f32>()?.to_owned())
+            }
+            #[cfg(all(feature = "onnxruntime", not(target_arch = "wasm32")))]
+            Engine::OnnxRuntime(session, _) => {
+                let input = ort::value::Tensor::from_array(([1usize, 3, side, side], input))?;
+                let mut session = session.lock().unwrap_or_else(|e| e.into_inner());
+                let outputs = session.run(ort::inputs![input])?;
+                let (shape, values) = outputs[0].try_extract_tensor::<f32>()?;
+                let shape: Vec<usize> = shape.iter().map(|&d| d as usize).collect();
+                Ok(tract_ndarray::ArrayD::from_shape_vec(shape, values.to_vec())?)
+            }
+        }
+    }
+
+    /// Пороги уверенности и IoU для NMS. Низкий `conf` повышает полноту
+    /// (поиск платёжных QR), низкий `iou` сильнее подавляет пересекающиеся боксы.
+    pub fn set_thresholds(&mut self, conf: f32, iou: f32) -> anyhow::Result<()> {
+        if !(0.0..=1.0).contains(&conf) || !(0.0..=1.0).contains(&iou) {
+            anyhow::bail!("thresholds must be within 0..1 (conf={}, iou={})", conf, iou);
+        }
+        self.conf_threshold = conf;
+        self.iou_threshold = iou;
+        Ok(())
+    }
+
+    /// Текущие пороги `(conf, iou)`
+    pub fn thresholds(&self) -> (f32, f32) {
+        (self.conf_threshold, self.iou_threshold)
+    }
+
+    /// Конфигурация предобработки для уточнения углов в кропах.
+    /// `QRScanner` при установке детектора передаёт сюда свою конфигурацию.
+    pub fn set_processing_config(&mut self, config: ProcessingConfig) {
+        self.processor = ImageProcessor::new(config);
+    }
+
+    /// Текущая конфигурация уточнения углов
+    pub fn processing_config(&self) -> &ProcessingConfig {
+        self.processor.config()
     }
 
     /// Detect QR codes in image
     pub fn detect(&self, img: &GrayImage) -> anyhow::Result<Vec<DetectedQR>> {
+        let detections = self.infer(img)?;
+        log::info!("OnnxDetector: Raw detections > {}: {}", self.conf_threshold, detections.len());
+
+        // NMS
+        let kept_boxes = nms(&detections, self.iou_threshold);
+        log::info!("OnnxDetector: After NMS: {}", kept_boxes.len());
+
+        Ok(self.extract(img, &kept_boxes))
+    }
+
+    /// Детекция по перекрывающимся тайлам `tile x tile` без уменьшения всего кадра:
+    /// мелкие удалённые коды не теряются при сжатии 4000px фото до входа модели.
+    /// Боксы тайлов переводятся в координаты кадра и объединяются общим NMS.
+    pub fn detect_tiled(&self, img: &GrayImage, tile: u32, overlap: u32) -> anyhow::Result<Vec<DetectedQR>> {
+        if tile == 0 || overlap >= tile {
+            anyhow::bail!("invalid tiling: tile={}, overlap={}", tile, overlap);
+        }
+        let (width, height) = img.dimensions();
+        if width <= tile && height <= tile {
+            return self.detect(img);
+        }
+
+        let stride = tile - overlap;
+        let mut detections = Vec::new();
+        for y in tile_starts(height, tile, stride) {
+            for x in tile_starts(width, tile, stride) {
+                let view = image::imageops::crop_imm(img, x, y, tile.min(width), tile.min(height)).to_image();
+                let boxes = self.infer(&view)?;
+                detections.extend(boxes.iter().map(|b| b.translate(x as f32, y as f32)));
+            }
+        }
+        log::info!("OnnxDetector: Raw tiled detections > {}: {}", self.conf_threshold, detections.len());
+
+        // Дубликаты на стыках тайлов подавляются тем же NMS
+        let kept_boxes = nms(&detections, self.iou_threshold);
+        log::info!("OnnxDetector: After global NMS: {}", kept_boxes.len());
+
+        Ok(self.extract(img, &kept_boxes))
+    }
+
+    /// Прогон модели: боксы выше порога уверенности в координатах `img` (до NMS)
+    fn infer(&self, img: &GrayImage) -> anyhow::Result<Vec<BBox>> {
         let (orig_w, orig_h) = img.dimensions();
-        const MODEL_SIZE: u32 = 640;
+        let model_size = self.input_size;
 
-        // 1. Preprocessing: Resize to 640x640 (Stretch for speed/simplicity)
+        // 1. Preprocessing: letterbox to model_size x model_size (aspect ratio preserved)
         // Convert Gray to RGB by triplicating channels (YOLO expects 3 channels)
-        let resized = image::imageops::resize(img, MODEL_SIZE, MODEL_SIZE, FilterType::Triangle);
+        let letterbox = Letterbox::new(orig_w, orig_h, model_size);
+        let resized = letterbox.apply(img);
         
-        let mut tensor_data = Vec::with_capacity((MODEL_SIZE * MODEL_SIZE * 3) as usize);
+        let mut tensor_data = Vec::with_capacity((model_size * model_size * 3) as usize);
         
-        // NCHW layout: (1, 3, 640, 640) -> Planar (RRR...GGG...BBB...)
+        // NCHW layout: (1, 3, N, N) -> Planar (RRR...GGG...BBB...)
         // Tract expects standard layout (check if RGB or BGR? usually RGB for ONNX from PyTorch)
-        // We will fill 3 planes.
-        
-        let mut plane_r = Vec::with_capacity((MODEL_SIZE * MODEL_SIZE) as usize);
-        let mut plane_g = Vec::with_capacity((MODEL_SIZE * MODEL_SIZE) as usize);
-        let mut plane_b = Vec::with_capacity((MODEL_SIZE * MODEL_SIZE) as usize);
-
-        for y in 0..MODEL_SIZE {
-            for x in 0..MODEL_SIZE {
-                let pixel = resized.get_pixel(x, y)[0] as f32 / 255.0;
-                plane_r.push(pixel);
-                plane_g.push(pixel);
-                plane_b.push(pixel);
-            }
+        // Все три плоскости одинаковы: копируем одну яркостную плоскость трижды.
+        let plane: Vec<f32> = resized.pixels().map(|p| p[0] as f32 / 255.0).collect();
+        for _ in 0..3 {
+            tensor_data.extend_from_slice(&plane);
         }
-        
-        tensor_data.extend_from_slice(&plane_r);
-        tensor_data.extend_from_slice(&plane_g);
-        tensor_data.extend_from_slice(&plane_b);
-
-        let input_tensor = tract_ndarray::Array4::from_shape_vec(
-            (1, 3, MODEL_SIZE as usize, MODEL_SIZE as usize),
-            tensor_data,
-        )?;
 
         // 2. Inference
         log::info!("OnnxDetector: Run model...");
-        let tensor = Tensor::from(input_tensor);
-        let result = self.model.run(tvec!(tensor.into()))?;
+        let output = self.run(tensor_data)?;
         
         // 3. Postprocessing
-        let output = result[0].to_array_view::<f32>()?;
-        let shape = output.shape(); // [1, nc+4, 8400]
-        log::info!("OnnxDetector: Output shape: {:?}", shape);
-        
-        if shape.len() != 3 {
-             return Ok(vec![]);
-        }
-        
-        let num_classes = shape[1] - 4;
-        let num_anchors = shape[2];
-        
-        let mut detections = Vec::new();
-        let conf_threshold = 0.5;
+        let output = output.view();
+        log::info!("OnnxDetector: Output shape: {:?}", output.shape()); // [1, nc+4, 8400]
 
-        // Iterate over anchors
-        for i in 0..num_anchors {
-            // Find max class score
-            let mut max_score = 0.0;
-            let mut best_class = 0;
-            
-            for c in 0..num_classes {
-                let score = output[[0, 4 + c, i]];
-                if score > max_score {
-                    max_score = score;
-                    best_class = c;
-                }
-            }
-
-            if max_score > conf_threshold {
-                let cx = output[[0, 0, i]];
-                let cy = output[[0, 1, i]];
-                let w = output[[0, 2, i]];
-                let h = output[[0, 3, i]];
-                
-                let x1 = cx - w / 2.0;
-                let y1 = cy - h / 2.0;
-                let x2 = cx + w / 2.0;
-                let y2 = cy + h / 2.0;
-                
-                detections.push(BBox { x1, y1, x2, y2, score: max_score, class: best_class });
-            }
-        }
-        
-        log::info!("OnnxDetector: Raw detections > {}: {}", conf_threshold, detections.len());
+        let detections = parse_output(&output, self.conf_threshold, self.oriented);
+        Ok(detections.iter().map(|b| letterbox.unmap_box(b)).collect())
+    }
 
-        // NMS
-        let kept_boxes = nms(&detections, 0.45);
-        log::info!("OnnxDetector: After NMS: {}", kept_boxes.len());
-        
-        // Map back to original image
+    /// Кропы (или выпрямленные OBB) для боксов в координатах `img`
+    fn extract(&self, img: &GrayImage, boxes: &[BBox]) -> Vec<DetectedQR> {
+        let (orig_w, orig_h) = img.dimensions();
         let mut qr_results = Vec::new();
-        let scale_x = orig_w as f32 / MODEL_SIZE as f32;
-        let scale_y = orig_h as f32 / MODEL_SIZE as f32;
 
-        for (i, bbox) in kept_boxes.iter().enumerate() {
+        for (i, bbox) in boxes.iter().enumerate() {
             log::info!("Processing Box #{}: Score={:.2}", i, bbox.score);
             
-            let x = (bbox.x1 * scale_x).max(0.0) as u32;
-            let y = (bbox.y1 * scale_y).max(0.0) as u32;
-            let width = ((bbox.x2 - bbox.x1) * scale_x).max(1.0) as u32;
-            let height = ((bbox.y2 - bbox.y1) * scale_y).max(1.0) as u32;
-            
-            if x + width > orig_w || y + height > orig_h {
-                log::warn!("Box out of bounds: x={}, y={}, w={}, h={}, orig_w={}, orig_h={}", x, y, width, height, orig_w, orig_h);
+            let Some([x, y, width, height]) = frame_box(bbox, orig_w, orig_h) else {
+                log::warn!("Box outside the image: {:?}, orig_w={}, orig_h={}", bbox, orig_w, orig_h);
                 continue;
+            };
+
+            // OBB: углы повёрнутого прямоугольника известны, выпрямляем напрямую из кадра
+            if let Some(rotated) = &bbox.rotated {
+                let corners = rotated.corners();
+                if let Some((warped, homography)) = warp_oriented(img, corners) {
+                    qr_results.push(DetectedQR {
+                        bbox: [x, y, width, height],
+                        corners: corners.map(|(cx, cy)| {
+                            (cx.clamp(0.0, orig_w as f32) as u32, cy.clamp(0.0, orig_h as f32) as u32)
+                        }),
+                        image: warped,
+                        confidence: bbox.score,
+                        homography: Some(homography),
+                        class: Some(bbox.class as u32),
+                        label: self.labels.get(bbox.class).cloned(),
+                        tilt: None,
+                    });
+                    continue;
+                }
+                log::warn!("Degenerate oriented box #{}, falling back to bbox crop", i);
             }
 
             // Crop image
             let mut crop = image::imageops::crop_imm(img, x, y, width, height).to_image();
+            let mut homography = None;
             let mut corners_abs = [
                 (x, y), 
                 (x + width, y), 
@@ -142,9 +394,7 @@ impl OnnxDetector {
                 (x, y + height)
             ];
 
-            let processor = ImageProcessor::new(ProcessingConfig::default());
-            
-            if let Some(corners) = processor.find_corners(&crop) {
+            if let Some(corners) = self.processor.find_corners(&crop) {
                  log::info!("Corners found for Box #{}. Refine & Warp...", i);
                  let side_len = width.max(height); 
                  
@@ -155,10 +405,23 @@ impl OnnxDetector {
                      nalgebra::Point2::new(0.0, side_len as f32),
                  ];
                  
-                 if let Some(h) = geometry::find_homography(corners, dst) {
-                     log::info!("Homography calculated. Warping...");
-                     let warped = geometry::warp_perspective(&crop, &h, side_len, side_len);
+                 // Белая заливка за краем кропа сохраняет quiet zone
+                 let warp = geometry::find_homography(corners, dst).and_then(|h| {
+                     let warped = geometry::warp_perspective(
+                         &crop,
+                         &h,
+                         side_len,
+                         side_len,
+                         geometry::Interpolation::Bilinear,
+                         geometry::DEFAULT_FILL,
+                     )?;
+                     Some((h, warped))
+                 });
+                 if let Some((h, warped)) = warp {
+                     log::info!("Homography calculated, crop warped");
                      crop = warped;
+                     let offset = nalgebra::Matrix3::new_translation(&nalgebra::Vector2::new(x as f32, y as f32));
+                     homography = h.try_inverse().map(|inv| offset * inv);
                      
                      // Update corners display (approximate)
                      let offset_x = x as f32;
@@ -170,7 +433,7 @@ impl OnnxDetector {
                          ((corners[3].x + offset_x) as u32, (corners[3].y + offset_y) as u32),
                      ];
                  } else {
-                     log::warn!("Homography calculation failed");
+                     log::warn!("Degenerate corners for Box #{}, using bbox crop", i);
                  }
             } else {
                 log::info!("No precise corners found in crop. Using full bbox crop.");
@@ -181,13 +444,190 @@ impl OnnxDetector {
                 corners: corners_abs,
                 image: crop,
                 confidence: bbox.score,
+                homography,
+                class: Some(bbox.class as u32),
+                label: self.labels.get(bbox.class).cloned(),
+                tilt: None,
             });
         }
 
-        Ok(qr_results)
+        qr_results
+    }
+}
+
+/// Метаданные модели, влияющие на разбор выхода
+#[derive(Default)]
+struct ModelMeta {
+    oriented: bool,
+    labels: Vec<String>,
+}
+
+/// Разбор `names` из метаданных Ultralytics: `{0: 'qr', 1: 'datamatrix'}`
+fn parse_names(value: &str) -> Vec<String> {
+    let mut names: Vec<(usize, String)> = value
+        .trim()
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .split(',')
+        .filter_map(|entry| {
+            let (index, name) = entry.split_once(':')?;
+            let name = name.trim().trim_matches(|c| c == '\'' || c == '"');
+            Some((index.trim().parse().ok()?, name.to_string()))
+        })
+        .collect();
+    names.sort_by_key(|(index, _)| *index);
+    // Пропуски в нумерации заполняются пустыми именами, чтобы индекс совпадал с классом
+    let mut labels = Vec::new();
+    for (index, name) in names {
+        if index >= labels.len() {
+            labels.resize(index, String::new());
+            labels.push(name);
+        }
+    }
+    labels
+}
+
+/// Параметры letterbox: масштаб с сохранением пропорций и серые поля до квадрата
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Letterbox {
+    size: u32,
+    scale: f32,
+    new_w: u32,
+    new_h: u32,
+    pad_x: u32,
+    pad_y: u32,
+}
+
+impl Letterbox {
+    /// Заливка полей (как в Ultralytics)
+    const FILL: u8 = 114;
+
+    fn new(orig_w: u32, orig_h: u32, size: u32) -> Self {
+        let scale = (size as f32 / orig_w.max(1) as f32).min(size as f32 / orig_h.max(1) as f32);
+        let new_w = ((orig_w as f32 * scale).round() as u32).clamp(1, size);
+        let new_h = ((orig_h as f32 * scale).round() as u32).clamp(1, size);
+        Self {
+            size,
+            scale,
+            new_w,
+            new_h,
+            pad_x: (size - new_w) / 2,
+            pad_y: (size - new_h) / 2,
+        }
+    }
+
+    /// Квадратное изображение `size x size` с вписанным по центру кадром
+    fn apply(&self, img: &GrayImage) -> GrayImage {
+        let resized = image::imageops::resize(img, self.new_w, self.new_h, FilterType::Triangle);
+        let mut canvas = GrayImage::from_pixel(self.size, self.size, image::Luma([Self::FILL]));
+        image::imageops::replace(&mut canvas, &resized, self.pad_x as i64, self.pad_y as i64);
+        canvas
+    }
+
+    /// Точка пространства модели → исходное изображение
+    fn unmap(&self, x: f32, y: f32) -> (f32, f32) {
+        ((x - self.pad_x as f32) / self.scale, (y - self.pad_y as f32) / self.scale)
     }
+
+    /// Бокс модели → бокс в координатах исходного изображения
+    fn unmap_box(&self, bbox: &BBox) -> BBox {
+        let (x1, y1) = self.unmap(bbox.x1, bbox.y1);
+        let (x2, y2) = self.unmap(bbox.x2, bbox.y2);
+        let rotated = bbox.rotated.map(|r| {
+            let (cx, cy) = self.unmap(r.cx, r.cy);
+            RotatedBox { cx, cy, w: r.w / self.scale, h: r.h / self.scale, angle: r.angle }
+        });
+        BBox { x1, y1, x2, y2, rotated, ..*bbox }
+    }
+}
+
+/// Начала тайлов вдоль стороны `len` с шагом `stride`; последний тайл прижат к краю
+fn tile_starts(len: u32, tile: u32, stride: u32) -> Vec<u32> {
+    if len <= tile {
+        return vec![0];
+    }
+    let mut starts: Vec<u32> = (0..len - tile).step_by(stride as usize).collect();
+    starts.push(len - tile);
+    starts
+}
+
+/// Бокс → `[x, y, w, h]` в пикселях (обрезается по границам кадра, вырожденный — `None`)
+fn frame_box(bbox: &BBox, orig_w: u32, orig_h: u32) -> Option<[u32; 4]> {
+    let x1 = bbox.x1.clamp(0.0, orig_w as f32);
+    let y1 = bbox.y1.clamp(0.0, orig_h as f32);
+    let x2 = bbox.x2.clamp(0.0, orig_w as f32);
+    let y2 = bbox.y2.clamp(0.0, orig_h as f32);
+    if x2 - x1 < 1.0 || y2 - y1 < 1.0 {
+        return None;
+    }
+    let (x, y) = (x1 as u32, y1 as u32);
+    let width = ((x2 - x1).round() as u32).clamp(1, orig_w - x);
+    let height = ((y2 - y1).round() as u32).clamp(1, orig_h - y);
+    Some([x, y, width, height])
 }
 
+/// Выпрямление повёрнутого прямоугольника (углы в координатах кадра) в квадрат.
+/// Возвращает кроп и гомографию из его координат в координаты кадра.
+/// Сторона кропа не больше большей стороны кадра; вырожденные прямоугольники — `None`.
+fn warp_oriented(img: &GrayImage, corners: [(f32, f32); 4]) -> Option<(GrayImage, nalgebra::Matrix3<f32>)> {
+    let dist = |a: (f32, f32), b: (f32, f32)| (a.0 - b.0).hypot(a.1 - b.1);
+    let (width, height) = (dist(corners[0], corners[1]), dist(corners[1], corners[2]));
+    if !width.is_finite() || !height.is_finite() || width.min(height) < 1.0 {
+        return None;
+    }
+    // Ошибочный прогноз модели не должен запрашивать буфер больше кадра
+    let side = width.max(height).min(img.width().max(img.height()) as f32).round();
+    if side < 1.0 {
+        return None;
+    }
+
+    let src = corners.map(|(x, y)| nalgebra::Point2::new(x, y));
+    let dst = [
+        nalgebra::Point2::new(0.0, 0.0),
+        nalgebra::Point2::new(side, 0.0),
+        nalgebra::Point2::new(side, side),
+        nalgebra::Point2::new(0.0, side),
+    ];
+    let h = geometry::find_homography(src, dst)?;
+    let warped = geometry::warp_perspective(
+        img,
+        &h,
+        side as u32,
+        side as u32,
+        geometry::Interpolation::Bilinear,
+        geometry::DEFAULT_FILL,
+    )?;
+    Some((warped, h.try_inverse()?))
+}
+
+/// Повёрнутый прямоугольник YOLOv8-OBB: центр, размеры и угол (радианы, по часовой в координатах изображения)
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RotatedBox {
+    cx: f32,
+    cy: f32,
+    w: f32,
+    h: f32,
+    angle: f32,
+}
+
+impl RotatedBox {
+    /// Углы [top-left, top-right, bottom-right, bottom-left] (для угла 0)
+    fn corners(&self) -> [(f32, f32); 4] {
+        let (sin, cos) = self.angle.sin_cos();
+        // Полуоси вдоль ширины и высоты
+        let (ax, ay) = (self.w / 2.0 * cos, self.w / 2.0 * sin);
+        let (bx, by) = (-self.h / 2.0 * sin, self.h / 2.0 * cos);
+        [
+            (self.cx - ax - bx, self.cy - ay - by),
+            (self.cx + ax - bx, self.cy + ay - by),
+            (self.cx + ax + bx, self.cy + ay + by),
+            (self.cx - ax + bx, self.cy - ay + by),
+        ]
+    }
+}
+
+/// Бокс в координатах модели (или кадра после `Letterbox::unmap_box`);
+/// для OBB `x1..y2` — описывающий прямоугольник
 #[derive(Clone, Copy, Debug)]
 struct BBox {
     x1: f32,
@@ -196,6 +636,83 @@ struct BBox {
     y2: f32,
     score: f32,
     class: usize,
+    rotated: Option<RotatedBox>,
+}
+
+impl BBox {
+    /// Сдвиг (из координат тайла в координаты кадра)
+    fn translate(&self, dx: f32, dy: f32) -> BBox {
+        BBox {
+            x1: self.x1 + dx,
+            y1: self.y1 + dy,
+            x2: self.x2 + dx,
+            y2: self.y2 + dy,
+            rotated: self.rotated.map(|r| RotatedBox { cx: r.cx + dx, cy: r.cy + dy, ..r }),
+            ..*self
+        }
+    }
+}
+
+/// Разбор выхода YOLOv8 `[1, 4 + nc, N]` (или OBB `[1, 4 + nc + 1, N]` с углом
+/// в последнем канале): боксы с лучшим классом выше `conf_threshold`
+fn parse_output(output: &tract_ndarray::ArrayViewD<f32>, conf_threshold: f32, oriented: bool) -> Vec<BBox> {
+    let shape = output.shape();
+    let extra = if oriented { 5 } else { 4 };
+    if shape.len() != 3 || shape[1] <= extra {
+        return vec![];
+    }
+
+    let num_classes = shape[1] - extra;
+    let num_anchors = shape[2];
+
+    let mut detections = Vec::new();
+
+    // Iterate over anchors
+    for i in 0..num_anchors {
+        // Find max class score
+        let mut max_score = 0.0;
+        let mut best_class = 0;
+
+        for c in 0..num_classes {
+            let score = output[[0, 4 + c, i]];
+            if score > max_score {
+                max_score = score;
+                best_class = c;
+            }
+        }
+
+        if max_score > conf_threshold {
+            let cx = output[[0, 0, i]];
+            let cy = output[[0, 1, i]];
+            let w = output[[0, 2, i]];
+            let h = output[[0, 3, i]];
+
+            if oriented {
+                let angle = output[[0, shape[1] - 1, i]];
+                let rotated = RotatedBox { cx, cy, w, h, angle };
+                let corners = rotated.corners();
+                let (xs, ys) = (corners.map(|c| c.0), corners.map(|c| c.1));
+                detections.push(BBox {
+                    x1: xs.iter().copied().fold(f32::INFINITY, f32::min),
+                    y1: ys.iter().copied().fold(f32::INFINITY, f32::min),
+                    x2: xs.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+                    y2: ys.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+                    score: max_score,
+                    class: best_class,
+                    rotated: Some(rotated),
+                });
+                continue;
+            }
+
+            let x1 = cx - w / 2.0;
+            let y1 = cy - h / 2.0;
+            let x2 = cx + w / 2.0;
+            let y2 = cy + h / 2.0;
+
+            detections.push(BBox { x1, y1, x2, y2, score: max_score, class: best_class, rotated: None });
+        }
+    }
+    detections
 }
 
 fn nms(boxes: &[BBox], iou_threshold: f32) -> Vec<BBox> {
@@ -238,3 +755,442 @@ fn iou(a: &BBox, b: &BBox) -> f32 {
     
     inter / (area_a + area_b - inter + 1e-6)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+    use tract_onnx::pb;
+
+    fn value_info(name: &str, dims: &[Option<i64>]) -> pb::ValueInfoProto {
+        use pb::tensor_shape_proto::{dimension::Value, Dimension};
+        let dim = dims
+            .iter()
+            .map(|d| Dimension {
+                value: Some(match d {
+                    Some(v) => Value::DimValue(*v),
+                    None => Value::DimParam("size".into()),
+                }),
+                ..Default::default()
+            })
+            .collect();
+        pb::ValueInfoProto {
+            name: name.into(),
+            r#type: Some(pb::TypeProto {
+                value: Some(pb::type_proto::Value::TensorType(pb::type_proto::Tensor {
+                    elem_type: pb::tensor_proto::DataType::Float as i32,
+                    shape: Some(pb::TensorShapeProto { dim }),
+                })),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn node(op_type: &str, input: &[&str], output: &str, attribute: Vec<pb::AttributeProto>) -> pb::NodeProto {
+        pb::NodeProto {
+            op_type: op_type.into(),
+            input: input.iter().map(|s| s.to_string()).collect(),
+            output: vec![output.into()],
+            attribute,
+            ..Default::default()
+        }
+    }
+
+    fn ints(name: &str, values: &[i64]) -> pb::AttributeProto {
+        pb::AttributeProto {
+            name: name.into(),
+            r#type: pb::attribute_proto::AttributeType::Ints as i32,
+            ints: values.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn int(name: &str, value: i64) -> pb::AttributeProto {
+        pb::AttributeProto {
+            name: name.into(),
+            r#type: pb::attribute_proto::AttributeType::Int as i32,
+            i: value,
+            ..Default::default()
+        }
+    }
+
+    /// Заглушка YOLO-подобной модели: усреднение блоков 32x32 → выход `[1, 6, N]`
+    /// (4 координаты и 2 класса). `size = None` — динамический размер входа.
+    fn stub_model(size: Option<i64>) -> Vec<u8> {
+        let graph = pb::GraphProto {
+            name: "stub".into(),
+            node: vec![
+                node("AveragePool", &["images"], "pooled", vec![ints("kernel_shape", &[32, 32]), ints("strides", &[32, 32])]),
+                node("Reshape", &["pooled", "shape"], "flat", vec![]),
+                node("Concat", &["flat", "flat"], "output0", vec![int("axis", 1)]),
+            ],
+            initializer: vec![pb::TensorProto {
+                name: "shape".into(),
+                dims: vec![3],
+                data_type: pb::tensor_proto::DataType::Int64 as i32,
+                int64_data: vec![1, 3, -1],
+                ..Default::default()
+            }],
+            input: vec![value_info("images", &[Some(1), Some(3), size, size])],
+            output: vec![value_info("output0", &[Some(1), Some(6), None])],
+            ..Default::default()
+        };
+        pb::ModelProto {
+            ir_version: 8,
+            opset_import: vec![pb::OperatorSetIdProto {
+                domain: String::new(),
+                version: 13,
+            }],
+            graph: Some(graph),
+            ..Default::default()
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn test_input_size_from_graph_and_explicit() {
+        let img = GrayImage::from_pixel(200, 120, image::Luma([255]));
+
+        let detector = OnnxDetector::load(&stub_model(Some(320))).unwrap();
+        assert_eq!(detector.input_size(), 320);
+        assert!(detector.detect(&img).is_ok());
+
+        let dynamic = stub_model(None);
+        assert_eq!(OnnxDetector::load(&dynamic).unwrap().input_size(), DEFAULT_INPUT_SIZE);
+        let detector = OnnxDetector::load_with_size(&dynamic, 320).unwrap();
+        assert_eq!(detector.input_size(), 320);
+        assert!(detector.detect(&img).is_ok());
+
+        assert!(OnnxDetector::load_with_size(&dynamic, 0).is_err());
+    }
+
+    /// Синтетический выход YOLOv8 `[1, 4 + nc, N]` из `(cx, cy, w, h, score...)`
+    fn yolo_output<const C: usize>(anchors: &[[f32; C]]) -> tract_ndarray::ArrayD<f32> {
+        let mut output = tract_ndarray::ArrayD::zeros(vec![1, C, anchors.len()]);
+        for (i, anchor) in anchors.iter().enumerate() {
+            for (c, value) in anchor.iter().enumerate() {
+                output[[0, c, i]] = *value;
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn test_backend_selection() {
+        let model = stub_model(Some(320));
+        let detector = OnnxDetector::load_with_backend(&model, InferenceBackend::Tract).unwrap();
+        assert_eq!(detector.backend(), InferenceBackend::Tract);
+        assert_eq!(OnnxDetector::load(&model).unwrap().backend(), InferenceBackend::Tract);
+
+        // Без ONNX Runtime в сборке загрузка переходит на tract, `detect` работает как прежде
+        #[cfg(not(feature = "onnxruntime"))]
+        {
+            let requested = InferenceBackend::OnnxRuntime(ExecutionProvider::Cuda);
+            let detector = OnnxDetector::load_with_backend(&model, requested).unwrap();
+            assert_eq!(detector.backend(), InferenceBackend::Tract);
+            assert!(detector.detect(&GrayImage::from_pixel(64, 64, image::Luma([255]))).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let output = yolo_output(&[
+            [100.0, 100.0, 50.0, 50.0, 0.9],
+            [104.0, 102.0, 50.0, 50.0, 0.6],
+            [300.0, 300.0, 40.0, 40.0, 0.3],
+            [500.0, 120.0, 40.0, 40.0, 0.15],
+        ]);
+        let view = output.view();
+        assert_eq!(parse_output(&view, DEFAULT_CONF_THRESHOLD, false).len(), 2);
+        let low = parse_output(&view, 0.1, false);
+        assert_eq!(low.len(), 4);
+
+        // Первые два бокса пересекаются с IoU ≈ 0.8
+        assert_eq!(nms(&low, DEFAULT_IOU_THRESHOLD).len(), 3);
+        assert_eq!(nms(&low, 0.9).len(), 4);
+
+        let mut detector = OnnxDetector::load(&stub_model(Some(320))).unwrap();
+        assert_eq!(detector.thresholds(), (DEFAULT_CONF_THRESHOLD, DEFAULT_IOU_THRESHOLD));
+        detector.set_thresholds(0.25, 0.6).unwrap();
+        assert_eq!(detector.thresholds(), (0.25, 0.6));
+        assert!(detector.set_thresholds(1.5, 0.5).is_err());
+        assert!(detector.set_thresholds(f32::NAN, 0.5).is_err());
+    }
+
+    #[test]
+    fn test_letterbox_mapping() {
+        // 1280x720 → 640x360 по центру квадрата 640, поля по 140px сверху и снизу
+        let letterbox = Letterbox::new(1280, 720, 640);
+        assert_eq!((letterbox.new_w, letterbox.new_h), (640, 360));
+        assert_eq!((letterbox.pad_x, letterbox.pad_y), (0, 140));
+
+        let img = GrayImage::from_pixel(1280, 720, image::Luma([0]));
+        let input = letterbox.apply(&img);
+        assert_eq!(input.dimensions(), (640, 640));
+        assert_eq!(input.get_pixel(320, 100).0[0], Letterbox::FILL);
+        assert_eq!(input.get_pixel(320, 320).0[0], 0);
+        assert_eq!(input.get_pixel(320, 530).0[0], Letterbox::FILL);
+
+        // Бокс 100x100 в центре входа модели — квадрат 200x200 в центре кадра
+        let centered = BBox { x1: 270.0, y1: 270.0, x2: 370.0, y2: 370.0, score: 0.9, class: 0, rotated: None };
+        assert_eq!(frame_box(&letterbox.unmap_box(&centered), 1280, 720), Some([540, 260, 200, 200]));
+
+        // Бокс, заходящий на поле, обрезается по кадру; целиком на поле — отбрасывается
+        let edge = BBox { x1: 600.0, y1: 100.0, x2: 660.0, y2: 160.0, score: 0.9, class: 0, rotated: None };
+        assert_eq!(frame_box(&letterbox.unmap_box(&edge), 1280, 720), Some([1200, 0, 80, 40]));
+        let padding = BBox { x1: 10.0, y1: 10.0, x2: 50.0, y2: 120.0, score: 0.9, class: 0, rotated: None };
+        assert_eq!(frame_box(&letterbox.unmap_box(&padding), 1280, 720), None);
+    }
+
+    #[test]
+    fn test_oriented_box_corners() {
+        // Квадрат 100x100, повёрнутый на 45°: углы на осях, проходящих через центр
+        let half_diag = 50.0 * std::f32::consts::SQRT_2;
+        let output = {
+            let mut output = tract_ndarray::ArrayD::zeros(vec![1, 6, 2]);
+            for (c, value) in [200.0, 200.0, 100.0, 100.0, 0.9, std::f32::consts::FRAC_PI_4].iter().enumerate() {
+                output[[0, c, 0]] = *value;
+            }
+            for (c, value) in [400.0, 300.0, 80.0, 40.0, 0.8, 0.0].iter().enumerate() {
+                output[[0, c, 1]] = *value;
+            }
+            output
+        };
+        let boxes = parse_output(&output.view(), DEFAULT_CONF_THRESHOLD, true);
+        assert_eq!(boxes.len(), 2);
+
+        let rotated = boxes[0].rotated.expect("oriented box");
+        let expected = [
+            (200.0, 200.0 - half_diag),
+            (200.0 + half_diag, 200.0),
+            (200.0, 200.0 + half_diag),
+            (200.0 - half_diag, 200.0),
+        ];
+        for (corner, want) in rotated.corners().iter().zip(expected) {
+            assert!((corner.0 - want.0).abs() < 1e-3 && (corner.1 - want.1).abs() < 1e-3, "{:?} vs {:?}", corner, want);
+        }
+        // Описывающий прямоугольник для NMS и bbox
+        assert!((boxes[0].x1 - (200.0 - half_diag)).abs() < 1e-3);
+        assert!((boxes[0].y2 - (200.0 + half_diag)).abs() < 1e-3);
+
+        // Нулевой угол — обычный прямоугольник
+        let corners = boxes[1].rotated.unwrap().corners();
+        assert_eq!(corners, [(360.0, 280.0), (440.0, 280.0), (440.0, 320.0), (360.0, 320.0)]);
+        assert_eq!((boxes[1].x1, boxes[1].y1, boxes[1].x2, boxes[1].y2), (360.0, 280.0, 440.0, 320.0));
+
+        // Без OBB-режима угол считается классом
+        let plain = parse_output(&output.view(), DEFAULT_CONF_THRESHOLD, false);
+        assert!(plain.iter().all(|b| b.rotated.is_none()));
+
+        // Выпрямление: повёрнутый тёмный квадрат в кадре даёт однородный кроп
+        let mut img = GrayImage::from_pixel(400, 400, image::Luma([255]));
+        for (x, y, px) in img.enumerate_pixels_mut() {
+            let (dx, dy) = (x as f32 + 0.5 - 200.0, y as f32 + 0.5 - 200.0);
+            if dx.abs() + dy.abs() < half_diag {
+                *px = image::Luma([0]);
+            }
+        }
+        let (warped, homography) = warp_oriented(&img, rotated.corners()).unwrap();
+        assert_eq!(warped.dimensions(), (100, 100));
+        assert!(warped.get_pixel(50, 50).0[0] < 10);
+        assert!(warped.get_pixel(10, 90).0[0] < 10);
+        let top_left = homography * nalgebra::Vector3::new(0.0, 0.0, 1.0);
+        assert!((top_left.x / top_left.z - 200.0).abs() < 0.5);
+        assert!((top_left.y / top_left.z - expected[0].1).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_warp_oriented_clamps_side() {
+        let img = GrayImage::from_pixel(400, 300, image::Luma([255]));
+        let obb = |w: f32, h: f32| RotatedBox { cx: 200.0, cy: 150.0, w, h, angle: 0.3 }.corners();
+
+        let (warped, _) = warp_oriented(&img, obb(1e7, 1e7)).unwrap();
+        assert_eq!(warped.dimensions(), (400, 400));
+        let (warped, _) = warp_oriented(&img, obb(80.0, 60.0)).unwrap();
+        assert_eq!(warped.dimensions(), (80, 80));
+
+        for (w, h) in [(0.0, 50.0), (50.0, 0.5), (f32::NAN, 50.0), (f32::INFINITY, 50.0)] {
+            assert!(warp_oriented(&img, obb(w, h)).is_none(), "{}x{}", w, h);
+        }
+        assert!(warp_oriented(&GrayImage::new(0, 0), obb(50.0, 50.0)).is_none());
+    }
+
+    #[test]
+    fn test_oriented_flag_from_metadata() {
+        assert!(!OnnxDetector::load(&stub_model(Some(320))).unwrap().oriented());
+
+        let mut proto = pb::ModelProto::decode(stub_model(Some(320)).as_slice()).unwrap();
+        proto.metadata_props.push(pb::StringStringEntryProto {
+            key: "task".into(),
+            value: "obb".into(),
+        });
+        let mut detector = OnnxDetector::load(&proto.encode_to_vec()).unwrap();
+        assert!(detector.oriented());
+        detector.set_oriented(false);
+        assert!(!detector.oriented());
+    }
+
+    #[test]
+    fn test_class_labels() {
+        // Три класса: QR, DataMatrix, Aztec
+        let output = yolo_output(&[
+            [100.0, 100.0, 50.0, 50.0, 0.9, 0.1, 0.0],
+            [300.0, 100.0, 50.0, 50.0, 0.2, 0.8, 0.1],
+            [500.0, 100.0, 50.0, 50.0, 0.0, 0.3, 0.7],
+            [500.0, 400.0, 50.0, 50.0, 0.2, 0.3, 0.1],
+        ]);
+        let boxes = parse_output(&output.view(), DEFAULT_CONF_THRESHOLD, false);
+        let classes: Vec<usize> = boxes.iter().map(|b| b.class).collect();
+        assert_eq!(classes, vec![0, 1, 2]);
+        assert!((boxes[1].score - 0.8).abs() < 1e-6);
+
+        assert_eq!(parse_names("{0: 'qr', 1: 'data_matrix', 2: \"Aztec\"}"), vec!["qr", "data_matrix", "Aztec"]);
+        assert_eq!(parse_names("{1: 'aztec', 0: 'qr'}"), vec!["qr", "aztec"]);
+        assert_eq!(parse_names("{0: 'qr', 2: 'aztec'}"), vec!["qr", "", "aztec"]);
+        assert!(parse_names("").is_empty());
+
+        let formats: Vec<_> = parse_names("{0: 'qr', 1: 'data_matrix', 2: 'Aztec'}")
+            .iter()
+            .map(|label| crate::BarcodeFormat::from_label(label))
+            .collect();
+        assert_eq!(
+            formats,
+            vec![
+                Some(crate::BarcodeFormat::QrCode),
+                Some(crate::BarcodeFormat::DataMatrix),
+                Some(crate::BarcodeFormat::Aztec)
+            ]
+        );
+
+        let mut proto = pb::ModelProto::decode(stub_model(Some(320)).as_slice()).unwrap();
+        proto.metadata_props.push(pb::StringStringEntryProto {
+            key: "names".into(),
+            value: "{0: 'qr', 1: 'datamatrix', 2: 'aztec'}".into(),
+        });
+        let mut detector = OnnxDetector::load(&proto.encode_to_vec()).unwrap();
+        assert_eq!(detector.labels(), ["qr", "datamatrix", "aztec"]);
+        detector.set_labels(vec!["qr".into()]);
+        assert_eq!(detector.labels(), ["qr"]);
+    }
+
+    /// Заглушка детектора на входе 320: сетка 10x10 ячеек 32px, в центре каждой
+    /// бокс 128x128 с уверенностью, равной доле тёмного в ячейке
+    fn grid_model() -> Vec<u8> {
+        let mut grid = vec![0.0f32; 4 * 100];
+        for i in 0..100 {
+            grid[i] = (i % 10) as f32 * 32.0 + 16.0;
+            grid[100 + i] = (i / 10) as f32 * 32.0 + 16.0;
+            grid[200 + i] = 128.0;
+            grid[300 + i] = 128.0;
+        }
+        let float = |name: &str, dims: Vec<i64>, data: Vec<f32>| pb::TensorProto {
+            name: name.into(),
+            dims,
+            data_type: pb::tensor_proto::DataType::Float as i32,
+            float_data: data,
+            ..Default::default()
+        };
+        let graph = pb::GraphProto {
+            name: "grid".into(),
+            node: vec![
+                node("AveragePool", &["images"], "pooled", vec![ints("kernel_shape", &[32, 32]), ints("strides", &[32, 32])]),
+                node("ReduceMean", &["pooled"], "mean", vec![ints("axes", &[1]), int("keepdims", 1)]),
+                node("Reshape", &["mean", "shape"], "flat", vec![]),
+                node("Neg", &["flat"], "negated", vec![]),
+                node("Add", &["negated", "one"], "score", vec![]),
+                node("Concat", &["grid", "score"], "output0", vec![int("axis", 1)]),
+            ],
+            initializer: vec![
+                pb::TensorProto {
+                    name: "shape".into(),
+                    dims: vec![3],
+                    data_type: pb::tensor_proto::DataType::Int64 as i32,
+                    int64_data: vec![1, 1, -1],
+                    ..Default::default()
+                },
+                float("one", vec![1], vec![1.0]),
+                float("grid", vec![1, 4, 100], grid),
+            ],
+            input: vec![value_info("images", &[Some(1), Some(3), Some(320), Some(320)])],
+            output: vec![value_info("output0", &[Some(1), Some(5), Some(100)])],
+            ..Default::default()
+        };
+        pb::ModelProto {
+            ir_version: 8,
+            opset_import: vec![pb::OperatorSetIdProto {
+                domain: String::new(),
+                version: 13,
+            }],
+            graph: Some(graph),
+            ..Default::default()
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn test_scanner_processing_config_reaches_ml_detector() {
+        use crate::{BinarizationMethod, QRScanner};
+
+        let detector = OnnxDetector::load(&stub_model(Some(320))).unwrap();
+        assert_eq!(detector.processing_config().block_size, ProcessingConfig::default().block_size);
+
+        let custom = ProcessingConfig {
+            block_size: 15,
+            binarization: Some(BinarizationMethod::Fixed(90)),
+            ..Default::default()
+        };
+        let built = QRScanner::builder().processing(custom.clone()).ml_detector(detector).build().unwrap();
+        let mut set = QRScanner::with_config(custom, Default::default());
+        set.set_ml_detector(OnnxDetector::load(&stub_model(Some(320))).unwrap());
+
+        for scanner in [built, set] {
+            let config = scanner.detector.ml_detector().expect("ml detector").processing_config();
+            assert_eq!(config.block_size, 15);
+            assert_eq!(config.binarization, Some(BinarizationMethod::Fixed(90)));
+        }
+    }
+
+    #[test]
+    fn test_tile_starts() {
+        assert_eq!(tile_starts(200, 320, 256), vec![0]);
+        assert_eq!(tile_starts(320, 320, 256), vec![0]);
+        assert_eq!(tile_starts(600, 320, 256), vec![0, 256, 280]);
+        assert_eq!(tile_starts(832, 320, 256), vec![0, 256, 512]);
+    }
+
+    #[test]
+    fn test_detect_tiled_small_distant_codes() {
+        // Два QR по 63px в противоположных углах кадра 1600x1600
+        let mut img = GrayImage::from_pixel(1600, 1600, image::Luma([255]));
+        let mut centers = Vec::new();
+        for (content, ox, oy) in [("far left", 150u32, 200u32), ("far right", 1300, 1250)] {
+            let qr = qrcode::QrCode::new(content).unwrap();
+            let width = qr.width() as u32;
+            crate::testing::draw_code(&mut img, &qr, (ox, oy), 3);
+            centers.push((ox + width * 3 / 2, oy + width * 3 / 2));
+        }
+
+        let mut detector = OnnxDetector::load(&grid_model()).unwrap();
+        detector.set_thresholds(0.2, 0.1).unwrap();
+
+        // При сжатии всего кадра до 320 коды сливаются с фоном
+        assert!(detector.detect(&img).unwrap().is_empty());
+
+        let found = detector.detect_tiled(&img, 320, 64).unwrap();
+        assert_eq!(found.len(), 2, "{:?}", found.iter().map(|d| d.bbox).collect::<Vec<_>>());
+        for (cx, cy) in centers {
+            let hits = found
+                .iter()
+                .filter(|d| {
+                    let [x, y, w, h] = d.bbox;
+                    (x..x + w).contains(&cx) && (y..y + h).contains(&cy)
+                })
+                .count();
+            assert_eq!(hits, 1, "code at ({}, {})", cx, cy);
+        }
+
+        assert!(detector.detect_tiled(&img, 320, 320).is_err());
+        assert!(detector.detect_tiled(&img, 0, 0).is_err());
+    }
+}
@@ -4,19 +4,48 @@
 //! - EMV QR Code (международный стандарт)
 //! - СБП (Система быстрых платежей, Россия)
 //! - ST.00012 (Стандарт ЦБ РФ)
+//! - EPC069-12 / GiroCode (SEPA Credit Transfer)
+//! - UPI (`upi://pay?...`, Индия)
+//! - Диплинки SberPay (`sberpay://`, `sbolpay://`)
+//!
+//! PIX, PromptPay, UnionPay, Alipay и WeChat Pay — EMV-коды: формат `EmvQR`,
+//! схема — в `extra["scheme"]`.
 
+use crate::currency::currency_alpha;
+use crate::emv::EmvData;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Платёжный формат
+///
+/// В JSON — snake_case (`"emv_qr"`, `"sbp_russia"`), как и `ContentType`
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
 pub enum PaymentFormat {
+    #[serde(rename = "emv_qr")]
     EmvQR,
     SbpRussia,
     StRussia,
+    EpcSepa,
+    /// Unified Payments Interface (`upi://pay`)
+    Upi,
+    /// Диплинк приложения СберБанк Онлайн / SberPay
+    SberPay,
     Unknown,
 }
 
+/// Чаевые / сервисный сбор EMV (теги 55–57)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum TipSpec {
+    /// 01 — плательщику предлагается ввести чаевые
+    Prompt,
+    /// 02 — фиксированный сбор (сумма из тега 56)
+    Fixed(Decimal),
+    /// 03 — сбор в процентах от суммы (тег 57)
+    Percentage(f64),
+}
+
 /// Платёжная информация
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaymentInfo {
@@ -32,12 +61,16 @@ pub struct PaymentInfo {
     pub bank: Option<String>,
     /// БИК банка
     pub bic: Option<String>,
-    /// Сумма платежа
-    pub amount: Option<f64>,
+    /// Сумма платежа (в JSON — строка с точным значением, например `"150.00"`)
+    pub amount: Option<Decimal>,
     /// Валюта (ISO 4217)
     pub currency: Option<String>,
     /// Назначение платежа
     pub purpose: Option<String>,
+    /// Чаевые / сервисный сбор (EMV)
+    pub tip: Option<TipSpec>,
+    /// Полный разбор EMV-полезной нагрузки (только для `PaymentFormat::EmvQR`, включая PIX и PromptPay)
+    pub emv: Option<EmvData>,
     /// Дополнительные поля
     pub extra: HashMap<String, String>,
 }
@@ -54,13 +87,19 @@ impl Default for PaymentInfo {
             amount: None,
             currency: None,
             purpose: None,
+            tip: None,
+            emv: None,
             extra: HashMap::new(),
         }
     }
 }
 
 /// Парсер платёжных QR-кодов
-pub struct PaymentParser;
+pub struct PaymentParser {
+    /// Отбрасывать EMV-коды с неверной CRC (тег 63) и, с feature `sbp-crc`,
+    /// ссылки СБП с неверным `crc=`
+    strict_crc: bool,
+}
 
 impl Default for PaymentParser {
     fn default() -> Self {
@@ -69,9 +108,15 @@ impl Default for PaymentParser {
 }
 
 impl PaymentParser {
-    /// Создание парсера
+    /// Создание парсера. EMV-коды с неверной CRC разбираются,
+    /// но помечаются `extra["crc_valid"] = "false"`.
     pub fn new() -> Self {
-        Self
+        Self { strict_crc: false }
+    }
+
+    /// Парсер, который не возвращает `PaymentInfo` для кодов с неверной CRC
+    pub fn strict() -> Self {
+        Self { strict_crc: true }
     }
     
     /// Парсинг платёжного QR
@@ -85,9 +130,21 @@ impl PaymentParser {
             return self.parse_st(content);
         }
         
-        if content.starts_with("00") && content.len() > 50 {
+        if EmvData::is_emv(content) {
             return self.parse_emv(content);
         }
+
+        if is_epc(content) {
+            return self.parse_epc(content);
+        }
+
+        if is_upi(content) {
+            return self.parse_upi(content);
+        }
+
+        if is_sberpay(content) {
+            return self.parse_sberpay(content);
+        }
         
         None
     }
@@ -102,7 +159,7 @@ impl PaymentParser {
         }
         
         // EMV QR
-        if content.starts_with("00") && content.len() > 50 {
+        if EmvData::is_emv(content) {
             return 0.95;
         }
         
@@ -110,6 +167,11 @@ impl PaymentParser {
         if content_lower.starts_with("st.") {
             return 0.9;
         }
+
+        // SEPA GiroCode, UPI, SberPay
+        if is_epc(content) || is_upi(content) || is_sberpay(content) {
+            return 0.9;
+        }
         
         // Ключевые слова платежей
         let payment_keywords = ["pay", "payment", "оплат", "платёж", "платеж", "перевод"];
@@ -143,30 +205,30 @@ impl PaymentParser {
             
             for param in query.split('&') {
                 if let Some(eq_pos) = param.find('=') {
-                    let key = &param[..eq_pos];
-                    let value = &param[eq_pos + 1..];
+                    // Ключи без учёта регистра (SUM, Sum), значения — с URL-декодированием
+                    let key = url_decode(&param[..eq_pos]).to_lowercase();
+                    let value = url_decode(&param[eq_pos + 1..]);
+                    let value = value.trim();
                     
-                    match key.to_lowercase().as_str() {
+                    match key.as_str() {
                         "sum" => {
                             // Сумма в копейках
-                            if let Ok(kopeks) = value.parse::<f64>() {
-                                info.amount = Some(kopeks / 100.0);
-                            }
+                            info.amount = kopeks_to_rubles(value);
                         }
                         "cur" => {
-                            info.currency = Some(value.to_string());
+                            info.currency = Some(value.to_uppercase());
                         }
                         "bank" => {
                             info.bank = Some(value.to_string());
                         }
                         "name" => {
-                            info.payee_name = Some(urlencoding::decode(value).unwrap_or_default().to_string());
+                            info.payee_name = Some(value.to_string());
                         }
                         "purpose" => {
-                            info.purpose = Some(urlencoding::decode(value).unwrap_or_default().to_string());
+                            info.purpose = Some(value.to_string());
                         }
                         _ => {
-                            info.extra.insert(key.to_string(), value.to_string());
+                            info.extra.insert(key, value.to_string());
                         }
                     }
                 }
@@ -175,10 +237,24 @@ impl PaymentParser {
         
         // Извлекаем идентификатор из пути
         // https://qr.nspk.ru/AS1234567890 -> AS1234567890
+        // Идентификатор НСПК — латиница и цифры; иное значение не сохраняется
         if let Some(path_start) = content.find("nspk.ru/") {
             let path = &content[path_start + 8..];
-            let id_end = path.find('?').unwrap_or(path.len());
-            info.payee_id = Some(path[..id_end].to_string());
+            let id_end = path.find(['?', '#']).unwrap_or(path.len());
+            let id = url_decode(&path[..id_end]);
+            let id = id.trim_end_matches('/').trim();
+            if !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                info.payee_id = Some(id.to_string());
+            }
+        }
+
+        #[cfg(feature = "sbp-crc")]
+        if let Some(crc_valid) = sbp_crc_valid(content) {
+            if self.strict_crc && !crc_valid {
+                log::info!("SBP: crc mismatch, link rejected");
+                return None;
+            }
+            info.extra.insert("crc_valid".to_string(), crc_valid.to_string());
         }
         
         Some(info)
@@ -203,11 +279,7 @@ impl PaymentParser {
                     "PersonalAcc" => info.account = Some(value.to_string()),
                     "BankName" => info.bank = Some(value.to_string()),
                     "BIC" => info.bic = Some(value.to_string()),
-                    "Sum" => {
-                        if let Ok(kopeks) = value.parse::<f64>() {
-                            info.amount = Some(kopeks / 100.0);
-                        }
-                    }
+                    "Sum" => info.amount = kopeks_to_rubles(value),
                     "Purpose" => info.purpose = Some(value.to_string()),
                     "PayeeINN" => info.payee_id = Some(value.to_string()),
                     _ => {
@@ -220,91 +292,440 @@ impl PaymentParser {
         Some(info)
     }
     
-    /// Парсинг EMV QR Code
-    fn parse_emv(&self, content: &str) -> Option<PaymentInfo> {
+    /// Парсинг EPC069-12 (GiroCode): поля через перевод строки
+    ///
+    /// BCD / версия / кодировка / SCT / BIC / получатель / IBAN /
+    /// [сумма EUR12.34] / [код цели] / [структурированная ссылка] / [текст] / [инфо]
+    fn parse_epc(&self, content: &str) -> Option<PaymentInfo> {
+        let lines: Vec<&str> = content.lines().collect();
+        if lines.len() < 7 || lines[0] != "BCD" {
+            return None;
+        }
+
+        let field = |idx: usize| {
+            lines
+                .get(idx)
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+        };
+
         let mut info = PaymentInfo {
-            format: PaymentFormat::EmvQR,
+            format: PaymentFormat::EpcSepa,
+            bic: field(4),
+            payee_name: field(5),
+            account: field(6),
             ..Default::default()
         };
-        
-        // EMV использует TLV (Tag-Length-Value) формат
-        // Каждое поле: 2 цифры тег + 2 цифры длина + значение
-        
-        let mut pos = 0;
-        let bytes = content.as_bytes();
-        
-        while pos + 4 <= bytes.len() {
-            // Тег (2 цифры)
-            let tag = std::str::from_utf8(&bytes[pos..pos + 2]).ok()?;
-            pos += 2;
-            
-            // Длина (2 цифры)
-            let len_str = std::str::from_utf8(&bytes[pos..pos + 2]).ok()?;
-            let len: usize = len_str.parse().ok()?;
-            pos += 2;
-            
-            if pos + len > bytes.len() {
-                break;
+
+        for (key, idx) in [("version", 1), ("charset", 2), ("identification", 3), ("purpose_code", 8), ("reference", 9), ("info", 11)] {
+            if let Some(value) = field(idx) {
+                info.extra.insert(key.to_string(), value);
             }
-            
-            // Значение
-            let value = std::str::from_utf8(&bytes[pos..pos + len]).ok()?;
-            pos += len;
-            
-            match tag {
-                "00" => {} // Payload Format Indicator
-                "01" => {} // Point of Initiation Method
-                "52" => {
-                    // Merchant Category Code
-                    info.extra.insert("mcc".to_string(), value.to_string());
+        }
+
+        // Сумма: трёхбуквенный код валюты + число (EUR12.34)
+        if let Some(amount) = field(7) {
+            if amount.len() > 3 && amount.is_char_boundary(3) {
+                let (currency, value) = amount.split_at(3);
+                info.currency = Some(currency.to_string());
+                info.amount = parse_decimal(value);
+            }
+        }
+
+        // Назначение: текст, иначе структурированная ссылка
+        info.purpose = field(10).or_else(|| field(9));
+
+        Some(info)
+    }
+    
+    /// Парсинг UPI: `upi://pay?pa=vpa@bank&pn=Имя&am=10.00&cu=INR&tn=Назначение`
+    fn parse_upi(&self, content: &str) -> Option<PaymentInfo> {
+        let mut info = PaymentInfo {
+            format: PaymentFormat::Upi,
+            currency: Some("INR".to_string()),
+            ..Default::default()
+        };
+
+        for (key, value) in query_params(content) {
+            match key.as_str() {
+                "pa" => info.account = Some(value),
+                "pn" => info.payee_name = Some(value),
+                "am" => info.amount = parse_decimal(&value),
+                "cu" => info.currency = Some(value.to_uppercase()),
+                "tn" => info.purpose = Some(value),
+                "mc" => {
+                    info.extra.insert("mcc".to_string(), value);
                 }
-                "53" => {
-                    // Transaction Currency (ISO 4217 numeric)
-                    info.currency = Some(self.currency_code_to_string(value));
+                _ => {
+                    info.extra.insert(key, value);
                 }
-                "54" => {
-                    // Transaction Amount
-                    info.amount = value.parse().ok();
+            }
+        }
+
+        // Без адреса получателя (VPA) платёж невозможен
+        info.account.as_ref()?;
+        Some(info)
+    }
+
+    /// Парсинг диплинка SberPay: путь (`invoicing/v2`) — в `extra["path"]`,
+    /// параметры запроса — в `extra`
+    fn parse_sberpay(&self, content: &str) -> Option<PaymentInfo> {
+        let mut info = PaymentInfo {
+            format: PaymentFormat::SberPay,
+            currency: Some("RUB".to_string()),
+            ..Default::default()
+        };
+
+        let rest = &content[content.find("://")? + 3..];
+        let path = &rest[..rest.find(['?', '#']).unwrap_or(rest.len())];
+        if !path.is_empty() {
+            info.extra.insert("path".to_string(), path.trim_end_matches('/').to_string());
+        }
+        for (key, value) in query_params(content) {
+            match key.as_str() {
+                "amount" | "sum" => info.amount = parse_decimal(&value),
+                "purpose" => info.purpose = Some(value),
+                _ => {
+                    info.extra.insert(key, value);
                 }
-                "58" => {
-                    // Country Code
-                    info.extra.insert("country".to_string(), value.to_string());
+            }
+        }
+
+        Some(info)
+    }
+    
+    /// Парсинг EMV QR Code
+    fn parse_emv(&self, content: &str) -> Option<PaymentInfo> {
+        // Повреждённый скан может дать правдоподобные, но неверные реквизиты
+        let crc_valid = EmvData::validate_crc(content).is_ok();
+        if self.strict_crc && !crc_valid {
+            log::info!("EMV: CRC mismatch, payload rejected");
+            return None;
+        }
+
+        let emv = EmvData::parse_unchecked(content).ok()?;
+
+        let mut info = PaymentInfo {
+            format: PaymentFormat::EmvQR,
+            payee_name: emv.merchant_name.clone(),
+            amount: emv.transaction_amount.as_deref().and_then(parse_decimal),
+            currency: emv
+                .transaction_currency
+                .as_deref()
+                .map(|code| currency_alpha(code).map_or_else(|| code.to_string(), str::to_string)),
+            tip: Self::tip_spec(
+                emv.tip_indicator.as_deref(),
+                emv.convenience_fee_fixed.as_deref().and_then(parse_decimal),
+                emv.convenience_fee_percentage.as_deref().and_then(|v| v.parse().ok()),
+            ),
+            ..Default::default()
+        };
+
+        // Merchant Account Information: шаблон с наименьшим тегом (26–51) даёт
+        // GUI схемы (payee_id) и первый идентификатор мерчанта (account);
+        // без шаблонов — сырое значение примитивного тега 02–25
+        let accounts = emv.parse_merchant_accounts();
+        let mut tags: Vec<&String> = accounts.keys().collect();
+        tags.sort();
+        if let Some(template) = tags.first().map(|tag| &accounts[*tag]) {
+            info.payee_id = template.get("00").cloned();
+            info.account = template
+                .iter()
+                .filter(|(subtag, _)| subtag.as_str() != "00")
+                .min_by_key(|(subtag, _)| subtag.as_str())
+                .map(|(_, value)| value.clone());
+        } else {
+            info.account = emv
+                .merchant_account_information
+                .iter()
+                .min_by_key(|(tag, _)| tag.as_str())
+                .map(|(_, value)| value.clone());
+        }
+
+        // Схема — по первому шаблону с известным GUI; неизвестные GUI
+        // сохраняются как `extra["gui_<тег>"]`
+        let mut scheme = None;
+        for tag in tags {
+            let Some(gui) = accounts[tag].get("00") else {
+                continue;
+            };
+            match emv_scheme(gui) {
+                Some(known) => {
+                    scheme.get_or_insert(known);
                 }
-                "59" => {
-                    // Merchant Name
-                    info.payee_name = Some(value.to_string());
+                None => {
+                    info.extra.insert(format!("gui_{}", tag), gui.clone());
                 }
-                "60" => {
-                    // Merchant City
-                    info.extra.insert("city".to_string(), value.to_string());
+            }
+        }
+        // Примитивные теги 15–16 EMVCo закрепил за UnionPay
+        if scheme.is_none() && ["15", "16"].iter().any(|tag| emv.merchant_account_information.contains_key(*tag)) {
+            scheme = Some("unionpay");
+        }
+        if let Some(scheme) = scheme {
+            info.extra.insert("scheme".to_string(), scheme.to_string());
+        }
+        info.extra.insert("crc_valid".to_string(), crc_valid.to_string());
+        for (key, value) in [
+            ("mcc", &emv.merchant_category_code),
+            ("country", &emv.country_code),
+            ("city", &emv.merchant_city),
+            ("postal_code", &emv.postal_code),
+        ] {
+            if let Some(value) = value {
+                info.extra.insert(key.to_string(), value.clone());
+            }
+        }
+
+        for (subtag, value) in emv.parse_additional_data() {
+            let key = match subtag.as_str() {
+                "01" => "bill_number",
+                "02" => "mobile_number",
+                "03" => "store_label",
+                "04" => "loyalty_number",
+                "05" => "reference_label",
+                "06" => "customer_label",
+                "07" => "terminal_label",
+                "08" => {
+                    info.purpose = Some(value);
+                    continue;
                 }
-                _ => {
-                    // Сохраняем остальные теги
-                    if tag.starts_with("26") || tag.starts_with("27") {
-                        // Merchant Account Information
-                        info.account = Some(value.to_string());
-                    }
+                other => {
+                    info.extra.insert(format!("additional_{}", other), value);
+                    continue;
                 }
-            }
+            };
+            info.extra.insert(key.to_string(), value);
         }
-        
+
+        info.emv = Some(emv);
         Some(info)
     }
-    
-    /// Конвертация числового кода валюты в строку
-    fn currency_code_to_string(&self, code: &str) -> String {
-        match code {
-            "643" => "RUB".to_string(),
-            "840" => "USD".to_string(),
-            "978" => "EUR".to_string(),
-            "156" => "CNY".to_string(),
-            "392" => "JPY".to_string(),
-            "826" => "GBP".to_string(),
-            _ => code.to_string(),
+
+    /// Сборка `TipSpec` из тегов 55–57 (индикатор без нужного значения игнорируется)
+    fn tip_spec(indicator: Option<&str>, fixed: Option<Decimal>, percentage: Option<f64>) -> Option<TipSpec> {
+        match indicator? {
+            "01" => Some(TipSpec::Prompt),
+            "02" => fixed.map(TipSpec::Fixed),
+            "03" => percentage.map(TipSpec::Percentage),
+            _ => None,
+        }
+    }
+}
+
+/// Служебные ключи `extra`, которые парсер вычисляет сам и генераторы не переносят
+const DERIVED_EXTRA: [&str; 2] = ["crc", "crc_valid"];
+
+/// Ссылка СБП `https://qr.nspk.ru/<payee_id>?type=..&bank=..&sum=..` из `PaymentInfo`.
+///
+/// Сумма записывается в копейках (округление до двух знаков), идентификатор в пути
+/// и текстовые значения URL-кодируются, поля `extra` добавляются по алфавиту (`type` — первым).
+/// С feature `sbp-crc` в конец добавляется `crc=`.
+pub fn build_sbp(info: &PaymentInfo) -> String {
+    let mut params: Vec<(&str, String)> = Vec::new();
+    if let Some(kind) = info.extra.get("type") {
+        params.push(("type", kind.clone()));
+    }
+    if let Some(bank) = &info.bank {
+        params.push(("bank", bank.clone()));
+    }
+    if let Some(amount) = info.amount {
+        params.push(("sum", to_kopeks(amount).to_string()));
+    }
+    if let Some(currency) = &info.currency {
+        params.push(("cur", currency.clone()));
+    }
+    if let Some(name) = &info.payee_name {
+        params.push(("name", name.clone()));
+    }
+    if let Some(purpose) = &info.purpose {
+        params.push(("purpose", purpose.clone()));
+    }
+    let mut extra: Vec<_> = info
+        .extra
+        .iter()
+        .filter(|(key, _)| key.as_str() != "type" && !DERIVED_EXTRA.contains(&key.as_str()))
+        .collect();
+    extra.sort();
+    params.extend(extra.into_iter().map(|(key, value)| (key.as_str(), value.clone())));
+
+    let mut url = format!("https://qr.nspk.ru/{}", urlencoding::encode(info.payee_id.as_deref().unwrap_or_default()));
+    for (idx, (key, value)) in params.iter().enumerate() {
+        url.push(if idx == 0 { '?' } else { '&' });
+        url.push_str(&urlencoding::encode(key));
+        url.push('=');
+        url.push_str(&urlencoding::encode(value));
+    }
+
+    #[cfg(feature = "sbp-crc")]
+    {
+        let crc = crate::emv::crc16_ccitt_kermit(url.as_bytes());
+        url.push(if params.is_empty() { '?' } else { '&' });
+        url.push_str(&format!("crc={:04X}", crc));
+    }
+
+    url
+}
+
+/// Строка ST.00012 `ST.00012|Name=..|PersonalAcc=..|...` из `PaymentInfo`.
+///
+/// Сумма — в копейках. Формат не экранирует `|`, поэтому в значениях он
+/// заменяется пробелом. Поля `extra` добавляются по алфавиту.
+pub fn build_st(info: &PaymentInfo) -> String {
+    let mut fields: Vec<(&str, String)> = Vec::new();
+    let known = [
+        ("Name", &info.payee_name),
+        ("PersonalAcc", &info.account),
+        ("BankName", &info.bank),
+        ("BIC", &info.bic),
+    ];
+    fields.extend(known.into_iter().filter_map(|(key, value)| Some((key, value.clone()?))));
+    if let Some(amount) = info.amount {
+        fields.push(("Sum", to_kopeks(amount).to_string()));
+    }
+    if let Some(purpose) = &info.purpose {
+        fields.push(("Purpose", purpose.clone()));
+    }
+    if let Some(inn) = &info.payee_id {
+        fields.push(("PayeeINN", inn.clone()));
+    }
+    let mut extra: Vec<_> = info
+        .extra
+        .iter()
+        .filter(|(key, _)| !DERIVED_EXTRA.contains(&key.as_str()))
+        .collect();
+    extra.sort();
+    fields.extend(extra.into_iter().map(|(key, value)| (key.as_str(), value.clone())));
+
+    let mut payload = String::from("ST.00012");
+    for (key, value) in fields {
+        payload.push('|');
+        payload.push_str(key);
+        payload.push('=');
+        payload.push_str(&value.replace('|', " "));
+    }
+    payload
+}
+
+/// Рубли → копейки с округлением до двух знаков
+fn to_kopeks(amount: Decimal) -> i128 {
+    let mut amount = amount.round_dp(2);
+    amount.rescale(2);
+    amount.mantissa()
+}
+
+/// Проверка `crc=` в ссылке СБП (`None`, если параметра нет).
+///
+/// Публичной спецификации алгоритма НСПК нет; используется CRC-16/CCITT-FALSE
+/// (полином 0x1021, начальное значение 0xFFFF, без отражения и финального XOR) —
+/// та же функция, что и для тега 63 EMV, — по байтам ссылки без параметра `crc`
+/// (остальные параметры в исходном порядке). Значение — ровно 4 hex-цифры без учёта
+/// регистра; знак (`+ABC`) или другая длина — несовпадение. Поэтому проверка
+/// вынесена в feature `sbp-crc`.
+#[cfg(feature = "sbp-crc")]
+fn sbp_crc_valid(url: &str) -> Option<bool> {
+    let (base, query) = url.split_once('?')?;
+    let mut expected = None;
+    let mut params = Vec::new();
+    for param in query.split('&') {
+        match param.split_once('=') {
+            Some((key, value)) if key.eq_ignore_ascii_case("crc") => expected = Some(value),
+            _ => params.push(param),
+        }
+    }
+    let expected = expected?;
+
+    let canonical = if params.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}?{}", base, params.join("&"))
+    };
+    let actual = crate::emv::crc16_ccitt_kermit(canonical.as_bytes());
+    let hex = expected.len() == 4 && expected.bytes().all(|b| b.is_ascii_hexdigit());
+    Some(hex && u16::from_str_radix(expected, 16).ok() == Some(actual))
+}
+
+/// URL-декодирование параметра запроса: `+` → пробел, `%XX` → байт.
+/// Невалидный UTF-8 после декодирования — исходная строка.
+fn url_decode(value: &str) -> String {
+    let value = value.replace('+', " ");
+    match urlencoding::decode(&value) {
+        Ok(decoded) => decoded.into_owned(),
+        Err(_) => value,
+    }
+}
+
+/// Сумма в копейках (СБП `sum`, ST.00012 `Sum`) → рубли с двумя знаками
+fn kopeks_to_rubles(value: &str) -> Option<Decimal> {
+    value.trim().parse::<i64>().ok().map(|kopeks| Decimal::new(kopeks, 2))
+}
+
+/// Десятичная сумма из строки (`"120.50"`), без экспоненты и лишних символов
+fn parse_decimal(value: &str) -> Option<Decimal> {
+    value.trim().parse().ok()
+}
+
+/// EPC069-12 (GiroCode): служебный тег `BCD`, версия `001`/`002` и идентификатор `SCT`
+/// в первых строках (LF или CRLF). Общая проверка для `ContentType::detect` и парсера.
+pub(crate) fn is_epc(content: &str) -> bool {
+    let mut lines = content.lines();
+    lines.next() == Some("BCD")
+        && matches!(lines.next(), Some("001" | "002"))
+        && lines.nth(1) == Some("SCT")
+}
+
+/// Ссылка UPI `upi://pay?...`
+pub(crate) fn is_upi(content: &str) -> bool {
+    starts_with_ignore_case(content, "upi://pay")
+}
+
+/// Диплинк SberPay (`sberpay://`, `sbolpay://`)
+pub(crate) fn is_sberpay(content: &str) -> bool {
+    starts_with_ignore_case(content, "sberpay://") || starts_with_ignore_case(content, "sbolpay://")
+}
+
+fn starts_with_ignore_case(content: &str, prefix: &str) -> bool {
+    content.get(..prefix.len()).is_some_and(|head| head.eq_ignore_ascii_case(prefix))
+}
+
+/// Платёжная схема EMV-кода по GUI/AID шаблона Merchant Account Information
+fn emv_scheme(gui: &str) -> Option<&'static str> {
+    if gui.eq_ignore_ascii_case("br.gov.bcb.pix") {
+        Some("pix")
+    } else if gui.starts_with("A000000677") {
+        // PromptPay: 010111 — мерчант, 010112 — счёт на оплату, 010114 — e-wallet
+        Some("promptpay")
+    } else if gui.starts_with("A000000333") {
+        // RID UnionPay (AID карточных продуктов A000000333010101/02/03)
+        Some("unionpay")
+    } else {
+        // Кошельки задают GUI доменным именем (`com.alipay...`, `com.tencent.wechat...`)
+        let gui = gui.to_ascii_lowercase();
+        if gui.contains("alipay") {
+            Some("alipay")
+        } else if gui.contains("wechat") || gui.contains("tenpay") {
+            Some("wechatpay")
+        } else {
+            None
         }
     }
 }
 
+/// URL-декодированные параметры запроса (ключи в нижнем регистре)
+fn query_params(content: &str) -> Vec<(String, String)> {
+    let Some(query_start) = content.find('?') else {
+        return Vec::new();
+    };
+    let query = &content[query_start + 1..];
+    let query = &query[..query.find('#').unwrap_or(query.len())];
+    query
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (url_decode(key).to_lowercase(), url_decode(value).trim().to_string()))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -316,10 +737,98 @@ mod tests {
         
         let result = parser.parse(content).unwrap();
         assert_eq!(result.format, PaymentFormat::SbpRussia);
-        assert_eq!(result.amount, Some(100.0)); // 10000 копеек = 100 рублей
+        assert_eq!(result.amount, Some(Decimal::new(10000, 2))); // 10000 копеек = 100 рублей
         assert_eq!(result.currency, Some("RUB".to_string()));
     }
     
+    #[test]
+    fn test_sbp_decodes_all_params() {
+        let parser = PaymentParser::new();
+        let content = "https://qr.nspk.ru/%20BD10003D8MKGQ7VL8QLO7CJRN8IK6I5A%20/?Type=02&BANK=100000000111\
+            &Sum=12345&cur=rub&Name=%D0%9E%D0%9E%D0%9E+%C2%AB%D0%A0%D0%BE%D0%BC%D0%B0%D1%88%D0%BA%D0%B0%C2%BB\
+            &PURPOSE=%D0%9E%D0%BF%D0%BB%D0%B0%D1%82%D0%B0+%D0%B7%D0%B0%D0%BA%D0%B0%D0%B7%D0%B0\
+            &Comment=%D0%A1%D1%82%D0%BE%D0%BB%D0%B8%D0%BA+7&crc=AB12";
+
+        let result = parser.parse(content).unwrap();
+        assert_eq!(result.payee_id.as_deref(), Some("BD10003D8MKGQ7VL8QLO7CJRN8IK6I5A"));
+        assert_eq!(result.payee_name.as_deref(), Some("ООО «Ромашка»"));
+        assert_eq!(result.purpose.as_deref(), Some("Оплата заказа"));
+        assert_eq!(result.bank.as_deref(), Some("100000000111"));
+        assert_eq!(result.amount, Some(Decimal::new(12345, 2)));
+        assert_eq!(result.currency.as_deref(), Some("RUB"));
+        assert_eq!(result.extra.get("type").map(String::as_str), Some("02"));
+        assert_eq!(result.extra.get("comment").map(String::as_str), Some("Столик 7"));
+        assert_eq!(result.extra.get("crc").map(String::as_str), Some("AB12"));
+
+        // Мусор в пути не становится идентификатором
+        let result = parser.parse("https://qr.nspk.ru/AS1%3Cscript%3E?sum=100").unwrap();
+        assert_eq!(result.payee_id, None);
+        // Невалидный UTF-8 остаётся как есть
+        let result = parser.parse("https://qr.nspk.ru/AS1?name=%FF%FE").unwrap();
+        assert_eq!(result.payee_name.as_deref(), Some("%FF%FE"));
+    }
+
+    #[cfg(feature = "sbp-crc")]
+    #[test]
+    fn test_sbp_crc() {
+        let link = "https://qr.nspk.ru/AS1000670LSS7DN18SJQDNP4B05KLJL2?type=02&bank=100000000001&sum=15000&cur=RUB";
+        let crc = crate::emv::crc16_ccitt_kermit(link.as_bytes());
+        let signed = format!("{}&crc={:04X}", link, crc);
+
+        let result = PaymentParser::new().parse(&signed).unwrap();
+        assert_eq!(result.extra.get("crc_valid").map(String::as_str), Some("true"));
+        assert!(PaymentParser::strict().parse(&signed).is_some());
+        // crc может стоять в середине запроса и в нижнем регистре
+        let reordered = format!("https://qr.nspk.ru/AS1000670LSS7DN18SJQDNP4B05KLJL2?type=02&crc={:04x}&bank=100000000001&sum=15000&cur=RUB", crc);
+        assert_eq!(sbp_crc_valid(&reordered), Some(true));
+
+        // Подменённая сумма
+        let tampered = signed.replace("sum=15000", "sum=95000");
+        let result = PaymentParser::new().parse(&tampered).unwrap();
+        assert_eq!(result.extra.get("crc_valid").map(String::as_str), Some("false"));
+        assert_eq!(result.amount, Some(Decimal::new(95000, 2)));
+        assert!(PaymentParser::strict().parse(&tampered).is_none());
+
+        // Без crc= ссылка не проверяется
+        let result = PaymentParser::strict().parse(link).unwrap();
+        assert!(!result.extra.contains_key("crc_valid"));
+
+        // `+` перед тремя hex-цифрами не считается CRC, даже если число совпадает
+        let (short, crc) = (0..)
+            .map(|sum| format!("https://qr.nspk.ru/AS1?sum={}", sum))
+            .map(|link| {
+                let crc = crate::emv::crc16_ccitt_kermit(link.as_bytes());
+                (link, crc)
+            })
+            .find(|&(_, crc)| crc < 0x1000)
+            .unwrap();
+        assert_eq!(sbp_crc_valid(&format!("{}&crc={:04X}", short, crc)), Some(true));
+        assert_eq!(sbp_crc_valid(&format!("{}&crc=+{:03X}", short, crc)), Some(false));
+    }
+
+    #[test]
+    fn test_amounts_are_exact() {
+        let parser = PaymentParser::new();
+        let amount = |content: &str| parser.parse(content).unwrap().amount.unwrap().to_string();
+
+        // В f64 эти значения не представимы точно (1999 / 100.0 = 19.990000000000002)
+        assert_eq!(amount("https://qr.nspk.ru/AS1?sum=1999"), "19.99");
+        assert_eq!(amount("https://qr.nspk.ru/AS1?sum=15000"), "150.00");
+        assert_eq!(amount("ST.00012|Name=Test|Sum=30"), "0.30");
+        assert_eq!(amount("ST.00012|Name=Test|Sum=9007199254740993"), "90071992547409.93");
+        assert_eq!(amount("BCD\n002\n1\nSCT\n\nShop\nDE89370400440532013000\nEUR0.07"), "0.07");
+
+        let info = parser.parse("https://qr.nspk.ru/AS1?sum=15000").unwrap();
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["amount"], serde_json::json!("150.00"));
+        let back: PaymentInfo = serde_json::from_value(json).unwrap();
+        assert_eq!(back.amount, info.amount);
+
+        // Некорректная сумма не превращается в 0 или NaN
+        assert_eq!(parser.parse("https://qr.nspk.ru/AS1?sum=12.5").unwrap().amount, None);
+        assert_eq!(parser.parse("ST.00012|Name=Test|Sum=abc").unwrap().amount, None);
+    }
+
     #[test]
     fn test_st_parsing() {
         let parser = PaymentParser::new();
@@ -328,9 +837,401 @@ mod tests {
         let result = parser.parse(content).unwrap();
         assert_eq!(result.format, PaymentFormat::StRussia);
         assert_eq!(result.payee_name, Some("ООО Тест".to_string()));
-        assert_eq!(result.amount, Some(1000.0)); // 100000 копеек = 1000 рублей
+        assert_eq!(result.amount, Some(Decimal::new(100000, 2))); // 100000 копеек = 1000 рублей
+    }
+    
+    /// Поля, которые генератор должен сохранить при разборе
+    fn assert_round_trip(input: &PaymentInfo, parsed: &PaymentInfo) {
+        assert_eq!(parsed.format, input.format);
+        assert_eq!(parsed.payee_name, input.payee_name);
+        assert_eq!(parsed.payee_id, input.payee_id);
+        assert_eq!(parsed.account, input.account);
+        assert_eq!(parsed.bank, input.bank);
+        assert_eq!(parsed.bic, input.bic);
+        assert_eq!(parsed.amount, input.amount);
+        assert_eq!(parsed.currency, input.currency);
+        assert_eq!(parsed.purpose, input.purpose);
+        let mut extra = parsed.extra.clone();
+        extra.retain(|key, _| !DERIVED_EXTRA.contains(&key.as_str()));
+        assert_eq!(extra, input.extra);
+    }
+
+    #[test]
+    fn test_build_sbp_round_trip() {
+        let info = PaymentInfo {
+            format: PaymentFormat::SbpRussia,
+            payee_id: Some("BD10003D8MKGQ7VL8QLO7CJRN8IK6I5A".to_string()),
+            bank: Some("100000000111".to_string()),
+            amount: Some(Decimal::new(12345, 2)),
+            currency: Some("RUB".to_string()),
+            payee_name: Some("ООО «Ромашка & Ко»".to_string()),
+            purpose: Some("Оплата заказа №42+1".to_string()),
+            extra: HashMap::from([
+                ("type".to_string(), "02".to_string()),
+                ("comment".to_string(), "a=b&c".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        let url = build_sbp(&info);
+        assert!(url.starts_with("https://qr.nspk.ru/BD10003D8MKGQ7VL8QLO7CJRN8IK6I5A?type=02&bank=100000000111&sum=12345&cur=RUB&"), "{}", url);
+        let parsed = PaymentParser::new().parse(&url).unwrap();
+        assert_round_trip(&info, &parsed);
+        #[cfg(feature = "sbp-crc")]
+        assert_eq!(parsed.extra.get("crc_valid").map(String::as_str), Some("true"));
+
+        // Сумма в копейках с округлением
+        let url = build_sbp(&PaymentInfo {
+            amount: Some(Decimal::new(1505, 1)),
+            ..info.clone()
+        });
+        assert!(url.contains("&sum=15050&"), "{}", url);
+
+        // Пробелы, `&`, `=` и `?` в значениях не ломают ссылку
+        let info = PaymentInfo {
+            payee_name: Some("Shop & Co = best?".to_string()),
+            purpose: Some("order 42&sum=1".to_string()),
+            extra: HashMap::from([("comment".to_string(), "a b & c=d".to_string())]),
+            ..info
+        };
+        let url = build_sbp(&info);
+        assert_eq!(url.matches('?').count(), 1, "{}", url);
+        assert!(!url.contains(' '), "{}", url);
+        assert_round_trip(&info, &PaymentParser::new().parse(&url).unwrap());
+
+        // Идентификатор в пути тоже кодируется: запрос после него не разваливается
+        let url = build_sbp(&PaymentInfo {
+            payee_id: Some("AS1 2&x=3?y".to_string()),
+            ..info.clone()
+        });
+        assert!(url.starts_with("https://qr.nspk.ru/AS1%202%26x%3D3%3Fy?"), "{}", url);
+        let parsed = PaymentParser::new().parse(&url).unwrap();
+        assert_eq!(parsed.payee_id, None);
+        assert_eq!(parsed.purpose, info.purpose);
+        assert_eq!(parsed.amount, info.amount);
+    }
+
+    #[test]
+    fn test_build_st_round_trip() {
+        let info = PaymentInfo {
+            format: PaymentFormat::StRussia,
+            payee_name: Some("ООО Рога и Копыта".to_string()),
+            account: Some("40702810099990001234".to_string()),
+            bank: Some("ПАО Сбербанк".to_string()),
+            bic: Some("044525225".to_string()),
+            amount: Some(Decimal::new(250000, 2)),
+            currency: Some("RUB".to_string()),
+            purpose: Some("Оплата по счёту 123".to_string()),
+            payee_id: Some("7700000000".to_string()),
+            extra: HashMap::from([("CorrespAcc".to_string(), "30101810400000000225".to_string())]),
+            ..Default::default()
+        };
+
+        let payload = build_st(&info);
+        assert!(payload.starts_with("ST.00012|Name=ООО Рога и Копыта|PersonalAcc=40702810099990001234|"), "{}", payload);
+        assert!(payload.contains("|Sum=250000|"));
+        assert_round_trip(&info, &PaymentParser::new().parse(&payload).unwrap());
+
+        // `|` в значении не ломает разбиение на поля
+        let payload = build_st(&PaymentInfo {
+            purpose: Some("Счёт 1|2".to_string()),
+            ..info
+        });
+        let parsed = PaymentParser::new().parse(&payload).unwrap();
+        assert_eq!(parsed.purpose.as_deref(), Some("Счёт 1 2"));
+        assert_eq!(parsed.payee_id.as_deref(), Some("7700000000"));
+    }
+
+    #[test]
+    fn test_epc_parsing() {
+        let parser = PaymentParser::new();
+        let content = "BCD\n002\n1\nSCT\nBFSWDE33BER\nWikimedia Foerdergesellschaft\nDE33100205000001194700\nEUR123.45\nCHAR\n\nSpende fuer Wikipedia\n";
+
+        let result = parser.parse(content).unwrap();
+        assert_eq!(result.format, PaymentFormat::EpcSepa);
+        assert_eq!(result.account.as_deref(), Some("DE33100205000001194700"));
+        assert_eq!(result.bic.as_deref(), Some("BFSWDE33BER"));
+        assert_eq!(result.payee_name.as_deref(), Some("Wikimedia Foerdergesellschaft"));
+        assert_eq!(result.amount, Some(Decimal::new(12345, 2)));
+        assert_eq!(result.currency.as_deref(), Some("EUR"));
+        assert_eq!(result.purpose.as_deref(), Some("Spende fuer Wikipedia"));
+        assert_eq!(result.extra.get("purpose_code").map(String::as_str), Some("CHAR"));
+        assert!(parser.relevance_score(content) > 0.8);
+    }
+
+    #[test]
+    fn test_epc_rejects_invalid() {
+        let parser = PaymentParser::new();
+        // Слишком мало строк
+        assert!(parser.parse("BCD\n002\n1\nSCT\nBIC\nName").is_none());
+        // Без заголовка BCD, с неизвестной версией или не SCT
+        assert!(parser.parse("XYZ\n002\n1\nSCT\nBIC\nName\nDE33100205000001194700").is_none());
+        assert!(parser.parse("BCD\n003\n1\nSCT\nBIC\nName\nDE33100205000001194700").is_none());
+        assert!(parser.parse("BCD\n002\n1\nINST\nBIC\nName\nDE33100205000001194700").is_none());
+        assert!(!is_epc("BCD"));
+        // CRLF и пустой BIC (версия 002) допустимы
+        let result = parser.parse("BCD\r\n002\r\n2\r\nSCT\r\n\r\nMax Mustermann\r\nDE02120300000000202051").unwrap();
+        assert_eq!(result.bic, None);
+        assert_eq!(result.amount, None);
+        assert_eq!(result.account.as_deref(), Some("DE02120300000000202051"));
+    }
+
+    /// Дописывает тег 63 с корректной CRC
+    fn with_crc(payload: &str) -> String {
+        let body = format!("{}6304", payload);
+        format!("{}{:04X}", body, crate::emv::crc16_ccitt_kermit(body.as_bytes()))
+    }
+
+    #[test]
+    fn test_emv_crc_validation() {
+        let payload = with_crc(&emv_with_tip(&[]));
+        let lenient = PaymentParser::new();
+        let strict = PaymentParser::strict();
+
+        let result = strict.parse(&payload).unwrap();
+        assert_eq!(result.extra.get("crc_valid").map(String::as_str), Some("true"));
+        assert_eq!(result.payee_name.as_deref(), Some("Corner Bistro"));
+
+        // Искажённое имя получателя: CRC больше не сходится
+        let corrupted = payload.replace("Corner Bistro", "Corner Bistre");
+        assert!(strict.parse(&corrupted).is_none());
+        let result = lenient.parse(&corrupted).unwrap();
+        assert_eq!(result.extra.get("crc_valid").map(String::as_str), Some("false"));
+    }
+
+    #[test]
+    fn test_emv_fields_from_emv_data() {
+        let parser = PaymentParser::new();
+        let payload = emv_payload(&[
+            ("00", "01"),
+            ("01", "12"),
+            ("26", "0014A0000006150001010656300002100000000001"),
+            ("52", "5812"),
+            ("53", "643"),
+            ("54", "150.00"),
+            ("58", "RU"),
+            ("59", "Кофейня"),
+            ("60", "Moscow"),
+            ("61", "101000"),
+        ]);
+
+        let result = parser.parse(&with_crc(&payload)).unwrap();
+        assert_eq!(result.payee_name.as_deref(), Some("Кофейня"));
+        assert_eq!(result.amount, Some(Decimal::new(150, 0)));
+        assert_eq!(result.currency.as_deref(), Some("RUB"));
+        assert_eq!(result.payee_id.as_deref(), Some("A0000006150001"));
+        assert_eq!(result.account.as_deref(), Some("563000"));
+        assert_eq!(result.extra.get("city").map(String::as_str), Some("Moscow"));
+        assert_eq!(result.extra.get("postal_code").map(String::as_str), Some("101000"));
+
+        let emv = result.emv.expect("EmvData attached");
+        assert_eq!(emv.country_code.as_deref(), Some("RU"));
+        assert_eq!(emv.merchant_category_code.as_deref(), Some("5812"));
+    }
+
+    #[test]
+    fn test_emv_currency_alpha() {
+        let parser = PaymentParser::new();
+        let currency = |code: &str| {
+            let payload = emv_payload(&[("00", "01"), ("53", code), ("58", "VN"), ("59", "Pho 24 Nguyen Hue"), ("60", "Ho Chi Minh City")]);
+            parser.parse(&with_crc(&payload)).unwrap().currency
+        };
+
+        assert_eq!(currency("704").as_deref(), Some("VND"));
+        assert_eq!(currency("036").as_deref(), Some("AUD"));
+        // Неизвестный код остаётся числовым
+        assert_eq!(currency("123").as_deref(), Some("123"));
+    }
+
+    #[test]
+    fn test_emv_promptpay_account() {
+        let parser = PaymentParser::new();
+        let promptpay = emv_payload(&[("00", "A000000677010111"), ("01", "0066812345678")]);
+        let payload = emv_payload(&[
+            ("00", "01"),
+            ("01", "11"),
+            ("29", &promptpay),
+            ("53", "764"),
+            ("58", "TH"),
+            ("59", "Som Tam Shop"),
+            ("60", "Bangkok"),
+        ]);
+
+        let result = parser.parse(&with_crc(&payload)).unwrap();
+        assert_eq!(result.payee_id.as_deref(), Some("A000000677010111"));
+        assert_eq!(result.account.as_deref(), Some("0066812345678"));
+    }
+
+    #[test]
+    fn test_emv_additional_data() {
+        let parser = PaymentParser::new();
+        let additional = emv_payload(&[("01", "INV-0042"), ("05", "REF-777"), ("07", "T1"), ("08", "Lunch")]);
+        let mut payload = emv_with_tip(&[]);
+        payload.push_str(&emv_payload(&[("62", &additional)]));
+
+        let result = parser.parse(&with_crc(&payload)).unwrap();
+        assert_eq!(result.extra.get("bill_number").map(String::as_str), Some("INV-0042"));
+        assert_eq!(result.extra.get("reference_label").map(String::as_str), Some("REF-777"));
+        assert_eq!(result.extra.get("terminal_label").map(String::as_str), Some("T1"));
+        assert_eq!(result.purpose.as_deref(), Some("Lunch"));
+    }
+
+    /// Сборка EMV-полезной нагрузки из пар (тег, значение); длина — в символах
+    fn emv_payload(fields: &[(&str, &str)]) -> String {
+        fields
+            .iter()
+            .map(|(tag, value)| format!("{}{:02}{}", tag, value.chars().count(), value))
+            .collect()
+    }
+
+    fn emv_with_tip(tip_fields: &[(&str, &str)]) -> String {
+        let mut fields = vec![
+            ("00", "01"),
+            ("01", "12"),
+            ("52", "5812"),
+            ("53", "840"),
+            ("54", "25.00"),
+        ];
+        fields.extend_from_slice(tip_fields);
+        fields.extend_from_slice(&[("58", "US"), ("59", "Corner Bistro"), ("60", "Springfield")]);
+        emv_payload(&fields)
+    }
+
+    #[test]
+    fn test_emv_tip_prompt() {
+        let parser = PaymentParser::new();
+        let result = parser.parse(&with_crc(&emv_with_tip(&[("55", "01")]))).unwrap();
+        assert_eq!(result.tip, Some(TipSpec::Prompt));
+    }
+
+    #[test]
+    fn test_emv_tip_fixed() {
+        let parser = PaymentParser::new();
+        let result = parser.parse(&with_crc(&emv_with_tip(&[("55", "02"), ("56", "1.50")]))).unwrap();
+        assert_eq!(result.tip, Some(TipSpec::Fixed(Decimal::new(150, 2))));
+        assert_eq!(result.amount, Some(Decimal::new(2500, 2)));
+    }
+
+    #[test]
+    fn test_emv_tip_percentage() {
+        let parser = PaymentParser::new();
+        let result = parser.parse(&with_crc(&emv_with_tip(&[("55", "03"), ("57", "15")]))).unwrap();
+        assert_eq!(result.tip, Some(TipSpec::Percentage(15.0)));
+
+        // Индикатор без значения процента не даёт TipSpec
+        let result = parser.parse(&with_crc(&emv_with_tip(&[("55", "03")]))).unwrap();
+        assert_eq!(result.tip, None);
     }
     
+    #[test]
+    fn test_upi_parsing() {
+        let parser = PaymentParser::new();
+        let result = parser
+            .parse("upi://pay?pa=chai.stall@okaxis&pn=Ravi%20Tea%20Stall&am=40.00&cu=INR&tn=Masala+chai&mc=5812&tr=ORD42")
+            .unwrap();
+        assert_eq!(result.format, PaymentFormat::Upi);
+        assert_eq!(result.account.as_deref(), Some("chai.stall@okaxis"));
+        assert_eq!(result.payee_name.as_deref(), Some("Ravi Tea Stall"));
+        assert_eq!(result.amount, Some(Decimal::new(4000, 2)));
+        assert_eq!(result.currency.as_deref(), Some("INR"));
+        assert_eq!(result.purpose.as_deref(), Some("Masala chai"));
+        assert_eq!(result.extra.get("mcc").map(String::as_str), Some("5812"));
+        assert_eq!(result.extra.get("tr").map(String::as_str), Some("ORD42"));
+
+        // Без pa= — не платёж
+        assert!(parser.parse("upi://pay?pn=Nobody").is_none());
+        assert!(parser.relevance_score("UPI://PAY?pa=a@b") > 0.8);
+    }
+
+    #[test]
+    fn test_sberpay_deep_link() {
+        let parser = PaymentParser::new();
+        for link in [
+            "sberpay://invoicing/v2?bankInvoiceId=8f0c1e2a&operationType=Web2App",
+            "sbolpay://invoicing/v2?bankInvoiceId=8f0c1e2a&operationType=Web2App",
+        ] {
+            let result = parser.parse(link).unwrap();
+            assert_eq!(result.format, PaymentFormat::SberPay);
+            assert_eq!(result.currency.as_deref(), Some("RUB"));
+            assert_eq!(result.extra.get("path").map(String::as_str), Some("invoicing/v2"));
+            assert_eq!(result.extra.get("bankinvoiceid").map(String::as_str), Some("8f0c1e2a"));
+        }
+    }
+
+    #[test]
+    fn test_emv_scheme_pix_and_promptpay() {
+        let parser = PaymentParser::new();
+        let pix_account = emv_payload(&[("00", "br.gov.bcb.pix"), ("01", "123e4567-e12b-12d1-a456-426655440000")]);
+        let pix = emv_payload(&[
+            ("00", "01"),
+            ("26", &pix_account),
+            ("52", "0000"),
+            ("53", "986"),
+            ("58", "BR"),
+            ("59", "Fulano de Tal"),
+            ("60", "Sao Paulo"),
+        ]);
+        let result = parser.parse(&with_crc(&pix)).unwrap();
+        assert_eq!(result.format, PaymentFormat::EmvQR);
+        assert_eq!(result.extra.get("scheme").map(String::as_str), Some("pix"));
+        assert_eq!(result.account.as_deref(), Some("123e4567-e12b-12d1-a456-426655440000"));
+        assert_eq!(result.currency.as_deref(), Some("BRL"));
+
+        let promptpay_account = emv_payload(&[("00", "A000000677010111"), ("01", "0066812345678")]);
+        let promptpay = emv_payload(&[("00", "01"), ("29", &promptpay_account), ("53", "764"), ("58", "TH")]);
+        let result = parser.parse(&with_crc(&promptpay)).unwrap();
+        assert_eq!(result.extra.get("scheme").map(String::as_str), Some("promptpay"));
+
+        let other = emv_with_tip(&[]);
+        assert!(!parser.parse(&with_crc(&other)).unwrap().extra.contains_key("scheme"));
+    }
+
+    #[test]
+    fn test_emv_scheme_chinese_wallets() {
+        let parser = PaymentParser::new();
+        let unionpay_account = emv_payload(&[("00", "A000000333010102"), ("01", "00042110000"), ("02", "777290058112233")]);
+        let unknown_account = emv_payload(&[("00", "cn.example.pay"), ("01", "M-88")]);
+        let unionpay = emv_payload(&[
+            ("00", "01"),
+            ("01", "12"),
+            ("26", &unknown_account),
+            ("27", &unionpay_account),
+            ("52", "5411"),
+            ("53", "156"),
+            ("58", "CN"),
+            ("59", "Lucky Mart"),
+            ("60", "Shanghai"),
+        ]);
+        let result = parser.parse(&with_crc(&unionpay)).unwrap();
+        assert_eq!(result.extra.get("scheme").map(String::as_str), Some("unionpay"));
+        assert_eq!(result.extra.get("gui_26").map(String::as_str), Some("cn.example.pay"));
+        assert!(!result.extra.contains_key("gui_27"));
+        // Реквизиты по-прежнему из шаблона с наименьшим тегом
+        assert_eq!(result.payee_id.as_deref(), Some("cn.example.pay"));
+        assert_eq!(result.currency.as_deref(), Some("CNY"));
+
+        let wallet = |gui: &str| {
+            let account = emv_payload(&[("00", gui), ("01", "2088000000000000")]);
+            let payload = emv_payload(&[("00", "01"), ("30", &account), ("53", "156"), ("58", "CN")]);
+            parser.parse(&with_crc(&payload)).unwrap().extra.get("scheme").cloned()
+        };
+        assert_eq!(wallet("com.alipay.qr").as_deref(), Some("alipay"));
+        assert_eq!(wallet("COM.TENCENT.WECHAT").as_deref(), Some("wechatpay"));
+
+        // Примитивный тег 15 без шаблонов
+        let primitive = emv_payload(&[
+            ("00", "01"),
+            ("15", "6229000012345678"),
+            ("53", "156"),
+            ("58", "CN"),
+            ("59", "Lucky Mart"),
+            ("60", "Shanghai"),
+        ]);
+        let result = parser.parse(&with_crc(&primitive)).unwrap();
+        assert_eq!(result.extra.get("scheme").map(String::as_str), Some("unionpay"));
+        assert_eq!(result.account.as_deref(), Some("6229000012345678"));
+    }
+
     #[test]
     fn test_relevance_score() {
         let parser = PaymentParser::new();
@@ -339,4 +1240,22 @@ mod tests {
         assert!(parser.relevance_score("Hello World") < 0.1);
         assert!(parser.relevance_score("Оплата заказа") > 0.5);
     }
+
+    #[test]
+    fn test_payment_format_wire_format() {
+        let variants = [
+            (PaymentFormat::EmvQR, "emv_qr"),
+            (PaymentFormat::SbpRussia, "sbp_russia"),
+            (PaymentFormat::StRussia, "st_russia"),
+            (PaymentFormat::EpcSepa, "epc_sepa"),
+            (PaymentFormat::Upi, "upi"),
+            (PaymentFormat::SberPay, "sber_pay"),
+            (PaymentFormat::Unknown, "unknown"),
+        ];
+        for (variant, name) in variants {
+            let json = serde_json::to_string(&variant).unwrap();
+            assert_eq!(json, format!("\"{}\"", name));
+            assert_eq!(serde_json::from_str::<PaymentFormat>(&json).unwrap(), variant);
+        }
+    }
 }
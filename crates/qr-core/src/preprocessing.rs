@@ -1,17 +1,95 @@
 //! Модуль предобработки изображений
 //! 
 //! Функции для улучшения качества изображения перед распознаванием QR:
-//! - Адаптивная бинаризация (отключено в V14)
-//! - Подавление шумов (отключено в V14)
-//! - Повышение контрастности (отключено в V14)
-//! - Нормализация освещения (отключено в V14)
+//! - Бинаризация: Bradley, Sauvola, Otsu, фиксированный порог
+//! - Подавление шумов: размытие по Гауссу, медианный фильтр 3x3
+//! - Повышение контрастности: растяжение гистограммы, CLAHE
+//! - Нормализация освещения делением на локальное среднее
+//! - Морфологические открытие и закрытие
+//! - Выравнивание небольшого наклона (deskew)
+//!
+//! Порядок шагов задаётся списком [`PreprocessStep`] в `ProcessingConfig::pipeline`.
 
-use image::{GrayImage, Luma};
+use image::{DynamicImage, GrayImage, Luma};
 use serde::{Deserialize, Serialize};
 use nalgebra; // Required for find_corners signature
+use crate::geometry;
 
-/// Конфигурация предобработки
+/// Способ получения grayscale из цветного изображения
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum GrayscaleMethod {
+    /// Яркость (luma) — поведение по умолчанию
+    #[default]
+    Luma,
+    /// Только красный канал
+    Red,
+    /// Только зелёный канал
+    Green,
+    /// Только синий канал
+    Blue,
+    /// Канал с максимальной дисперсией (лучшее разделение модулей и фона
+    /// для цветных QR, например тёмно-синий на белом или белый на красном)
+    BestVarianceChannel,
+}
+
+/// Метод бинаризации
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum BinarizationMethod {
+    /// Порог — среднее по окну `block_size` минус 15% (Bradley–Roth)
+    Bradley,
+    /// Локальный порог `mean * (1 + k * (std / 128 - 1))` по окну `window`.
+    /// Устойчив к теням и бликам на глянцевых чеках; типичное `k` — 0.2..0.5.
+    Sauvola { k: f32, window: u32 },
+    /// Глобальный порог Otsu
+    Otsu,
+    /// Фиксированный глобальный порог
+    Fixed(u8),
+}
+
+/// Метод повышения контрастности
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum ContrastMethod {
+    /// Глобальное растяжение гистограммы на весь диапазон 0..255
+    #[default]
+    Stretch,
+    /// CLAHE: выравнивание гистограммы по сетке `tiles` (столбцы, строки)
+    /// с ограничением `clip_limit` (кратно среднему значению бина).
+    /// Справляется с бликом и тёмным углом на одном кадре.
+    Clahe { tiles: (u32, u32), clip_limit: f32 },
+}
+
+/// Шаг конвейера предобработки
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum PreprocessStep {
+    /// Ресайз до заданной большей стороны (меньшие изображения не увеличиваются)
+    Resize(u32),
+    /// Размытие по Гауссу с заданной sigma
+    Denoise(f32),
+    /// Медианный фильтр 3x3
+    Median,
+    /// Повышение контрастности методом из `contrast` (по умолчанию `Stretch`)
+    EnhanceContrast,
+    /// Нормализация освещения
+    NormalizeLighting,
+    /// Адаптивная бинаризация (Bradley, окно `block_size`)
+    AdaptiveThreshold,
+    /// Бинаризация заданным методом
+    Binarize(BinarizationMethod),
+    /// Морфологическое открытие с радиусом структурного элемента
+    Open(u32),
+    /// Морфологическое закрытие с радиусом структурного элемента
+    Close(u32),
+    /// Инверсия яркости
+    Invert,
+    /// Выравнивание наклона по `estimate_skew`. Холст увеличивается,
+    /// координаты результатов относятся к выровненному изображению.
+    Deskew,
+}
+
+/// Конфигурация предобработки. При десериализации незаданные поля берутся
+/// из `Default`, неизвестные — ошибка.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ProcessingConfig {
     /// Включить адаптивную бинаризацию
     pub adaptive_threshold: bool,
@@ -23,6 +101,19 @@ pub struct ProcessingConfig {
     pub denoise_strength: f32,
     /// Включить повышение контрастности
     pub enhance_contrast: bool,
+    /// Способ преобразования цветного изображения в grayscale
+    pub grayscale: GrayscaleMethod,
+    /// Предел большей стороны изображения перед распознаванием (`None` — без ресайза).
+    /// Слишком малое значение разрушает мелкие модули плотных кодов (версии 20+)
+    /// и небольшие QR на фотографиях высокого разрешения.
+    pub max_dimension: Option<u32>,
+    /// Повышение контрастности в `process` перед бинаризацией (`None` — не применяется)
+    pub contrast: Option<ContrastMethod>,
+    /// Бинаризация в конце `process` (`None` — изображение передаётся в декодер в оттенках серого)
+    pub binarization: Option<BinarizationMethod>,
+    /// Явный порядок шагов `process`. `None` — [`ProcessingConfig::default_pipeline`]
+    /// из `max_dimension`, `contrast` и `binarization`.
+    pub pipeline: Option<Vec<PreprocessStep>>,
 }
 
 impl Default for ProcessingConfig {
@@ -33,10 +124,38 @@ impl Default for ProcessingConfig {
             denoise: true,
             denoise_strength: 1.0,
             enhance_contrast: true,
+            grayscale: GrayscaleMethod::Luma,
+            max_dimension: Some(1000),
+            contrast: None,
+            binarization: None,
+            pipeline: None,
         }
     }
 }
 
+impl ProcessingConfig {
+    /// Конвейер, эквивалентный отдельным полям конфигурации:
+    /// ресайз → контраст → бинаризация
+    pub fn default_pipeline(&self) -> Vec<PreprocessStep> {
+        let mut steps = Vec::new();
+        if let Some(max_dimension) = self.max_dimension {
+            steps.push(PreprocessStep::Resize(max_dimension));
+        }
+        if self.contrast.is_some() {
+            steps.push(PreprocessStep::EnhanceContrast);
+        }
+        if let Some(method) = self.binarization {
+            steps.push(PreprocessStep::Binarize(method));
+        }
+        steps
+    }
+
+    /// Шаги, которые выполняет `process`
+    pub fn steps(&self) -> Vec<PreprocessStep> {
+        self.pipeline.clone().unwrap_or_else(|| self.default_pipeline())
+    }
+}
+
 /// Процессор изображений
 pub struct ImageProcessor {
     config: ProcessingConfig,
@@ -47,44 +166,309 @@ impl ImageProcessor {
     pub fn new(config: ProcessingConfig) -> Self {
         Self { config }
     }
+
+    /// Текущая конфигурация
+    pub fn config(&self) -> &ProcessingConfig {
+        &self.config
+    }
     
-    /// Полная обработка изображения
+    /// Полная обработка изображения: шаги `config.steps()` по порядку
     pub fn process(&self, img: &GrayImage) -> GrayImage {
-        // 0. Resize if too large (improves performance and consistency)
-        let mut result = self.resize(img, 1000); // Max 1000px
-        
-        // В V14 мы полагаемся на встроенный fallback в decoding.rs
-        // Поэтому здесь просто возвращаем ресайзнутое изображение
-        // Методы оставлены для совместимости API.
-        
-        result
+        // В V14 основную работу делает встроенный fallback в decoding.rs,
+        // поэтому конвейер по умолчанию ограничивается ресайзом.
+        self.config
+            .steps()
+            .into_iter()
+            .fold(img.clone(), |result, step| self.apply_step(&result, step))
+    }
+
+    /// Выполнение одного шага конвейера
+    pub fn apply_step(&self, img: &GrayImage, step: PreprocessStep) -> GrayImage {
+        match step {
+            PreprocessStep::Resize(max_dimension) => self.resize(img, max_dimension),
+            PreprocessStep::Denoise(sigma) => gaussian_blur(img, sigma),
+            PreprocessStep::Median => self.median_denoise(img),
+            PreprocessStep::EnhanceContrast => self.enhance_contrast(img),
+            PreprocessStep::NormalizeLighting => self.normalize_lighting(img),
+            PreprocessStep::AdaptiveThreshold => self.adaptive_threshold(img),
+            PreprocessStep::Binarize(method) => self.binarize(img, method),
+            PreprocessStep::Open(radius) => self.morphology_open(img, radius),
+            PreprocessStep::Close(radius) => self.morphology_close(img, radius),
+            PreprocessStep::Invert => self.invert(img),
+            PreprocessStep::Deskew => self.deskew(img),
+        }
+    }
+    
+    /// Преобразование цветного изображения в grayscale согласно `config.grayscale`
+    pub fn to_grayscale(&self, img: &DynamicImage) -> GrayImage {
+        let channel = match self.config.grayscale {
+            GrayscaleMethod::Luma => return img.to_luma8(),
+            GrayscaleMethod::Red => 0,
+            GrayscaleMethod::Green => 1,
+            GrayscaleMethod::Blue => 2,
+            GrayscaleMethod::BestVarianceChannel => {
+                let rgb = img.to_rgb8();
+                let channel = Self::best_variance_channel(rgb.as_raw(), 3);
+                return Self::extract_channel(rgb.as_raw(), 3, channel, rgb.width(), rgb.height());
+            }
+        };
+        let rgb = img.to_rgb8();
+        Self::extract_channel(rgb.as_raw(), 3, channel, rgb.width(), rgb.height())
+    }
+
+    /// Преобразование сырого RGBA-буфера (например, canvas ImageData) в grayscale
+    /// для канальных методов. Для `Luma` возвращает `None` — вызывающий код
+    /// использует собственную формулу яркости.
+    pub fn rgba_channel_to_gray(&self, rgba: &[u8], width: u32, height: u32) -> Option<GrayImage> {
+        if rgba.len() != (width as usize) * (height as usize) * 4 {
+            return None;
+        }
+        let channel = match self.config.grayscale {
+            GrayscaleMethod::Luma => return None,
+            GrayscaleMethod::Red => 0,
+            GrayscaleMethod::Green => 1,
+            GrayscaleMethod::Blue => 2,
+            GrayscaleMethod::BestVarianceChannel => Self::best_variance_channel(rgba, 4),
+        };
+        Some(Self::extract_channel(rgba, 4, channel, width, height))
+    }
+
+    /// Индекс RGB-канала с максимальной дисперсией
+    fn best_variance_channel(data: &[u8], stride: usize) -> usize {
+        let mut sum = [0f64; 3];
+        let mut sum_sq = [0f64; 3];
+        let mut count = 0f64;
+
+        for px in data.chunks_exact(stride) {
+            for c in 0..3 {
+                let v = px[c] as f64;
+                sum[c] += v;
+                sum_sq[c] += v * v;
+            }
+            count += 1.0;
+        }
+
+        if count == 0.0 {
+            return 0;
+        }
+
+        let variance = |c: usize| {
+            let mean = sum[c] / count;
+            sum_sq[c] / count - mean * mean
+        };
+
+        (0..3)
+            .max_by(|&a, &b| variance(a).partial_cmp(&variance(b)).unwrap_or(std::cmp::Ordering::Equal))
+            .unwrap_or(0)
+    }
+
+    /// Извлечение одного канала из упакованного буфера пикселей
+    fn extract_channel(data: &[u8], stride: usize, channel: usize, width: u32, height: u32) -> GrayImage {
+        let gray: Vec<u8> = data.chunks_exact(stride).map(|px| px[channel]).collect();
+        GrayImage::from_raw(width, height, gray).expect("buffer size matches dimensions")
     }
     
-    /// Адаптивная бинаризация (Stub)
+    /// Адаптивная бинаризация (Bradley, окно `block_size`)
     pub fn adaptive_threshold(&self, img: &GrayImage) -> GrayImage {
-        img.clone()
+        self.binarize(img, BinarizationMethod::Bradley)
+    }
+
+    /// Бинаризация выбранным методом (0 — тёмный модуль, 255 — фон)
+    pub fn binarize(&self, img: &GrayImage, method: BinarizationMethod) -> GrayImage {
+        match method {
+            BinarizationMethod::Fixed(threshold) => threshold_image(img, |_, _| threshold as f32),
+            BinarizationMethod::Otsu => {
+                // Порог Otsu — последний уровень тёмного класса: пиксели с яркостью,
+                // равной порогу, тоже тёмные (иначе двухуровневое изображение целиком белое)
+                let threshold = otsu_threshold(img) as f32 + 0.5;
+                threshold_image(img, |_, _| threshold)
+            }
+            BinarizationMethod::Bradley => {
+                let integral = IntegralImage::new(img);
+                let radius = self.config.block_size / 2;
+                threshold_image(img, |x, y| integral.window(x, y, radius).0 * 0.85)
+            }
+            BinarizationMethod::Sauvola { k, window } => {
+                const DYNAMIC_RANGE: f32 = 128.0;
+                let integral = IntegralImage::new(img);
+                let radius = window / 2;
+                threshold_image(img, |x, y| {
+                    let (mean, std) = integral.window(x, y, radius);
+                    mean * (1.0 + k * (std / DYNAMIC_RANGE - 1.0))
+                })
+            }
+        }
     }
     
-    /// Подавление шумов (Stub)
+    /// Подавление шумов размытием по Гауссу (sigma — `denoise_strength`)
     pub fn denoise(&self, img: &GrayImage) -> GrayImage {
-        img.clone()
+        gaussian_blur(img, self.config.denoise_strength)
     }
     
-    /// Медианный фильтр для удаления импульсного шума (Stub)
+    /// Медианный фильтр 3x3 для удаления импульсного шума
     pub fn median_denoise(&self, img: &GrayImage) -> GrayImage {
-        img.clone()
+        let (width, height) = img.dimensions();
+        let mut result = GrayImage::new(width, height);
+        let mut window = [0u8; 9];
+        for y in 0..height {
+            for x in 0..width {
+                let mut n = 0;
+                for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                    for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                        window[n] = img.get_pixel(nx, ny).0[0];
+                        n += 1;
+                    }
+                }
+                let window = &mut window[..n];
+                window.sort_unstable();
+                result.put_pixel(x, y, Luma([window[n / 2]]));
+            }
+        }
+        result
     }
     
-    /// Повышение контрастности (Stub)
+    /// Морфологическое открытие (эрозия, затем дилатация) тёмных модулей квадратным
+    /// элементом `(2 * radius + 1)²`: убирает тёмные точки и тонкие перемычки между модулями.
+    /// Работает и на полутоновых изображениях (min/max-фильтры).
+    pub fn morphology_open(&self, img: &GrayImage, radius: u32) -> GrayImage {
+        let mut result = img.clone();
+        let mut scratch = Vec::new();
+        rank_filter(&mut result, &mut scratch, radius, Rank::Max);
+        rank_filter(&mut result, &mut scratch, radius, Rank::Min);
+        result
+    }
+
+    /// Морфологическое закрытие (дилатация, затем эрозия) тёмных модулей:
+    /// заполняет светлые точки и разрывы внутри модулей
+    pub fn morphology_close(&self, img: &GrayImage, radius: u32) -> GrayImage {
+        let mut result = img.clone();
+        let mut scratch = Vec::new();
+        rank_filter(&mut result, &mut scratch, radius, Rank::Min);
+        rank_filter(&mut result, &mut scratch, radius, Rank::Max);
+        result
+    }
+
+    /// Повышение контрастности методом из `config.contrast` (по умолчанию `Stretch`)
     pub fn enhance_contrast(&self, img: &GrayImage) -> GrayImage {
-        img.clone()
+        self.apply_contrast(img, self.config.contrast.unwrap_or_default())
+    }
+
+    fn apply_contrast(&self, img: &GrayImage, method: ContrastMethod) -> GrayImage {
+        match method {
+            ContrastMethod::Stretch => stretch_contrast(img),
+            ContrastMethod::Clahe { tiles, clip_limit } => self.clahe(img, tiles, clip_limit),
+        }
+    }
+
+    /// CLAHE: эквализация гистограммы по тайлам с ограничением `clip_limit`
+    /// и билинейной интерполяцией между соседними тайлами
+    pub fn clahe(&self, img: &GrayImage, tiles: (u32, u32), clip_limit: f32) -> GrayImage {
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return img.clone();
+        }
+        let tiles_x = tiles.0.clamp(1, width);
+        let tiles_y = tiles.1.clamp(1, height);
+        let tile_w = width.div_ceil(tiles_x);
+        let tile_h = height.div_ceil(tiles_y);
+        // При округлении вверх последние тайлы могут оказаться пустыми
+        let tiles_x = width.div_ceil(tile_w);
+        let tiles_y = height.div_ceil(tile_h);
+
+        let luts: Vec<[u8; 256]> = (0..tiles_y)
+            .flat_map(|ty| (0..tiles_x).map(move |tx| (tx, ty)))
+            .map(|(tx, ty)| {
+                let x0 = tx * tile_w;
+                let y0 = ty * tile_h;
+                let x1 = (x0 + tile_w).min(width);
+                let y1 = (y0 + tile_h).min(height);
+                let mut histogram = [0u32; 256];
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        histogram[img.get_pixel(x, y).0[0] as usize] += 1;
+                    }
+                }
+                clipped_equalization(&histogram, (x1 - x0) * (y1 - y0), clip_limit)
+            })
+            .collect();
+
+        // Координата пикселя в сетке центров тайлов: (индекс левого тайла, вес правого)
+        let grid = |pos: u32, size: u32, count: u32| {
+            let f = ((pos as f32 + 0.5) / size as f32 - 0.5).max(0.0);
+            let i = (f as u32).min(count - 1);
+            let j = (i + 1).min(count - 1);
+            (i, j, (f - i as f32).min(1.0))
+        };
+
+        let mut result = GrayImage::new(width, height);
+        for y in 0..height {
+            let (ty0, ty1, wy) = grid(y, tile_h, tiles_y);
+            for x in 0..width {
+                let (tx0, tx1, wx) = grid(x, tile_w, tiles_x);
+                let v = img.get_pixel(x, y).0[0] as usize;
+                let lut = |tx: u32, ty: u32| luts[(ty * tiles_x + tx) as usize][v] as f32;
+                let top = lut(tx0, ty0) * (1.0 - wx) + lut(tx1, ty0) * wx;
+                let bottom = lut(tx0, ty1) * (1.0 - wx) + lut(tx1, ty1) * wx;
+                let value = top * (1.0 - wy) + bottom * wy;
+                result.put_pixel(x, y, Luma([value.round() as u8]));
+            }
+        }
+        result
     }
     
-    /// Нормализация освещения через локальное выравнивание (Stub)
+    /// Нормализация освещения: деление на среднее по окну `block_size`,
+    /// фон приводится к одной яркости независимо от теней
     pub fn normalize_lighting(&self, img: &GrayImage) -> GrayImage {
-        img.clone()
+        let integral = IntegralImage::new(img);
+        let radius = self.config.block_size / 2;
+        let mut result = GrayImage::new(img.width(), img.height());
+        for (x, y, p) in img.enumerate_pixels() {
+            let mean = integral.window(x, y, radius).0.max(1.0);
+            let value = (p.0[0] as f32 * 192.0 / mean).min(255.0);
+            result.put_pixel(x, y, Luma([value as u8]));
+        }
+        result
     }
     
+    /// Оценка наклона содержимого в градусах (-45..45, положительный — по часовой стрелке).
+    /// Края модулей QR параллельны двум перпендикулярным осям, поэтому направления
+    /// градиентов Собеля усредняются по модулю 90° (круговое среднее учетверённых углов).
+    pub fn estimate_skew(&self, img: &GrayImage) -> f32 {
+        const MIN_MAGNITUDE: f32 = 64.0;
+
+        let (width, height) = img.dimensions();
+        let px = |x: u32, y: u32| img.get_pixel(x, y).0[0] as f32;
+        let (mut sum_cos, mut sum_sin) = (0f64, 0f64);
+        for y in 1..height.saturating_sub(1) {
+            for x in 1..width.saturating_sub(1) {
+                let gx = (px(x + 1, y - 1) + 2.0 * px(x + 1, y) + px(x + 1, y + 1))
+                    - (px(x - 1, y - 1) + 2.0 * px(x - 1, y) + px(x - 1, y + 1));
+                let gy = (px(x - 1, y + 1) + 2.0 * px(x, y + 1) + px(x + 1, y + 1))
+                    - (px(x - 1, y - 1) + 2.0 * px(x, y - 1) + px(x + 1, y - 1));
+                let magnitude = gx.hypot(gy);
+                if magnitude < MIN_MAGNITUDE {
+                    continue;
+                }
+                let (sin, cos) = (4.0 * gy.atan2(gx) as f64).sin_cos();
+                sum_cos += magnitude as f64 * cos;
+                sum_sin += magnitude as f64 * sin;
+            }
+        }
+        if sum_cos == 0.0 && sum_sin == 0.0 {
+            return 0.0;
+        }
+        (sum_sin.atan2(sum_cos) / 4.0).to_degrees() as f32
+    }
+
+    /// Поворот на угол, обратный `estimate_skew` (наклон меньше 0.5° не исправляется)
+    pub fn deskew(&self, img: &GrayImage) -> GrayImage {
+        let skew = self.estimate_skew(img);
+        if skew.abs() < 0.5 {
+            return img.clone();
+        }
+        geometry::rotate_image(img, -skew)
+    }
+
     /// Инвертирование изображения (для QR с инвертированными цветами)
     pub fn invert(&self, img: &GrayImage) -> GrayImage {
         let (width, height) = img.dimensions();
@@ -114,8 +498,8 @@ impl ImageProcessor {
             max_dimension as f32 / height as f32
         };
         
-        let new_width = (width as f32 * scale) as u32;
-        let new_height = (height as f32 * scale) as u32;
+        let new_width = ((width as f32 * scale) as u32).max(1);
+        let new_height = ((height as f32 * scale) as u32).max(1);
         
         image::imageops::resize(
             img,
@@ -125,11 +509,411 @@ impl ImageProcessor {
         )
     }
 
+    /// Перцептивный average hash (8x8) для сравнения соседних кадров
+    pub fn average_hash(&self, img: &GrayImage) -> u64 {
+        if img.width() == 0 || img.height() == 0 {
+            return 0;
+        }
+
+        let small = image::imageops::resize(img, 8, 8, image::imageops::FilterType::Triangle);
+        let mean = small.pixels().map(|p| p.0[0] as u32).sum::<u32>() / 64;
+
+        small
+            .pixels()
+            .enumerate()
+            .filter(|(_, p)| p.0[0] as u32 > mean)
+            .fold(0u64, |hash, (i, _)| hash | (1 << i))
+    }
+
     /// Find corners of the QR code within the image (or ROI)
     /// Returns 4 points [TL, TR, BR, BL] if a valid quad is found.
-    pub fn find_corners(&self, _img: &GrayImage) -> Option<[nalgebra::Point2<f32>; 4]> {
-        // Disabled in V14 due to dependency removal
-        None
+    /// Грубые вершины контура уточняются до субпикселя (`refine_corners`).
+    pub fn find_corners(&self, img: &GrayImage) -> Option<[nalgebra::Point2<f32>; 4]> {
+        self.coarse_corners(img).map(|corners| self.refine_corners(img, corners))
+    }
+
+    /// Грубые вершины: выпуклая оболочка тёмных пикселей и вписанный в неё
+    /// четырёхугольник наибольшей площади. Тёмные пиксели — по `binarization`
+    /// из конфигурации (по умолчанию Otsu). `None`, если оболочка заметно
+    /// отличается от четырёхугольника или занимает меньше 10% изображения.
+    fn coarse_corners(&self, img: &GrayImage) -> Option<[nalgebra::Point2<f32>; 4]> {
+        let binary = self.binarize(img, self.config.binarization.unwrap_or(BinarizationMethod::Otsu));
+        // Для оболочки достаточно крайних тёмных пикселей каждой строки
+        let mut extremes = Vec::new();
+        for (y, row) in binary.rows().enumerate() {
+            let mut dark = row.enumerate().filter(|(_, p)| p.0[0] == 0).map(|(x, _)| x);
+            if let Some(first) = dark.next() {
+                let last = dark.next_back().unwrap_or(first);
+                extremes.push(nalgebra::Point2::new(first as f32, y as f32));
+                extremes.push(nalgebra::Point2::new(last as f32, y as f32));
+            }
+        }
+
+        let hull = geometry::convex_hull(&extremes);
+        let (quad, area) = geometry::largest_quad(&hull)?;
+        if area < 0.9 * geometry::polygon_area(&hull) || area < 0.1 * (img.width() * img.height()) as f32 {
+            return None;
+        }
+
+        // Оболочка обходится по часовой стрелке; первой ставим вершину с минимальной x + y
+        let start = (0..4).min_by(|&i, &j| (quad[i].x + quad[i].y).total_cmp(&(quad[j].x + quad[j].y)))?;
+        Some(std::array::from_fn(|i| quad[(start + i) % 4]))
+    }
+
+    /// Субпиксельное уточнение вершин четырёхугольника: вдоль каждой стороны по нормали
+    /// ищется переход тёмное→светлое (пересечение уровня 50%), через найденные точки
+    /// проводится прямая (полные наименьшие квадраты), углы — пересечения соседних прямых.
+    /// Вершина, для которой прямые не найдены, остаётся без изменений.
+    pub fn refine_corners(&self, img: &GrayImage, corners: [nalgebra::Point2<f32>; 4]) -> [nalgebra::Point2<f32>; 4] {
+        let center = corners.iter().fold(nalgebra::Vector2::zeros(), |acc, c| acc + c.coords) / 4.0;
+        let lines: Vec<Option<EdgeLine>> = (0..4)
+            .map(|i| fit_edge(img, corners[i], corners[(i + 1) % 4], center))
+            .collect();
+
+        let mut refined = corners;
+        for (i, corner) in refined.iter_mut().enumerate() {
+            // Вершина i — пересечение стороны (i-1, i) и стороны (i, i+1)
+            if let (Some(prev), Some(next)) = (&lines[(i + 3) % 4], &lines[i]) {
+                if let Some(p) = prev.intersect(next) {
+                    if (p - *corner).norm() < 4.0 {
+                        *corner = p;
+                    }
+                }
+            }
+        }
+        refined
+    }
+}
+
+/// Прямая `normal · p = offset` (единичная нормаль)
+struct EdgeLine {
+    normal: nalgebra::Vector2<f32>,
+    offset: f32,
+}
+
+impl EdgeLine {
+    fn intersect(&self, other: &EdgeLine) -> Option<nalgebra::Point2<f32>> {
+        let m = nalgebra::Matrix2::new(self.normal.x, self.normal.y, other.normal.x, other.normal.y);
+        let p = m.try_inverse()? * nalgebra::Vector2::new(self.offset, other.offset);
+        Some(nalgebra::Point2::from(p))
+    }
+}
+
+/// Точки перехода вдоль стороны `a → b` и прямая через них
+fn fit_edge(
+    img: &GrayImage,
+    a: nalgebra::Point2<f32>,
+    b: nalgebra::Point2<f32>,
+    center: nalgebra::Vector2<f32>,
+) -> Option<EdgeLine> {
+    const SEARCH: f32 = 3.0;
+    const STEP: f32 = 0.25;
+    const MIN_CONTRAST: f32 = 40.0;
+
+    let along = b - a;
+    let length = along.norm();
+    if length < 4.0 {
+        return None;
+    }
+    let dir = along / length;
+    // Нормаль наружу от центра четырёхугольника
+    let mut normal = nalgebra::Vector2::new(dir.y, -dir.x);
+    if normal.dot(&(a.coords - center)) < 0.0 {
+        normal = -normal;
+    }
+
+    // Для билинейной интерполяции нужно хотя бы 2x2 пикселя
+    if img.width() < 2 || img.height() < 2 {
+        return None;
+    }
+
+    let sample = |p: nalgebra::Vector2<f32>| {
+        let (x, y) = (p.x, p.y);
+        if x < 0.0 || y < 0.0 || x >= (img.width() - 1) as f32 || y >= (img.height() - 1) as f32 {
+            return None;
+        }
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let px = |dx: u32, dy: u32| img.get_pixel(x0 + dx, y0 + dy).0[0] as f32;
+        let top = px(0, 0) * (1.0 - fx) + px(1, 0) * fx;
+        let bottom = px(0, 1) * (1.0 - fx) + px(1, 1) * fx;
+        Some(top * (1.0 - fy) + bottom * fy)
+    };
+
+    // Концы сторон пропускаем: у вершин профиль искажён соседней стороной
+    let steps = (length as usize).max(2);
+    let mut points = Vec::new();
+    for k in 0..=steps {
+        let t = 0.15 + 0.7 * k as f32 / steps as f32;
+        let base = a.coords + along * t;
+        let profile: Option<Vec<f32>> = (0..=((2.0 * SEARCH / STEP) as usize))
+            .map(|j| sample(base + normal * (SEARCH - j as f32 * STEP)))
+            .collect();
+        let Some(profile) = profile else {
+            continue;
+        };
+        let (min, max) = profile.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+        if max - min < MIN_CONTRAST {
+            continue;
+        }
+        // Снаружи внутрь: первое пересечение середины между фоном и модулем
+        let mid = (min + max) / 2.0;
+        if let Some(j) = (1..profile.len()).find(|&j| profile[j - 1] >= mid && profile[j] < mid) {
+            let frac = (profile[j - 1] - mid) / (profile[j - 1] - profile[j]);
+            let offset = SEARCH - (j as f32 - 1.0 + frac) * STEP;
+            points.push(base + normal * offset);
+        }
+    }
+    if points.len() < 3 {
+        return None;
+    }
+
+    // Полные наименьшие квадраты: нормаль — собственный вектор ковариации
+    // с наименьшим собственным значением
+    let mean = points.iter().fold(nalgebra::Vector2::zeros(), |acc, p| acc + p) / points.len() as f32;
+    let cov = points.iter().fold(nalgebra::Matrix2::zeros(), |acc, p| {
+        let d = p - mean;
+        acc + d * d.transpose()
+    });
+    let eigen = cov.symmetric_eigen();
+    let line_normal = eigen.eigenvectors.column(eigen.eigenvalues.imin()).into_owned();
+    Some(EdgeLine {
+        normal: line_normal,
+        offset: line_normal.dot(&mean),
+    })
+}
+
+/// Вычисление порога по методу Otsu (минимизация внутриклассовой дисперсии)
+pub(crate) fn otsu_threshold(img: &GrayImage) -> u8 {
+    otsu_from_histogram(&histogram(img))
+}
+
+/// Гистограмма яркости
+pub(crate) fn histogram<C: std::ops::Deref<Target = [u8]>>(img: &image::ImageBuffer<Luma<u8>, C>) -> [u32; 256] {
+    let mut histogram = [0u32; 256];
+    for &value in img.as_raw().iter() {
+        histogram[value as usize] += 1;
+    }
+    histogram
+}
+
+/// Порог Otsu по готовой гистограмме
+pub(crate) fn otsu_from_histogram(histogram: &[u32; 256]) -> u8 {
+    let total_pixels: f64 = histogram.iter().map(|&count| count as f64).sum();
+
+    let mut sum: f64 = 0.0;
+    for (i, &count) in histogram.iter().enumerate() {
+        sum += i as f64 * count as f64;
+    }
+
+    let mut sum_b: f64 = 0.0;
+    let mut w_b: f64 = 0.0;
+    let mut max_variance: f64 = 0.0;
+    let mut threshold: u8 = 128; // Default fallback
+
+    for (t, &count) in histogram.iter().enumerate() {
+        w_b += count as f64;
+        if w_b == 0.0 { continue; }
+        
+        let w_f = total_pixels - w_b;
+        if w_f == 0.0 { break; }
+
+        sum_b += t as f64 * count as f64;
+        
+        let m_b = sum_b / w_b;
+        let m_f = (sum - sum_b) / w_f;
+        
+        let variance = w_b * w_f * (m_b - m_f) * (m_b - m_f);
+        
+        if variance > max_variance {
+            max_variance = variance;
+            threshold = t as u8;
+        }
+    }
+    
+    threshold
+}
+
+/// Min- или max-фильтр
+#[derive(Clone, Copy)]
+enum Rank {
+    Min,
+    Max,
+}
+
+/// Сепарабельный min/max-фильтр с квадратным окном радиуса `radius` на месте.
+/// Оба прохода идут по строкам: вертикальный — по транспонированному изображению.
+fn rank_filter(img: &mut GrayImage, scratch: &mut Vec<u8>, radius: u32, rank: Rank) {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    if radius == 0 || width == 0 || height == 0 {
+        return;
+    }
+    let r = radius as usize;
+    let data: &mut [u8] = img;
+    scratch.resize(data.len(), 0);
+
+    filter_rows(data, scratch, width, r, rank);
+    transpose(scratch, data, width, height);
+    filter_rows(data, scratch, height, r, rank);
+    transpose(scratch, data, height, width);
+}
+
+/// Min/max по окну `[x - r, x + r]` в каждой строке скользящей гистограммой:
+/// на пиксель одно добавление и одно удаление, экстремум сдвигается,
+/// только когда пустеет его уровень
+fn filter_rows(src: &[u8], dst: &mut [u8], width: usize, r: usize, rank: Rank) {
+    let pick = |a: u8, b: u8| match rank {
+        Rank::Min => a.min(b),
+        Rank::Max => a.max(b),
+    };
+    for (src, dst) in src.chunks_exact(width).zip(dst.chunks_exact_mut(width)) {
+        let mut counts = [0u32; 256];
+        let mut extreme = src[0];
+        for &value in &src[..r.min(width)] {
+            counts[value as usize] += 1;
+            extreme = pick(extreme, value);
+        }
+        for (x, out) in dst.iter_mut().enumerate() {
+            if let Some(&value) = src.get(x + r) {
+                counts[value as usize] += 1;
+                extreme = pick(extreme, value);
+            }
+            if let Some(leaving) = x.checked_sub(r + 1) {
+                counts[src[leaving] as usize] -= 1;
+                // В окне остаётся src[x], поэтому поиск всегда останавливается
+                while counts[extreme as usize] == 0 {
+                    extreme = match rank {
+                        Rank::Min => extreme + 1,
+                        Rank::Max => extreme - 1,
+                    };
+                }
+            }
+            *out = extreme;
+        }
+    }
+}
+
+/// Транспонирование изображения `width` x `height` из `src` в `dst`
+fn transpose(src: &[u8], dst: &mut [u8], width: usize, height: usize) {
+    for (y, row) in src.chunks_exact(width).enumerate() {
+        for (x, &value) in row.iter().enumerate() {
+            dst[x * height + y] = value;
+        }
+    }
+}
+
+/// Размытие по Гауссу (`sigma <= 0` — без изменений)
+fn gaussian_blur(img: &GrayImage, sigma: f32) -> GrayImage {
+    if sigma > 0.0 {
+        image::imageops::blur(img, sigma)
+    } else {
+        img.clone()
+    }
+}
+
+/// Линейное растяжение диапазона яркостей на 0..255
+fn stretch_contrast(img: &GrayImage) -> GrayImage {
+    let (min, max) = img
+        .pixels()
+        .fold((255u8, 0u8), |(min, max), p| (min.min(p.0[0]), max.max(p.0[0])));
+    if max <= min {
+        return img.clone();
+    }
+    let scale = 255.0 / (max - min) as f32;
+    let mut result = img.clone();
+    for p in result.pixels_mut() {
+        p.0[0] = ((p.0[0] - min) as f32 * scale).round() as u8;
+    }
+    result
+}
+
+/// Таблица эквализации гистограммы тайла: бины обрезаются по `clip_limit`
+/// (кратно среднему заполнению), излишек равномерно перераспределяется
+fn clipped_equalization(histogram: &[u32; 256], pixels: u32, clip_limit: f32) -> [u8; 256] {
+    let mut histogram = *histogram;
+    if clip_limit > 0.0 {
+        let limit = ((clip_limit * pixels as f32 / 256.0) as u32).max(1);
+        let mut excess = 0;
+        for count in histogram.iter_mut() {
+            if *count > limit {
+                excess += *count - limit;
+                *count = limit;
+            }
+        }
+        let (share, remainder) = (excess / 256, excess % 256);
+        for (i, count) in histogram.iter_mut().enumerate() {
+            *count += share + u32::from((i as u32) < remainder);
+        }
+    }
+
+    let mut lut = [0u8; 256];
+    let mut cdf = 0u32;
+    for (value, &count) in lut.iter_mut().zip(histogram.iter()) {
+        cdf += count;
+        *value = (cdf as u64 * 255 / pixels.max(1) as u64) as u8;
+    }
+    lut
+}
+
+/// Бинаризация по среднему яркости в окне `window` вокруг пикселя
+pub(crate) fn local_mean_binarize(img: &GrayImage, window: u32) -> GrayImage {
+    let integral = IntegralImage::new(img);
+    threshold_image(img, |x, y| integral.window(x, y, window / 2).0)
+}
+
+/// Попиксельная бинаризация по порогу `threshold(x, y)`
+fn threshold_image(img: &GrayImage, threshold: impl Fn(u32, u32) -> f32) -> GrayImage {
+    let mut result = GrayImage::new(img.width(), img.height());
+    for (x, y, p) in img.enumerate_pixels() {
+        let value = if (p.0[0] as f32) < threshold(x, y) { 0 } else { 255 };
+        result.put_pixel(x, y, Luma([value]));
+    }
+    result
+}
+
+/// Интегральные изображения суммы и суммы квадратов для статистики по окну за O(1)
+struct IntegralImage {
+    width: u32,
+    height: u32,
+    sum: Vec<u64>,
+    sum_sq: Vec<u64>,
+}
+
+impl IntegralImage {
+    fn new(img: &GrayImage) -> Self {
+        let (width, height) = img.dimensions();
+        let stride = width as usize + 1;
+        let mut sum = vec![0u64; stride * (height as usize + 1)];
+        let mut sum_sq = vec![0u64; sum.len()];
+        for y in 0..height as usize {
+            let (mut row, mut row_sq) = (0u64, 0u64);
+            for x in 0..width as usize {
+                let v = img.as_raw()[y * width as usize + x] as u64;
+                row += v;
+                row_sq += v * v;
+                sum[(y + 1) * stride + x + 1] = sum[y * stride + x + 1] + row;
+                sum_sq[(y + 1) * stride + x + 1] = sum_sq[y * stride + x + 1] + row_sq;
+            }
+        }
+        Self { width, height, sum, sum_sq }
+    }
+
+    /// Среднее и стандартное отклонение в окне радиуса `radius` вокруг (x, y),
+    /// обрезанном по границам изображения
+    fn window(&self, x: u32, y: u32, radius: u32) -> (f32, f32) {
+        let stride = self.width as usize + 1;
+        let (x0, y0) = (x.saturating_sub(radius) as usize, y.saturating_sub(radius) as usize);
+        let x1 = (x + radius + 1).min(self.width) as usize;
+        let y1 = (y + radius + 1).min(self.height) as usize;
+        let area = ((x1 - x0) * (y1 - y0)) as f64;
+
+        let rect = |table: &[u64]| {
+            (table[y1 * stride + x1] + table[y0 * stride + x0]) as f64
+                - (table[y0 * stride + x1] + table[y1 * stride + x0]) as f64
+        };
+        let mean = rect(&self.sum) / area;
+        let variance = (rect(&self.sum_sq) / area - mean * mean).max(0.0);
+        (mean as f32, variance.sqrt() as f32)
     }
 }
 
@@ -143,6 +927,339 @@ mod tests {
         let _processor = ImageProcessor::new(config);
     }
     
+    #[test]
+    fn test_best_variance_channel() {
+        // Белые модули на красном фоне: в зелёном канале контраст максимален
+        let mut img = image::RgbImage::from_pixel(10, 10, image::Rgb([200, 0, 0]));
+        for x in 0..5 {
+            img.put_pixel(x, 0, image::Rgb([255, 255, 255]));
+        }
+        let processor = ImageProcessor::new(ProcessingConfig {
+            grayscale: GrayscaleMethod::BestVarianceChannel,
+            ..Default::default()
+        });
+        let gray = processor.to_grayscale(&DynamicImage::ImageRgb8(img));
+        assert_eq!(gray.get_pixel(0, 0).0[0], 255);
+        assert_eq!(gray.get_pixel(9, 9).0[0], 0);
+    }
+
+    #[test]
+    fn test_rgba_channel_to_gray() {
+        let rgba = [10, 20, 30, 255, 40, 50, 60, 255];
+        let processor = ImageProcessor::new(ProcessingConfig {
+            grayscale: GrayscaleMethod::Blue,
+            ..Default::default()
+        });
+        let gray = processor.rgba_channel_to_gray(&rgba, 2, 1).unwrap();
+        assert_eq!(gray.as_raw(), &vec![30, 60]);
+
+        let luma = ImageProcessor::new(ProcessingConfig::default());
+        assert!(luma.rgba_channel_to_gray(&rgba, 2, 1).is_none());
+    }
+    
+    #[test]
+    fn test_average_hash() {
+        let processor = ImageProcessor::new(ProcessingConfig::default());
+        let mut img = GrayImage::from_fn(64, 64, |x, _| Luma([if x < 32 { 0 } else { 255 }]));
+        let hash = processor.average_hash(&img);
+
+        img.put_pixel(10, 10, Luma([255]));
+        assert!((hash ^ processor.average_hash(&img)).count_ones() <= 1);
+
+        let flipped = GrayImage::from_fn(64, 64, |x, _| Luma([if x < 32 { 255 } else { 0 }]));
+        assert!((hash ^ processor.average_hash(&flipped)).count_ones() > 32);
+    }
+    
+    #[test]
+    fn test_otsu_threshold_level_is_dark() {
+        let img = GrayImage::from_fn(8, 8, |x, _| Luma([if x < 3 { 40 } else { 200 }]));
+        assert_eq!(otsu_threshold(&img), 40);
+
+        let processor = ImageProcessor::new(ProcessingConfig::default());
+        let binary = processor.binarize(&img, BinarizationMethod::Otsu);
+        assert!(binary.enumerate_pixels().all(|(x, _, p)| p.0[0] == if x < 3 { 0 } else { 255 }));
+    }
+
+    #[test]
+    fn test_sauvola_on_gradient_background() {
+        // QR под тенью: освещённость падает слева направо с 1.0 до 0.2
+        let qr = qrcode::QrCode::new("https://example.com/shadow").unwrap();
+        let clean = crate::testing::render_code(&qr, 6, 4);
+        let side = clean.width();
+        let dark = |x: u32, y: u32| clean.get_pixel(x, y).0[0] == 0;
+        let img = GrayImage::from_fn(side, side, |x, y| {
+            let light = 1.0 - 0.8 * x as f32 / side as f32;
+            let base = if dark(x, y) { 30.0 } else { 230.0 };
+            Luma([(base * light) as u8])
+        });
+
+        let processor = ImageProcessor::new(ProcessingConfig::default());
+        let errors = |binary: &GrayImage| {
+            binary
+                .enumerate_pixels()
+                .filter(|(x, y, p)| (p.0[0] == 0) != dark(*x, *y))
+                .count()
+        };
+
+        let sauvola = processor.binarize(&img, BinarizationMethod::Sauvola { k: 0.2, window: 31 });
+        let fixed = processor.binarize(&img, BinarizationMethod::Fixed(128));
+        let otsu = processor.binarize(&img, BinarizationMethod::Otsu);
+        assert!(errors(&sauvola) * 100 < errors(&fixed));
+        assert!(errors(&sauvola) * 100 < errors(&otsu));
+
+        let decoder = crate::QRDecoder::with_config(crate::DecoderConfig {
+            strategy_order: vec![crate::DecodeStrategy::Standard],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(decoder.decode(&sauvola).unwrap().content, "https://example.com/shadow");
+
+        let processor = ImageProcessor::new(ProcessingConfig {
+            binarization: Some(BinarizationMethod::Sauvola { k: 0.2, window: 31 }),
+            ..Default::default()
+        });
+        assert_eq!(processor.process(&img), sauvola);
+    }
+
+    #[test]
+    fn test_clahe_local_contrast() {
+        // Левая половина — тёмная и низкоконтрастная (20/40), правая — светлая (200/230)
+        let img = GrayImage::from_fn(128, 64, |x, y| {
+            let stripe = (x / 4 + y / 4) % 2 == 0;
+            Luma([match (x < 64, stripe) {
+                (true, true) => 20,
+                (true, false) => 40,
+                (false, true) => 200,
+                (false, false) => 230,
+            }])
+        });
+        let processor = ImageProcessor::new(ProcessingConfig::default());
+        let spread = |out: &GrayImage| out.get_pixel(8, 4).0[0] as i32 - out.get_pixel(4, 4).0[0] as i32;
+
+        let stretched = processor.enhance_contrast(&img);
+        assert_eq!(stretched.get_pixel(0, 0).0[0], 0);
+        assert_eq!(stretched.get_pixel(64, 4).0[0], 255);
+
+        let equalized = processor.clahe(&img, (4, 2), 0.0);
+        assert!(spread(&equalized) > 2 * spread(&stretched));
+        // Ограничение клиппинга сдерживает усиление, но не опускает его ниже глобального
+        let clipped = processor.clahe(&img, (4, 2), 4.0);
+        assert!(spread(&clipped) >= spread(&stretched) && spread(&clipped) < spread(&equalized));
+
+        // Однородное изображение и вырожденная сетка не паникуют
+        let flat = GrayImage::from_pixel(5, 3, Luma([77]));
+        assert_eq!(processor.clahe(&flat, (16, 16), 2.0).dimensions(), (5, 3));
+        assert_eq!(processor.enhance_contrast(&flat), flat);
+    }
+
+    #[test]
+    fn test_pipeline_steps() {
+        let img = GrayImage::from_fn(40, 40, |x, y| Luma([if (x / 5 + y / 5) % 2 == 0 { 60 } else { 180 }]));
+
+        // Конвейер по умолчанию повторяет отдельные поля конфигурации
+        let config = ProcessingConfig {
+            max_dimension: Some(20),
+            binarization: Some(BinarizationMethod::Otsu),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.default_pipeline(),
+            vec![PreprocessStep::Resize(20), PreprocessStep::Binarize(BinarizationMethod::Otsu)]
+        );
+        let processor = ImageProcessor::new(config);
+        let expected = processor.binarize(&processor.resize(&img, 20), BinarizationMethod::Otsu);
+        assert_eq!(processor.process(&img), expected);
+
+        // Порядок шагов имеет значение: порог 70 до инверсии отделяет модули 60/180,
+        // после инверсии (195/75) всё изображение выше порога
+        let run = |steps: Vec<PreprocessStep>| {
+            ImageProcessor::new(ProcessingConfig {
+                pipeline: Some(steps),
+                ..Default::default()
+            })
+            .process(&img)
+        };
+        let binarize = PreprocessStep::Binarize(BinarizationMethod::Fixed(70));
+        let binarized_first = run(vec![binarize, PreprocessStep::Invert]);
+        assert_eq!(binarized_first.get_pixel(0, 0).0[0], 255);
+        assert_eq!(binarized_first.get_pixel(5, 0).0[0], 0);
+        let inverted_first = run(vec![PreprocessStep::Invert, binarize]);
+        assert!(inverted_first.pixels().all(|p| p.0[0] == 255));
+        assert_eq!(run(vec![]), img);
+
+        let json = r#"{"adaptive_threshold":true,"block_size":51,"denoise":false,"denoise_strength":1.0,
+            "enhance_contrast":false,"pipeline":["Median",{"Denoise":1.5},"NormalizeLighting","AdaptiveThreshold"]}"#;
+        let config: ProcessingConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.steps()[1], PreprocessStep::Denoise(1.5));
+        assert_eq!(ImageProcessor::new(config).process(&img).dimensions(), (40, 40));
+    }
+
+    #[test]
+    fn test_median_removes_impulse_noise() {
+        let mut img = GrayImage::from_pixel(9, 9, Luma([200]));
+        img.put_pixel(4, 4, Luma([0]));
+        img.put_pixel(0, 0, Luma([0]));
+        let processor = ImageProcessor::new(ProcessingConfig::default());
+        let filtered = processor.median_denoise(&img);
+        assert!(filtered.pixels().all(|p| p.0[0] == 200));
+    }
+
+    #[test]
+    fn test_rank_filter_matches_naive_window() {
+        // Псевдослучайная картинка нечётного размера; радиус и больше, и меньше сторон
+        let img = GrayImage::from_fn(13, 7, |x, y| Luma([((x * 37 + y * 101 + x * y * 13) % 251) as u8]));
+        for radius in [1u32, 2, 5, 20] {
+            for rank in [Rank::Min, Rank::Max] {
+                let mut filtered = img.clone();
+                rank_filter(&mut filtered, &mut Vec::new(), radius, rank);
+                let r = radius as i64;
+                for (x, y, p) in filtered.enumerate_pixels() {
+                    let window = (y as i64 - r..=y as i64 + r)
+                        .flat_map(|ny| (x as i64 - r..=x as i64 + r).map(move |nx| (nx, ny)))
+                        .filter(|&(nx, ny)| nx >= 0 && ny >= 0 && nx < 13 && ny < 7)
+                        .map(|(nx, ny)| img.get_pixel(nx as u32, ny as u32).0[0]);
+                    let expected = match rank {
+                        Rank::Min => window.min(),
+                        Rank::Max => window.max(),
+                    };
+                    assert_eq!(Some(p.0[0]), expected, "({}, {}) r={}", x, y, radius);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_morphology_open_close() {
+        // Тёмный модуль 8x8 с тонкой перемычкой, одиночной тёмной точкой и светлой дыркой
+        let mut img = GrayImage::from_pixel(24, 14, Luma([255]));
+        for y in 3..11 {
+            for x in 2..10 {
+                img.put_pixel(x, y, Luma([0]));
+            }
+        }
+        for x in 10..16 {
+            img.put_pixel(x, 6, Luma([0]));
+        }
+        img.put_pixel(21, 1, Luma([0]));
+        img.put_pixel(6, 7, Luma([255]));
+
+        let processor = ImageProcessor::new(ProcessingConfig::default());
+        let opened = processor.morphology_open(&img, 1);
+        assert_eq!(opened.get_pixel(21, 1).0[0], 255);
+        assert!((10..16).all(|x| opened.get_pixel(x, 6).0[0] == 255));
+        assert_eq!(opened.get_pixel(2, 3).0[0], 0);
+        assert_eq!(opened.get_pixel(9, 10).0[0], 0);
+
+        let closed = processor.morphology_close(&img, 1);
+        assert_eq!(closed.get_pixel(6, 7).0[0], 0);
+        assert_eq!(closed.get_pixel(1, 3).0[0], 255);
+        assert_eq!(closed.get_pixel(21, 1).0[0], 0);
+
+        assert_eq!(processor.morphology_open(&img, 0), img);
+        assert_eq!(processor.apply_step(&img, PreprocessStep::Close(1)), closed);
+    }
+
+    #[test]
+    fn test_estimate_skew_sign() {
+        let img = GrayImage::from_fn(120, 120, |x, y| {
+            let inside = (20..100).contains(&x) && (20..100).contains(&y);
+            Luma([if inside && (x / 10 + y / 10) % 2 == 0 { 0 } else { 255 }])
+        });
+        let processor = ImageProcessor::new(ProcessingConfig::default());
+        assert!(processor.estimate_skew(&img).abs() < 0.1);
+        assert_eq!(processor.deskew(&img), img);
+
+        for angle in [-7.0, 4.0, 25.0] {
+            let rotated = geometry::rotate_image(&img, angle);
+            let skew = processor.estimate_skew(&rotated);
+            assert!((skew - angle).abs() < 1.0, "{} vs {}", skew, angle);
+        }
+        assert_eq!(processor.estimate_skew(&GrayImage::from_pixel(8, 8, Luma([90]))), 0.0);
+    }
+
+    #[test]
+    fn test_refine_corners_subpixel() {
+        use nalgebra::Point2;
+
+        // Тёмный четырёхугольник со сглаженными краями (суперсэмплинг 8x8 вокруг
+        // центра пикселя; значение пикселя относится к его целочисленной координате)
+        let truth = [
+            Point2::new(20.3, 18.7),
+            Point2::new(101.6, 24.2),
+            Point2::new(95.8, 104.4),
+            Point2::new(16.1, 97.9),
+        ];
+        let inside = |x: f32, y: f32| {
+            (0..4).all(|i| {
+                let (a, b) = (truth[i], truth[(i + 1) % 4]);
+                (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x) >= 0.0
+            })
+        };
+        let img = GrayImage::from_fn(120, 120, |x, y| {
+            let covered = (0..64)
+                .filter(|k| inside(x as f32 + (k % 8) as f32 / 8.0 - 0.4375, y as f32 + (k / 8) as f32 / 8.0 - 0.4375))
+                .count();
+            Luma([(255 - covered * 255 / 64) as u8])
+        });
+
+        // Грубые вершины: округление и сдвиг на пиксель-полтора
+        let coarse = [
+            Point2::new(21.0, 18.0),
+            Point2::new(100.0, 25.0),
+            Point2::new(97.0, 103.0),
+            Point2::new(15.0, 99.0),
+        ];
+        let processor = ImageProcessor::new(ProcessingConfig::default());
+        let refined = processor.refine_corners(&img, coarse);
+        for (r, t) in refined.iter().zip(&truth) {
+            assert!((r - t).norm() < 0.5, "{:?} vs {:?}", r, t);
+        }
+
+        // Полный путь: грубые вершины по оболочке, затем уточнение
+        let found = processor.find_corners(&img).expect("quad corners");
+        for (r, t) in found.iter().zip(&truth) {
+            assert!((r - t).norm() < 0.5, "{:?} vs {:?}", r, t);
+        }
+
+        // На пустом изображении вершины не меняются
+        let blank = GrayImage::from_pixel(120, 120, Luma([255]));
+        assert_eq!(processor.refine_corners(&blank, coarse), coarse);
+        assert!(processor.find_corners(&blank).is_none());
+        // Вырожденные размеры не приводят к переполнению
+        for (w, h) in [(0, 0), (1, 1), (0, 5), (5, 1)] {
+            let tiny = GrayImage::new(w, h);
+            assert_eq!(processor.refine_corners(&tiny, coarse), coarse);
+            assert!(processor.find_corners(&tiny).is_none());
+        }
+    }
+
+    #[test]
+    fn test_find_corners_uses_configured_binarization() {
+        // Чёрный квадрат внутри серого на белом фоне: порог решает, чей контур найдётся
+        let img = GrayImage::from_fn(120, 120, |x, y| match (x, y) {
+            (40..80, 40..80) => Luma([0]),
+            (20..100, 20..100) => Luma([128]),
+            _ => Luma([255]),
+        });
+        let corners = |binarization| {
+            let processor = ImageProcessor::new(ProcessingConfig {
+                binarization: Some(binarization),
+                ..Default::default()
+            });
+            processor.find_corners(&img).expect("quad corners")
+        };
+
+        let inner = corners(BinarizationMethod::Fixed(64));
+        let outer = corners(BinarizationMethod::Fixed(192));
+        // Переход на границе пикселей: уровень 50% — на полпикселя раньше целочисленной координаты
+        let expected = |lo: f32, hi: f32| [(lo, lo), (hi, lo), (hi, hi), (lo, hi)].map(|(x, y)| nalgebra::Point2::new(x - 0.5, y - 0.5));
+        for (found, truth) in [(inner, expected(40.0, 80.0)), (outer, expected(20.0, 100.0))] {
+            for (r, t) in found.iter().zip(&truth) {
+                assert!((r - t).norm() < 0.75, "{:?} vs {:?}", found, truth);
+            }
+        }
+    }
+
     #[test]
     fn test_invert() {
         let processor = ImageProcessor::new(ProcessingConfig::default());
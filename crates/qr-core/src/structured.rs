@@ -0,0 +1,66 @@
+//! Модуль разбора произвольных структурированных QR
+//!
+//! Промышленные коды часто содержат поля `KEY=VALUE` или `KEY:VALUE`,
+//! разделённые `|`, `;` или переводом строки. Такие строки распознаются
+//! как `ContentType::Structured` только для разделителей, заданных через
+//! `QRScanner::set_key_value_delimiters`; по умолчанию это обычный текст.
+
+use std::collections::HashMap;
+
+/// Разбор `content` на пары ключ-значение.
+///
+/// Поля разделяются `field_sep` (для `'\n'` допускается и `\r\n`), ключ от значения —
+/// первым `kv_sep`; пробелы по краям отбрасываются, пустые поля пропускаются.
+/// `None`, если какое-то поле не является парой, ключ пустой или повторяется,
+/// либо пар меньше двух (одиночное `Note: text` остаётся текстом).
+pub fn parse_key_value(content: &str, field_sep: char, kv_sep: char) -> Option<HashMap<String, String>> {
+    if field_sep == kv_sep {
+        return None;
+    }
+
+    let mut fields = HashMap::new();
+    for field in content.split(field_sep).map(str::trim).filter(|f| !f.is_empty()) {
+        let (key, value) = field.split_once(kv_sep)?;
+        let key = key.trim();
+        if key.is_empty() || key.chars().any(char::is_whitespace) {
+            return None;
+        }
+        if fields.insert(key.to_string(), value.trim().to_string()).is_some() {
+            return None;
+        }
+    }
+
+    (fields.len() >= 2).then_some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipe_separated() {
+        let fields = parse_key_value("LOT=A-17|PART=4410 =B|QTY=25|", '|', '=').unwrap();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields["LOT"], "A-17");
+        assert_eq!(fields["PART"], "4410 =B");
+        assert_eq!(fields["QTY"], "25");
+
+        // Поле без разделителя или с пустым ключом — не структура
+        assert!(parse_key_value("LOT=A-17|broken|QTY=25", '|', '=').is_none());
+        assert!(parse_key_value("LOT=A-17|=5", '|', '=').is_none());
+        assert!(parse_key_value("LOT=A|LOT=B", '|', '=').is_none());
+        assert!(parse_key_value("LOT=A-17", '|', '=').is_none());
+    }
+
+    #[test]
+    fn test_newline_separated() {
+        let fields = parse_key_value("Serial: 00042\r\nModel: X-200\r\nDate: 2024-05-01 10:30\n", '\n', ':').unwrap();
+        assert_eq!(fields["Serial"], "00042");
+        assert_eq!(fields["Model"], "X-200");
+        assert_eq!(fields["Date"], "2024-05-01 10:30");
+
+        // Обычный текст с двоеточием
+        assert!(parse_key_value("Meeting at noon\nRoom: 5", '\n', ':').is_none());
+        assert!(parse_key_value("https://example.com", '\n', ':').is_none());
+    }
+}
@@ -0,0 +1,50 @@
+//! Отрисовка QR-кодов для тестов и бенчмарков
+//!
+//! Собирается в unit-тестах qr-core и с feature `test-utils` (интеграционные
+//! тесты, бенчмарки, тесты qr-wasm).
+
+use image::{GrayImage, Luma};
+use qrcode::{Color, QrCode};
+
+/// Размер модуля `render_qr`, px
+pub const MODULE: u32 = 8;
+/// Quiet zone `render_qr`, модулей
+pub const QUIET: u32 = 4;
+
+/// QR с текстом `content` (уровень коррекции M), модуль 8px, quiet zone 4 модуля
+pub fn render_qr(content: &str) -> GrayImage {
+    render_code(&QrCode::new(content).unwrap(), MODULE, QUIET)
+}
+
+/// Код на белом фоне: модуль `module` px, quiet zone `quiet` модулей
+pub fn render_code(code: &QrCode, module: u32, quiet: u32) -> GrayImage {
+    render_modules(&code.to_colors(), code.width() as u32, module, quiet)
+}
+
+/// Матрица модулей (построчно, `width` x `width`) на белом фоне
+pub fn render_modules(colors: &[Color], width: u32, module: u32, quiet: u32) -> GrayImage {
+    let side = (width + quiet * 2) * module;
+    let mut img = GrayImage::from_pixel(side, side, Luma([255]));
+    draw_modules(&mut img, colors, width, (quiet * module, quiet * module), module, Luma([0]));
+    img
+}
+
+/// Чёрные модули кода поверх `img`; `origin` — левый верхний угол кода (без quiet zone)
+pub fn draw_code(img: &mut GrayImage, code: &QrCode, origin: (u32, u32), module: u32) {
+    draw_modules(img, &code.to_colors(), code.width() as u32, origin, module, Luma([0]));
+}
+
+/// Тёмные модули матрицы цветом `dark` поверх `img`
+pub fn draw_modules(img: &mut GrayImage, colors: &[Color], width: u32, (ox, oy): (u32, u32), module: u32, dark: Luma<u8>) {
+    for y in 0..width {
+        for x in 0..width {
+            if colors[(y * width + x) as usize] == Color::Dark {
+                for dy in 0..module {
+                    for dx in 0..module {
+                        img.put_pixel(ox + x * module + dx, oy + y * module + dy, dark);
+                    }
+                }
+            }
+        }
+    }
+}
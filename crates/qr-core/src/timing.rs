@@ -0,0 +1,29 @@
+//! Замер времени обработки
+//!
+//! `std::time::Instant::now()` паникует на `wasm32-unknown-unknown`,
+//! поэтому там используется `Date.now()` из JavaScript.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::Instant;
+
+/// Момент времени на основе `Date.now()` (миллисекунды)
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Instant(f64);
+
+#[cfg(target_arch = "wasm32")]
+impl Instant {
+    pub fn now() -> Self {
+        Self(js_sys::Date::now())
+    }
+
+    pub fn elapsed(&self) -> std::time::Duration {
+        let ms = (js_sys::Date::now() - self.0).max(0.0);
+        std::time::Duration::from_secs_f64(ms / 1000.0)
+    }
+}
+
+/// Прошедшее время в миллисекундах
+pub(crate) fn elapsed_ms(start: Instant) -> u64 {
+    start.elapsed().as_millis() as u64
+}
@@ -0,0 +1,119 @@
+//! Модуль разбора URI вида `scheme://authority/path?query#fragment`
+//!
+//! Кроме `http(s)` маркетинговые QR содержат диплинки приложений:
+//! `myapp://open/item/42`, `market://details?id=com.example`,
+//! `intent://scan/#Intent;scheme=zxing;package=com.google.zxing.client.android;end`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Составные части URI
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UriInfo {
+    /// Схема в нижнем регистре (`https`, `market`, `intent`)
+    pub scheme: String,
+    /// Хост с необязательными userinfo и портом (может быть пустым: `file:///x`)
+    pub authority: String,
+    /// Путь, начиная с `/` (или пустой)
+    pub path: String,
+    /// URL-декодированные параметры запроса; при повторе ключа остаётся последнее значение
+    pub query: HashMap<String, String>,
+    /// Фрагмент после `#` без декодирования (у `intent://` — параметры интента)
+    pub fragment: Option<String>,
+}
+
+impl UriInfo {
+    /// Разбор `scheme://...`; схема — буква, затем буквы, цифры, `+`, `-`, `.` (RFC 3986)
+    pub fn parse(content: &str) -> Option<Self> {
+        let content = content.trim();
+        let (scheme, rest) = content.split_once("://")?;
+        if !is_scheme(scheme) {
+            return None;
+        }
+
+        let (rest, fragment) = match rest.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment.to_string())),
+            None => (rest, None),
+        };
+        let (rest, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+
+        let query = query
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                let (key, value) = param.split_once('=').unwrap_or((param, ""));
+                (decode(key), decode(value))
+            })
+            .collect();
+
+        Some(Self {
+            scheme: scheme.to_ascii_lowercase(),
+            authority: authority.to_string(),
+            path: path.to_string(),
+            query,
+            fragment,
+        })
+    }
+}
+
+/// Начинается ли `content` с `scheme://`
+pub(crate) fn has_scheme(content: &str) -> bool {
+    content.split_once("://").is_some_and(|(scheme, _)| is_scheme(scheme))
+}
+
+fn is_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// `+` → пробел, `%XX` → байт; невалидный UTF-8 — исходная строка
+fn decode(value: &str) -> String {
+    let value = value.replace('+', " ");
+    match urlencoding::decode(&value) {
+        Ok(decoded) => decoded.into_owned(),
+        Err(_) => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_market_details() {
+        let uri = UriInfo::parse("market://details?id=com.example.app&referrer=utm_source%3Dqr").unwrap();
+        assert_eq!(uri.scheme, "market");
+        assert_eq!(uri.authority, "details");
+        assert_eq!(uri.path, "");
+        assert_eq!(uri.query["id"], "com.example.app");
+        assert_eq!(uri.query["referrer"], "utm_source=qr");
+        assert_eq!(uri.fragment, None);
+    }
+
+    #[test]
+    fn test_intent() {
+        let uri = UriInfo::parse("intent://scan/#Intent;scheme=zxing;package=com.google.zxing.client.android;end").unwrap();
+        assert_eq!(uri.scheme, "intent");
+        assert_eq!(uri.authority, "scan");
+        assert_eq!(uri.path, "/");
+        assert!(uri.query.is_empty());
+        assert_eq!(uri.fragment.as_deref(), Some("Intent;scheme=zxing;package=com.google.zxing.client.android;end"));
+    }
+
+    #[test]
+    fn test_http_and_invalid() {
+        let uri = UriInfo::parse("HTTPS://user@example.com:8443/a/b?q=1+2&flag").unwrap();
+        assert_eq!(uri.scheme, "https");
+        assert_eq!(uri.authority, "user@example.com:8443");
+        assert_eq!(uri.path, "/a/b");
+        assert_eq!(uri.query["q"], "1 2");
+        assert_eq!(uri.query["flag"], "");
+
+        assert!(UriInfo::parse("mailto:a@b.c").is_none());
+        assert!(UriInfo::parse("1app://x").is_none());
+        assert!(UriInfo::parse("see: ://x").is_none());
+        assert!(!has_scheme("Hello world"));
+    }
+}
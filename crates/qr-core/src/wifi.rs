@@ -0,0 +1,175 @@
+//! Модуль парсинга WiFi QR-кодов
+//!
+//! Формат: `WIFI:T:WPA;S:ssid;P:password;H:true;;`
+//! Спецсимволы `\`, `;`, `,`, `:` и `"` в значениях экранируются обратным слэшем.
+
+use serde::{Deserialize, Serialize};
+
+/// Тип шифрования WiFi-сети
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum WiFiEncryption {
+    Wep,
+    Wpa,
+    Wpa2,
+    /// Открытая сеть (`nopass` или поле `T` отсутствует)
+    NoPass,
+    /// Нестандартное значение поля `T`
+    Unknown(String),
+}
+
+/// Параметры WiFi-сети из QR-кода
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WiFiInfo {
+    /// Имя сети
+    pub ssid: String,
+    /// Пароль (отсутствует для открытых сетей)
+    pub password: Option<String>,
+    /// Тип шифрования
+    pub encryption: WiFiEncryption,
+    /// Скрытая сеть (не транслирует SSID)
+    pub hidden: bool,
+}
+
+impl WiFiInfo {
+    /// Парсинг строки `WIFI:...`. Возвращает `None`, если это не WiFi QR или нет SSID.
+    pub fn parse(content: &str) -> Option<Self> {
+        let prefix = content.get(..5)?;
+        if !prefix.eq_ignore_ascii_case("wifi:") {
+            return None;
+        }
+
+        let mut ssid = None;
+        let mut password = None;
+        let mut encryption = WiFiEncryption::NoPass;
+        let mut hidden = false;
+
+        for field in split_unescaped(&content[5..], ';') {
+            let Some((key, raw)) = field.split_once(':') else {
+                continue;
+            };
+            let value = unescape(raw);
+
+            match key.to_ascii_uppercase().as_str() {
+                "S" => ssid = Some(unquote(raw)),
+                "P" => password = Some(unquote(raw)),
+                "T" => encryption = match value.to_ascii_uppercase().as_str() {
+                    "WEP" => WiFiEncryption::Wep,
+                    "WPA" => WiFiEncryption::Wpa,
+                    "WPA2" => WiFiEncryption::Wpa2,
+                    "" | "NOPASS" => WiFiEncryption::NoPass,
+                    _ => WiFiEncryption::Unknown(value),
+                },
+                "H" => hidden = value.eq_ignore_ascii_case("true"),
+                _ => {}
+            }
+        }
+
+        let ssid = ssid.filter(|s| !s.is_empty())?;
+        let password = password.filter(|p| !p.is_empty());
+
+        Some(Self {
+            ssid,
+            password,
+            encryption,
+            hidden,
+        })
+    }
+}
+
+/// Разбиение по разделителю, не предварённому обратным слэшем (экранирование сохраняется)
+fn split_unescaped(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == separator {
+            parts.push(&s[start..i]);
+            start = i + 1;
+        }
+    }
+    if start < s.len() {
+        parts.push(&s[start..]);
+    }
+    parts
+}
+
+/// Снятие экранирования `\;`, `\:`, `\\`, `\,`, `\"`
+fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(next) = chars.next() {
+                result.push(next);
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Некоторые генераторы берут SSID/пароль в кавычки. Кавычки снимаются до раскрытия
+/// экранирования, поэтому `\"` на краях значения остаётся литеральной кавычкой.
+fn unquote(raw: &str) -> String {
+    let inner = raw
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .filter(|inner| inner.chars().rev().take_while(|&c| c == '\\').count() % 2 == 0);
+    unescape(inner.unwrap_or(raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wpa() {
+        let wifi = WiFiInfo::parse("WIFI:T:WPA;S:HomeNet;P:secret123;H:true;;").unwrap();
+        assert_eq!(wifi.ssid, "HomeNet");
+        assert_eq!(wifi.password.as_deref(), Some("secret123"));
+        assert_eq!(wifi.encryption, WiFiEncryption::Wpa);
+        assert!(wifi.hidden);
+    }
+
+    #[test]
+    fn test_parse_escaped_characters() {
+        let wifi = WiFiInfo::parse(r"WIFI:S:Cafe\;Bar\:2;T:WPA2;P:pa\\ss\,w\;rd;;").unwrap();
+        assert_eq!(wifi.ssid, "Cafe;Bar:2");
+        assert_eq!(wifi.password.as_deref(), Some(r"pa\ss,w;rd"));
+        assert_eq!(wifi.encryption, WiFiEncryption::Wpa2);
+    }
+
+    #[test]
+    fn test_parse_missing_optional_fields() {
+        let wifi = WiFiInfo::parse("wifi:S:OpenNet;;").unwrap();
+        assert_eq!(wifi.ssid, "OpenNet");
+        assert_eq!(wifi.password, None);
+        assert_eq!(wifi.encryption, WiFiEncryption::NoPass);
+        assert!(!wifi.hidden);
+
+        let wep = WiFiInfo::parse("WIFI:T:WEP;S:\"Quoted\";P:\"abc\"").unwrap();
+        assert_eq!(wep.ssid, "Quoted");
+        assert_eq!(wep.password.as_deref(), Some("abc"));
+        assert_eq!(wep.encryption, WiFiEncryption::Wep);
+
+        // Экранированные кавычки на краях — часть значения
+        let escaped = WiFiInfo::parse(r#"WIFI:S:\"Quoted\";P:"pass\"";;"#).unwrap();
+        assert_eq!(escaped.ssid, "\"Quoted\"");
+        assert_eq!(escaped.password.as_deref(), Some("pass\""));
+        let backslash = WiFiInfo::parse(r#"WIFI:S:"Net\\";;"#).unwrap();
+        assert_eq!(backslash.ssid, "Net\\");
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid() {
+        assert!(WiFiInfo::parse("WIFI:T:WPA;P:nossid;;").is_none());
+        assert!(WiFiInfo::parse("https://example.com").is_none());
+        assert!(WiFiInfo::parse("WIF").is_none());
+    }
+}
@@ -1,7 +1,9 @@
 //! Integration tests for QR recognition
 
 use qr_core::{QRScanner, ContentType};
+use qr_core::testing::{draw_code, render_qr};
 use image::{GrayImage, Luma};
+use std::time::{Duration, Instant};
 
 /// Helper to create a simple test image
 fn create_test_image(width: u32, height: u32) -> GrayImage {
@@ -19,13 +21,175 @@ fn test_scanner_creation() {
 fn test_empty_image_scan() {
     let scanner = QRScanner::new();
     let img = create_test_image(100, 100);
+    let start = Instant::now();
     
-    let result = scanner.scan_image(&img);
+    let result = scanner.scan_image_timed(&img, start);
     
     assert!(result.is_ok());
     let scan_result = result.unwrap();
     // Empty image should return empty or fallback result
-    assert!(scan_result.qr_codes.is_empty() || scan_result.qr_codes.len() >= 0);
+    assert!(scan_result.qr_codes.is_empty());
+}
+
+#[test]
+fn test_processing_time_reported() {
+    use qr_core::QRDecoder;
+
+    // Пустой кадр проходит весь каскад стратегий декодера: проверяется проделанная
+    // работа, а не измеренное время, которое на быстрой машине может быть 0 мс
+    let img = create_test_image(300, 300);
+    let (result, report) = QRDecoder::new().decode_with_report(&img);
+    assert!(result.is_err());
+    assert!(report.stages_tried.len() > 1, "{:?}", report.stages_tried);
+    assert_eq!(report.winning_stage, None);
+    assert!(QRScanner::new().scan_image(&img).unwrap().qr_codes.is_empty());
+
+    // Отсчёт идёт от переданного start: 50 мс до вызова — нижняя граница результата
+    let start = Instant::now() - Duration::from_millis(50);
+    let result = QRScanner::new().scan_image_timed(&create_test_image(100, 100), start).unwrap();
+    assert!(result.processing_time_ms >= 50);
+}
+
+#[test]
+fn test_multiple_codes_in_one_image() {
+    let left = render_qr("https://example.com/menu");
+    let right = render_qr("https://qr.nspk.ru/AS1000670LSS7DN18SJQDNP4B05KLJL2?type=01&bank=100000000001");
+    let mut img = GrayImage::from_pixel(left.width() + right.width() + 40, left.height().max(right.height()), Luma([255]));
+    image::imageops::overlay(&mut img, &left, 0, 0);
+    image::imageops::overlay(&mut img, &right, (left.width() + 40) as i64, 0);
+
+    let decoder = qr_core::QRDecoder::new();
+    assert_eq!(decoder.decode_all(&img).len(), 2);
+
+    let result = QRScanner::new().scan_image(&img).unwrap();
+    let contents: Vec<&str> = result.qr_codes.iter().map(|qr| qr.content.as_str()).collect();
+    assert!(contents.contains(&"https://example.com/menu"));
+    assert!(contents.iter().any(|c| c.contains("qr.nspk.ru")));
+    let best = result.best_payment.expect("payment code ranked");
+    assert!(result.qr_codes[best].content.contains("qr.nspk.ru"));
+}
+
+#[test]
+fn test_multi_code_mode_finds_codes_missed_by_detector() {
+    use qr_core::testing::render_code;
+    use qr_core::{DetectorConfig, ProcessingConfig};
+
+    // Маленький код (84px) меньше min_size: его находит только декодирование всего кадра
+    let large = render_qr("https://example.com/large");
+    let small = render_code(&qrcode::QrCode::new("small").unwrap(), 4, 4);
+    let mut img = GrayImage::from_pixel(large.width() + small.width() + 40, large.height(), Luma([255]));
+    image::imageops::overlay(&mut img, &large, 0, 0);
+    image::imageops::overlay(&mut img, &small, (large.width() + 40) as i64, 0);
+    let detection = DetectorConfig { min_size: 150, ..Default::default() };
+
+    // По умолчанию кадр целиком не декодируется, раз детектор уже прочитал код
+    let mut scanner = QRScanner::with_config(ProcessingConfig::default(), detection);
+    let contents = |scanner: &QRScanner| -> Vec<String> {
+        scanner.scan_image(&img).unwrap().qr_codes.into_iter().map(|qr| qr.content).collect()
+    };
+    assert_eq!(contents(&scanner), vec!["https://example.com/large".to_string()]);
+
+    scanner.set_multi_code(true);
+    let found = contents(&scanner);
+    assert_eq!(found.len(), 2, "{:?}", found);
+    assert!(found.contains(&"small".to_string()));
+}
+
+#[test]
+fn test_payment_ranking_lists_every_payment_code() {
+    use qr_core::{EmvBuilder, PaymentFormat};
+
+    // EMV слева, обычная ссылка в центре, СБП справа: СБП ранжируется выше EMV
+    let emv = EmvBuilder::new()
+        .merchant_name("Flyer Cafe")
+        .merchant_city("Bangkok")
+        .add_merchant_account(29, "A000000677010111", "0066812345678")
+        .currency("764")
+        .amount(qr_core::Decimal::new(42, 0))
+        .country_code("TH")
+        .build();
+    let codes = [
+        render_qr(&emv),
+        render_qr("https://example.com/menu"),
+        render_qr("https://qr.nspk.ru/AS1000670LSS7DN18SJQDNP4B05KLJL2?type=01&bank=100000000001"),
+    ];
+    let width = codes.iter().map(|c| c.width() + 40).sum();
+    let height = codes.iter().map(|c| c.height()).max().unwrap();
+    let mut img = GrayImage::from_pixel(width, height, Luma([255]));
+    let mut x = 0;
+    for code in &codes {
+        image::imageops::overlay(&mut img, code, x, 0);
+        x += (code.width() + 40) as i64;
+    }
+
+    let result = QRScanner::new().scan_image(&img).unwrap();
+    assert_eq!(result.qr_codes.len(), 3);
+
+    let ranking = &result.payment_ranking;
+    assert_eq!(ranking.len(), 2, "{:?}", ranking);
+    assert!(ranking[0].1 >= ranking[1].1);
+    let top = &result.qr_codes[ranking[0].0];
+    let second = &result.qr_codes[ranking[1].0];
+    assert!(top.content.contains("qr.nspk.ru"));
+    assert_eq!(second.payment.as_ref().map(|p| &p.format), Some(&PaymentFormat::EmvQR));
+    assert_eq!(result.best_payment, Some(ranking[0].0));
+}
+
+#[test]
+fn test_wifi_code_is_parsed() {
+    let img = render_qr("WIFI:T:WPA;S:Office;P:p\\;ss;;");
+    let result = QRScanner::new().scan_image(&img).unwrap();
+
+    let qr = &result.qr_codes[0];
+    assert_eq!(qr.content_type, ContentType::WiFi);
+    let wifi = qr.wifi.as_ref().expect("wifi parsed");
+    assert_eq!(wifi.ssid, "Office");
+    assert_eq!(wifi.password.as_deref(), Some("p;ss"));
+    assert!(result.qr_codes[0].payment.is_none());
+}
+
+#[test]
+fn test_debug_crops() {
+    let img = render_qr("debug crop");
+    let mut scanner = QRScanner::new();
+
+    let result = scanner.scan_image(&img).unwrap();
+    assert!(result.qr_codes.iter().all(|qr| qr.debug_crop.is_none()));
+
+    scanner.set_debug_crops(true);
+    let result = scanner.scan_image(&img).unwrap();
+    let crop = result.qr_codes[0].debug_crop.as_ref().expect("crop attached");
+    assert!(crop.width() > 0 && crop.height() > 0);
+}
+
+#[test]
+fn test_zero_dimension_image_is_rejected() {
+    use qr_core::QRError;
+
+    let scanner = QRScanner::new();
+    let result = scanner.scan_image(&GrayImage::new(0, 10));
+    assert!(matches!(result, Err(QRError::ImageProcessing(_))));
+}
+
+#[test]
+fn test_extreme_aspect_ratio_does_not_panic() {
+    let scanner = QRScanner::new();
+    let result = scanner.scan_image(&create_test_image(1, 5000));
+    assert!(result.unwrap().qr_codes.is_empty());
+}
+
+#[test]
+fn test_invalid_detector_config_is_reported() {
+    use qr_core::{DetectorConfig, ProcessingConfig, QRError};
+
+    let detection = DetectorConfig {
+        min_size: 500,
+        max_size: 100,
+        ..Default::default()
+    };
+    let scanner = QRScanner::with_config(ProcessingConfig::default(), detection);
+    let result = scanner.scan_image(&create_test_image(100, 100));
+    assert!(matches!(result, Err(QRError::Detection(_))));
 }
 
 #[test]
@@ -36,6 +200,20 @@ fn test_content_type_detection() {
     
     // Payment URLs
     assert_eq!(ContentType::detect("https://qr.nspk.ru/123"), ContentType::Payment);
+    assert_eq!(ContentType::detect("https://pay.example.com/order/7"), ContentType::Payment);
+    assert_eq!(ContentType::detect("https://shop.example/checkout/payment?id=7"), ContentType::Payment);
+    // «pay» внутри слова — не платёж
+    assert_eq!(ContentType::detect("https://paypal-lookalike.com/pages"), ContentType::Url);
+    assert_eq!(ContentType::detect("https://example.com/?next=qr.nspk.ru"), ContentType::Url);
+
+    // UPI, SberPay, PIX и PromptPay (EMV)
+    assert_eq!(ContentType::detect("upi://pay?pa=shop@upi&pn=Shop&am=10"), ContentType::Payment);
+    assert_eq!(ContentType::detect("sberpay://invoicing/v2?bankInvoiceId=1"), ContentType::Payment);
+    assert_eq!(ContentType::detect("sbolpay://invoicing/v2?bankInvoiceId=1"), ContentType::Payment);
+    let pix = "00020126580014br.gov.bcb.pix0136123e4567-e12b-12d1-a456-4266554400005204000053039865802BR5913Fulano de Tal6008BRASILIA62070503***63041D3D";
+    assert_eq!(ContentType::detect(pix), ContentType::Payment);
+    let promptpay = "00020101021129370016A000000677010111011300668123456785802TH53037646304";
+    assert_eq!(ContentType::detect(promptpay), ContentType::Payment);
     
     // VCard
     assert_eq!(ContentType::detect("BEGIN:VCARD\nVERSION:3.0\nN:Test"), ContentType::VCard);
@@ -55,10 +233,43 @@ fn test_content_type_detection() {
     // Geo
     assert_eq!(ContentType::detect("geo:55.7558,37.6173"), ContentType::Geo);
     
+    // Диплинки приложений — Url; mailto:/tel: сохраняют свои типы
+    assert_eq!(ContentType::detect("market://details?id=com.example.app"), ContentType::Url);
+    assert_eq!(ContentType::detect("intent://scan/#Intent;scheme=zxing;end"), ContentType::Url);
+    assert_eq!(ContentType::detect("myapp://open/item/42"), ContentType::Url);
+    assert_eq!(ContentType::detect("mailto://test@example.com"), ContentType::Email);
+    assert_eq!(ContentType::detect("Note: see ://x"), ContentType::Text);
+
+    // Calendar
+    assert_eq!(ContentType::detect("BEGIN:VEVENT\nDTSTART:20240601\nEND:VEVENT"), ContentType::Calendar);
+    assert_eq!(
+        ContentType::detect("BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART:20240601\nEND:VEVENT\nEND:VCALENDAR"),
+        ContentType::Calendar
+    );
+    
     // Plain text
     assert_eq!(ContentType::detect("Hello World"), ContentType::Text);
 }
 
+#[test]
+fn test_short_emv_detection() {
+    use qr_core::{EmvBuilder, PaymentFormat, PaymentParser};
+
+    // Минимальный статический код мерчанта короче 50 символов
+    let emv = EmvBuilder::new().point_of_initiation("11").currency("643").country_code("RU").build();
+    assert!(emv.len() < 50, "{}", emv);
+    assert_eq!(ContentType::detect(&emv), ContentType::Payment);
+
+    let payment = PaymentParser::new().parse(&emv).expect("short EMV parsed");
+    assert_eq!(payment.format, PaymentFormat::EmvQR);
+    assert_eq!(payment.currency.as_deref(), Some("RUB"));
+
+    // Без тега 63 или с битым TLV — обычный текст
+    assert_eq!(ContentType::detect("00020101021153036435802RU"), ContentType::Text);
+    assert_eq!(ContentType::detect("000201010211539943"), ContentType::Text);
+    assert_eq!(ContentType::detect("0042 is the answer, 6304"), ContentType::Text);
+}
+
 #[test]
 fn test_sbp_payment_parsing() {
     use qr_core::{PaymentParser, PaymentFormat};
@@ -72,7 +283,8 @@ fn test_sbp_payment_parsing() {
     let payment = result.unwrap();
     
     assert_eq!(payment.format, PaymentFormat::SbpRussia);
-    assert_eq!(payment.amount, Some(150.0)); // 15000 копеек = 150 рублей
+    assert_eq!(payment.amount, Some(qr_core::Decimal::new(15000, 2))); // 15000 копеек = 150 рублей
+    assert_eq!(payment.amount.unwrap().to_string(), "150.00");
     assert_eq!(payment.currency, Some("RUB".to_string()));
 }
 
@@ -92,7 +304,7 @@ fn test_st_payment_parsing() {
     assert_eq!(payment.payee_name, Some("ООО Рога и Копыта".to_string()));
     assert_eq!(payment.account, Some("40702810099990001234".to_string()));
     assert_eq!(payment.bic, Some("044525225".to_string()));
-    assert_eq!(payment.amount, Some(2500.0)); // 250000 копеек = 2500 рублей
+    assert_eq!(payment.amount, Some(qr_core::Decimal::new(250000, 2))); // 250000 копеек = 2500 рублей
     assert_eq!(payment.purpose, Some("Оплата по счёту 123".to_string()));
 }
 
@@ -159,3 +371,347 @@ fn test_decoder_creation() {
     let result = decoder.decode(&img);
     assert!(result.is_err());
 }
+
+#[test]
+fn test_frame_cache() {
+    let mut scanner = QRScanner::new();
+    scanner.enable_frame_cache(64);
+
+    let frame = render_qr("cached frame");
+    let (width, height) = frame.dimensions();
+    let first = scanner.scan_image(&frame).unwrap();
+    assert_eq!(first.qr_codes[0].content, "cached frame");
+
+    // Порог 64 совпадает с любым кадром того же размера: пустой кадр отдаёт закэшированный результат
+    let cached = scanner.scan_image(&create_test_image(width, height)).unwrap();
+    assert_eq!(cached.qr_codes.len(), 1);
+    assert_eq!(cached.qr_codes[0].content, "cached frame");
+
+    // Кадр другого размера — всегда промах
+    let resized = scanner.scan_image(&create_test_image(width + 1, height)).unwrap();
+    assert!(resized.qr_codes.is_empty());
+
+    scanner.disable_frame_cache();
+    let fresh = scanner.scan_image(&create_test_image(width, height)).unwrap();
+    assert!(fresh.qr_codes.is_empty());
+}
+
+#[test]
+fn test_bbox_in_input_coordinates_after_resize() {
+    // 2000px кадр уменьшается до 1000px, но bbox и углы — в пикселях входа
+    let qr = qrcode::QrCode::new("https://example.com/hd").unwrap();
+    let side = qr.width() as u32 * 8;
+    let mut img = GrayImage::from_pixel(2000, 1200, Luma([255]));
+    draw_code(&mut img, &qr, (1400, 700), 8);
+
+    let result = QRScanner::new().scan_image(&img).unwrap();
+    assert_eq!(result.qr_codes.len(), 1);
+    let [x, y, w, h] = result.qr_codes[0].bbox;
+    assert!(x.abs_diff(1400) <= 8 && y.abs_diff(700) <= 8, "{:?}", result.qr_codes[0].bbox);
+    assert!(w.abs_diff(side) <= 16 && h.abs_diff(side) <= 16, "{:?}", result.qr_codes[0].bbox);
+    let corners = result.qr_codes[0].corners.expect("corners");
+    assert!((corners[0].0 - 1400.0).abs() < 10.0 && (corners[0].1 - 700.0).abs() < 10.0, "{:?}", corners);
+}
+
+#[test]
+fn test_small_code_in_large_image_without_resize() {
+    use qr_core::{DetectorConfig, ProcessingConfig};
+
+    // 4000px кадр с кодом 3px на модуль: после ресайза до 1000px модуль меньше пикселя
+    let mut img = GrayImage::from_pixel(4000, 600, Luma([255]));
+    draw_code(&mut img, &qrcode::QrCode::new("https://example.com/tiny").unwrap(), (2500, 200), 3);
+
+    let processing = ProcessingConfig {
+        max_dimension: None,
+        ..Default::default()
+    };
+    let scanner = QRScanner::with_config(processing, DetectorConfig::default());
+    let result = scanner.scan_image(&img).unwrap();
+    assert_eq!(result.qr_codes.len(), 1);
+    assert_eq!(result.qr_codes[0].content, "https://example.com/tiny");
+    let bbox = result.qr_codes[0].bbox;
+    assert!(bbox[0] >= 2400 && bbox[0] <= 2510, "{:?}", bbox);
+}
+
+#[test]
+fn test_animated_gif_scans_every_frame() {
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, DynamicImage, Frame};
+
+    let qr = render_qr("frame two");
+    let (w, h) = qr.dimensions();
+    let blank = GrayImage::from_pixel(w, h, Luma([255]));
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut bytes);
+        for gray in [&blank, &qr, &blank] {
+            let rgba = DynamicImage::ImageLuma8(gray.clone()).to_rgba8();
+            encoder
+                .encode_frame(Frame::from_parts(rgba, 0, 0, Delay::from_numer_denom_ms(100, 1)))
+                .unwrap();
+        }
+    }
+
+    let scanner = QRScanner::new();
+    // Обычное сканирование видит только первый (пустой) кадр
+    assert!(scanner.scan_bytes(&bytes).unwrap().qr_codes.is_empty());
+
+    let result = scanner.scan_animated_bytes(&bytes).unwrap();
+    assert_eq!(result.qr_codes.len(), 1);
+    assert_eq!(result.qr_codes[0].content, "frame two");
+    assert_eq!(result.qr_codes[0].page, Some(1));
+
+    // Кадры после предела не декодируются
+    let mut limited = QRScanner::new();
+    limited.set_max_frames(1);
+    let result = limited.scan_animated_bytes(&bytes).unwrap();
+    assert!(result.qr_codes.is_empty());
+    limited.set_max_frames(0);
+    assert!(limited.scan_animated_bytes(&bytes).unwrap().qr_codes.is_empty());
+    limited.set_max_frames(2);
+    assert_eq!(limited.scan_animated_bytes(&bytes).unwrap().qr_codes.len(), 1);
+}
+
+#[cfg(feature = "multipage")]
+#[test]
+fn test_two_page_tiff_reports_page() {
+    use tiff::encoder::{colortype, TiffEncoder};
+
+    let qr = render_qr("https://example.com/invoice");
+    let (w, h) = qr.dimensions();
+    let blank = GrayImage::from_pixel(w, h, Luma([255]));
+
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    {
+        let mut encoder = TiffEncoder::new(&mut bytes).unwrap();
+        for page in [&blank, &qr] {
+            encoder.write_image::<colortype::Gray8>(w, h, page.as_raw()).unwrap();
+        }
+    }
+
+    let result = QRScanner::new().scan_pages(bytes.get_ref()).unwrap();
+    assert_eq!(result.qr_codes.len(), 1);
+    assert_eq!(result.qr_codes[0].content, "https://example.com/invoice");
+    assert_eq!(result.qr_codes[0].page, Some(1));
+
+    assert!(QRScanner::new().scan_pages(b"not a tiff").is_err());
+}
+
+#[cfg(feature = "multipage")]
+#[test]
+fn test_pdf_pages_report_page() {
+    use lopdf::{dictionary, Document, Object, Stream};
+
+    // Страница 0 — без изображений, 1 — FlateDecode Gray, 2 — JPEG RGB
+    let flate = render_qr("https://example.com/invoice/flate");
+    let jpeg = image::DynamicImage::ImageLuma8(render_qr("https://example.com/invoice/jpeg")).to_rgb8();
+    let mut jpeg_bytes = std::io::Cursor::new(Vec::new());
+    jpeg.write_to(&mut jpeg_bytes, image::ImageFormat::Jpeg).unwrap();
+
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let mut flate_stream = Stream::new(
+        dictionary! {
+            "Type" => "XObject", "Subtype" => "Image",
+            "Width" => flate.width() as i64, "Height" => flate.height() as i64,
+            "ColorSpace" => "DeviceGray", "BitsPerComponent" => 8,
+        },
+        flate.into_raw(),
+    );
+    flate_stream.compress().unwrap();
+    let jpeg_stream = Stream::new(
+        dictionary! {
+            "Type" => "XObject", "Subtype" => "Image",
+            "Width" => jpeg.width() as i64, "Height" => jpeg.height() as i64,
+            "ColorSpace" => "DeviceRGB", "BitsPerComponent" => 8, "Filter" => "DCTDecode",
+        },
+        jpeg_bytes.into_inner(),
+    );
+
+    let mut kids = vec![doc.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id })];
+    for stream in [flate_stream, jpeg_stream] {
+        let image_id = doc.add_object(stream);
+        kids.push(doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Resources" => dictionary! { "XObject" => dictionary! { "Im0" => image_id } },
+        }));
+    }
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Count" => kids.len() as i64,
+            "Kids" => kids.into_iter().map(Object::Reference).collect::<Vec<_>>(),
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog_id);
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes).unwrap();
+
+    let result = QRScanner::new().scan_pages(&bytes).unwrap();
+    let mut pages: Vec<_> = result.qr_codes.iter().map(|qr| (qr.page, qr.content.as_str())).collect();
+    pages.sort();
+    assert_eq!(
+        pages,
+        [
+            (Some(1), "https://example.com/invoice/flate"),
+            (Some(2), "https://example.com/invoice/jpeg"),
+        ]
+    );
+
+    assert!(QRScanner::new().scan_pages(b"%PDF-1.5 truncated").is_err());
+}
+
+#[test]
+fn test_full_image_decode_can_be_disabled() {
+    use qr_core::{DetectorConfig, ProcessingConfig};
+
+    // Код меньше min_size: детектор его отбрасывает, находит только декодирование всего кадра
+    let detection = DetectorConfig { min_size: 1000, ..Default::default() };
+    let img = render_qr("whole frame");
+
+    let mut scanner = QRScanner::with_config(ProcessingConfig::default(), detection.clone());
+    assert_eq!(scanner.scan_image(&img).unwrap().qr_codes.len(), 1);
+
+    // Весь кадр ещё отдаёт сам детектор (whole_image_fallback)
+    scanner.set_full_image_decode(false);
+    assert_eq!(scanner.scan_image(&img).unwrap().qr_codes.len(), 1);
+
+    let detection = DetectorConfig { whole_image_fallback: false, ..detection };
+    let mut strict = QRScanner::with_config(ProcessingConfig::default(), detection);
+    strict.set_full_image_decode(false);
+    assert!(strict.scan_image(&img).unwrap().qr_codes.is_empty());
+}
+
+#[test]
+fn test_decode_text_returns_content_only() {
+    let scanner = QRScanner::new();
+    assert_eq!(scanner.decode_text(&render_qr("just text")).unwrap(), "just text");
+    assert!(scanner.decode_text(&create_test_image(64, 64)).is_err());
+    assert!(scanner.decode_text(&GrayImage::new(0, 0)).is_err());
+}
+
+#[test]
+fn test_decode_cropped_matches_scan_image() {
+    let crop = render_qr("https://qr.nspk.ru/AS1000?type=02&bank=100000000111&sum=50000&cur=RUB");
+    let scanner = QRScanner::new();
+
+    let started = Instant::now();
+    let direct = scanner.decode_cropped(&crop).unwrap();
+    let direct_time = started.elapsed();
+
+    let started = Instant::now();
+    let scanned = scanner.scan_image(&crop).unwrap();
+    let scan_time = started.elapsed();
+    // Без детекции и дополнительного прохода по всему кадру
+    assert!(direct_time <= scan_time, "decode_cropped {:?} vs scan_image {:?}", direct_time, scan_time);
+
+    assert_eq!(scanned.qr_codes.len(), 1);
+    let full = &scanned.qr_codes[0];
+    assert_eq!(direct.content, full.content);
+    assert_eq!(direct.content_type, ContentType::Payment);
+    assert_eq!(direct.payment.as_ref().map(|p| p.amount), full.payment.as_ref().map(|p| p.amount));
+    assert_eq!(direct.bbox, [0, 0, crop.width(), crop.height()]);
+
+    assert!(scanner.decode_cropped(&create_test_image(100, 100)).is_err());
+    assert!(scanner.decode_cropped(&GrayImage::new(0, 0)).is_err());
+}
+
+#[test]
+fn test_whole_image_fallback_bbox_is_tight() {
+    use qr_core::{DetectorConfig, ProcessingConfig};
+
+    let qr = render_qr("centered");
+    let mut frame = create_test_image(600, 600);
+    let offset = (600 - qr.width()) / 2;
+    image::imageops::replace(&mut frame, &qr, offset as i64, offset as i64);
+
+    // Детектор не принимает код (min_size), поэтому он декодируется с целого кадра
+    for whole_image_fallback in [true, false] {
+        let detection = DetectorConfig { min_size: 1000, whole_image_fallback, ..Default::default() };
+        let scanner = QRScanner::with_config(ProcessingConfig::default(), detection);
+        let result = scanner.scan_image(&frame).unwrap();
+        assert_eq!(result.qr_codes.len(), 1);
+
+        let [x, y, w, h] = result.qr_codes[0].bbox;
+        assert!(w < 600 && h < 600, "{:?}", result.qr_codes[0].bbox);
+        // Рамка охватывает модули кода (без quiet zone) с точностью до модуля
+        let quiet = 4 * 8;
+        let code = qr.width() - 2 * quiet;
+        assert!(x.abs_diff(offset + quiet) <= 8 && y.abs_diff(offset + quiet) <= 8, "{:?}", result.qr_codes[0].bbox);
+        assert!(w.abs_diff(code) <= 16 && h.abs_diff(code) <= 16, "{:?}", result.qr_codes[0].bbox);
+    }
+}
+
+#[test]
+fn test_structured_key_value_content() {
+    let img = render_qr("LOT=A-17|PART=4410|QTY=25");
+
+    // По умолчанию — обычный текст
+    let plain = QRScanner::new().scan_image(&img).unwrap();
+    assert_eq!(plain.qr_codes[0].content_type, ContentType::Text);
+    assert!(plain.qr_codes[0].fields.is_none());
+
+    let mut scanner = QRScanner::new();
+    scanner.set_key_value_delimiters(vec![('\n', ':'), ('|', '=')]);
+    let result = scanner.scan_image(&img).unwrap();
+    let qr = &result.qr_codes[0];
+    assert_eq!(qr.content_type, ContentType::Structured);
+    let fields = qr.fields.as_ref().expect("fields");
+    assert_eq!(fields["PART"], "4410");
+    assert_eq!(fields["QTY"], "25");
+
+    // URL не переклассифицируется
+    let url = scanner.scan_image(&render_qr("https://example.com/a=b|c=d")).unwrap();
+    assert_eq!(url.qr_codes[0].content_type, ContentType::Url);
+}
+
+#[test]
+fn test_calendar_event_is_parsed() {
+    let img = render_qr("BEGIN:VEVENT\nSUMMARY:Demo day\nDTSTART;VALUE=DATE:20240601\nLOCATION:Hall A\nEND:VEVENT");
+    let result = QRScanner::new().scan_image(&img).unwrap();
+    let qr = &result.qr_codes[0];
+    assert_eq!(qr.content_type, ContentType::Calendar);
+    let event = qr.calendar.as_ref().expect("calendar");
+    assert_eq!(event.summary.as_deref(), Some("Demo day"));
+    assert!(event.start.is_all_day());
+    assert_eq!(event.location.as_deref(), Some("Hall A"));
+}
+
+#[test]
+fn test_deep_link_uri_is_parsed() {
+    let result = QRScanner::new().scan_image(&render_qr("market://details?id=com.example.app")).unwrap();
+    let qr = &result.qr_codes[0];
+    assert_eq!(qr.content_type, ContentType::Url);
+    let uri = qr.uri.as_ref().expect("uri");
+    assert_eq!(uri.scheme, "market");
+    assert_eq!(uri.query["id"], "com.example.app");
+}
+
+#[test]
+fn test_micro_qr_in_large_frame() {
+    use qr_core::{BarcodeFormat, DetectorConfig, ProcessingConfig};
+
+    let qr = qrcode::QrCode::with_version(b"MICRO 42", qrcode::Version::Micro(3), qrcode::EcLevel::M).unwrap();
+    let width = qr.width() as u32;
+    let mut img = GrayImage::from_pixel(800, 600, Luma([255]));
+    draw_code(&mut img, &qr, (300, 200), 6);
+
+    // Только кроп вокруг одиночного finder-паттерна, без декодирования всего кадра
+    let detection = DetectorConfig { whole_image_fallback: false, ..Default::default() };
+    let mut scanner = QRScanner::with_config(ProcessingConfig::default(), detection);
+    scanner.set_full_image_decode(false);
+    let result = scanner.scan_image(&img).unwrap();
+    assert_eq!(result.qr_codes.len(), 1);
+    assert_eq!(result.qr_codes[0].content, "MICRO 42");
+    let [x, y, w, h] = result.qr_codes[0].bbox;
+    assert!(x <= 300 && y <= 200 && x + w >= 300 + width * 6 && y + h >= 200 + width * 6);
+
+    // Кадр целиком читает ридер Micro QR
+    let decoded = qr_core::QRDecoder::new().decode(&img).unwrap();
+    assert_eq!(decoded.format, BarcodeFormat::MicroQr);
+}
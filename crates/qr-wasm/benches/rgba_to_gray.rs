@@ -0,0 +1,46 @@
+//! Benchmarks for RGBA -> grayscale conversion (нативно, без getrandom)
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use qr_wasm::rgba_to_gray;
+
+/// Кадр 1920x1080 (2 Мп) из xorshift-шума
+fn create_frame() -> Vec<u8> {
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    (0..1920 * 1080 * 4)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+/// Прежний путь: умножение-сложение в f32 по одному пикселю
+fn rgba_to_gray_float(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let pixel_count = (width * height) as usize;
+    let mut gray = Vec::with_capacity(pixel_count);
+    for i in 0..pixel_count {
+        let offset = i * 4;
+        if offset + 2 < rgba.len() {
+            let (r, g, b) = (rgba[offset] as f32, rgba[offset + 1] as f32, rgba[offset + 2] as f32);
+            gray.push((0.299 * r + 0.587 * g + 0.114 * b) as u8);
+        }
+    }
+    gray
+}
+
+fn benchmark_rgba_to_gray(c: &mut Criterion) {
+    let frame = create_frame();
+
+    c.bench_function("rgba_to_gray_float_1920x1080", |b| {
+        b.iter(|| rgba_to_gray_float(black_box(&frame), 1920, 1080))
+    });
+
+    c.bench_function("rgba_to_gray_fixed_1920x1080", |b| {
+        b.iter(|| rgba_to_gray(black_box(&frame), 1920, 1080))
+    });
+}
+
+criterion_group!(benches, benchmark_rgba_to_gray);
+criterion_main!(benches);
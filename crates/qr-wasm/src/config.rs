@@ -0,0 +1,98 @@
+//! Конфигурация сканера из JS-объекта
+//!
+//! Формат — `qr_core::ScannerConfig` (тот же, что у `qr-cli --config`):
+//! ключи `ProcessingConfig` на верхнем уровне, `DetectorConfig` — во вложенном
+//! объекте `detection`. `configObject` отдаёт ключи в camelCase.
+
+use qr_core::{DetectorConfig, ProcessingConfig, ScannerConfig};
+use serde_json::Value;
+
+/// JSON-объект (из JS) → конфигурации поверх значений по умолчанию
+pub(crate) fn configs_from_json(value: Value) -> Result<(ProcessingConfig, DetectorConfig), String> {
+    let config = ScannerConfig::from_json(value).map_err(|e| e.to_string())?;
+    Ok((config.processing, config.detection))
+}
+
+/// Конфигурации → JSON-объект с ключами в camelCase (форма, которую принимает `configs_from_json`)
+pub(crate) fn configs_to_json(processing: &ProcessingConfig, detection: &DetectorConfig) -> Value {
+    let config = ScannerConfig {
+        processing: processing.clone(),
+        detection: detection.clone(),
+    };
+    camel_case_keys(config.to_json())
+}
+
+/// `block_size` → `blockSize` во всех ключах объектов
+fn camel_case_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (camel_case(&key), camel_case_keys(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(camel_case_keys).collect()),
+        other => other,
+    }
+}
+
+fn camel_case(key: &str) -> String {
+    let mut camel = String::with_capacity(key.len());
+    let mut upper = false;
+    for c in key.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            camel.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    camel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qr_core::BinarizationMethod;
+    use serde_json::json;
+
+    #[test]
+    fn test_config_object_round_trip() {
+        let (processing, detection) = configs_from_json(json!({
+            "adaptiveThreshold": false,
+            "blockSize": 31,
+            "maxDimension": null,
+            "binarization": { "Sauvola": { "k": 0.3, "window": 25 } },
+            "detection": { "minSize": 40, "perspective_correct": false }
+        }))
+        .unwrap();
+
+        assert!(!processing.adaptive_threshold);
+        assert_eq!(processing.block_size, 31);
+        assert_eq!(processing.max_dimension, None);
+        assert_eq!(processing.binarization, Some(BinarizationMethod::Sauvola { k: 0.3, window: 25 }));
+        // Незаданные поля — по умолчанию
+        assert_eq!(processing.denoise_strength, ProcessingConfig::default().denoise_strength);
+        assert_eq!(detection.min_size, 40);
+        assert!(!detection.perspective_correct);
+        assert_eq!(detection.max_size, DetectorConfig::default().max_size);
+
+        let object = configs_to_json(&processing, &detection);
+        assert_eq!(object["blockSize"], json!(31));
+        assert_eq!(object["detection"]["minSize"], json!(40));
+        assert_eq!(object["binarization"]["Sauvola"]["window"], json!(25));
+
+        let (again, detection_again) = configs_from_json(object.clone()).unwrap();
+        assert_eq!(configs_to_json(&again, &detection_again), object);
+    }
+
+    #[test]
+    fn test_config_object_errors() {
+        assert!(configs_from_json(json!({ "blokSize": 31 })).unwrap_err().contains("blok_size"));
+        assert!(configs_from_json(json!({ "blockSize": "big" })).is_err());
+        assert!(configs_from_json(json!({ "detection": 5 })).is_err());
+        assert!(configs_from_json(json!([1, 2])).is_err());
+        assert!(configs_from_json(json!({})).is_ok());
+    }
+}
@@ -0,0 +1,114 @@
+//! Ошибки на границе WASM
+//!
+//! Методы бросают в JS объект `{ code, message }`: `code` — стабильная строка,
+//! по которой можно ветвиться (`error.code === "NOT_FOUND"`), `message` —
+//! текст для человека и может меняться.
+
+use qr_core::{DecodeError, QRError};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use wasm_bindgen::JsValue;
+
+/// Стабильные коды ошибок (перечислены в `ErrorCode` в TS_TYPES)
+pub(crate) mod codes {
+    /// Код не найден на изображении
+    pub const NOT_FOUND: &str = "NOT_FOUND";
+    /// Код найден, но не прочитан
+    pub const DECODE_FAILED: &str = "DECODE_FAILED";
+    /// Reed–Solomon не смог исправить ошибки
+    pub const CHECKSUM: &str = "CHECKSUM";
+    /// Байты не являются изображением или формат не поддерживается
+    pub const INVALID_FORMAT: &str = "INVALID_FORMAT";
+    /// Изображение непригодно для обработки (например, нулевой размер)
+    pub const INVALID_IMAGE: &str = "INVALID_IMAGE";
+    /// Неверные аргументы: размер буфера, область кадра, уровень логов
+    pub const INVALID_INPUT: &str = "INVALID_INPUT";
+    /// Некорректная конфигурация сканера
+    pub const INVALID_CONFIG: &str = "INVALID_CONFIG";
+    /// Модель ML-детектора не загружена
+    #[cfg_attr(not(feature = "ml"), allow(dead_code))]
+    pub const MODEL: &str = "MODEL";
+    /// Внутренняя ошибка (сериализация результата и т.п.)
+    pub const INTERNAL: &str = "INTERNAL";
+}
+
+/// Ошибка, которую получает JS
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WasmError {
+    /// Один из `codes`
+    pub code: String,
+    /// Описание для человека
+    pub message: String,
+}
+
+impl WasmError {
+    pub(crate) fn new(code: &str, message: impl Display) -> Self {
+        Self {
+            code: code.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    pub(crate) fn invalid_input(message: impl Display) -> Self {
+        Self::new(codes::INVALID_INPUT, message)
+    }
+
+    pub(crate) fn invalid_config(message: impl Display) -> Self {
+        Self::new(codes::INVALID_CONFIG, message)
+    }
+
+    pub(crate) fn internal(message: impl Display) -> Self {
+        Self::new(codes::INTERNAL, message)
+    }
+}
+
+impl From<DecodeError> for WasmError {
+    fn from(error: DecodeError) -> Self {
+        let code = match &error {
+            DecodeError::NotFound => codes::NOT_FOUND,
+            DecodeError::DecodeFailed(_) => codes::DECODE_FAILED,
+            DecodeError::ChecksumError => codes::CHECKSUM,
+            DecodeError::InvalidImage(_) => codes::INVALID_IMAGE,
+            DecodeError::InvalidConfig(_) => codes::INVALID_CONFIG,
+        };
+        Self::new(code, error)
+    }
+}
+
+impl From<QRError> for WasmError {
+    fn from(error: QRError) -> Self {
+        match error {
+            QRError::Decode(error) => error.into(),
+            QRError::ImageProcessing(_) => Self::new(codes::INVALID_IMAGE, error),
+            QRError::Detection(_) => Self::invalid_config(error),
+            QRError::InvalidFormat(_) => Self::new(codes::INVALID_FORMAT, error),
+        }
+    }
+}
+
+impl From<WasmError> for JsValue {
+    fn from(error: WasmError) -> Self {
+        serde_wasm_bindgen::to_value(&error).unwrap_or_else(|_| JsValue::from_str(&error.message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        let code = |error: QRError| WasmError::from(error).code;
+        assert_eq!(code(QRError::Decode(DecodeError::NotFound)), "NOT_FOUND");
+        assert_eq!(code(QRError::Decode(DecodeError::ChecksumError)), "CHECKSUM");
+        assert_eq!(code(QRError::InvalidFormat("png".into())), "INVALID_FORMAT");
+        assert_eq!(code(QRError::ImageProcessing("empty".into())), "INVALID_IMAGE");
+        assert_eq!(code(QRError::Detection("min_size".into())), "INVALID_CONFIG");
+
+        let error = WasmError::from(DecodeError::DecodeFailed("format info".into()));
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({ "code": "DECODE_FAILED", "message": "Failed to decode QR: format info" })
+        );
+    }
+}
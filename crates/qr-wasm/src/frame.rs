@@ -0,0 +1,226 @@
+//! Сканер видеопотока: состояние между кадрами камеры
+//!
+//! - Дебаунс по хэшу содержимого: код сообщается один раз, пока он в кадре
+//! - ROI: следующий кадр сначала сканируется вокруг найденных кодов,
+//!   при потере — снова целиком
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use image::GrayImage;
+use qr_core::{QRError, QRResult, QRScanner};
+use wasm_bindgen::prelude::*;
+
+use crate::error::WasmError;
+
+/// Сколько кадров подряд код может отсутствовать, прежде чем будет сообщён снова
+const DEFAULT_FORGET_AFTER: u32 = 30;
+
+/// Дебаунс кодов по хэшу содержимого
+pub(crate) struct Debouncer {
+    forget_after: u64,
+    frame: u64,
+    /// Хэш содержимого → номер кадра, где код встречался последним
+    seen: HashMap<u64, u64>,
+}
+
+impl Debouncer {
+    pub(crate) fn new(forget_after: u32) -> Self {
+        Self {
+            forget_after: forget_after as u64,
+            frame: 0,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Учёт очередного кадра: `true` для кодов, которые нужно сообщить
+    /// (впервые или после отсутствия дольше `forget_after` кадров)
+    pub(crate) fn observe<'a>(&mut self, contents: impl IntoIterator<Item = &'a str>) -> Vec<bool> {
+        self.frame += 1;
+        let (frame, forget_after) = (self.frame, self.forget_after);
+        // Между `last` и текущим кадром код отсутствовал `frame - last - 1` кадров
+        self.seen.retain(|_, last| frame - *last - 1 <= forget_after);
+
+        contents
+            .into_iter()
+            .map(|content| self.seen.insert(content_hash(content), frame).is_none())
+            .collect()
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.seen.clear();
+    }
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Область вокруг найденных кодов с запасом в половину их размера (`None`, если кодов нет)
+pub(crate) fn roi_around(codes: &[QRResult], width: u32, height: u32) -> Option<[u32; 4]> {
+    let x1 = codes.iter().map(|qr| qr.bbox[0]).min()?;
+    let y1 = codes.iter().map(|qr| qr.bbox[1]).min()?;
+    let x2 = codes.iter().map(|qr| qr.bbox[0] + qr.bbox[2]).max()?;
+    let y2 = codes.iter().map(|qr| qr.bbox[1] + qr.bbox[3]).max()?;
+    let margin = (x2 - x1).max(y2 - y1) / 2;
+
+    let (x1, y1) = (x1.saturating_sub(margin), y1.saturating_sub(margin));
+    let (x2, y2) = ((x2 + margin).min(width), (y2 + margin).min(height));
+    (x2 > x1 && y2 > y1).then_some([x1, y1, x2 - x1, y2 - y1])
+}
+
+/// Сканер кадров камеры для JavaScript: возвращает только новые коды
+#[wasm_bindgen]
+pub struct FrameScanner {
+    scanner: QRScanner,
+    debouncer: Debouncer,
+    roi: Option<[u32; 4]>,
+}
+
+#[wasm_bindgen]
+impl FrameScanner {
+    /// @param forget_after - сколько кадров код может пропадать, прежде чем
+    /// будет сообщён повторно (не указан — 30, около секунды видео)
+    #[wasm_bindgen(constructor)]
+    pub fn new(forget_after: Option<u32>) -> Self {
+        Self {
+            scanner: QRScanner::new(),
+            debouncer: Debouncer::new(forget_after.unwrap_or(DEFAULT_FORGET_AFTER)),
+            roi: None,
+        }
+    }
+
+    /// Сканирование кадра из canvas.getImageData()
+    ///
+    /// @returns массив QRResult, появившихся в этом кадре (пустой, если новых нет)
+    #[wasm_bindgen(js_name = scanFrame, unchecked_return_type = "QRResult[]")]
+    pub fn scan_frame(&mut self, data: &[u8], width: u32, height: u32) -> Result<JsValue, JsValue> {
+        let gray = crate::rgba_to_gray_image(&self.scanner, data, width, height).map_err(WasmError::invalid_input)?;
+        let new_codes = self.scan_gray(&gray).map_err(WasmError::from)?;
+        Ok(serde_wasm_bindgen::to_value(&new_codes).map_err(WasmError::internal)?)
+    }
+
+    /// Забыть увиденные коды и ROI (например, при смене камеры)
+    pub fn reset(&mut self) {
+        self.debouncer.reset();
+        self.roi = None;
+    }
+}
+
+impl FrameScanner {
+    /// Новые коды кадра; ROI следующего кадра — вокруг всех найденных
+    fn scan_gray(&mut self, gray: &GrayImage) -> Result<Vec<QRResult>, QRError> {
+        let (width, height) = gray.dimensions();
+        let mut codes = Vec::new();
+        // Сначала область вокруг кодов предыдущего кадра
+        if let Some([x, y, w, h]) = self.roi.filter(|r| r[0] + r[2] <= width && r[1] + r[3] <= height) {
+            let crop = image::imageops::crop_imm(gray, x, y, w, h).to_image();
+            if let Ok(result) = self.scanner.scan_image(&crop) {
+                codes = result.qr_codes;
+                codes.iter_mut().for_each(|qr| crate::offset_result(qr, x, y));
+            }
+        }
+        // Код потерян в ROI — расширяемся до всего кадра
+        if codes.is_empty() {
+            codes = self.scanner.scan_image(gray)?.qr_codes;
+        }
+        self.roi = roi_around(&codes, width, height);
+
+        let fresh = self.debouncer.observe(codes.iter().map(|qr| qr.content.as_str()));
+        Ok(codes
+            .into_iter()
+            .zip(fresh)
+            .filter_map(|(qr, fresh)| fresh.then_some(qr))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debounce_fires_once() {
+        let mut debouncer = Debouncer::new(2);
+        assert_eq!(debouncer.observe(["a"]), vec![true]);
+        assert_eq!(debouncer.observe(["a", "b"]), vec![false, true]);
+        assert_eq!(debouncer.observe(["a", "b", "b"]), vec![false, false, false]);
+        assert!(debouncer.observe(std::iter::empty()).is_empty());
+    }
+
+    #[test]
+    fn test_debounce_forgets_after_absence() {
+        let mut debouncer = Debouncer::new(2);
+        assert_eq!(debouncer.observe(["a"]), vec![true]);
+        // Два пропущенных кадра допустимы
+        debouncer.observe(std::iter::empty());
+        debouncer.observe(std::iter::empty());
+        assert_eq!(debouncer.observe(["a"]), vec![false]);
+        // Третий подряд — код забывается и сообщается снова
+        for _ in 0..3 {
+            debouncer.observe(std::iter::empty());
+        }
+        assert_eq!(debouncer.observe(["a"]), vec![true]);
+
+        debouncer.reset();
+        assert_eq!(debouncer.observe(["a"]), vec![true]);
+    }
+
+    #[test]
+    fn test_roi_around_codes() {
+        let qr = |bbox: [u32; 4]| QRResult {
+            content: String::new(),
+            bbox,
+            content_type: qr_core::ContentType::Text,
+            payment: None,
+            wifi: None,
+            geo: None,
+            uri: None,
+            calendar: None,
+            fields: None,
+            confidence: 1.0,
+            errors_corrected: None,
+            corners: None,
+            page: None,
+            debug_crop: None,
+        };
+        assert_eq!(roi_around(&[], 640, 480), None);
+        assert_eq!(roi_around(&[qr([200, 100, 100, 100])], 640, 480), Some([150, 50, 200, 200]));
+        // Запас обрезается по кадру
+        assert_eq!(roi_around(&[qr([10, 400, 60, 60])], 640, 480), Some([0, 370, 100, 110]));
+
+        let mut moved = qr([5, 6, 10, 10]);
+        moved.corners = Some([(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        crate::offset_result(&mut moved, 100, 50);
+        assert_eq!(moved.bbox, [105, 56, 10, 10]);
+        assert_eq!(moved.corners.unwrap()[2], (110.0, 60.0));
+    }
+
+    #[test]
+    fn test_roi_contains_code_in_hd_frame() {
+        // 1600px кадр сканер уменьшает до 1000px; ROI всё равно в пикселях кадра
+        let qr = qrcode::QrCode::new("hd frame").unwrap();
+        let side = qr.width() as u32 * 6;
+        let mut gray = GrayImage::from_pixel(1600, 900, image::Luma([255]));
+        qr_core::testing::draw_code(&mut gray, &qr, (1200, 500), 6);
+
+        let mut frames = FrameScanner::new(None);
+        let first = frames.scan_gray(&gray).unwrap();
+        assert_eq!(first.len(), 1);
+        let contains_code = |roi: Option<[u32; 4]>| {
+            let [x, y, w, h] = roi.expect("roi");
+            x <= 1200 && y <= 500 && x + w >= 1200 + side && y + h >= 500 + side && x + w <= 1600 && y + h <= 900
+        };
+        assert!(contains_code(frames.roi), "{:?}", frames.roi);
+
+        // Код читается внутри ROI; ROI следующего кадра — снова вокруг кода
+        let [x, y, w, h] = frames.roi.unwrap();
+        let crop = image::imageops::crop_imm(&gray, x, y, w, h).to_image();
+        assert_eq!(frames.scanner.scan_image(&crop).unwrap().qr_codes.len(), 1);
+        assert!(frames.scan_gray(&gray).unwrap().is_empty());
+        assert!(contains_code(frames.roi), "{:?}", frames.roi);
+    }
+}
@@ -2,15 +2,24 @@
 //!
 //! Предоставляет JavaScript API для распознавания QR-кодов
 
-use qr_core::{QRScanner, ScanResult, ProcessingConfig, DetectorConfig};
+use qr_core::{QRScanner, QRResult, ScanResult, ProcessingConfig, DetectorConfig, GrayscaleMethod, PaymentInfo, PaymentParser};
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
-use serde_wasm_bindgen;
+
+mod config;
+mod error;
+mod frame;
+mod logging;
+mod types;
+
+pub use error::WasmError;
+pub use frame::FrameScanner;
 
 /// Инициализация panic hook для отладки
 #[wasm_bindgen(start)]
 pub fn start() {
     console_error_panic_hook::set_once();
-    console_log::init_with_level(log::Level::Info).expect("error initializing logger");
+    logging::init();
     log::info!("WASM VERSION: RXING_NO_ML_V18");
     log::info!("QR Scanner WASM module initialized");
 }
@@ -19,6 +28,9 @@ pub fn start() {
 #[wasm_bindgen]
 pub struct WasmQRScanner {
     scanner: QRScanner,
+    debug_crops: bool,
+    /// Grayscale-буфер `scanImageDataReuse`, переживающий вызовы
+    gray_buffer: Vec<u8>,
 }
 
 #[wasm_bindgen]
@@ -28,10 +40,15 @@ impl WasmQRScanner {
     pub fn new() -> Self {
         Self {
             scanner: QRScanner::new(),
+            debug_crops: false,
+            gray_buffer: Vec::new(),
         }
     }
     
     /// Создание сканера с пользовательскими настройками
+    ///
+    /// @param max_dimension - предел большей стороны кадра перед распознаванием;
+    /// не указан — 1000px, 0 — без уменьшения (медленнее, но сохраняет мелкие модули)
     #[wasm_bindgen(js_name = withConfig)]
     pub fn with_config(
         adaptive_threshold: bool,
@@ -39,34 +56,118 @@ impl WasmQRScanner {
         denoise: bool,
         denoise_strength: f32,
         enhance_contrast: bool,
+        max_dimension: Option<u32>,
     ) -> Self {
-        let processing = ProcessingConfig {
+        let mut processing = ProcessingConfig {
             adaptive_threshold,
             block_size,
             denoise,
             denoise_strength,
             enhance_contrast,
+            ..Default::default()
         };
+        if let Some(max_dimension) = max_dimension {
+            processing.max_dimension = (max_dimension > 0).then_some(max_dimension);
+        }
         
         let detection = DetectorConfig::default();
         
         Self {
             scanner: QRScanner::with_config(processing, detection),
+            debug_crops: false,
+            gray_buffer: Vec::new(),
         }
     }
+
+    /// Создание сканера с выбором способа получения grayscale
+    ///
+    /// @param method - "luma", "red", "green", "blue" или "best_variance"
+    #[wasm_bindgen(js_name = withGrayscaleMethod)]
+    pub fn with_grayscale_method(method: &str) -> Result<WasmQRScanner, JsValue> {
+        let grayscale = match method {
+            "luma" => GrayscaleMethod::Luma,
+            "red" => GrayscaleMethod::Red,
+            "green" => GrayscaleMethod::Green,
+            "blue" => GrayscaleMethod::Blue,
+            "best_variance" => GrayscaleMethod::BestVarianceChannel,
+            other => return Err(WasmError::invalid_config(format!("Unknown grayscale method: {}", other)).into()),
+        };
+
+        let processing = ProcessingConfig {
+            grayscale,
+            ..Default::default()
+        };
+
+        Ok(Self {
+            scanner: QRScanner::with_config(processing, DetectorConfig::default()),
+            debug_crops: false,
+            gray_buffer: Vec::new(),
+        })
+    }
+    
+    /// Создание сканера из объекта с полями `ProcessingConfig`
+    ///
+    /// @param config - объект вида `{ adaptiveThreshold: true, blockSize: 31, maxDimension: null,
+    /// detection: { minSize: 30 } }`; незаданные поля — по умолчанию, неизвестные — ошибка
+    #[wasm_bindgen(js_name = withConfigObject)]
+    pub fn with_config_object(config: JsValue) -> Result<WasmQRScanner, JsValue> {
+        let value: serde_json::Value = if config.is_undefined() || config.is_null() {
+            serde_json::Value::Object(Default::default())
+        } else {
+            serde_wasm_bindgen::from_value(config).map_err(WasmError::invalid_config)?
+        };
+        let (processing, detection) = config::configs_from_json(value).map_err(WasmError::invalid_config)?;
+
+        Ok(Self {
+            scanner: QRScanner::with_config(processing, detection),
+            debug_crops: false,
+            gray_buffer: Vec::new(),
+        })
+    }
+
+    /// Текущая конфигурация в форме, которую принимает `withConfigObject`
+    #[wasm_bindgen(js_name = configObject)]
+    pub fn config_object(&self) -> Result<JsValue, JsValue> {
+        let value = config::configs_to_json(self.scanner.processing_config(), self.scanner.detector_config());
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+        Ok(serde::Serialize::serialize(&value, &serializer).map_err(WasmError::internal)?)
+    }
+
+    /// Загрузка ONNX-модели детектора YOLOv8 (сборка с feature `ml`)
+    ///
+    /// @param model_bytes - Uint8Array с моделью
+    /// @param conf_threshold - порог уверенности (не указан — 0.5)
+    /// @param iou_threshold - порог IoU для NMS (не указан — 0.45)
+    #[cfg(feature = "ml")]
+    #[wasm_bindgen(js_name = loadModel)]
+    pub fn load_model(
+        &mut self,
+        model_bytes: &[u8],
+        conf_threshold: Option<f32>,
+        iou_threshold: Option<f32>,
+    ) -> Result<(), JsValue> {
+        use qr_core::ml_detection::{OnnxDetector, DEFAULT_CONF_THRESHOLD, DEFAULT_IOU_THRESHOLD};
+
+        let mut detector = OnnxDetector::load(model_bytes).map_err(|e| WasmError::new(error::codes::MODEL, e))?;
+        detector
+            .set_thresholds(
+                conf_threshold.unwrap_or(DEFAULT_CONF_THRESHOLD),
+                iou_threshold.unwrap_or(DEFAULT_IOU_THRESHOLD),
+            )
+            .map_err(WasmError::invalid_config)?;
+        self.scanner.set_ml_detector(detector);
+        Ok(())
+    }
     
     /// Сканирование изображения из байтов (PNG, JPEG)
     /// 
     /// @param image_data - Uint8Array с данными изображения
     /// @returns Object с результатами сканирования
-    #[wasm_bindgen(js_name = scanImage)]
-    pub fn scan_image(&self, image_data: &[u8]) -> Result<JsValue, JsError> {
+    #[wasm_bindgen(js_name = scanImage, unchecked_return_type = "ScanResult")]
+    pub fn scan_image(&self, image_data: &[u8]) -> Result<JsValue, JsValue> {
         match self.scanner.scan_bytes(image_data) {
-            Ok(result) => {
-                serde_wasm_bindgen::to_value(&result)
-                    .map_err(|e| JsError::new(&e.to_string()))
-            }
-            Err(e) => Err(JsError::new(&e.to_string())),
+            Ok(result) => self.result_to_js(&result),
+            Err(e) => Err(WasmError::from(e).into()),
         }
     }
     
@@ -76,69 +177,245 @@ impl WasmQRScanner {
     /// @param width - Ширина изображения
     /// @param height - Высота изображения
     /// @returns Object с результатами сканирования
-    #[wasm_bindgen(js_name = scanImageData)]
+    #[wasm_bindgen(js_name = scanImageData, unchecked_return_type = "ScanResult")]
     pub fn scan_image_data(
         &self,
         data: &[u8],
         width: u32,
         height: u32,
-    ) -> Result<JsValue, JsError> {
-        // Конвертируем RGBA в Grayscale
-        let gray = self.rgba_to_gray(data, width, height);
-        
-        // Создаём GrayImage
-        let img = match image::GrayImage::from_raw(width, height, gray) {
-            Some(img) => img,
-            None => return Err(JsError::new("Failed to create image from data")),
-        };
-        
-        // Сканируем
-        // Сканируем
-        match self.scanner.scan_image(&img) {
-            Ok(result) => {
-                serde_wasm_bindgen::to_value(&result)
-                    .map_err(|e| JsError::new(&e.to_string()))
-            }
-            Err(e) => Err(JsError::new(&e.to_string())),
+    ) -> Result<JsValue, JsValue> {
+        // Яркость из RGBA передаётся в сканер без повторного копирования
+        let luma = rgba_to_luma(&self.scanner, data, width, height).map_err(WasmError::invalid_input)?;
+        match self.scanner.scan_luma(width, height, luma) {
+            Ok(result) => self.result_to_js(&result),
+            Err(e) => Err(WasmError::from(e).into()),
         }
     }
     
+    /// Сканирование ImageData с повторным использованием grayscale-буфера сканера
+    ///
+    /// В видеоцикле не выделяет новый буфер на каждый кадр: память перевыделяется
+    /// только при смене размера кадра. Метод изменяет состояние сканера и не
+    /// потокобезопасен — в браузере вызовы идут из одного потока; один сканер
+    /// нельзя делить между воркерами. Канальные методы grayscale (`Red`,
+    /// `BestVarianceChannel`, ...) по-прежнему выделяют буфер на кадр.
+    ///
+    /// @param data - Uint8ClampedArray из canvas.getImageData()
+    #[wasm_bindgen(js_name = scanImageDataReuse, unchecked_return_type = "ScanResult")]
+    pub fn scan_image_data_reuse(&mut self, data: &[u8], width: u32, height: u32) -> Result<JsValue, JsValue> {
+        let result = self.scan_reusing_buffer(data, width, height).map_err(WasmError::invalid_input)?;
+        self.result_to_js(&result)
+    }
+
+    /// Сканирование сырого плана яркости (Y-плоскость NV21/YUV420 камеры)
+    ///
+    /// @param luma - Uint8Array ровно из `width * height` байт, построчно без отступов
+    /// @param width - Ширина кадра
+    /// @param height - Высота кадра
+    #[wasm_bindgen(js_name = scanLuma, unchecked_return_type = "ScanResult")]
+    pub fn scan_luma(&self, luma: &[u8], width: u32, height: u32) -> Result<JsValue, JsValue> {
+        match self.scanner.scan_luma(width, height, luma) {
+            Ok(result) => self.result_to_js(&result),
+            Err(e) => Err(WasmError::from(e).into()),
+        }
+    }
+
+    /// Сканирование только прямоугольной области кадра (рамка прицела):
+    /// быстрее и игнорирует коды на фоне. `bbox` и углы в результате — в координатах всего кадра.
+    ///
+    /// @param data - Uint8ClampedArray из canvas.getImageData()
+    /// @param x, y, w, h - область в пикселях кадра
+    #[wasm_bindgen(js_name = scanImageDataRegion, unchecked_return_type = "ScanResult")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn scan_image_data_region(
+        &self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+    ) -> Result<JsValue, JsValue> {
+        let result = self
+            .scan_region(data, width, height, [x, y, w, h])
+            .map_err(WasmError::invalid_input)?;
+        self.result_to_js(&result)
+    }
+
+    /// Включение кэша кадров для камерного цикла
+    ///
+    /// @param threshold - допустимое расстояние Хэмминга между хэшами кадров (0-64)
+    #[wasm_bindgen(js_name = enableFrameCache)]
+    pub fn enable_frame_cache(&mut self, threshold: u32) {
+        self.scanner.enable_frame_cache(threshold);
+    }
+
+    /// Отключение кэша кадров
+    #[wasm_bindgen(js_name = disableFrameCache)]
+    pub fn disable_frame_cache(&mut self) {
+        self.scanner.disable_frame_cache();
+    }
+
+    /// Уровень логов модуля (общий для всех сканеров): "off", "error", "warn",
+    /// "info" (по умолчанию), "debug", "trace"
+    #[wasm_bindgen(js_name = setLogLevel)]
+    pub fn set_log_level(level: &str) -> Result<(), JsValue> {
+        Ok(logging::set_level(level).map_err(WasmError::invalid_input)?)
+    }
+
+    /// Перенаправление логов в функцию `(level, message, target) => void`
+    /// вместо консоли браузера; `null` возвращает вывод в консоль
+    #[wasm_bindgen(js_name = setLogSink)]
+    pub fn set_log_sink(sink: Option<js_sys::Function>) {
+        logging::set_sink(sink);
+    }
+
+    /// Отладочный режим: каждый QR в результате получает поле `debug_crop`
+    /// с data URL (PNG) изображения, которое было подано в декодер
+    #[wasm_bindgen(js_name = setDebugCrops)]
+    pub fn set_debug_crops(&mut self, enabled: bool) {
+        self.debug_crops = enabled;
+        self.scanner.set_debug_crops(enabled);
+    }
+    
     /// Поиск платёжного QR-кода
     /// 
     /// @param image_data - Uint8Array с данными изображения
     /// @returns PaymentInfo или null
-    #[wasm_bindgen(js_name = scanForPayment)]
-    pub fn scan_for_payment(&self, image_data: &[u8]) -> Result<JsValue, JsError> {
+    #[wasm_bindgen(js_name = scanForPayment, unchecked_return_type = "PaymentInfo | null")]
+    pub fn scan_for_payment(&self, image_data: &[u8]) -> Result<JsValue, JsValue> {
         match self.scanner.scan_for_payment(image_data) {
             Ok(Some(payment)) => {
-                serde_wasm_bindgen::to_value(&payment)
-                    .map_err(|e| JsError::new(&e.to_string()))
+                Ok(serde_wasm_bindgen::to_value(&payment).map_err(WasmError::internal)?)
             }
             Ok(None) => Ok(JsValue::NULL),
-            Err(e) => Err(JsError::new(&e.to_string())),
+            Err(e) => Err(WasmError::from(e).into()),
         }
     }
 
+    /// Поиск платёжного QR-кода с позицией и уверенностью
+    ///
+    /// @param image_data - Uint8Array с данными изображения
+    /// @returns `{ found, payment, confidence, bbox }`; при `found === false`
+    /// `payment` и `bbox` не заданы, `confidence` равен 0
+    #[wasm_bindgen(js_name = scanForPaymentDetailed, unchecked_return_type = "PaymentScan")]
+    pub fn scan_for_payment_detailed(&self, image_data: &[u8]) -> Result<JsValue, JsValue> {
+        let result = self
+            .scanner
+            .scan_bytes(image_data)
+            .map_err(WasmError::from)?;
+        Ok(serde_wasm_bindgen::to_value(&payment_scan(&result)).map_err(WasmError::internal)?)
+    }
+
 
     
-    /// Конвертация RGBA в Grayscale
-    fn rgba_to_gray(&self, rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
-        let pixel_count = (width * height) as usize;
-        let mut gray = Vec::with_capacity(pixel_count);
-        
-        for i in 0..pixel_count {
-            let offset = i * 4;
-            if offset + 2 < rgba.len() {
-                let r = rgba[offset] as f32;
-                let g = rgba[offset + 1] as f32;
-                let b = rgba[offset + 2] as f32;
-                // ITU-R BT.601 luma formula
-                let luma = (0.299 * r + 0.587 * g + 0.114 * b) as u8;
-                gray.push(luma);
-            }
+    /// Только текст QR из ImageData: без детекции, типа контента и платёжного разбора
+    ///
+    /// @param data - Uint8ClampedArray из canvas.getImageData()
+    /// @returns строка или null, если код не найден
+    #[wasm_bindgen(js_name = decodeText, unchecked_return_type = "string | null")]
+    pub fn decode_text(&self, data: &[u8], width: u32, height: u32) -> Result<JsValue, JsValue> {
+        let img = rgba_to_gray_image(&self.scanner, data, width, height).map_err(WasmError::invalid_input)?;
+        Ok(text_to_js(self.decode_gray_text(&img)))
+    }
+
+    /// Только текст QR из байтов изображения (PNG, JPEG)
+    ///
+    /// @param image_data - Uint8Array с данными изображения
+    /// @returns строка или null, если код не найден
+    #[wasm_bindgen(js_name = decodeTextBytes, unchecked_return_type = "string | null")]
+    pub fn decode_text_bytes(&self, image_data: &[u8]) -> Result<JsValue, JsValue> {
+        let img = image::load_from_memory(image_data).map_err(|e| WasmError::new(error::codes::INVALID_FORMAT, e))?;
+        Ok(text_to_js(self.decode_gray_text(&self.scanner.to_grayscale(&img))))
+    }
+
+    /// Текст кода; ненайденный код и пустой кадр — `None`
+    fn decode_gray_text(&self, img: &image::GrayImage) -> Option<String> {
+        self.scanner.decode_text(img).ok()
+    }
+
+    fn scan_reusing_buffer(&mut self, data: &[u8], width: u32, height: u32) -> Result<ScanResult, String> {
+        let pixels = checked_rgba_len(data, width, height)? / 4;
+        if let Some(gray) = self.scanner.rgba_channel_to_gray(data, width, height) {
+            return self.scanner.scan_image(&gray).map_err(|e| e.to_string());
         }
-        
-        gray
+
+        let mut buffer = std::mem::take(&mut self.gray_buffer);
+        buffer.resize(pixels, 0);
+        rgba_to_gray_into(data, &mut buffer);
+        let img = image::GrayImage::from_raw(width, height, buffer)
+            .ok_or_else(|| "Failed to create image from data".to_string())?;
+        let result = self.scanner.scan_image(&img);
+        self.gray_buffer = img.into_raw();
+        result.map_err(|e| e.to_string())
+    }
+
+    fn scan_region(&self, data: &[u8], width: u32, height: u32, roi: [u32; 4]) -> Result<ScanResult, String> {
+        let [x, y, w, h] = roi;
+        let inside = w > 0
+            && h > 0
+            && x.checked_add(w).is_some_and(|right| right <= width)
+            && y.checked_add(h).is_some_and(|bottom| bottom <= height);
+        if !inside {
+            return Err(format!(
+                "Region {}x{} at ({}, {}) is outside the {}x{} frame",
+                w, h, x, y, width, height
+            ));
+        }
+
+        let img = rgba_to_gray_image(&self.scanner, data, width, height)?;
+        let crop = image::imageops::crop_imm(&img, x, y, w, h).to_image();
+
+        let mut result = self.scanner.scan_image(&crop).map_err(|e| e.to_string())?;
+        result.qr_codes.iter_mut().for_each(|qr| offset_result(qr, x, y));
+        Ok(result)
+    }
+
+    /// Сериализация результата; в отладочном режиме добавляет `debug_crop` к каждому QR
+    fn result_to_js(&self, result: &ScanResult) -> Result<JsValue, JsValue> {
+        let value = serde_wasm_bindgen::to_value(result).map_err(WasmError::internal)?;
+        if !self.debug_crops {
+            return Ok(value);
+        }
+
+        let codes = js_sys::Reflect::get(&value, &JsValue::from_str("qr_codes"))
+            .map_err(|_| WasmError::internal("qr_codes missing in scan result"))?;
+        let codes = js_sys::Array::from(&codes);
+        for (item, qr) in codes.iter().zip(&result.qr_codes) {
+            let crop = qr.debug_crop.as_ref().and_then(png_data_url);
+            let crop = crop.map(|url| JsValue::from_str(&url)).unwrap_or(JsValue::NULL);
+            js_sys::Reflect::set(&item, &JsValue::from_str("debug_crop"), &crop)
+                .map_err(|_| WasmError::internal("failed to attach debug_crop"))?;
+        }
+        Ok(value)
+    }
+}
+
+/// Результат `scanForPaymentDetailed`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct PaymentScan {
+    /// Найден разобранный платёжный QR
+    pub found: bool,
+    /// Платёжные данные лучшего QR
+    pub payment: Option<PaymentInfo>,
+    /// Релевантность (`PaymentParser::relevance_score`), 0 если не найден
+    pub confidence: f32,
+    /// Bounding box [x, y, width, height] в пикселях исходного изображения (не уменьшенного до `max_dimension`)
+    pub bbox: Option<[u32; 4]>,
+}
+
+/// Лучший платёжный QR из результата сканирования
+fn payment_scan(result: &ScanResult) -> PaymentScan {
+    let best = result.best_payment.and_then(|idx| result.qr_codes.get(idx));
+    match best.and_then(|qr| qr.payment.clone().map(|payment| (qr, payment))) {
+        Some((qr, payment)) => PaymentScan {
+            found: true,
+            payment: Some(payment),
+            confidence: PaymentParser::new().relevance_score(&qr.content),
+            bbox: Some(qr.bbox),
+        },
+        None => PaymentScan::default(),
     }
 }
 
@@ -148,13 +425,138 @@ impl Default for WasmQRScanner {
     }
 }
 
+/// RGBA-буфер → GrayImage (канальный метод из конфигурации сканера или BT.601 luma).
+/// Длина буфера должна быть ровно `width * height * 4`.
+pub(crate) fn rgba_to_gray_image(scanner: &QRScanner, data: &[u8], width: u32, height: u32) -> Result<image::GrayImage, String> {
+    image::GrayImage::from_raw(width, height, rgba_to_luma(scanner, data, width, height)?)
+        .ok_or_else(|| "Failed to create image from data".to_string())
+}
+
+/// RGBA-буфер → план яркости `width * height` байт для `QRScanner::scan_luma`
+fn rgba_to_luma(scanner: &QRScanner, data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
+    checked_rgba_len(data, width, height)?;
+    Ok(match scanner.rgba_channel_to_gray(data, width, height) {
+        Some(gray) => gray.into_raw(),
+        None => rgba_to_gray(data, width, height),
+    })
+}
+
+/// Проверка длины RGBA-буфера: ровно `width * height * 4` байт
+fn checked_rgba_len(data: &[u8], width: u32, height: u32) -> Result<usize, String> {
+    let expected = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .ok_or_else(|| format!("Frame {}x{} is too large", width, height))?;
+    if data.len() != expected {
+        return Err(format!(
+            "Invalid RGBA buffer for {}x{} frame: expected {} bytes, got {}",
+            width, height, expected, data.len()
+        ));
+    }
+    Ok(expected)
+}
+
+/// Конвертация RGBA в Grayscale (BT.601 luma в фиксированной точке)
+///
+/// `(77 * r + 150 * g + 29 * b) >> 8` отличается от `0.299 * r + 0.587 * g + 0.114 * b`
+/// не более чем на 1 и помещается в u16: блоки по `LANES` пикселей считаются
+/// целочисленно и без ветвлений.
+/// Пиксели сверх `width * height` и неполный хвост буфера отбрасываются.
+pub fn rgba_to_gray(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let pixel_count = (width as usize * height as usize).min(rgba.len() / 4);
+    let mut gray = vec![0u8; pixel_count];
+    rgba_to_gray_into(rgba, &mut gray);
+    gray
+}
+
+/// `rgba_to_gray` в готовый буфер: заполняется `min(gray.len(), rgba.len() / 4)` пикселей
+fn rgba_to_gray_into(rgba: &[u8], gray: &mut [u8]) {
+    const LANES: usize = 16;
+
+    let pixel_count = gray.len().min(rgba.len() / 4);
+    let rgba = &rgba[..pixel_count * 4];
+    let gray = &mut gray[..pixel_count];
+
+    let luma = |px: &[u8]| ((77 * px[0] as u16 + 150 * px[1] as u16 + 29 * px[2] as u16) >> 8) as u8;
+    let mut blocks = gray.chunks_exact_mut(LANES);
+    let mut pixels = rgba.chunks_exact(LANES * 4);
+    for (out, block) in blocks.by_ref().zip(pixels.by_ref()) {
+        for (value, px) in out.iter_mut().zip(block.chunks_exact(4)) {
+            *value = luma(px);
+        }
+    }
+    for (value, px) in blocks.into_remainder().iter_mut().zip(pixels.remainder().chunks_exact(4)) {
+        *value = luma(px);
+    }
+}
+
+/// Строка или `null` для JS
+fn text_to_js(text: Option<String>) -> JsValue {
+    text.map_or(JsValue::NULL, |text| JsValue::from_str(&text))
+}
+
+/// Перевод результата из координат области в координаты кадра
+pub(crate) fn offset_result(qr: &mut QRResult, dx: u32, dy: u32) {
+    qr.bbox[0] += dx;
+    qr.bbox[1] += dy;
+    if let Some(corners) = qr.corners.as_mut() {
+        for corner in corners.iter_mut() {
+            corner.0 += dx as f32;
+            corner.1 += dy as f32;
+        }
+    }
+}
+
+/// Кодирование grayscale-изображения в `data:image/png;base64,...`
+fn png_data_url(img: &image::GrayImage) -> Option<String> {
+    let mut png = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).ok()?;
+    Some(format!("data:image/png;base64,{}", base64_encode(&png)))
+}
+
+/// Стандартный base64 (RFC 4648) с паддингом
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
 /// Удобная функция для быстрого сканирования
-#[wasm_bindgen(js_name = quickScan)]
-pub fn quick_scan(image_data: &[u8]) -> Result<JsValue, JsError> {
+#[wasm_bindgen(js_name = quickScan, unchecked_return_type = "ScanResult")]
+pub fn quick_scan(image_data: &[u8]) -> Result<JsValue, JsValue> {
     let scanner = WasmQRScanner::new();
     scanner.scan_image(image_data)
 }
 
+/// Y-плоскость кадра NV21 без отступов строк (для `scanLuma`)
+///
+/// @param data - весь кадр NV21 (Y, затем VU)
+/// @param y_stride - длина строки Y-плоскости в байтах (не меньше `width`)
+#[wasm_bindgen(js_name = nv21ToLuma)]
+pub fn nv21_to_luma(data: &[u8], width: u32, height: u32, y_stride: u32) -> Result<Vec<u8>, JsValue> {
+    Ok(qr_core::color::nv21_to_luma(data, width, height, y_stride).map_err(WasmError::from)?)
+}
+
+/// Y-плоскость планарного YUV420 без отступов строк (для `scanLuma`)
+///
+/// @param y_plane - только плоскость яркости
+/// @param y_stride - длина строки Y-плоскости в байтах (не меньше `width`)
+#[wasm_bindgen(js_name = yuv420ToLuma)]
+pub fn yuv420_to_luma(y_plane: &[u8], width: u32, height: u32, y_stride: u32) -> Result<Vec<u8>, JsValue> {
+    Ok(qr_core::color::yuv420_to_luma(y_plane, width, height, y_stride).map_err(WasmError::from)?)
+}
+
 /// Информация о версии
 #[wasm_bindgen(js_name = version)]
 pub fn version() -> String {
@@ -168,12 +570,209 @@ mod tests {
     
     wasm_bindgen_test_configure!(run_in_browser);
     
-    #[wasm_bindgen_test]
+    #[test]
     fn test_scanner_creation() {
         let _scanner = WasmQRScanner::new();
     }
     
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+    
+    /// RGBA-кадр с QR (4px на модуль) в точке `(ox, oy)`
+    fn rgba_frame(content: &str, width: u32, height: u32, ox: u32, oy: u32) -> Vec<u8> {
+        let mut gray = image::GrayImage::from_pixel(width, height, image::Luma([255]));
+        qr_core::testing::draw_code(&mut gray, &qrcode::QrCode::new(content).unwrap(), (ox, oy), 4);
+        gray.pixels().flat_map(|p| [p.0[0], p.0[0], p.0[0], 255]).collect()
+    }
+
+    #[test]
+    fn test_scan_region_off_center() {
+        let scanner = WasmQRScanner::new();
+        let rgba = rgba_frame("region", 640, 480, 420, 300);
+
+        let result = scanner.scan_region(&rgba, 640, 480, [380, 260, 200, 180]).unwrap();
+        assert_eq!(result.qr_codes.len(), 1);
+        let qr = &result.qr_codes[0];
+        assert_eq!(qr.content, "region");
+        // Координаты — в системе всего кадра, а не области
+        assert!(qr.bbox[0] >= 380 && qr.bbox[0] <= 430, "{:?}", qr.bbox);
+        assert!(qr.bbox[1] >= 260 && qr.bbox[1] <= 310, "{:?}", qr.bbox);
+        let corners = qr.corners.expect("corners");
+        assert!((corners[0].0 - 420.0).abs() < 8.0 && (corners[0].1 - 300.0).abs() < 8.0, "{:?}", corners);
+
+        // Код вне области не находится
+        let empty = scanner.scan_region(&rgba, 640, 480, [0, 0, 200, 200]).unwrap();
+        assert!(empty.qr_codes.is_empty());
+
+        assert!(scanner.scan_region(&rgba, 640, 480, [500, 0, 200, 100]).is_err());
+        assert!(scanner.scan_region(&rgba, 640, 480, [0, 0, 0, 100]).is_err());
+        assert!(scanner.scan_region(&rgba, 640, 480, [u32::MAX, 0, 10, 10]).is_err());
+    }
+
+    #[test]
+    fn test_scan_region_larger_than_max_dimension() {
+        // Область 1500x1100 сканер уменьшает до 1000px; координаты всё равно в кадре
+        let mut gray = image::GrayImage::from_pixel(1800, 1300, image::Luma([255]));
+        let qr = qrcode::QrCode::new("large region").unwrap();
+        let side = qr.width() as u32 * 6;
+        qr_core::testing::draw_code(&mut gray, &qr, (1200, 900), 6);
+        let rgba: Vec<u8> = gray.pixels().flat_map(|p| [p.0[0], p.0[0], p.0[0], 255]).collect();
+
+        let scanner = WasmQRScanner::new();
+        let result = scanner.scan_region(&rgba, 1800, 1300, [100, 100, 1500, 1100]).unwrap();
+        assert_eq!(result.qr_codes.len(), 1);
+        let [x, y, w, h] = result.qr_codes[0].bbox;
+        assert!(x <= 1200 && y <= 900 && x + w >= 1200 + side && y + h >= 900 + side, "{:?}", result.qr_codes[0].bbox);
+        assert!(x >= 1100 && y >= 800, "{:?}", result.qr_codes[0].bbox);
+        let corners = result.qr_codes[0].corners.expect("corners");
+        assert!((corners[0].0 - 1200.0).abs() < 10.0 && (corners[0].1 - 900.0).abs() < 10.0, "{:?}", corners);
+    }
+
+    #[test]
+    fn test_payment_scan() {
+        let scanner = WasmQRScanner::new();
+        let gray = |content: &str| {
+            let rgba = rgba_frame(content, 320, 320, 40, 40);
+            rgba_to_gray_image(&scanner.scanner, &rgba, 320, 320).unwrap()
+        };
+
+        let result = scanner.scanner.scan_image(&gray("https://example.com/menu")).unwrap();
+        assert_eq!(result.qr_codes.len(), 1);
+        let none = payment_scan(&result);
+        assert!(!none.found);
+        assert!(none.payment.is_none() && none.bbox.is_none());
+        assert_eq!(none.confidence, 0.0);
+
+        let result = scanner.scanner.scan_image(&gray("https://qr.nspk.ru/AS1000670LSS7DN18SJQDNP4B05KLJL2?type=01")).unwrap();
+        let found = payment_scan(&result);
+        assert!(found.found);
+        assert_eq!(found.confidence, 1.0);
+        let bbox = found.bbox.expect("bbox");
+        assert!(bbox[0] >= 30 && bbox[0] <= 50, "{:?}", bbox);
+    }
+
+    #[test]
+    fn test_payment_scan_bbox_in_frame_coordinates() {
+        // Фото 2400x1600 (больше max_dimension): bbox в пикселях исходного изображения
+        let sbp = qrcode::QrCode::new("https://qr.nspk.ru/AS1000670LSS7DN18SJQDNP4B05KLJL2?type=01").unwrap();
+        let side = sbp.width() as u32 * 8;
+        let mut photo = image::GrayImage::from_pixel(2400, 1600, image::Luma([255]));
+        qr_core::testing::draw_code(&mut photo, &sbp, (1800, 1100), 8);
+        let mut png = Vec::new();
+        image::DynamicImage::ImageLuma8(photo)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let scanner = WasmQRScanner::new();
+        let found = payment_scan(&scanner.scanner.scan_bytes(&png).unwrap());
+        assert!(found.found);
+        let [x, y, w, h] = found.bbox.expect("bbox");
+        assert!(x.abs_diff(1800) <= 4 && y.abs_diff(1100) <= 4, "{:?}", found.bbox);
+        assert!(w.abs_diff(side) <= 8 && h.abs_diff(side) <= 8, "{:?}", found.bbox);
+    }
+
+    #[test]
+    fn test_scan_reusing_buffer() {
+        let mut scanner = WasmQRScanner::new();
+
+        let first = scanner.scan_reusing_buffer(&rgba_frame("frame one", 320, 240, 40, 40), 320, 240).unwrap();
+        assert_eq!(first.qr_codes.len(), 1);
+        assert_eq!(first.qr_codes[0].content, "frame one");
+        let buffer = scanner.gray_buffer.as_ptr();
+        assert_eq!(scanner.gray_buffer.len(), 320 * 240);
+
+        // Кадр того же размера — тот же буфер, результат не зависит от предыдущего кадра
+        let second = scanner.scan_reusing_buffer(&rgba_frame("frame two", 320, 240, 150, 90), 320, 240).unwrap();
+        assert_eq!(second.qr_codes.len(), 1);
+        assert_eq!(second.qr_codes[0].content, "frame two");
+        assert!(second.qr_codes[0].bbox[0] >= 140, "{:?}", second.qr_codes[0].bbox);
+        assert_eq!(scanner.gray_buffer.as_ptr(), buffer);
+
+        assert!(scanner.scan_reusing_buffer(&[0; 16], 320, 240).is_err());
+        let empty = scanner.scan_reusing_buffer(&vec![255; 160 * 120 * 4], 160, 120).unwrap();
+        assert!(empty.qr_codes.is_empty());
+        assert_eq!(scanner.gray_buffer.len(), 160 * 120);
+    }
+
+    #[test]
+    fn test_decode_text_skips_payment_parsing() {
+        let scanner = WasmQRScanner::new();
+        let gray = |content: &str| {
+            let rgba = rgba_frame(content, 320, 320, 40, 40);
+            rgba_to_gray_image(&scanner.scanner, &rgba, 320, 320).unwrap()
+        };
+
+        assert_eq!(scanner.decode_gray_text(&gray("plain text")).as_deref(), Some("plain text"));
+        let sbp = "https://qr.nspk.ru/AS1000670LSS7DN18SJQDNP4B05KLJL2?type=01";
+        assert_eq!(scanner.decode_gray_text(&gray(sbp)).as_deref(), Some(sbp));
+        assert_eq!(scanner.decode_gray_text(&image::GrayImage::from_pixel(320, 320, image::Luma([255]))), None);
+        assert_eq!(scanner.decode_gray_text(&image::GrayImage::new(0, 0)), None);
+    }
+
+    #[test]
+    fn test_rgba_to_gray_matches_float_bt601() {
+        // Прежняя реализация с плавающей точкой
+        let reference = |px: &[u8]| (0.299 * px[0] as f32 + 0.587 * px[1] as f32 + 0.114 * px[2] as f32) as u8;
+
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let rgba: Vec<u8> = (0..4 * 1001)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .chain([255, 255, 255, 255, 0, 0, 0, 255])
+            .collect();
+        let gray = rgba_to_gray(&rgba, 1003, 1);
+        assert_eq!(gray.len(), 1003);
+        for (value, px) in gray.iter().zip(rgba.chunks_exact(4)) {
+            assert!(value.abs_diff(reference(px)) <= 1, "{:?}: {} vs {}", px, value, reference(px));
+        }
+        assert_eq!(gray[1001..], [255, 0]);
+
+        // Буфер короче кадра: только целые пиксели
+        assert_eq!(rgba_to_gray(&rgba[..10], 4, 1).len(), 2);
+    }
+
+    #[test]
+    fn test_short_rgba_buffer() {
+        let scanner = WasmQRScanner::new();
+        let rgba = rgba_frame("short", 128, 128, 8, 8);
+
+        let error = rgba_to_gray_image(&scanner.scanner, &rgba[..rgba.len() - 3], 128, 128).unwrap_err();
+        assert!(error.contains("expected 65536 bytes, got 65533"), "{}", error);
+        assert!(rgba_to_gray_image(&scanner.scanner, &rgba, 128, 129).is_err());
+        assert!(rgba_to_gray_image(&scanner.scanner, &rgba, u32::MAX, u32::MAX).is_err());
+        assert!(rgba_to_gray_image(&scanner.scanner, &rgba, 128, 128).is_ok());
+    }
+
     #[wasm_bindgen_test]
+    fn test_scan_image_data_short_buffer() {
+        let scanner = WasmQRScanner::new();
+        let rgba = rgba_frame("short", 128, 128, 8, 8);
+        assert!(scanner.scan_image_data(&rgba[..100], 128, 128).is_err());
+    }
+
+    #[test]
+    fn test_undecodable_buffer_error_code() {
+        let scanner = WasmQRScanner::new();
+
+        let error = WasmError::from(scanner.scanner.scan_bytes(b"not an image").unwrap_err());
+        assert_eq!(error.code, "INVALID_FORMAT");
+        assert!(!error.message.is_empty());
+
+        let blank = image::GrayImage::from_pixel(64, 64, image::Luma([255]));
+        let error = WasmError::from(scanner.scanner.decode_text(&blank).unwrap_err());
+        assert_eq!(error.code, "NOT_FOUND");
+    }
+
+    #[test]
     fn test_version() {
         let v = version();
         assert!(!v.is_empty());
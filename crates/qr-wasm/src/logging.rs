@@ -0,0 +1,125 @@
+//! Вывод `log` из qr-core и qr-wasm
+//!
+//! По умолчанию записи уровня Info и выше уходят в консоль браузера
+//! (`console_log`). Хост может сменить уровень (`setLogLevel`) или
+//! перенаправить записи в свою функцию (`setLogSink`). Настройки общие
+//! для всего модуля: wasm в браузере однопоточный.
+
+use log::{LevelFilter, Log, Metadata, Record};
+use std::cell::RefCell;
+use std::str::FromStr;
+use wasm_bindgen::JsValue;
+
+/// Уровень до первого вызова `setLogLevel`
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+
+static LOGGER: JsLogger = JsLogger;
+
+/// Куда уходят записи
+enum Sink {
+    /// Консоль браузера
+    Console,
+    /// Функция хоста `(level, message, target)`
+    Function(js_sys::Function),
+    /// Строки `"<LEVEL> <message>"` для тестов
+    #[cfg(test)]
+    Capture(Vec<String>),
+}
+
+thread_local! {
+    static SINK: RefCell<Sink> = const { RefCell::new(Sink::Console) };
+}
+
+/// `log::Log`, пересылающий записи в текущий `Sink`
+struct JsLogger;
+
+impl Log for JsLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        // Функция вызывается вне заимствования: колбэк может сам сменить sink
+        let function = SINK.with(|sink| match &mut *sink.borrow_mut() {
+            Sink::Console => {
+                console(record);
+                None
+            }
+            Sink::Function(function) => Some(function.clone()),
+            #[cfg(test)]
+            Sink::Capture(lines) => {
+                lines.push(format!("{} {}", record.level(), record.args()));
+                None
+            }
+        });
+        if let Some(function) = function {
+            let level = JsValue::from_str(record.level().as_str());
+            let message = JsValue::from_str(&record.args().to_string());
+            let target = JsValue::from_str(record.target());
+            // Исключение в колбэке хоста не должно прерывать сканирование
+            let _ = function.call3(&JsValue::NULL, &level, &message, &target);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Запись в консоль браузера (вне wasm — в нативных тестах — отбрасывается)
+fn console(record: &Record) {
+    #[cfg(target_arch = "wasm32")]
+    console_log::log(record);
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = record;
+}
+
+/// Установка логгера модуля; повторные вызовы ничего не меняют
+pub(crate) fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(DEFAULT_LEVEL);
+    }
+}
+
+/// Уровень по имени: `off`, `error`, `warn`, `info`, `debug`, `trace` (без учёта регистра)
+pub(crate) fn set_level(level: &str) -> Result<(), String> {
+    let filter = LevelFilter::from_str(level.trim()).map_err(|_| format!("unknown log level: {}", level))?;
+    init();
+    log::set_max_level(filter);
+    Ok(())
+}
+
+/// Функция хоста вместо консоли; `None` возвращает вывод в консоль
+pub(crate) fn set_sink(function: Option<js_sys::Function>) {
+    init();
+    let sink = function.map_or(Sink::Console, Sink::Function);
+    SINK.with(|current| *current.borrow_mut() = sink);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_off_suppresses_output() {
+        init();
+        SINK.with(|sink| *sink.borrow_mut() = Sink::Capture(Vec::new()));
+
+        set_level("Info").unwrap();
+        log::info!("visible");
+        log::debug!("hidden");
+        set_level("off").unwrap();
+        log::error!("suppressed");
+        assert!(set_level("loud").is_err());
+
+        let lines = SINK.with(|sink| match std::mem::replace(&mut *sink.borrow_mut(), Sink::Console) {
+            Sink::Capture(lines) => lines,
+            _ => Vec::new(),
+        });
+        assert_eq!(lines, vec!["INFO visible".to_string()]);
+        assert_eq!(log::max_level(), LevelFilter::Off);
+        set_level("info").unwrap();
+    }
+}
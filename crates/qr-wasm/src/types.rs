@@ -0,0 +1,230 @@
+//! TypeScript-описания объектов, которые возвращает `serde_wasm_bindgen`
+//!
+//! wasm-bindgen вставляет этот блок в `qr_wasm.d.ts`, а методы сканера ссылаются
+//! на интерфейсы через `unchecked_return_type`. `Option` сериализуется как
+//! `undefined`, `HashMap` — как `Map`. При изменении структур qr-core блок
+//! нужно обновить (тест ниже сверяет имена полей).
+
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_SECTION: &'static str = TS_TYPES;
+
+// Вне wasm32 секция не генерируется, и константу читают только тесты
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub(crate) const TS_TYPES: &str = r#"
+export type ContentType =
+  | "text" | "url" | "vcard" | "wifi" | "payment"
+  | "email" | "phone" | "sms" | "geo" | "calendar" | "structured" | "unknown";
+
+export type PaymentFormat = "emv_qr" | "sbp_russia" | "st_russia" | "epc_sepa" | "upi" | "sber_pay" | "unknown";
+
+export type TipSpec = "Prompt" | { Fixed: string } | { Percentage: number };
+
+export type WiFiEncryption = "Wep" | "Wpa" | "Wpa2" | "NoPass" | { Unknown: string };
+
+export interface EmvData {
+  raw_data: string;
+  pfi: string;
+  point_of_initiation?: string;
+  merchant_account_information: Map<string, string>;
+  merchant_category_code?: string;
+  transaction_currency?: string;
+  transaction_amount?: string;
+  tip_indicator?: string;
+  convenience_fee_fixed?: string;
+  convenience_fee_percentage?: string;
+  country_code?: string;
+  merchant_name?: string;
+  merchant_city?: string;
+  postal_code?: string;
+  additional_data: Map<string, string>;
+  crc: string;
+  unparsed_tags: Map<string, string>;
+}
+
+export interface PaymentInfo {
+  format: PaymentFormat;
+  payee_name?: string;
+  payee_id?: string;
+  account?: string;
+  bank?: string;
+  bic?: string;
+  /** Точная десятичная сумма, например "150.00" */
+  amount?: string;
+  currency?: string;
+  purpose?: string;
+  tip?: TipSpec;
+  emv?: EmvData;
+  extra: Map<string, string>;
+}
+
+export interface WiFiInfo {
+  ssid: string;
+  password?: string;
+  encryption: WiFiEncryption;
+  hidden: boolean;
+}
+
+export interface GeoLocation {
+  lat: number;
+  lon: number;
+  alt?: number;
+  uncertainty?: number;
+}
+
+export type EventTime =
+  | { Date: { year: number; month: number; day: number } }
+  | { DateTime: { year: number; month: number; day: number; hour: number; minute: number; second: number; utc: boolean } };
+
+export interface CalendarEvent {
+  summary?: string;
+  start: EventTime;
+  end?: EventTime;
+  location?: string;
+  description?: string;
+}
+
+export interface UriInfo {
+  /** Схема в нижнем регистре: "https", "market", "intent" */
+  scheme: string;
+  authority: string;
+  path: string;
+  query: Map<string, string>;
+  fragment?: string;
+}
+
+export interface QRResult {
+  content: string;
+  bbox: [number, number, number, number];
+  content_type: ContentType;
+  payment?: PaymentInfo;
+  wifi?: WiFiInfo;
+  geo?: GeoLocation;
+  uri?: UriInfo;
+  calendar?: CalendarEvent;
+  /** Поля ключ-значение для ContentType "structured" */
+  fields?: Map<string, string>;
+  confidence: number;
+  errors_corrected?: number;
+  corners?: [[number, number], [number, number], [number, number], [number, number]];
+  /** Номер страницы или кадра (с нуля) для многостраничных и анимированных входов */
+  page?: number;
+  /** PNG data URL входа декодера (только после setDebugCrops(true)) */
+  debug_crop?: string | null;
+}
+
+export interface ScanResult {
+  qr_codes: QRResult[];
+  best_payment?: number;
+  /** [индекс в qr_codes, релевантность] по убыванию релевантности */
+  payment_ranking: [number, number][];
+  processing_time_ms: number;
+}
+
+export interface PaymentScan {
+  found: boolean;
+  payment?: PaymentInfo;
+  /** Релевантность платёжного QR, 0 если не найден */
+  confidence: number;
+  bbox?: [number, number, number, number];
+}
+
+export type ErrorCode =
+  | "NOT_FOUND" | "DECODE_FAILED" | "CHECKSUM" | "INVALID_FORMAT" | "INVALID_IMAGE"
+  | "INVALID_INPUT" | "INVALID_CONFIG" | "MODEL" | "INTERNAL";
+
+/** Объект, который бросают методы модуля */
+export interface WasmError {
+  code: ErrorCode;
+  message: string;
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qr_core::{CalendarEvent, ContentType, GeoLocation, PaymentFormat, PaymentInfo, QRResult, ScanResult, UriInfo, WiFiInfo};
+
+    /// Тело `export interface <name> { ... }`
+    fn interface(name: &str) -> &'static str {
+        let start = TS_TYPES
+            .find(&format!("export interface {} {{", name))
+            .unwrap_or_else(|| panic!("interface {} missing", name));
+        let body = &TS_TYPES[start..];
+        &body[..body.find("\n}").unwrap()]
+    }
+
+    /// Каждый ключ сериализованного объекта описан в интерфейсе
+    fn assert_fields(name: &str, value: serde_json::Value) {
+        let body = interface(name);
+        for key in value.as_object().unwrap().keys() {
+            assert!(
+                body.contains(&format!("\n  {}:", key)) || body.contains(&format!("\n  {}?:", key)),
+                "{}.{} missing in TS_TYPES",
+                name,
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn test_typescript_fields_match_serialized_shape() {
+        let payment = PaymentInfo {
+            format: PaymentFormat::SbpRussia,
+            ..Default::default()
+        };
+        let wifi = WiFiInfo::parse("WIFI:T:WPA;S:Office;P:secret;;").unwrap();
+        let geo = GeoLocation::parse("geo:55.75,37.61").unwrap();
+        let uri = UriInfo::parse("market://details?id=com.example#top").unwrap();
+        let event = CalendarEvent::parse("BEGIN:VEVENT\nSUMMARY:Demo\nDTSTART:20240515T090000Z\nEND:VEVENT").unwrap();
+        let qr = QRResult {
+            content: "https://qr.nspk.ru/AS1".into(),
+            bbox: [0, 0, 10, 10],
+            content_type: ContentType::Payment,
+            payment: Some(payment.clone()),
+            wifi: Some(wifi.clone()),
+            geo: Some(geo.clone()),
+            uri: Some(uri.clone()),
+            calendar: Some(event.clone()),
+            fields: None,
+            confidence: 1.0,
+            errors_corrected: Some(0),
+            corners: Some([(0.0, 0.0); 4]),
+            page: None,
+            debug_crop: None,
+        };
+        let result = ScanResult {
+            qr_codes: vec![qr.clone()],
+            best_payment: Some(0),
+            payment_ranking: vec![(0, 1.0)],
+            processing_time_ms: 1,
+        };
+
+        assert_fields("ScanResult", serde_json::to_value(&result).unwrap());
+        assert_fields("QRResult", serde_json::to_value(&qr).unwrap());
+        assert_fields("PaymentInfo", serde_json::to_value(&payment).unwrap());
+        assert_fields("WiFiInfo", serde_json::to_value(&wifi).unwrap());
+        assert_fields("GeoLocation", serde_json::to_value(&geo).unwrap());
+        assert_fields("CalendarEvent", serde_json::to_value(&event).unwrap());
+        assert_fields("UriInfo", serde_json::to_value(&uri).unwrap());
+        let scan = crate::PaymentScan {
+            found: true,
+            payment: Some(payment.clone()),
+            confidence: 1.0,
+            bbox: Some([0, 0, 10, 10]),
+        };
+        assert_fields("PaymentScan", serde_json::to_value(&scan).unwrap());
+        let error = crate::WasmError::from(qr_core::DecodeError::NotFound);
+        assert_fields("WasmError", serde_json::to_value(&error).unwrap());
+        assert!(TS_TYPES.contains(&format!("\"{}\"", error.code)));
+
+        // JSON, отданный в JS, читается обратно без потерь
+        let back: ScanResult = serde_json::from_value(serde_json::to_value(&result).unwrap()).unwrap();
+        assert_eq!(serde_json::to_value(&back).unwrap(), serde_json::to_value(&result).unwrap());
+        let back: crate::PaymentScan = serde_json::from_value(serde_json::to_value(&scan).unwrap()).unwrap();
+        assert_eq!(serde_json::to_value(&back).unwrap(), serde_json::to_value(&scan).unwrap());
+        assert!(TS_TYPES.contains(&serde_json::to_string(&payment.format).unwrap()));
+        assert!(TS_TYPES.contains(&serde_json::to_string(&ContentType::Payment).unwrap()));
+    }
+}
@@ -274,7 +274,7 @@ export default function Home() {
                                 const jsQrCode = jsQR(cropData.data, w, h);
                                 if (jsQrCode) {
                                     log('SCAN', `✅ jsQR found code: ${jsQrCode.data}`);
-                                    result = { qr_codes: [{ content: jsQrCode.data, content_type: "Text" }] };
+                                    result = { qr_codes: [{ content: jsQrCode.data, content_type: "text" }] };
                                     break;
                                 }
 
@@ -318,7 +318,7 @@ export default function Home() {
                                         const jsQrRot = jsQR(rotData.data, nw, nh);
                                         if (jsQrRot) {
                                             log('SCAN', `✅ jsQR found code in rotation (${angle}°): ${jsQrRot.data}`);
-                                            cropResult = { qr_codes: [{ content: jsQrRot.data, content_type: "Text" }] };
+                                            cropResult = { qr_codes: [{ content: jsQrRot.data, content_type: "text" }] };
                                             break;
                                         }
 
@@ -575,7 +575,7 @@ export default function Home() {
                     <div className="results">
                         <h3>Results</h3>
                         {results.map((qr, idx) => (
-                            <div key={idx} className={`result-card ${qr.content_type === 'Payment' ? 'payment' : ''}`}>
+                            <div key={idx} className={`result-card ${qr.content_type === 'payment' ? 'payment' : ''}`}>
                                 <div className="result-type">{qr.content_type}</div>
                                 <div className="result-content">{qr.content}</div>
                                 {qr.payment && (